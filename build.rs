@@ -0,0 +1,143 @@
+//! Packs the directory `initramfs` (or the directory, which is specified
+//! by the environment variable `EDUOS_INITRAMFS`) into a cpio archive
+//! in the "new ASCII" format. The kernel embeds the archive and unpacks
+//! it into its virtual file system at boot time.
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Default directory, which contains the files of the initramfs
+const DEFAULT_INITRAMFS: &str = "initramfs";
+
+/// File type of a directory
+const S_IFDIR: u32 = 0o040000;
+/// File type of a regular file
+const S_IFREG: u32 = 0o100000;
+/// File type of a symbolic link
+const S_IFLNK: u32 = 0o120000;
+
+/// Append a header, the name and the data of an entry to the archive
+fn write_entry(archive: &mut Vec<u8>, ino: u32, name: &str, mode: u32, data: &[u8]) {
+	let header = format!(
+		"070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+		ino,
+		mode,
+		0, // uid
+		0, // gid
+		if mode & S_IFDIR == S_IFDIR { 2 } else { 1 },
+		0, // mtime
+		data.len(),
+		0, // devmajor
+		0, // devminor
+		0, // rdevmajor
+		0, // rdevminor
+		name.len() + 1,
+		0, // check
+	);
+
+	archive.extend_from_slice(header.as_bytes());
+	archive.extend_from_slice(name.as_bytes());
+	archive.push(0);
+	archive.resize(archive.len().next_multiple_of(4), 0);
+	archive.extend_from_slice(data);
+	archive.resize(archive.len().next_multiple_of(4), 0);
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &fs::Metadata) -> u32 {
+	use std::os::unix::fs::PermissionsExt;
+
+	metadata.permissions().mode() & 0o7777
+}
+
+/// Without Unix permissions, all files are considered
+/// executable, because the kernel checks the execute bits
+#[cfg(not(unix))]
+fn permissions(metadata: &fs::Metadata) -> u32 {
+	if metadata.permissions().readonly() && !metadata.is_dir() {
+		0o555
+	} else {
+		0o755
+	}
+}
+
+/// Add all entries of the directory `dir` recursively to the archive,
+/// where `prefix` is the path of `dir` within the archive
+fn pack_directory(
+	archive: &mut Vec<u8>,
+	ino: &mut u32,
+	dir: &Path,
+	prefix: &str,
+) -> io::Result<()> {
+	let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+	// a sorted archive is reproducible
+	entries.sort_by_key(|entry| entry.file_name());
+
+	for entry in entries {
+		let path = entry.path();
+		let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+		let metadata = fs::symlink_metadata(&path)?;
+		let mode = permissions(&metadata);
+		*ino += 1;
+
+		if metadata.file_type().is_symlink() {
+			let target = fs::read_link(&path)?;
+			let target = target.to_string_lossy();
+			write_entry(archive, *ino, &name, S_IFLNK | 0o777, target.as_bytes());
+		} else if metadata.is_dir() {
+			write_entry(archive, *ino, &name, S_IFDIR | mode, &[]);
+			pack_directory(archive, ino, &path, &format!("{}/", name))?;
+		} else if metadata.is_file() {
+			write_entry(archive, *ino, &name, S_IFREG | mode, &fs::read(&path)?);
+		} else {
+			println!(
+				"cargo:warning=Ignore {}, which isn't a file, a directory or a symbolic link",
+				path.display()
+			);
+		}
+	}
+
+	Ok(())
+}
+
+/// Copy the disk image `EDUOS_DISK_IMAGE` to `out`, which the kernel
+/// provides as RAM disk. Without a disk image, `out` is empty.
+fn copy_disk_image(out: &Path) -> io::Result<()> {
+	println!("cargo:rerun-if-env-changed=EDUOS_DISK_IMAGE");
+
+	match env::var("EDUOS_DISK_IMAGE") {
+		Ok(image) => {
+			println!("cargo:rerun-if-changed={}", image);
+			fs::copy(image, out).map(|_| ())
+		}
+		Err(_) => fs::write(out, []),
+	}
+}
+
+fn main() -> io::Result<()> {
+	println!("cargo:rerun-if-env-changed=EDUOS_INITRAMFS");
+
+	let dir = PathBuf::from(
+		env::var("EDUOS_INITRAMFS").unwrap_or_else(|_| String::from(DEFAULT_INITRAMFS)),
+	);
+	println!("cargo:rerun-if-changed={}", dir.display());
+
+	let mut archive = Vec::new();
+	let mut ino = 0;
+	if dir.is_dir() {
+		pack_directory(&mut archive, &mut ino, &dir, "")?;
+	} else {
+		println!(
+			"cargo:warning=Initramfs directory {} doesn't exist",
+			dir.display()
+		);
+	}
+	write_entry(&mut archive, 0, "TRAILER!!!", 0, &[]);
+
+	let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+	fs::File::create(out_dir.join("initramfs.cpio"))?.write_all(&archive)?;
+
+	copy_disk_image(&out_dir.join("disk.img"))
+}
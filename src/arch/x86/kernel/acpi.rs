@@ -0,0 +1,759 @@
+//! Discovery of the ACPI tables
+//!
+//! The firmware stores the Root System Description Pointer (RSDP) in the
+//! first KiB of the Extended BIOS Data Area or in the BIOS area below
+//! 1 MiB. The RSDP refers to the RSDT (or to the XSDT since ACPI 2.0),
+//! which lists the physical addresses of all other tables.
+//!
+//! The kernel evaluates the following tables:
+//! - FADT: registers to enter the sleep state S5 (power-off) and the reset register.
+//!   The sleep type of S5 is taken from the package `_S5_` of the DSDT.
+//! - MADT: local APICs of the processors, I/O APICs and interrupt source overrides
+//! - HPET: address and capabilities of the High Precision Event Timer
+
+use crate::arch::x86::mm::paging::{self, BasePageSize, PageSize, PageTableEntryFlags};
+use crate::arch::x86::mm::virtualmem;
+use crate::arch::x86::mm::{PhysAddr, VirtAddr};
+use crate::logging::*;
+use crate::synch::spinlock::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::ptr::{read_unaligned, write_volatile};
+use x86::io::*;
+
+/// Signature of the Root System Description Pointer
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+/// The RSDP is aligned to 16 bytes
+const RSDP_ALIGNMENT: usize = 16;
+/// Physical address of the real mode segment of the EBDA
+const EBDA_POINTER: u64 = 0x40E;
+/// Size of the EBDA area, which may contain the RSDP
+const EBDA_SEARCH_SIZE: usize = 1024;
+/// Physical address of the BIOS area, which may contain the RSDP
+const BIOS_AREA_START: u64 = 0xE0000;
+/// Size of the BIOS area
+const BIOS_AREA_SIZE: usize = 0x20000;
+
+// offsets of the fields of the FADT
+const FADT_DSDT: usize = 40;
+const FADT_SMI_CMD: usize = 48;
+const FADT_ACPI_ENABLE: usize = 52;
+const FADT_PM1A_CNT_BLK: usize = 64;
+const FADT_PM1B_CNT_BLK: usize = 68;
+const FADT_FLAGS: usize = 112;
+const FADT_RESET_REG: usize = 116;
+const FADT_RESET_VALUE: usize = 128;
+const FADT_X_DSDT: usize = 140;
+
+/// FADT flags: the reset register is supported
+const FADT_RESET_REG_SUP: u32 = 1 << 10;
+
+/// PM1 control register: the system uses ACPI instead of legacy power management
+const PM1_SCI_EN: u16 = 1 << 0;
+/// PM1 control register: position of the sleep type
+const PM1_SLP_TYP_SHIFT: u16 = 10;
+/// PM1 control register: enter the sleep state
+const PM1_SLP_EN: u16 = 1 << 13;
+/// Number of polls, until the firmware switches to the ACPI mode
+const ACPI_ENABLE_TIMEOUT: usize = 1_000_000;
+
+// AML opcodes, which define the package `_S5_`
+const AML_NAME_OP: u8 = 0x08;
+const AML_ROOT_PREFIX: u8 = b'\\';
+const AML_PACKAGE_OP: u8 = 0x12;
+const AML_BYTE_PREFIX: u8 = 0x0A;
+
+// address spaces of a Generic Address Structure
+const GAS_SYSTEM_MEMORY: u8 = 0;
+const GAS_SYSTEM_IO: u8 = 1;
+const GAS_PCI_CONFIG: u8 = 2;
+
+// types of the entries of the MADT
+const MADT_LOCAL_APIC: u8 = 0;
+const MADT_IO_APIC: u8 = 1;
+const MADT_INTERRUPT_OVERRIDE: u8 = 2;
+const MADT_LOCAL_APIC_OVERRIDE: u8 = 5;
+const MADT_LOCAL_X2APIC: u8 = 9;
+
+/// Local APIC flags: the processor is usable
+const LOCAL_APIC_ENABLED: u32 = 1 << 0;
+/// Local APIC flags: the processor can be enabled at runtime
+const LOCAL_APIC_ONLINE_CAPABLE: u32 = 1 << 1;
+
+/// Tables, which are found at the boot time
+static TABLES: Spinlock<Vec<AcpiTable>> = Spinlock::new(Vec::new());
+
+/// Registers for the power management, which are described by the FADT
+static POWER_MANAGEMENT: Spinlock<Option<PowerManagement>> = Spinlock::new(None);
+
+/// Interrupt controllers and processors, which are described by the MADT
+static MADT: Spinlock<Option<Madt>> = Spinlock::new(None);
+
+/// High Precision Event Timer, which is described by the table HPET
+static HPET: Spinlock<Option<HpetInfo>> = Spinlock::new(None);
+
+/// Root System Description Pointer
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug)]
+struct Rsdp {
+	signature: [u8; 8],
+	checksum: u8,
+	oem_id: [u8; 6],
+	revision: u8,
+	rsdt_address: u32,
+	// the following fields exist since ACPI 2.0
+	length: u32,
+	xsdt_address: u64,
+	extended_checksum: u8,
+	reserved: [u8; 3],
+}
+
+/// Size of the RSDP of ACPI 1.0
+const RSDP_V1_SIZE: usize = 20;
+
+/// Header, which starts each System Description Table
+#[repr(C, packed)]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct SdtHeader {
+	pub signature: [u8; 4],
+	pub length: u32,
+	pub revision: u8,
+	pub checksum: u8,
+	pub oem_id: [u8; 6],
+	pub oem_table_id: [u8; 8],
+	pub oem_revision: u32,
+	pub creator_id: u32,
+	pub creator_revision: u32,
+}
+
+/// Returns true, if the sum of all bytes is zero
+fn verify_checksum(data: &[u8]) -> bool {
+	data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+/// Mapping of a physical memory region, which is removed by `drop`
+struct PhysicalMapping {
+	/// first mapped page
+	virtual_address: VirtAddr,
+	/// size of the mapped pages
+	size: usize,
+	/// offset of the region within the first page
+	offset: usize,
+	/// size of the region
+	len: usize,
+}
+
+impl PhysicalMapping {
+	/// Map the region of `len` bytes at the physical address `address` read-only
+	fn new(address: u64, len: usize) -> Self {
+		let offset = (address % BasePageSize::SIZE as u64) as usize;
+		let size = (offset + len).next_multiple_of(BasePageSize::SIZE);
+		let virtual_address = virtualmem::allocate(size);
+
+		paging::map::<BasePageSize>(
+			virtual_address,
+			PhysAddr(address - offset as u64),
+			size / BasePageSize::SIZE,
+			PageTableEntryFlags::EXECUTE_DISABLE,
+		);
+
+		PhysicalMapping {
+			virtual_address,
+			size,
+			offset,
+			len,
+		}
+	}
+
+	fn as_slice(&self) -> &[u8] {
+		unsafe {
+			core::slice::from_raw_parts(
+				(self.virtual_address.as_usize() + self.offset) as *const u8,
+				self.len,
+			)
+		}
+	}
+
+	/// Keep the region mapped until the system stops
+	fn leak(self) -> &'static [u8] {
+		let slice = unsafe { core::slice::from_raw_parts(self.as_slice().as_ptr(), self.len) };
+		core::mem::forget(self);
+
+		slice
+	}
+}
+
+impl Drop for PhysicalMapping {
+	fn drop(&mut self) {
+		paging::unmap::<BasePageSize>(self.virtual_address, self.size / BasePageSize::SIZE);
+		virtualmem::deallocate(self.virtual_address, self.size);
+	}
+}
+
+/// System Description Table, which is mapped into the kernel space
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct AcpiTable {
+	/// content of the table including the header
+	data: &'static [u8],
+}
+
+impl AcpiTable {
+	/// Map the table at the physical address `address` and verify its checksum
+	fn map(address: u64) -> Option<Self> {
+		let header = PhysicalMapping::new(address, size_of::<SdtHeader>());
+		let length = unsafe { read_unaligned(header.as_slice().as_ptr() as *const SdtHeader) }
+			.length as usize;
+		drop(header);
+
+		if length < size_of::<SdtHeader>() {
+			return None;
+		}
+
+		let mapping = PhysicalMapping::new(address, length);
+		if verify_checksum(mapping.as_slice()) {
+			Some(AcpiTable {
+				data: mapping.leak(),
+			})
+		} else {
+			warn!("ACPI table at 0x{:x} has an invalid checksum", address);
+			None
+		}
+	}
+
+	/// Returns the header of the table
+	pub fn header(&self) -> SdtHeader {
+		unsafe { read_unaligned(self.data.as_ptr() as *const SdtHeader) }
+	}
+
+	/// Returns the signature of the table
+	pub fn signature(&self) -> [u8; 4] {
+		self.header().signature
+	}
+
+	/// Returns the content of the table behind the header
+	pub fn body(&self) -> &'static [u8] {
+		&self.data[size_of::<SdtHeader>()..]
+	}
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+	Some(u16::from_le_bytes(
+		data.get(offset..offset + 2)?.try_into().unwrap(),
+	))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(
+		data.get(offset..offset + 4)?.try_into().unwrap(),
+	))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+	Some(u64::from_le_bytes(
+		data.get(offset..offset + 8)?.try_into().unwrap(),
+	))
+}
+
+/// Generic Address Structure, which describes a register
+#[derive(Copy, Clone, Debug)]
+pub struct GenericAddress {
+	/// address space of the register
+	pub space: u8,
+	/// width of the register in bits
+	pub bit_width: u8,
+	pub address: u64,
+}
+
+impl GenericAddress {
+	fn parse(data: &[u8], offset: usize) -> Option<Self> {
+		Some(GenericAddress {
+			space: *data.get(offset)?,
+			bit_width: *data.get(offset + 1)?,
+			address: read_u64(data, offset + 4)?,
+		})
+	}
+
+	/// Write the byte `value` to the register
+	fn write_u8(&self, value: u8) {
+		match self.space {
+			GAS_SYSTEM_IO => unsafe { outb(self.address as u16, value) },
+			GAS_SYSTEM_MEMORY => {
+				let mut flags = PageTableEntryFlags::empty();
+				flags.device().writable().execute_disable();
+
+				let offset = (self.address % BasePageSize::SIZE as u64) as usize;
+				let virtual_address = virtualmem::allocate(BasePageSize::SIZE);
+				paging::map::<BasePageSize>(
+					virtual_address,
+					PhysAddr(self.address - offset as u64),
+					1,
+					flags,
+				);
+				unsafe {
+					write_volatile((virtual_address.as_usize() + offset) as *mut u8, value);
+				}
+				paging::unmap::<BasePageSize>(virtual_address, 1);
+				virtualmem::deallocate(virtual_address, BasePageSize::SIZE);
+			}
+			GAS_PCI_CONFIG => {
+				// bus 0, the device and the function are encoded in the upper bits
+				let address = crate::drivers::pci::PciAddress::new(
+					0,
+					(self.address >> 32) as u8,
+					(self.address >> 16) as u8,
+				);
+				let offset = (self.address & 0xFFFF) as u16;
+				let shift = 8 * u32::from(offset & 0x3);
+				let old = address.read_config(offset);
+				address.write_config(offset, (old & !(0xFF << shift)) | u32::from(value) << shift);
+			}
+			_ => warn!("ACPI address space {} isn't supported", self.space),
+		}
+	}
+}
+
+/// Registers to turn the machine off and to reset it
+#[derive(Copy, Clone, Debug)]
+struct PowerManagement {
+	/// port of the System Management Interrupt command register
+	smi_cmd: u16,
+	/// value of `smi_cmd` to switch to the ACPI mode
+	acpi_enable: u8,
+	/// ports of the PM1 control registers
+	pm1a_cnt: u16,
+	pm1b_cnt: u16,
+	/// sleep types of the state S5, if the DSDT defines them
+	s5: Option<(u16, u16)>,
+	/// reset register and the value, which resets the machine
+	reset: Option<(GenericAddress, u8)>,
+}
+
+/// Returns the sleep types of the state S5 for PM1a and PM1b, which
+/// are defined by the package `_S5_` of the DSDT. Instead of interpreting
+/// the AML code, the package is searched in the table.
+fn parse_s5(dsdt: &[u8]) -> Option<(u16, u16)> {
+	let pos = dsdt.windows(4).position(|name| name == b"_S5_")?;
+
+	// the name has to be defined by NameOp, optionally with the root prefix
+	let defined = (pos >= 1 && dsdt[pos - 1] == AML_NAME_OP)
+		|| (pos >= 2 && dsdt[pos - 2] == AML_NAME_OP && dsdt[pos - 1] == AML_ROOT_PREFIX);
+	if !defined || *dsdt.get(pos + 4)? != AML_PACKAGE_OP {
+		return None;
+	}
+
+	// skip PkgLength, whose first byte contains the number of following bytes,
+	// and NumElements
+	let mut index = pos + 5;
+	index += usize::from(*dsdt.get(index)? >> 6) + 1;
+	index += 1;
+
+	let mut element = || {
+		let mut value = *dsdt.get(index)?;
+		if value == AML_BYTE_PREFIX {
+			index += 1;
+			value = *dsdt.get(index)?;
+		}
+		index += 1;
+		Some(u16::from(value))
+	};
+	let slp_typa = element()?;
+	let slp_typb = element()?;
+
+	Some((slp_typa, slp_typb))
+}
+
+fn parse_fadt(fadt: &AcpiTable) -> Option<PowerManagement> {
+	let data = fadt.data;
+
+	let dsdt_address = match read_u64(data, FADT_X_DSDT) {
+		Some(address) if address != 0 => address,
+		_ => u64::from(read_u32(data, FADT_DSDT)?),
+	};
+	let s5 = AcpiTable::map(dsdt_address).and_then(|dsdt| parse_s5(dsdt.body()));
+
+	let reset =
+		if fadt.header().revision >= 2 && read_u32(data, FADT_FLAGS)? & FADT_RESET_REG_SUP != 0 {
+			Some((
+				GenericAddress::parse(data, FADT_RESET_REG)?,
+				*data.get(FADT_RESET_VALUE)?,
+			))
+		} else {
+			None
+		};
+
+	Some(PowerManagement {
+		smi_cmd: read_u32(data, FADT_SMI_CMD)? as u16,
+		acpi_enable: *data.get(FADT_ACPI_ENABLE)?,
+		pm1a_cnt: read_u32(data, FADT_PM1A_CNT_BLK)? as u16,
+		pm1b_cnt: read_u32(data, FADT_PM1B_CNT_BLK)? as u16,
+		s5,
+		reset,
+	})
+}
+
+/// Processor, which is described by the MADT
+#[derive(Copy, Clone, Debug)]
+pub struct ProcessorInfo {
+	/// ID of the processor in the ACPI namespace
+	pub acpi_id: u32,
+	/// ID of the local APIC of the processor
+	pub apic_id: u32,
+	/// the processor is usable, otherwise it can be enabled at runtime
+	pub enabled: bool,
+}
+
+/// I/O APIC, which is described by the MADT
+#[derive(Copy, Clone, Debug)]
+pub struct IoApicInfo {
+	pub id: u8,
+	/// physical address of the registers
+	pub address: u64,
+	/// first global system interrupt of the I/O APIC
+	pub gsi_base: u32,
+}
+
+/// Mapping of an ISA interrupt to a global system interrupt
+#[derive(Copy, Clone, Debug)]
+pub struct InterruptOverride {
+	/// ISA interrupt
+	pub source: u8,
+	/// global system interrupt
+	pub gsi: u32,
+	/// polarity and trigger mode
+	pub flags: u16,
+}
+
+/// Content of the Multiple APIC Description Table (MADT)
+#[derive(Clone, Debug, Default)]
+pub struct Madt {
+	/// physical address of the local APICs
+	pub local_apic_address: u64,
+	/// the system has 8259 PICs, which have to be disabled to use the APICs
+	pub legacy_pics: bool,
+	pub processors: Vec<ProcessorInfo>,
+	pub io_apics: Vec<IoApicInfo>,
+	pub overrides: Vec<InterruptOverride>,
+}
+
+fn parse_madt(table: &AcpiTable) -> Option<Madt> {
+	let body = table.body();
+	let mut madt = Madt {
+		local_apic_address: u64::from(read_u32(body, 0)?),
+		legacy_pics: read_u32(body, 4)? & 1 != 0,
+		..Default::default()
+	};
+
+	let mut offset = 8;
+	while offset + 2 <= body.len() {
+		let kind = body[offset];
+		let length = usize::from(body[offset + 1]);
+		if length < 2 || offset + length > body.len() {
+			break;
+		}
+		let entry = &body[offset..offset + length];
+
+		match kind {
+			MADT_LOCAL_APIC => {
+				let flags = read_u32(entry, 4)?;
+				if flags & (LOCAL_APIC_ENABLED | LOCAL_APIC_ONLINE_CAPABLE) != 0 {
+					madt.processors.push(ProcessorInfo {
+						acpi_id: u32::from(entry[2]),
+						apic_id: u32::from(entry[3]),
+						enabled: flags & LOCAL_APIC_ENABLED != 0,
+					});
+				}
+			}
+			MADT_LOCAL_X2APIC => {
+				let flags = read_u32(entry, 8)?;
+				if flags & (LOCAL_APIC_ENABLED | LOCAL_APIC_ONLINE_CAPABLE) != 0 {
+					madt.processors.push(ProcessorInfo {
+						acpi_id: read_u32(entry, 12)?,
+						apic_id: read_u32(entry, 4)?,
+						enabled: flags & LOCAL_APIC_ENABLED != 0,
+					});
+				}
+			}
+			MADT_IO_APIC => madt.io_apics.push(IoApicInfo {
+				id: entry[2],
+				address: u64::from(read_u32(entry, 4)?),
+				gsi_base: read_u32(entry, 8)?,
+			}),
+			MADT_INTERRUPT_OVERRIDE => madt.overrides.push(InterruptOverride {
+				source: entry[3],
+				gsi: read_u32(entry, 4)?,
+				flags: read_u16(entry, 8)?,
+			}),
+			MADT_LOCAL_APIC_OVERRIDE => madt.local_apic_address = read_u64(entry, 4)?,
+			_ => {}
+		}
+
+		offset += length;
+	}
+
+	Some(madt)
+}
+
+/// High Precision Event Timer, which is described by the table HPET
+#[derive(Copy, Clone, Debug)]
+pub struct HpetInfo {
+	/// physical address of the registers
+	pub address: u64,
+	/// sequence number of the timer block
+	pub number: u8,
+	/// number of comparators
+	pub comparators: u8,
+	/// the main counter has 64 bits
+	pub counter_64bit: bool,
+	/// the timer is able to replace the PIT and the RTC interrupts
+	pub legacy_replacement: bool,
+	/// minimum number of ticks in the periodic mode
+	pub min_tick: u16,
+}
+
+fn parse_hpet(table: &AcpiTable) -> Option<HpetInfo> {
+	let body = table.body();
+	let id = read_u32(body, 0)?;
+	let base = GenericAddress::parse(body, 4)?;
+
+	if base.space != GAS_SYSTEM_MEMORY {
+		return None;
+	}
+
+	Some(HpetInfo {
+		address: base.address,
+		number: *body.get(16)?,
+		comparators: ((id >> 8) & 0x1F) as u8 + 1,
+		counter_64bit: id & (1 << 13) != 0,
+		legacy_replacement: id & (1 << 15) != 0,
+		min_tick: read_u16(body, 17)?,
+	})
+}
+
+/// Search the RSDP in the region of `len` bytes at the physical address `address`
+fn search_rsdp(address: u64, len: usize) -> Option<Rsdp> {
+	let mapping = PhysicalMapping::new(address, len);
+	let region = mapping.as_slice();
+
+	for candidate in region.chunks_exact(RSDP_ALIGNMENT) {
+		if &candidate[..RSDP_SIGNATURE.len()] != RSDP_SIGNATURE {
+			continue;
+		}
+
+		let start = candidate.as_ptr() as usize - region.as_ptr() as usize;
+		let rest = &region[start..];
+		if rest.len() < RSDP_V1_SIZE || !verify_checksum(&rest[..RSDP_V1_SIZE]) {
+			continue;
+		}
+
+		let mut buf = [0u8; size_of::<Rsdp>()];
+		let copy = buf.len().min(rest.len());
+		buf[..copy].copy_from_slice(&rest[..copy]);
+		let rsdp = unsafe { read_unaligned(buf.as_ptr() as *const Rsdp) };
+
+		// the extended fields are only valid since ACPI 2.0
+		if rsdp.revision >= 2 {
+			let length = rsdp.length as usize;
+			if length < size_of::<Rsdp>()
+				|| length > rest.len()
+				|| !verify_checksum(&rest[..length])
+			{
+				continue;
+			}
+		}
+
+		return Some(rsdp);
+	}
+
+	None
+}
+
+/// Search the RSDP in the EBDA and in the BIOS area
+fn find_rsdp() -> Option<Rsdp> {
+	let ebda = {
+		let mapping = PhysicalMapping::new(EBDA_POINTER, 2);
+		let segment = u16::from_le_bytes([mapping.as_slice()[0], mapping.as_slice()[1]]);
+		u64::from(segment) << 4
+	};
+
+	if ebda != 0 {
+		if let Some(rsdp) = search_rsdp(ebda, EBDA_SEARCH_SIZE) {
+			return Some(rsdp);
+		}
+	}
+
+	search_rsdp(BIOS_AREA_START, BIOS_AREA_SIZE)
+}
+
+/// Returns the processors and interrupt controllers, which are described by the MADT
+pub fn madt() -> Option<Madt> {
+	MADT.lock().clone()
+}
+
+/// Returns the High Precision Event Timer, if the firmware describes it
+pub fn hpet() -> Option<HpetInfo> {
+	*HPET.lock()
+}
+
+/// Enter the sleep state S5, i.e. turn the machine off.
+/// Returns only, if ACPI doesn't support the power-off.
+pub fn poweroff() {
+	let Some(pm) = *POWER_MANAGEMENT.lock() else {
+		return;
+	};
+	let Some((slp_typa, slp_typb)) = pm.s5 else {
+		return;
+	};
+	if pm.pm1a_cnt == 0 {
+		return;
+	}
+
+	unsafe {
+		// switch from the legacy mode to the ACPI mode
+		if inw(pm.pm1a_cnt) & PM1_SCI_EN == 0 && pm.smi_cmd != 0 && pm.acpi_enable != 0 {
+			outb(pm.smi_cmd, pm.acpi_enable);
+			for _ in 0..ACPI_ENABLE_TIMEOUT {
+				if inw(pm.pm1a_cnt) & PM1_SCI_EN != 0 {
+					break;
+				}
+				core::hint::spin_loop();
+			}
+		}
+
+		outw(pm.pm1a_cnt, slp_typa << PM1_SLP_TYP_SHIFT | PM1_SLP_EN);
+		if pm.pm1b_cnt != 0 {
+			outw(pm.pm1b_cnt, slp_typb << PM1_SLP_TYP_SHIFT | PM1_SLP_EN);
+		}
+	}
+}
+
+/// Reset the machine by the reset register of the FADT.
+/// Returns only, if the reset register isn't supported.
+pub fn reset() {
+	let reset = POWER_MANAGEMENT.lock().and_then(|pm| pm.reset);
+
+	if let Some((register, value)) = reset {
+		register.write_u8(value);
+	}
+}
+
+/// Returns the table with the signature `signature`
+pub(crate) fn get_table(signature: &[u8; 4]) -> Option<AcpiTable> {
+	TABLES
+		.lock()
+		.iter()
+		.find(|table| &table.signature() == signature)
+		.copied()
+}
+
+/// Find and map all tables, which are listed in the RSDT or XSDT
+pub(crate) fn init() {
+	let Some(rsdp) = find_rsdp() else {
+		info!("No ACPI tables found");
+		return;
+	};
+
+	// the XSDT of ACPI 2.0 contains 64 bit addresses
+	let (root, entry_size) = if rsdp.revision >= 2 && rsdp.xsdt_address != 0 {
+		(AcpiTable::map(rsdp.xsdt_address), size_of::<u64>())
+	} else {
+		(
+			AcpiTable::map(u64::from(rsdp.rsdt_address)),
+			size_of::<u32>(),
+		)
+	};
+	let Some(root) = root else {
+		warn!("Invalid ACPI root table");
+		return;
+	};
+
+	let mut tables = TABLES.lock();
+	for entry in root.body().chunks_exact(entry_size) {
+		let address = if entry_size == size_of::<u64>() {
+			u64::from_le_bytes(entry.try_into().unwrap())
+		} else {
+			u64::from(u32::from_le_bytes(entry.try_into().unwrap()))
+		};
+
+		if let Some(table) = AcpiTable::map(address) {
+			tables.push(table);
+		}
+	}
+
+	let names: Vec<String> = tables
+		.iter()
+		.map(|table| String::from_utf8_lossy(&table.signature()).into_owned())
+		.collect();
+	info!(
+		"ACPI {} tables: {}",
+		if rsdp.revision >= 2 { "2.0" } else { "1.0" },
+		names.join(" ")
+	);
+	drop(tables);
+
+	if let Some(fadt) = get_table(b"FACP") {
+		let pm = parse_fadt(&fadt);
+		if let Some(pm) = pm {
+			info!(
+				"ACPI power-off {}, reset register {}",
+				if pm.s5.is_some() && pm.pm1a_cnt != 0 {
+					"supported"
+				} else {
+					"unsupported"
+				},
+				if pm.reset.is_some() {
+					"supported"
+				} else {
+					"unsupported"
+				}
+			);
+		}
+		*POWER_MANAGEMENT.lock() = pm;
+	}
+
+	if let Some(madt) = get_table(b"APIC").and_then(|table| parse_madt(&table)) {
+		info!(
+			"Local APIC at 0x{:x}, {} processor(s), 8259 PICs {}",
+			madt.local_apic_address,
+			madt.processors.len(),
+			if madt.legacy_pics {
+				"present"
+			} else {
+				"absent"
+			}
+		);
+		for cpu in madt.processors.iter() {
+			info!(
+				"CPU {}: APIC ID {}{}",
+				cpu.acpi_id,
+				cpu.apic_id,
+				if cpu.enabled { "" } else { " (disabled)" }
+			);
+		}
+		for ioapic in madt.io_apics.iter() {
+			info!(
+				"I/O APIC {} at 0x{:x}, first GSI {}",
+				ioapic.id, ioapic.address, ioapic.gsi_base
+			);
+		}
+		for entry in madt.overrides.iter() {
+			info!(
+				"IRQ {} is mapped to GSI {} (flags 0x{:x})",
+				entry.source, entry.gsi, entry.flags
+			);
+		}
+		*MADT.lock() = Some(madt);
+	}
+
+	if let Some(hpet) = get_table(b"HPET").and_then(|table| parse_hpet(&table)) {
+		info!(
+			"HPET {} at 0x{:x} with {} comparators ({} bit counter, minimum tick {})",
+			hpet.number,
+			hpet.address,
+			hpet.comparators,
+			if hpet.counter_64bit { 64 } else { 32 },
+			hpet.min_tick
+		);
+		*HPET.lock() = Some(hpet);
+	}
+}
@@ -1,5 +1,9 @@
 use crate::arch::mm::get_boot_stack;
+#[cfg(target_arch = "x86_64")]
+use crate::arch::mm::Aligned;
 use crate::arch::mm::VirtAddr;
+#[cfg(target_arch = "x86_64")]
+use crate::consts::INTERRUPT_STACK_SIZE;
 use crate::scheduler;
 use crate::scheduler::task::Stack;
 use core::mem;
@@ -29,6 +33,16 @@ const TSS_ENTRIES: usize = 2;
 const TSS_ENTRIES: usize = 1;
 const GDT_ENTRIES: usize = GDT_FIRST_TSS + TSS_ENTRIES;
 
+/// Index of the interrupt stack table entry, which is used by the double
+/// fault handler. The handler requires an own stack to report an overflow
+/// of the kernel stack.
+#[cfg(target_arch = "x86_64")]
+pub(crate) const DOUBLE_FAULT_IST_INDEX: u8 = 1;
+
+#[cfg(target_arch = "x86_64")]
+static mut DOUBLE_FAULT_STACK: Aligned<[u8; INTERRUPT_STACK_SIZE]> =
+	Aligned::new([0; INTERRUPT_STACK_SIZE]);
+
 // thread_local on a static mut, signals that the value of this static may
 // change depending on the current thread.
 static mut GDT: [Descriptor; GDT_ENTRIES] = [Descriptor::NULL; GDT_ENTRIES];
@@ -148,6 +162,8 @@ pub(crate) fn init() {
 				));
 
 			TSS.0.rsp[0] = get_boot_stack().interrupt_top().into();
+			TSS.0.ist[usize::from(DOUBLE_FAULT_IST_INDEX) - 1] =
+				(&raw const DOUBLE_FAULT_STACK as usize + INTERRUPT_STACK_SIZE - 16) as u64;
 		}
 		#[cfg(target_arch = "x86")]
 		{
@@ -1,10 +1,14 @@
+use crate::arch::x86::kernel::gdt::DOUBLE_FAULT_IST_INDEX;
 use crate::arch::x86::mm::paging::page_fault_handler;
+use crate::arch::x86::mm::VirtAddr;
 use crate::logging::*;
 use crate::scheduler::*;
 use crate::synch::spinlock::*;
 use core::arch::asm;
 use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
 use x86::bits64::paging::VAddr;
+use x86::controlregs::cr2;
 use x86::dtables::{lidt, DescriptorTablePointer};
 use x86::io::*;
 use x86::segmentation::{SegmentSelector, SystemDescriptorTypes64};
@@ -13,6 +17,47 @@ use x86::Ring;
 /// Maximum possible number of interrupts
 const IDT_ENTRIES: usize = 256;
 const KERNEL_CODE_SELECTOR: SegmentSelector = SegmentSelector::new(1, Ring::Ring0);
+/// Interrupt vector of the page fault exception
+pub(crate) const PAGE_FAULT_VECTOR: usize = 14;
+/// Interrupt vector of the timer (IRQ0)
+const TIMER_VECTOR: usize = 32;
+/// Interrupt vector of the primary serial port (IRQ4)
+const SERIAL_VECTOR: usize = 36;
+
+/// Number of received interrupts per vector
+static INTERRUPT_COUNTERS: [AtomicU64; IDT_ENTRIES] = [const { AtomicU64::new(0) }; IDT_ENTRIES];
+
+/// Count an interrupt, which is received on the vector `int_no`
+pub(crate) fn increment_irq_counter(int_no: usize) {
+	INTERRUPT_COUNTERS[int_no].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the vector, the description and the number of
+/// received interrupts for each vector, which has been used
+pub fn get_irq_counters() -> alloc::vec::Vec<(usize, &'static str, u64)> {
+	INTERRUPT_COUNTERS
+		.iter()
+		.enumerate()
+		.map(|(int_no, counter)| (int_no, counter.load(Ordering::Relaxed)))
+		.filter(|(_, count)| *count > 0)
+		.map(|(int_no, count)| (int_no, get_irq_name(int_no), count))
+		.collect()
+}
+
+/// Returns a short description of the interrupt vector `int_no`
+fn get_irq_name(int_no: usize) -> &'static str {
+	match int_no {
+		PAGE_FAULT_VECTOR => "page fault",
+		TIMER_VECTOR => "timer",
+		SERIAL_VECTOR => "serial",
+		_ => "",
+	}
+}
+
+/// Returns the number of timer interrupts since the boot
+pub fn get_timer_ticks() -> u64 {
+	INTERRUPT_COUNTERS[TIMER_VECTOR].load(Ordering::Relaxed)
+}
 
 /// Enable Interrupts
 pub fn irq_enable() {
@@ -178,12 +223,25 @@ extern "x86-interrupt" fn double_fault_exception(
 	stack_frame: ExceptionStackFrame,
 	error_code: u64,
 ) {
-	info!(
-		"Task {} receive a Double Fault Exception: {:#?}, error_code {}",
-		get_current_taskid(),
-		stack_frame,
-		error_code
-	);
+	// A page fault in a guard page isn't able to store its stack
+	// frame => the double fault handler runs on its own stack
+	let virtual_address = unsafe { VirtAddr::from_usize(cr2()) };
+
+	if is_guard_page(virtual_address) {
+		error!(
+			"stack overflow in task {}: {:#?}, address {:#X}",
+			get_current_taskid(),
+			stack_frame,
+			virtual_address
+		);
+	} else {
+		info!(
+			"Task {} receive a Double Fault Exception: {:#?}, error_code {}",
+			get_current_taskid(),
+			stack_frame,
+			error_code
+		);
+	}
 	send_eoi_to_master();
 	abort();
 }
@@ -327,8 +385,37 @@ extern "x86-interrupt" fn timer_handler(stack_frame: ExceptionStackFrame) {
 		stack_frame
 	);
 
+	increment_irq_counter(TIMER_VECTOR);
 	send_eoi_to_master();
 	schedule();
+
+	// the interrupted task doesn't hold any kernel lock,
+	// if it returns to user space
+	if stack_frame.code_segment & 0x3 == 0x3 {
+		exit_if_killed();
+	}
+}
+
+/// Key, which triggers a list of all tasks (Ctrl-T, similar to SIGINFO on BSD)
+#[cfg(not(feature = "vga"))]
+const PS_HOTKEY: u8 = 0x14;
+
+#[cfg(not(feature = "vga"))]
+extern "x86-interrupt" fn serial_handler(_stack_frame: ExceptionStackFrame) {
+	increment_irq_counter(SERIAL_VECTOR);
+
+	loop {
+		// release the serial port before printing the task list
+		let byte = crate::arch::serial::COM1.lock().read_byte();
+
+		match byte {
+			Some(PS_HOTKEY) => dump_tasks(),
+			Some(byte) => crate::arch::serial::COM1_INPUT.lock().push(byte),
+			None => break,
+		}
+	}
+
+	send_eoi_to_master();
 }
 
 /// An interrupt gate descriptor.
@@ -531,7 +618,7 @@ impl InteruptHandler {
 			KERNEL_CODE_SELECTOR,
 			Ring::Ring0,
 			Type::InterruptGate,
-			0,
+			DOUBLE_FAULT_IST_INDEX,
 		);
 		self.idt[9] = IdtEntry::new(
 			VAddr::from_usize(overrun_exception as usize),
@@ -568,7 +655,7 @@ impl InteruptHandler {
 			Type::InterruptGate,
 			0,
 		);
-		self.idt[14] = IdtEntry::new(
+		self.idt[PAGE_FAULT_VECTOR] = IdtEntry::new(
 			VAddr::from_usize(page_fault_handler as usize),
 			KERNEL_CODE_SELECTOR,
 			Ring::Ring0,
@@ -612,7 +699,7 @@ impl InteruptHandler {
 				0,
 			);
 		}
-		self.idt[32] = IdtEntry::new(
+		self.idt[TIMER_VECTOR] = IdtEntry::new(
 			VAddr::from_usize(timer_handler as usize),
 			KERNEL_CODE_SELECTOR,
 			Ring::Ring0,
@@ -630,6 +717,17 @@ impl InteruptHandler {
 				0,
 			);
 		}
+		// IRQ4 => primary serial port
+		#[cfg(not(feature = "vga"))]
+		{
+			self.idt[SERIAL_VECTOR] = IdtEntry::new(
+				VAddr::from_usize(serial_handler as usize),
+				KERNEL_CODE_SELECTOR,
+				Ring::Ring0,
+				Type::InterruptGate,
+				0,
+			);
+		}
 		// send  eoi to the master and to the slave
 		for i in 40..IDT_ENTRIES {
 			self.idt[i] = IdtEntry::new(
@@ -1,7 +1,10 @@
-mod gdt;
+#[cfg(target_arch = "x86_64")]
+pub mod acpi;
+pub(crate) mod gdt;
 pub mod irq;
 mod pit;
 pub(crate) mod processor;
+pub mod rtc;
 #[cfg(not(feature = "vga"))]
 pub(crate) mod serial;
 #[cfg(target_arch = "x86_64")]
@@ -13,7 +16,7 @@ pub(crate) mod task;
 pub(crate) mod vga;
 
 use crate::arch::x86::kernel::syscall::syscall_handler;
-use crate::consts::USER_ENTRY;
+use crate::consts::{USER_ENTRY, USER_STACK_TOP};
 use bootloader::BootInfo;
 use core::arch::{asm, naked_asm};
 
@@ -32,14 +35,12 @@ unsafe extern "C" fn __jump_to_user_land(ds: usize, stack: usize, cs: usize, ent
 ///
 /// # Safety
 ///
-/// Be sure the the user-level function mapped into the user space.
-pub(crate) unsafe fn jump_to_user_land(func: usize) -> ! {
-	__jump_to_user_land(
-		0x23,
-		USER_ENTRY.as_usize() + 0x400000usize,
-		0x2b,
-		USER_ENTRY.as_usize() | func,
-	)
+/// Be sure the the user-level function mapped into the user space
+/// and that `stack` is a valid user-level stack pointer.
+pub(crate) unsafe fn jump_to_user_land(func: usize, stack: usize) -> ! {
+	debug_assert!(stack <= USER_STACK_TOP.as_usize());
+
+	__jump_to_user_land(0x23, stack, 0x2b, USER_ENTRY.as_usize() | func)
 }
 
 pub fn register_task() {
@@ -256,6 +257,10 @@ pub(crate) fn init() {
 	gdt::init();
 	irq::init();
 	pit::init();
+	rtc::init();
+
+	#[cfg(not(feature = "vga"))]
+	serial::init();
 
 	#[cfg(feature = "vga")]
 	vga::init();
@@ -8,6 +8,8 @@ use qemu_exit::QEMUExit;
 use x86::controlregs::*;
 use x86::cpuid::*;
 use x86::msr::*;
+use x86::random::rdrand32;
+use x86::time::rdtsc;
 
 // MSR EFER bits
 #[allow(dead_code)]
@@ -30,6 +32,15 @@ const EFER_TCE: u64 = 1 << 15;
 static mut PHYSICAL_ADDRESS_BITS: u8 = 0;
 static mut LINEAR_ADDRESS_BITS: u8 = 0;
 static mut SUPPORTS_1GIB_PAGES: bool = false;
+static mut SUPPORTS_RDRAND: bool = false;
+
+/// Command port of the keyboard controller
+const KBD_STATUS_PORT: u16 = 0x64;
+/// Command to pulse the reset line of the processor
+const KBD_RESET_CPU: u8 = 0xFE;
+
+/// Number of attempts to receive a random number by `rdrand`
+const RDRAND_RETRIES: usize = 10;
 
 /// Force strict CPU ordering, serializes load and store operations.
 #[inline(always)]
@@ -60,6 +71,32 @@ pub(crate) extern "C" fn shutdown(error_code: i32) -> ! {
 	}
 
 	#[cfg(not(feature = "qemu-exit"))]
+	poweroff()
+}
+
+/// Turn the machine off. If ACPI doesn't support the power-off,
+/// the processor is halted.
+pub(crate) fn poweroff() -> ! {
+	#[cfg(target_arch = "x86_64")]
+	crate::arch::acpi::poweroff();
+
+	loop {
+		unsafe {
+			x86::halt();
+		}
+	}
+}
+
+/// Reset the machine by the reset register of the FADT. As fallback,
+/// the reset line of the keyboard controller is pulsed.
+pub(crate) fn reboot() -> ! {
+	#[cfg(target_arch = "x86_64")]
+	crate::arch::acpi::reset();
+
+	unsafe {
+		x86::io::outb(KBD_STATUS_PORT, KBD_RESET_CPU);
+	}
+
 	loop {
 		unsafe {
 			x86::halt();
@@ -71,6 +108,35 @@ pub(crate) fn supports_1gib_pages() -> bool {
 	unsafe { SUPPORTS_1GIB_PAGES }
 }
 
+pub(crate) fn supports_rdrand() -> bool {
+	unsafe { SUPPORTS_RDRAND }
+}
+
+/// Returns a random number of the hardware random number generator,
+/// if the processor supports the instruction `rdrand`
+pub(crate) fn get_random() -> Option<u64> {
+	if !supports_rdrand() {
+		return None;
+	}
+
+	// the instruction fails temporarily, if no entropy is available
+	for _ in 0..RDRAND_RETRIES {
+		let mut low: u32 = 0;
+		let mut high: u32 = 0;
+
+		if unsafe { rdrand32(&mut low) && rdrand32(&mut high) } {
+			return Some((u64::from(high) << 32) | u64::from(low));
+		}
+	}
+
+	None
+}
+
+/// Returns the current value of the time stamp counter
+pub(crate) fn get_timestamp() -> u64 {
+	unsafe { rdtsc() }
+}
+
 pub(crate) fn get_linear_address_bits() -> u8 {
 	unsafe { LINEAR_ADDRESS_BITS }
 }
@@ -167,6 +233,9 @@ pub(crate) fn init() {
 			.get_extended_processor_and_feature_identifiers()
 			.expect("CPUID Extended Processor and Feature Info is not available!")
 			.has_1gib_pages();
+		SUPPORTS_RDRAND = cpuid
+			.get_feature_info()
+			.is_some_and(|info| info.has_rdrand());
 	}
 
 	if supports_1gib_pages() {
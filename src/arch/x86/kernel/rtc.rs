@@ -0,0 +1,121 @@
+//! Reads the wall-clock time from the real-time clock (RTC) of the CMOS
+
+use crate::logging::*;
+use crate::time::days_from_civil;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86::io::*;
+
+/// Port to select a register of the CMOS
+const CMOS_ADDRESS: u16 = 0x70;
+/// Port to read the selected register of the CMOS
+const CMOS_DATA: u16 = 0x71;
+/// Disables the non-maskable interrupt while a register is selected
+const NMI_DISABLE: u8 = 0x80;
+
+const RTC_SECONDS: u8 = 0x00;
+const RTC_MINUTES: u8 = 0x02;
+const RTC_HOURS: u8 = 0x04;
+const RTC_DAY_OF_MONTH: u8 = 0x07;
+const RTC_MONTH: u8 = 0x08;
+const RTC_YEAR: u8 = 0x09;
+const RTC_STATUS_A: u8 = 0x0A;
+const RTC_STATUS_B: u8 = 0x0B;
+
+/// Status A: the RTC is updating its registers
+const UPDATE_IN_PROGRESS: u8 = 0x80;
+/// Status B: the registers are binary encoded instead of BCD
+const BINARY_MODE: u8 = 0x04;
+/// Status B: the hours are given in the 24 hour format
+const HOUR_FORMAT_24: u8 = 0x02;
+/// In the 12 hour format, the highest bit of the hours marks PM
+const HOUR_PM: u8 = 0x80;
+
+/// Seconds since the Unix epoch at the boot time
+static BOOT_TIME: AtomicU64 = AtomicU64::new(0);
+
+fn read_register(reg: u8) -> u8 {
+	unsafe {
+		outb(CMOS_ADDRESS, NMI_DISABLE | reg);
+		inb(CMOS_DATA)
+	}
+}
+
+/// Returns the raw values of second, minute, hour, day, month and year
+fn read_date_time() -> [u8; 6] {
+	while read_register(RTC_STATUS_A) & UPDATE_IN_PROGRESS != 0 {
+		core::hint::spin_loop();
+	}
+
+	[
+		read_register(RTC_SECONDS),
+		read_register(RTC_MINUTES),
+		read_register(RTC_HOURS),
+		read_register(RTC_DAY_OF_MONTH),
+		read_register(RTC_MONTH),
+		read_register(RTC_YEAR),
+	]
+}
+
+const fn bcd_to_binary(value: u8) -> u8 {
+	(value & 0x0F) + (value >> 4) * 10
+}
+
+/// Returns the seconds since the Unix epoch, which are stored in the RTC
+fn read_time() -> u64 {
+	// an update may happen between the reads of two registers,
+	// consequently we read the registers until they are stable
+	let mut values = read_date_time();
+	loop {
+		let next = read_date_time();
+		if next == values {
+			break;
+		}
+		values = next;
+	}
+
+	let status = read_register(RTC_STATUS_B);
+	let pm = values[2] & HOUR_PM != 0;
+	values[2] &= !HOUR_PM;
+
+	if status & BINARY_MODE == 0 {
+		for value in values.iter_mut() {
+			*value = bcd_to_binary(*value);
+		}
+	}
+
+	let [second, minute, mut hour, day, month, year] = values;
+	if status & HOUR_FORMAT_24 == 0 {
+		// 12 AM is midnight and 12 PM is noon
+		hour %= 12;
+		if pm {
+			hour += 12;
+		}
+	}
+
+	// the century register isn't standardized, we assume
+	// years between 1970 and 2069
+	let year = if year < 70 {
+		2000 + i64::from(year)
+	} else {
+		1900 + i64::from(year)
+	};
+
+	let days = days_from_civil(year, i64::from(month), i64::from(day));
+	let seconds =
+		days * 86400 + i64::from(hour) * 3600 + i64::from(minute) * 60 + i64::from(second);
+
+	seconds.max(0) as u64
+}
+
+/// Returns the seconds since the Unix epoch at the boot time
+pub fn get_boot_time() -> u64 {
+	BOOT_TIME.load(Ordering::Relaxed)
+}
+
+/// Read the wall-clock time, must be called once, and only once
+pub(crate) fn init() {
+	let time = read_time();
+	BOOT_TIME.store(time, Ordering::Relaxed);
+
+	debug!("boot time: {} seconds since the epoch", time);
+}
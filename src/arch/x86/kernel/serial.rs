@@ -15,6 +15,29 @@ impl ComPort {
 		Self { base_addr }
 	}
 
+	/// Enable the interrupt, which signals received data.
+	pub fn enable_receive_interrupt(&mut self) {
+		unsafe {
+			// Interrupt Enable Register: received data available
+			outb(self.base_addr + 1, 0x01);
+			// Modem Control Register: DTR, RTS and OUT2, which
+			// connects the interrupt line to the PIC
+			outb(self.base_addr + 4, 0x0B);
+		}
+	}
+
+	/// Returns a received byte, if one is available.
+	pub fn read_byte(&mut self) -> Option<u8> {
+		unsafe {
+			// Line Status Register: data ready
+			if inb(self.base_addr + 5) & 0x01 != 0 {
+				Some(inb(self.base_addr))
+			} else {
+				None
+			}
+		}
+	}
+
 	pub fn write_bytes(&mut self, buf: &[u8]) {
 		unsafe {
 			// Output each byte of our string.
@@ -42,5 +65,57 @@ impl fmt::Write for ComPort {
 	}
 }
 
+/// Size of the buffer, which stores received bytes
+const INPUT_BUFFER_SIZE: usize = 256;
+
+/// Ring buffer of received bytes, which aren't consumed yet
+pub(crate) struct InputBuffer {
+	buffer: [u8; INPUT_BUFFER_SIZE],
+	/// index of the oldest byte
+	head: usize,
+	/// number of stored bytes
+	len: usize,
+}
+
+impl InputBuffer {
+	const fn new() -> Self {
+		Self {
+			buffer: [0; INPUT_BUFFER_SIZE],
+			head: 0,
+			len: 0,
+		}
+	}
+
+	/// Store a received byte. If the buffer is full, the byte is dropped.
+	pub fn push(&mut self, byte: u8) {
+		if self.len < INPUT_BUFFER_SIZE {
+			self.buffer[(self.head + self.len) % INPUT_BUFFER_SIZE] = byte;
+			self.len += 1;
+		}
+	}
+
+	/// Returns the oldest received byte
+	pub fn pop(&mut self) -> Option<u8> {
+		if self.len > 0 {
+			let byte = self.buffer[self.head];
+			self.head = (self.head + 1) % INPUT_BUFFER_SIZE;
+			self.len -= 1;
+
+			Some(byte)
+		} else {
+			None
+		}
+	}
+}
+
 /// Our primary serial port.
 pub(crate) static COM1: SpinlockIrqSave<ComPort> = SpinlockIrqSave::new(ComPort::new(0x3F8));
+
+/// Bytes, which are received by the primary serial port
+pub(crate) static COM1_INPUT: SpinlockIrqSave<InputBuffer> =
+	SpinlockIrqSave::new(InputBuffer::new());
+
+/// Initialize the primary serial port to receive data.
+pub(crate) fn init() {
+	COM1.lock().enable_receive_interrupt();
+}
@@ -21,7 +21,6 @@ unsafe fn bss_init() {
 	);
 }
 
-#[cfg(not(test))]
 unsafe extern "C" fn entry() -> ! {
 	arch::init();
 
@@ -33,7 +32,6 @@ unsafe extern "C" fn entry() -> ! {
 	shutdown(ret)
 }
 
-#[cfg(not(test))]
 #[cfg(target_arch = "x86_64")]
 #[no_mangle]
 pub unsafe extern "C" fn _start(boot_info: &'static bootloader::BootInfo) -> ! {
@@ -42,7 +40,6 @@ pub unsafe extern "C" fn _start(boot_info: &'static bootloader::BootInfo) -> ! {
 	entry();
 }
 
-#[cfg(not(test))]
 #[cfg(target_arch = "x86")]
 #[no_mangle]
 #[unsafe(naked)]
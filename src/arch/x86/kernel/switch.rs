@@ -75,6 +75,11 @@ pub(crate) unsafe extern "C" fn switch(_old_stack: *mut VirtAddr, _new_stack: Vi
 		"rdgsbase rdx",
 		"push rax",
 		"push rdx",
+		// Store the inactive GS base, which `swapgs` swaps in
+		"swapgs",
+		"rdgsbase rax",
+		"swapgs",
+		"push rax",
 		// Store the old `rsp` behind `old_stack`
 		"mov [rdi], rsp",
 		// Set `rsp` to `new_stack`
@@ -86,6 +91,10 @@ pub(crate) unsafe extern "C" fn switch(_old_stack: *mut VirtAddr, _new_stack: Vi
 		// set stack pointer in TSS
 		"call {set_stack}",
 		"pop r15",
+		"swapgs",
+		"wrgsbase r15",
+		"swapgs",
+		"pop r15",
 		"wrgsbase r15",
 		"pop r15",
 		"wrfsbase r15",
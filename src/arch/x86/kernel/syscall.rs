@@ -1,6 +1,70 @@
 use crate::syscall::SYSHANDLER_TABLE;
 use core::arch::naked_asm;
 
+/// Registers of a user-level task, which the syscall handler
+/// stores on the user stack.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub(crate) struct SyscallUserFrame {
+	/// user-level status flags
+	pub r11: u64,
+	pub r10: u64,
+	pub r9: u64,
+	pub r8: u64,
+	pub rdi: u64,
+	pub rsi: u64,
+	pub rdx: u64,
+	/// user-level instruction pointer
+	pub rcx: u64,
+}
+
+/// Registers of a user-level task, which the syscall handler
+/// stores on the kernel stack. The frame ends directly below
+/// the top of the kernel stack.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub(crate) struct SyscallKernelFrame {
+	pub r15: u64,
+	pub r14: u64,
+	pub r13: u64,
+	pub r12: u64,
+	pub rbp: u64,
+	pub rbx: u64,
+	/// user-level stack pointer, which points to a `SyscallUserFrame`
+	pub rsp: u64,
+}
+
+/// Restore the user-level context, which is stored on the kernel
+/// stack and on the user stack, and return to user space.
+/// `rax` holds the return value of the system call.
+macro_rules! syscall_epilogue {
+	() => {
+		concat!(
+			r#"
+			cli
+			pop r15
+			pop r14
+			pop r13
+			pop r12
+			pop rbp
+			pop rbx
+			pop rcx
+			mov rsp, rcx
+			swapgs
+			pop r11
+			pop r10
+			pop r9
+			pop r8
+			pop rdi
+			pop rsi
+			pop rdx
+			pop rcx
+			sysretq
+			"#
+		)
+	};
+}
+
 /// Helper function to save and to restore the register states
 /// during a system call. `rax` is the system call identifier.
 /// The identifier is used to determine the address of the function,
@@ -22,24 +86,28 @@ pub(crate) extern "C" fn syscall_handler() {
 		"mov rcx, rsp",
 		"rdgsbase rsp",
 		"push rcx",
+		// save callee-saved registers to reconstruct the
+		// complete user-level context (e.g. for clone)
+		"push rbx",
+		"push rbp",
+		"push r12",
+		"push r13",
+		"push r14",
+		"push r15",
 		// copy 4th argument to rcx to adhere x86_64 ABI
 		"mov rcx, r10",
 		"sti",
 		"call [{sys_handler}+8*rax]",
 		// restore context, see x86_64 ABI
-		"cli",
-		// switch to user stack
-		"pop rcx",
-		"mov rsp, rcx",
-		"swapgs",
-		"pop r11",
-		"pop r10",
-		"pop r9",
-		"pop r8",
-		"pop rdi",
-		"pop rsi",
-		"pop rdx",
-		"pop rcx",
-		"sysretq",
+		// and switch to user stack
+		syscall_epilogue!(),
 		sys_handler = sym SYSHANDLER_TABLE);
 }
+
+/// Entry point of a new thread, which is created by `clone`.
+/// The kernel stack of the new thread contains a copy of the
+/// `SyscallKernelFrame` of its parent, while `rax` is already zero.
+#[unsafe(naked)]
+pub(crate) extern "C" fn ret_from_clone() {
+	naked_asm!(syscall_epilogue!());
+}
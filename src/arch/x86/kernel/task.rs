@@ -1,16 +1,24 @@
 //! Architecture dependent interface to initialize a task
 
 use crate::arch::mm::VirtAddr;
+#[cfg(target_arch = "x86_64")]
+use crate::arch::x86::kernel::syscall::{ret_from_clone, SyscallKernelFrame, SyscallUserFrame};
 use crate::consts::*;
 use crate::logging::*;
 use crate::scheduler::task::*;
 use crate::scheduler::{do_exit, get_current_taskid};
 use core::mem::size_of;
 use core::ptr::write_bytes;
+#[cfg(target_arch = "x86_64")]
+use x86::bits64::segmentation::rdfsbase;
+#[cfg(target_arch = "x86_64")]
+use x86::msr::{rdmsr, IA32_KERNEL_GSBASE};
 
 #[cfg(target_arch = "x86_64")]
 #[repr(C, packed)]
 struct State {
+	/// inactive GS register, which will be activated by `swapgs`
+	shadow_gs: u64,
 	/// GS register
 	gs: u64,
 	/// FS register
@@ -86,18 +94,22 @@ extern "C" fn leave_task() -> ! {
 
 impl TaskFrame for Task {
 	#[cfg(target_arch = "x86_64")]
-	fn create_stack_frame(&mut self, func: extern "C" fn()) {
+	fn create_stack_frame(&mut self, func: extern "C" fn(usize), arg: usize) {
 		unsafe {
 			let mut stack: *mut u64 = ((*self.stack).top()).as_mut_ptr();
 
-			write_bytes((*self.stack).bottom().as_mut_ptr::<u8>(), 0xCD, STACK_SIZE);
+			write_bytes(
+				(*self.stack).bottom().as_mut_ptr::<u8>(),
+				STACK_PATTERN,
+				STACK_SIZE,
+			);
 
 			/* Only marker for debugging purposes, ... */
 			*stack = 0xDEADBEEFu64;
 			stack = (stack as usize - size_of::<u64>()) as *mut u64;
 
-			/* the first-function-to-be-called's arguments, ... */
-			//TODO: add arguments
+			/* the first-function-to-be-called's arguments are passed
+			 * by register (see below), ... */
 
 			/* and the "caller" we shall return to.
 			 * This procedure cleans the task after exit. */
@@ -113,6 +125,70 @@ impl TaskFrame for Task {
 
 			(*state).rip = (func as *const ()) as u64;
 			(*state).rflags = 0x1202u64;
+			/* the first argument is passed by rdi, see x86_64 ABI */
+			(*state).rdi = arg as u64;
+
+			/* Set the task's stack pointer entry to the stack we have crafted right now. */
+			self.last_stack_pointer = VirtAddr(stack as u64);
+		}
+	}
+
+	#[cfg(target_arch = "x86_64")]
+	fn create_thread_frame(
+		&mut self,
+		parent_stack: VirtAddr,
+		user_stack: VirtAddr,
+		tls: Option<VirtAddr>,
+	) {
+		unsafe {
+			/* The syscall handler of the parent stores its user-level context
+			 * directly below the top of its kernel stack. */
+			let parent_frame = (parent_stack.as_usize() - size_of::<SyscallKernelFrame>())
+				as *const SyscallKernelFrame;
+			let parent_user_frame = (*parent_frame).rsp as *const SyscallUserFrame;
+
+			/* Without a new stack, the thread continues on the stack of its parent. */
+			let user_stack = if user_stack.is_zero() {
+				parent_user_frame as usize + size_of::<SyscallUserFrame>()
+			} else {
+				user_stack.as_usize()
+			};
+
+			/* Copy the caller-saved registers of the parent to the user stack of the thread, ... */
+			let user_frame = (user_stack - size_of::<SyscallUserFrame>()) as *mut SyscallUserFrame;
+			*user_frame = *parent_user_frame;
+
+			let mut stack: *mut u64 = ((*self.stack).top()).as_mut_ptr();
+
+			write_bytes(
+				(*self.stack).bottom().as_mut_ptr::<u8>(),
+				STACK_PATTERN,
+				STACK_SIZE,
+			);
+
+			/* Only marker for debugging purposes, ... */
+			*stack = 0xDEADBEEFu64;
+
+			/* the callee-saved registers of the parent, ... */
+			let frame =
+				(stack as usize - size_of::<SyscallKernelFrame>()) as *mut SyscallKernelFrame;
+			*frame = *parent_frame;
+			(*frame).rsp = user_frame as u64;
+			stack = (frame as usize - size_of::<State>()) as *mut u64;
+
+			/* and the state, which the function "switch" restores. */
+			let state: *mut State = stack as *mut State;
+			write_bytes(state, 0x00, 1);
+
+			(*state).rsp = frame as u64;
+			(*state).gs = ((*self.stack).top()).as_u64();
+			/* During a system call, the user-level GS register is inactive. */
+			(*state).shadow_gs = rdmsr(IA32_KERNEL_GSBASE);
+			(*state).fs = tls.map_or_else(|| rdfsbase(), |addr| addr.as_u64());
+
+			/* The thread starts with the return from the system call, where rax is zero. */
+			(*state).rip = (ret_from_clone as *const ()) as u64;
+			(*state).rflags = 0x1202u64;
 
 			/* Set the task's stack pointer entry to the stack we have crafted right now. */
 			self.last_stack_pointer = VirtAddr(stack as u64);
@@ -120,18 +196,24 @@ impl TaskFrame for Task {
 	}
 
 	#[cfg(target_arch = "x86")]
-	fn create_stack_frame(&mut self, func: extern "C" fn()) {
+	fn create_stack_frame(&mut self, func: extern "C" fn(usize), arg: usize) {
 		unsafe {
 			let mut stack: *mut u32 = ((*self.stack).top()).as_mut_ptr();
 
-			write_bytes((*self.stack).bottom().as_mut_ptr::<u8>(), 0xCD, STACK_SIZE);
+			write_bytes(
+				(*self.stack).bottom().as_mut_ptr::<u8>(),
+				STACK_PATTERN,
+				STACK_SIZE,
+			);
 
 			/* Only marker for debugging purposes, ... */
 			*stack = 0xDEADBEEFu32;
 			stack = (stack as usize - size_of::<u32>()) as *mut u32;
 
-			/* the first-function-to-be-called's arguments, ... */
-			//TODO: add arguments
+			/* the first-function-to-be-called's arguments, which
+			 * are passed on the stack (see i386 ABI), ... */
+			*stack = arg as u32;
+			stack = (stack as usize - size_of::<u32>()) as *mut u32;
 
 			/* and the "caller" we shall return to.
 			 * This procedure cleans the task after exit. */
@@ -151,4 +233,14 @@ impl TaskFrame for Task {
 			self.last_stack_pointer = stack as usize;
 		}
 	}
+
+	#[cfg(target_arch = "x86")]
+	fn create_thread_frame(
+		&mut self,
+		_parent_stack: VirtAddr,
+		_user_stack: VirtAddr,
+		_tls: Option<VirtAddr>,
+	) {
+		panic!("User-level threads are only supported on x86_64");
+	}
 }
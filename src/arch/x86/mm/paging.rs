@@ -395,6 +395,8 @@ trait PageTableMethods {
 		physical_address: PhysAddr,
 		flags: PageTableEntryFlags,
 	) -> bool;
+	fn unmap_page_in_this_table<S: PageSize>(&mut self, page: Page<S>);
+	fn unmap_page<S: PageSize>(&mut self, page: Page<S>);
 	fn drop_user_space(&mut self);
 }
 
@@ -425,6 +427,19 @@ impl<L: PageTableLevel> PageTableMethods for PageTable<L> {
 		flush
 	}
 
+	/// Removes the mapping of a single page in this table.
+	///
+	/// Must only be called if a page of this size is mapped at this page table level!
+	fn unmap_page_in_this_table<S: PageSize>(&mut self, page: Page<S>) {
+		assert!(L::LEVEL == S::MAP_LEVEL);
+		let index = page.table_index::<L>();
+
+		if self.entries[index].is_present() {
+			self.entries[index].physical_address_and_flags = PhysAddr::zero();
+			page.flush_from_tlb();
+		}
+	}
+
 	/// Returns the PageTableEntry for the given page if it is present, otherwise returns None.
 	///
 	/// This is the default implementation called only for PT.
@@ -469,6 +484,14 @@ impl<L: PageTableLevel> PageTableMethods for PageTable<L> {
 	) -> bool {
 		self.map_page_in_this_table::<S>(page, physical_address, flags)
 	}
+
+	/// Removes the mapping of a single page.
+	///
+	/// This is the default implementation that just calls the unmap_page_in_this_table method.
+	/// It is overridden by a specialized implementation for all tables with sub tables (all except PT).
+	default fn unmap_page<S: PageSize>(&mut self, page: Page<S>) {
+		self.unmap_page_in_this_table::<S>(page)
+	}
 }
 
 impl<L: PageTableLevelWithSubtables> PageTableMethods for PageTable<L>
@@ -564,6 +587,26 @@ where
 			self.map_page_in_this_table::<S>(page, physical_address, flags)
 		}
 	}
+
+	/// Removes the mapping of a single page.
+	///
+	/// This is the implementation for all tables with subtables (PML4, PDPT, PDT).
+	/// It overrides the default implementation above.
+	fn unmap_page<S: PageSize>(&mut self, page: Page<S>) {
+		assert!(L::LEVEL >= S::MAP_LEVEL);
+
+		if L::LEVEL > S::MAP_LEVEL {
+			let index = page.table_index::<L>();
+
+			// Without a subtable, the page isn't mapped.
+			if self.entries[index].is_present() {
+				let subtable = self.subtable::<S>(page);
+				subtable.unmap_page::<S>(page);
+			}
+		} else {
+			self.unmap_page_in_this_table::<S>(page);
+		}
+	}
 }
 
 impl<L: PageTableLevelWithSubtables> PageTable<L>
@@ -605,6 +648,13 @@ where
 		}
 	}
 
+	/// Removes the mapping of a continuous range of pages.
+	fn unmap_pages<S: PageSize>(&mut self, range: PageIter<S>) {
+		for page in range {
+			self.unmap_page(page);
+		}
+	}
+
 	fn drop_user_space(&mut self) {
 		assert!(L::LEVEL == PML4::LEVEL);
 
@@ -635,8 +685,10 @@ pub(crate) extern "x86-interrupt" fn page_fault_handler(
 ) {
 	let mut virtual_address = unsafe { VirtAddr::from_usize(controlregs::cr2()) };
 
+	irq::increment_irq_counter(irq::PAGE_FAULT_VECTOR);
+
 	// do we have to create the user-space stack?
-	if virtual_address > USER_ENTRY + 0x400000u64 - 64u64 * 1024u64 {
+	if virtual_address > USER_STACK_TOP - USER_STACK_SIZE {
 		virtual_address = align_down!(virtual_address, BasePageSize::SIZE);
 
 		// Ok, user space want to have memory
@@ -670,6 +722,9 @@ pub(crate) extern "x86-interrupt" fn page_fault_handler(
 		// Anything else is an error!
 		let pferror = PageFaultError::from_bits_truncate(error_code as u32);
 
+		if scheduler::is_guard_page(virtual_address) {
+			error!("stack overflow in task {}", scheduler::get_current_taskid());
+		}
 		error!("Page Fault (#PF) Exception: {:#?}", stack_frame);
 		error!(
 			"virtual_address = {:#X}, page fault error = {}",
@@ -731,7 +786,7 @@ pub(crate) fn unmap<S: PageSize>(virtual_address: VirtAddr, count: usize) {
 
 	let range = get_page_range::<S>(virtual_address, count);
 	let root_pagetable = unsafe { &mut *PML4_ADDRESS };
-	root_pagetable.map_pages(range, PhysAddr::zero(), PageTableEntryFlags::BLANK);
+	root_pagetable.unmap_pages(range);
 }
 
 pub(crate) fn map<S: PageSize>(
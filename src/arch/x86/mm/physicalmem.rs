@@ -29,6 +29,18 @@ pub(crate) fn init() {
 	}
 }
 
+/// Returns the number of bytes, which are still available
+pub fn get_free_size() -> usize {
+	let _preemption = DisabledPreemption::new();
+	unsafe {
+		PHYSICAL_FREE_LIST
+			.list
+			.iter()
+			.map(|entry| entry.end.as_usize() - entry.start.as_usize())
+			.sum()
+	}
+}
+
 pub fn allocate(size: usize) -> PhysAddr {
 	assert!(size > 0);
 	assert!(
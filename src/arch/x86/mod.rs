@@ -8,18 +8,116 @@ use crate::consts::*;
 use crate::fs;
 use crate::io::{self, Read};
 use crate::logging::*;
+use crate::scheduler;
 use alloc::string::String;
 use alloc::vec::Vec;
-use core::ptr::write_bytes;
+use core::cmp::max;
+use core::mem::size_of;
+use core::ptr::{copy_nonoverlapping, write_bytes};
 use core::slice;
-use goblin::elf::program_header::{PT_DYNAMIC, PT_GNU_RELRO, PT_LOAD};
+use goblin::elf::program_header::{PT_DYNAMIC, PT_GNU_RELRO, PT_LOAD, PT_PHDR, PT_TLS};
 use goblin::elf64::dynamic::{DT_RELA, DT_RELASZ};
 use goblin::elf64::reloc::{R_386_GLOB_DAT, R_386_RELATIVE};
 use goblin::{elf, elf64};
+use x86::bits64::segmentation::wrfsbase;
 use x86::controlregs;
+use x86::time::rdtsc;
+
+/// End of the auxiliary vector
+const AT_NULL: u64 = 0;
+/// Address of the program headers
+const AT_PHDR: u64 = 3;
+/// Size of a program header entry
+const AT_PHENT: u64 = 4;
+/// Number of program headers
+const AT_PHNUM: u64 = 5;
+/// System page size
+const AT_PAGESZ: u64 = 6;
+/// Entry point of the program
+const AT_ENTRY: u64 = 9;
+/// Real user ID
+const AT_UID: u64 = 11;
+/// Effective user ID
+const AT_EUID: u64 = 12;
+/// Real group ID
+const AT_GID: u64 = 13;
+/// Effective group ID
+const AT_EGID: u64 = 14;
+/// Non-zero, if the program runs with changed privileges (set-user-ID or set-group-ID)
+const AT_SECURE: u64 = 23;
+/// Address of 16 random bytes
+const AT_RANDOM: u64 = 25;
+
+/// Create the static TLS block of the application and set the FS register
+/// to the thread pointer. The layout follows variant II of the x86_64 ABI,
+/// where the TLS block ends at the thread pointer and the thread pointer
+/// points to itself.
+unsafe fn init_tls(image: &[u8], memsz: usize, align: usize) {
+	let align = max(align, 16);
+	let tls_size = align_up!(memsz, align);
+
+	// reserve memory for the TLS block and the thread control block
+	let block = scheduler::reserve_user_memory(tls_size + align + BasePageSize::SIZE)
+		.expect("unable to reserve the TLS block");
+	let tp = align_up!(block.as_usize() + tls_size, align);
+
+	debug!(
+		"Create TLS block at 0x{:x} (size 0x{:x}), thread pointer 0x{:x}",
+		tp - tls_size,
+		tls_size,
+		tp
+	);
+
+	// the memory is already zeroed => copy only the initialized data
+	copy_nonoverlapping(image.as_ptr(), (tp - tls_size) as *mut u8, image.len());
+	*(tp as *mut u64) = tp as u64;
+
+	wrfsbase(tp as u64);
+}
+
+/// Create the initial user stack, which contains the argument vector,
+/// an empty environment and the auxiliary vector `auxv`.
+/// Returns the initial stack pointer.
+unsafe fn init_user_stack(path: &str, auxv: &[(u64, u64)]) -> usize {
+	let mut sp = USER_STACK_TOP.as_usize();
+
+	// copy the path as first and only argument
+	sp -= path.len() + 1;
+	let argv0 = sp;
+	copy_nonoverlapping(path.as_ptr(), argv0 as *mut u8, path.len());
+	*((argv0 + path.len()) as *mut u8) = 0;
+
+	// the C library uses these random bytes, e.g. for the stack protector
+	sp = align_down!(sp - 16, 16);
+	let random = sp;
+	*(random as *mut u64) = rdtsc();
+	*((random + 8) as *mut u64) = rdtsc().rotate_left(32);
+
+	// argc, argv[0], NULL, envp[0] = NULL, auxv and AT_RANDOM, AT_NULL
+	let entries = 4 + 2 * (auxv.len() + 2);
+	sp = align_down!(sp - entries * size_of::<u64>(), 16);
+
+	let stack = slice::from_raw_parts_mut(sp as *mut u64, entries);
+	stack[0] = 1;
+	stack[1] = argv0 as u64;
+	stack[2] = 0;
+	stack[3] = 0;
+	for (i, (key, value)) in auxv
+		.iter()
+		.chain([(AT_RANDOM, random as u64), (AT_NULL, 0)].iter())
+		.enumerate()
+	{
+		stack[4 + 2 * i] = *key;
+		stack[5 + 2 * i] = *value;
+	}
+
+	sp
+}
 
 pub fn load_application(path: &String) -> io::Result<()> {
 	debug!("Try to load application!");
+	// the file has to be executable by the current task
+	let attr = fs::check_executable(path)?;
 	unsafe {
 		controlregs::cr3_write(paging::create_usr_pgd().as_u64());
 	}
@@ -86,6 +184,7 @@ pub fn load_application(path: &String) -> io::Result<()> {
 		exec_size / BasePageSize::SIZE,
 		PageTableEntryFlags::WRITABLE | PageTableEntryFlags::USER_ACCESSIBLE,
 	);
+	scheduler::set_image_size(exec_size);
 
 	unsafe {
 		write_bytes(USER_ENTRY.as_mut_ptr() as *mut u8, 0x00, exec_size);
@@ -94,16 +193,34 @@ pub fn load_application(path: &String) -> io::Result<()> {
 	let mut rela_addr: u64 = 0;
 	let mut relasz: u64 = 0;
 	//let mut relaent: u64 = 0;
+	let mut phdr_addr: u64 = 0;
+	let mut tls_header = None;
 	for i in &elf.program_headers {
 		if i.p_type == PT_LOAD {
 			debug!("Load code for address 0x{:x}", i.p_vaddr);
 
+			// are the program headers part of this segment?
+			if phdr_addr == 0
+				&& elf.header.e_phoff >= i.p_offset
+				&& elf.header.e_phoff < i.p_offset + i.p_filesz
+			{
+				phdr_addr = USER_ENTRY.as_u64() + i.p_vaddr + elf.header.e_phoff - i.p_offset;
+			}
+
 			let mem = (USER_ENTRY.as_usize() + i.p_vaddr as usize - vstart) as *mut u8;
 			let mem_slice = unsafe { slice::from_raw_parts_mut(mem, i.p_filesz as usize) };
 
 			mem_slice[0..i.p_filesz as usize].clone_from_slice(
 				&buffer[(i.p_offset as usize)..(i.p_offset + i.p_filesz) as usize],
 			);
+		} else if i.p_type == PT_PHDR {
+			phdr_addr = USER_ENTRY.as_u64() + i.p_vaddr - vstart as u64;
+		} else if i.p_type == PT_TLS {
+			debug!(
+				"PT_TLS at 0x{:x} (filesz 0x{:x}, memsz 0x{:x})",
+				i.p_vaddr, i.p_filesz, i.p_memsz
+			);
+			tls_header = Some(i.clone());
 		} else if i.p_type == PT_GNU_RELRO {
 			debug!(
 				"PT_GNU_RELRO at 0x{:x} (size 0x{:x})",
@@ -145,11 +262,51 @@ pub fn load_application(path: &String) -> io::Result<()> {
 
 	let entry = elf.entry as usize - vstart as usize + USER_ENTRY.as_usize();
 
+	if let Some(tls) = tls_header {
+		unsafe {
+			init_tls(
+				&buffer[tls.p_offset as usize..(tls.p_offset + tls.p_filesz) as usize],
+				tls.p_memsz as usize,
+				tls.p_align as usize,
+			);
+		}
+	}
+
+	// a set-user-ID or set-group-ID program runs with the privileges of its owner
+	let mut cred = scheduler::get_current_credentials();
+	if attr.mode & fs::S_ISUID != 0 {
+		cred.euid = attr.uid;
+		cred.suid = attr.uid;
+	}
+	if attr.mode & fs::S_ISGID != 0 {
+		cred.egid = attr.gid;
+		cred.sgid = attr.gid;
+	}
+	let secure = cred.euid != cred.uid || cred.egid != cred.gid;
+	scheduler::set_current_credentials(cred.clone());
+
+	let auxv = [
+		(AT_PHDR, phdr_addr),
+		(AT_PHENT, elf.header.e_phentsize as u64),
+		(AT_PHNUM, elf.header.e_phnum as u64),
+		(AT_PAGESZ, BasePageSize::SIZE as u64),
+		(AT_ENTRY, entry as u64),
+		(AT_UID, u64::from(cred.uid)),
+		(AT_EUID, u64::from(cred.euid)),
+		(AT_GID, u64::from(cred.gid)),
+		(AT_EGID, u64::from(cred.egid)),
+		(AT_SECURE, u64::from(secure)),
+	];
+	let stack = unsafe { init_user_stack(path, &auxv) };
+
 	// free temporary buffer
 	drop(buffer);
 
+	// the task is named after the executed file
+	crate::scheduler::set_current_task_name(path.rsplit('/').next().unwrap_or(path));
+
 	debug!("jump to user land at 0x{:x}", entry);
 	unsafe {
-		self::kernel::jump_to_user_land(entry);
+		self::kernel::jump_to_user_land(entry, stack);
 	}
 }
@@ -11,6 +11,9 @@ pub(crate) const STACK_SIZE: usize = 0x3000;
 #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
 pub(crate) const INTERRUPT_STACK_SIZE: usize = 0x3000;
 
+/// Pattern, which marks the unused part of a kernel stack
+pub(crate) const STACK_PATTERN: u8 = 0xCD;
+
 /// Size of a cache line
 pub(crate) const CACHE_LINE: usize = 64;
 
@@ -23,5 +26,17 @@ pub(crate) const TIMER_FREQ: u32 = 100; /* in HZ */
 /// Entry point of the user tasks
 pub const USER_ENTRY: VirtAddr = VirtAddr(0x20000000000u64);
 
+/// Top of the user-level stack
+pub const USER_STACK_TOP: VirtAddr = VirtAddr(0x20000400000u64);
+
+/// Maximum size of the user-level stack
+pub const USER_STACK_SIZE: usize = 64 * 1024;
+
+/// Start of the user-level memory region for anonymous mappings
+pub const USER_MMAP_START: VirtAddr = VirtAddr(0x20001000000u64);
+
+/// End of the user-level memory region for anonymous mappings
+pub const USER_MMAP_END: VirtAddr = VirtAddr(0x8000_0000_0000u64);
+
 /// Size of the kernel heap
 pub(crate) const HEAP_SIZE: usize = 8 * 1024 * 1024;
@@ -0,0 +1,350 @@
+//! Driver for ATA disks, which are attached to the legacy IDE controller
+//!
+//! The driver transfers the data by programmed I/O (PIO) and polls the
+//! status of the drive, i.e. interrupts of the controller are disabled.
+//! The disks are registered as `hda` to `hdd` in the order primary
+//! master, primary slave, secondary master and secondary slave.
+
+use crate::drivers::block::{check_request, register_block_device, BlockDevice, SECTOR_SIZE};
+use crate::io;
+use crate::logging::*;
+use crate::synch::spinlock::*;
+use alloc::format;
+use alloc::sync::Arc;
+use x86::io::*;
+
+// offsets of the command block registers
+const ATA_REG_DATA: u16 = 0;
+const ATA_REG_ERROR: u16 = 1;
+const ATA_REG_SECCOUNT: u16 = 2;
+const ATA_REG_LBA0: u16 = 3;
+const ATA_REG_LBA1: u16 = 4;
+const ATA_REG_LBA2: u16 = 5;
+const ATA_REG_DRIVE: u16 = 6;
+const ATA_REG_STATUS: u16 = 7;
+const ATA_REG_COMMAND: u16 = 7;
+
+// bits of the status register
+const ATA_SR_ERR: u8 = 0x01;
+const ATA_SR_DRQ: u8 = 0x08;
+const ATA_SR_DF: u8 = 0x20;
+const ATA_SR_BSY: u8 = 0x80;
+
+/// Device control register: disable interrupts of the drive
+const ATA_CTRL_NIEN: u8 = 0x02;
+
+const ATA_CMD_READ_PIO: u8 = 0x20;
+const ATA_CMD_READ_PIO_EXT: u8 = 0x24;
+const ATA_CMD_WRITE_PIO: u8 = 0x30;
+const ATA_CMD_WRITE_PIO_EXT: u8 = 0x34;
+const ATA_CMD_CACHE_FLUSH: u8 = 0xE7;
+const ATA_CMD_CACHE_FLUSH_EXT: u8 = 0xEA;
+const ATA_CMD_IDENTIFY: u8 = 0xEC;
+
+/// Drive register: use LBA addressing
+const ATA_DRIVE_LBA: u8 = 0xE0;
+/// Drive register: select the slave
+const ATA_DRIVE_SLAVE: u8 = 0x10;
+
+// word offsets of the data, which is returned by IDENTIFY
+const IDENT_LBA28_SECTORS: usize = 60;
+const IDENT_COMMAND_SETS: usize = 83;
+const IDENT_LBA48_SECTORS: usize = 100;
+/// Command sets: the drive supports 48 bit addresses
+const IDENT_LBA48_SUPPORTED: u16 = 1 << 10;
+
+/// Largest sector, which is addressable by 28 bit
+const LBA28_LIMIT: u64 = 1 << 28;
+/// Maximum number of sectors per command with 28 bit addresses
+const LBA28_MAX_SECTORS: usize = 256;
+/// Maximum number of sectors per command with 48 bit addresses
+const LBA48_MAX_SECTORS: usize = 65536;
+
+/// Number of status polls, before a command is aborted
+const ATA_TIMEOUT: usize = 10_000_000;
+
+/// Ports of both IDE channels, i.e. command block and control register
+const CHANNELS: [(u16, u16); 2] = [(0x1F0, 0x3F6), (0x170, 0x376)];
+
+/// Ports of an IDE channel. Master and slave share the channel.
+#[derive(Debug)]
+struct Channel {
+	/// first port of the command block
+	base: u16,
+	/// device control and alternate status register
+	control: u16,
+}
+
+impl Channel {
+	fn status(&self) -> u8 {
+		unsafe { inb(self.base + ATA_REG_STATUS) }
+	}
+
+	/// Wait about 400ns by reading the alternate status register
+	fn delay(&self) {
+		for _ in 0..4 {
+			unsafe {
+				inb(self.control);
+			}
+		}
+	}
+
+	fn select(&self, slave: bool, lba_bits: u8) {
+		let drive = ATA_DRIVE_LBA | if slave { ATA_DRIVE_SLAVE } else { 0 };
+
+		unsafe {
+			outb(self.base + ATA_REG_DRIVE, drive | (lba_bits & 0x0F));
+		}
+		self.delay();
+	}
+
+	/// Wait until the drive is not busy and return the status
+	fn wait_idle(&self) -> io::Result<u8> {
+		for _ in 0..ATA_TIMEOUT {
+			let status = self.status();
+			if status & ATA_SR_BSY == 0 {
+				return Ok(status);
+			}
+			core::hint::spin_loop();
+		}
+
+		Err(io::Error::EIO)
+	}
+
+	/// Wait until the drive requests a data transfer
+	fn wait_data(&self) -> io::Result<()> {
+		let status = self.wait_idle()?;
+
+		if status & (ATA_SR_ERR | ATA_SR_DF) != 0 {
+			let error = unsafe { inb(self.base + ATA_REG_ERROR) };
+			debug!("ATA error: status 0x{:x}, error 0x{:x}", status, error);
+			Err(io::Error::EIO)
+		} else if status & ATA_SR_DRQ == 0 {
+			Err(io::Error::EIO)
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Issue the command `command` for `count` sectors starting at `sector`
+	fn command(&self, slave: bool, lba48: bool, sector: u64, count: usize, command: u8) {
+		unsafe {
+			if lba48 {
+				self.select(slave, 0);
+				// the high-order bytes are written first
+				outb(self.base + ATA_REG_SECCOUNT, (count >> 8) as u8);
+				outb(self.base + ATA_REG_LBA0, (sector >> 24) as u8);
+				outb(self.base + ATA_REG_LBA1, (sector >> 32) as u8);
+				outb(self.base + ATA_REG_LBA2, (sector >> 40) as u8);
+			} else {
+				self.select(slave, (sector >> 24) as u8);
+			}
+
+			outb(self.base + ATA_REG_SECCOUNT, count as u8);
+			outb(self.base + ATA_REG_LBA0, sector as u8);
+			outb(self.base + ATA_REG_LBA1, (sector >> 8) as u8);
+			outb(self.base + ATA_REG_LBA2, (sector >> 16) as u8);
+			outb(self.base + ATA_REG_COMMAND, command);
+		}
+		self.delay();
+	}
+
+	fn read_sector(&self, buf: &mut [u8]) {
+		for word in buf.chunks_exact_mut(2) {
+			word.copy_from_slice(&unsafe { inw(self.base + ATA_REG_DATA) }.to_le_bytes());
+		}
+	}
+
+	fn write_sector(&self, buf: &[u8]) {
+		for word in buf.chunks_exact(2) {
+			unsafe {
+				outw(
+					self.base + ATA_REG_DATA,
+					u16::from_le_bytes([word[0], word[1]]),
+				);
+			}
+		}
+	}
+
+	/// Identify the drive and return its number of sectors and the
+	/// support of 48 bit addresses. Returns `None`, if the drive
+	/// is absent or doesn't support the ATA command set.
+	fn identify(&self, slave: bool) -> Option<(u64, bool)> {
+		// a floating bus reads as 0xFF
+		if self.status() == 0xFF {
+			return None;
+		}
+
+		self.select(slave, 0);
+		unsafe {
+			outb(self.base + ATA_REG_SECCOUNT, 0);
+			outb(self.base + ATA_REG_LBA0, 0);
+			outb(self.base + ATA_REG_LBA1, 0);
+			outb(self.base + ATA_REG_LBA2, 0);
+			outb(self.base + ATA_REG_COMMAND, ATA_CMD_IDENTIFY);
+		}
+		self.delay();
+
+		if self.status() == 0 {
+			return None;
+		}
+		self.wait_idle().ok()?;
+
+		// ATAPI and SATA devices set the signature in the LBA registers
+		let (mid, high) = unsafe { (inb(self.base + ATA_REG_LBA1), inb(self.base + ATA_REG_LBA2)) };
+		if mid != 0 || high != 0 {
+			return None;
+		}
+		self.wait_data().ok()?;
+
+		let mut data = [0u8; SECTOR_SIZE];
+		self.read_sector(&mut data);
+		let word = |index: usize| u16::from_le_bytes([data[2 * index], data[2 * index + 1]]);
+
+		if word(IDENT_COMMAND_SETS) & IDENT_LBA48_SUPPORTED != 0 {
+			let sectors = (0..4).fold(0u64, |sum, i| {
+				sum | u64::from(word(IDENT_LBA48_SECTORS + i)) << (16 * i)
+			});
+			Some((sectors, true))
+		} else {
+			let sectors = u64::from(word(IDENT_LBA28_SECTORS))
+				| u64::from(word(IDENT_LBA28_SECTORS + 1)) << 16;
+			Some((sectors, false))
+		}
+	}
+}
+
+/// Disk, which is attached to an IDE channel
+#[derive(Debug)]
+struct AtaDisk {
+	/// channel of the disk, which is locked during a command
+	channel: Arc<Spinlock<Channel>>,
+	/// the disk is the slave of the channel
+	slave: bool,
+	/// the disk supports 48 bit addresses
+	lba48: bool,
+	/// number of sectors
+	sectors: u64,
+}
+
+impl AtaDisk {
+	/// Returns true and the maximum number of sectors per command,
+	/// if the request ending before `end` requires 48 bit addresses
+	fn addressing(&self, end: u64) -> (bool, usize) {
+		if self.lba48 && end > LBA28_LIMIT {
+			(true, LBA48_MAX_SECTORS)
+		} else {
+			(false, LBA28_MAX_SECTORS)
+		}
+	}
+}
+
+impl BlockDevice for AtaDisk {
+	fn num_sectors(&self) -> u64 {
+		self.sectors
+	}
+
+	fn is_read_only(&self) -> bool {
+		false
+	}
+
+	fn read_sectors(&self, sector: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_request(self, sector, buf.len())?;
+
+		let channel = self.channel.lock();
+		let (lba48, max) = self.addressing(sector + (buf.len() / SECTOR_SIZE) as u64);
+		let command = if lba48 {
+			ATA_CMD_READ_PIO_EXT
+		} else {
+			ATA_CMD_READ_PIO
+		};
+
+		for (index, chunk) in buf.chunks_mut(max * SECTOR_SIZE).enumerate() {
+			let first = sector + (index * max) as u64;
+			channel.command(self.slave, lba48, first, chunk.len() / SECTOR_SIZE, command);
+
+			for data in chunk.chunks_exact_mut(SECTOR_SIZE) {
+				channel.wait_data()?;
+				channel.read_sector(data);
+			}
+		}
+
+		Ok(())
+	}
+
+	fn write_sectors(&self, sector: u64, buf: &[u8]) -> io::Result<()> {
+		check_request(self, sector, buf.len())?;
+
+		let channel = self.channel.lock();
+		let (lba48, max) = self.addressing(sector + (buf.len() / SECTOR_SIZE) as u64);
+		let command = if lba48 {
+			ATA_CMD_WRITE_PIO_EXT
+		} else {
+			ATA_CMD_WRITE_PIO
+		};
+
+		for (index, chunk) in buf.chunks(max * SECTOR_SIZE).enumerate() {
+			let first = sector + (index * max) as u64;
+			channel.command(self.slave, lba48, first, chunk.len() / SECTOR_SIZE, command);
+
+			for data in chunk.chunks_exact(SECTOR_SIZE) {
+				channel.wait_data()?;
+				channel.write_sector(data);
+			}
+		}
+
+		Ok(())
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		let channel = self.channel.lock();
+		let command = if self.lba48 {
+			ATA_CMD_CACHE_FLUSH_EXT
+		} else {
+			ATA_CMD_CACHE_FLUSH
+		};
+
+		// the data is persistent after the write cache is flushed
+		channel.command(self.slave, false, 0, 0, command);
+		let status = channel.wait_idle()?;
+		if status & (ATA_SR_ERR | ATA_SR_DF) != 0 {
+			return Err(io::Error::EIO);
+		}
+
+		Ok(())
+	}
+}
+
+/// Detect the disks of both IDE channels
+pub(crate) fn init() {
+	let mut index = 0;
+
+	for (base, control) in CHANNELS {
+		let channel = Channel { base, control };
+
+		// the driver polls the status, interrupts aren't required
+		unsafe {
+			outb(control, ATA_CTRL_NIEN);
+		}
+
+		let channel = Arc::new(Spinlock::new(channel));
+		for slave in [false, true] {
+			let letter = (b'a' + index) as char;
+			index += 1;
+
+			let Some((sectors, lba48)) = channel.lock().identify(slave) else {
+				continue;
+			};
+
+			let disk = AtaDisk {
+				channel: channel.clone(),
+				slave,
+				lba48,
+				sectors,
+			};
+			if let Err(e) = register_block_device(&format!("hd{}", letter), Arc::new(disk)) {
+				warn!("Unable to register ATA disk hd{}: {:?}", letter, e);
+			}
+		}
+	}
+}
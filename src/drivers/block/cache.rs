@@ -0,0 +1,397 @@
+//! Buffer cache, which is shared by all block devices
+//!
+//! The cache stores blocks of `CACHE_BLOCK_SIZE` bytes. If the cache is
+//! full, the least recently used block is replaced. Writes only modify
+//! the cached block, which is written back to the device, if the block
+//! is replaced or the device is flushed (e.g. by `sync`).
+//!
+//! If a device is read sequentially, a miss additionally loads the
+//! following blocks (read-ahead) by the same request to the device.
+//!
+//! Limitation: the cache is protected by a single spinlock, which is
+//! also held during the requests to the devices (loading a block,
+//! writing back a replaced block and `flush`). The drivers poll for the
+//! completion of a request. Consequently, all accesses to cached devices
+//! are serialized and a task, which accesses any cached device, spins,
+//! while another task waits for a device. Requests outside the lock
+//! require buffers, which are marked as busy during the transfer.
+
+use crate::arch::mm::get_memory_size;
+use crate::drivers::block::{check_request, BlockDevice, SECTOR_SIZE};
+use crate::drivers::dma::DmaBuffer;
+use crate::io;
+use crate::logging::*;
+use crate::synch::spinlock::*;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Size of a cached block in bytes
+const CACHE_BLOCK_SIZE: usize = 4096;
+/// Number of sectors per cached block
+const SECTORS_PER_BLOCK: u64 = (CACHE_BLOCK_SIZE / SECTOR_SIZE) as u64;
+/// Lower bound of the number of cached blocks
+const MIN_CACHE_BLOCKS: usize = 64;
+/// Upper bound of the number of cached blocks
+const MAX_CACHE_BLOCKS: usize = 16384;
+/// Part of the main memory, which is used by the cache
+const CACHE_MEMORY_SHARE: usize = 32;
+/// Default number of blocks, which are loaded in advance
+const DEFAULT_READ_AHEAD: usize = 8;
+
+/// Number of blocks, which are loaded in advance by sequential reads
+static READ_AHEAD: AtomicUsize = AtomicUsize::new(DEFAULT_READ_AHEAD);
+
+/// The buffer cache, which is created by `init`. The lock is
+/// held during the requests to the devices.
+static CACHE: Spinlock<Option<BufferCache>> = Spinlock::new(None);
+
+/// Counters of the buffer cache
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CacheStatistics {
+	/// maximum number of cached blocks
+	pub capacity: usize,
+	/// number of cached blocks
+	pub used: usize,
+	/// number of modified blocks, which aren't written back
+	pub dirty: usize,
+	/// number of accesses to cached blocks
+	pub hits: u64,
+	/// number of accesses, which have to load the block
+	pub misses: u64,
+	/// number of blocks, which are loaded in advance
+	pub read_ahead: u64,
+	/// number of blocks, which are written back to the device
+	pub writebacks: u64,
+	/// number of blocks, which are replaced
+	pub evictions: u64,
+}
+
+/// Identifies a block by the device and the block number
+type BlockKey = (usize, u64);
+
+/// Cached block
+#[derive(Debug)]
+struct Buffer {
+	key: BlockKey,
+	/// content of the block
+	data: DmaBuffer,
+	/// the block is modified and not written back
+	dirty: bool,
+	/// time of the last access, which orders the blocks by their use
+	stamp: u64,
+}
+
+#[derive(Debug)]
+struct BufferCache {
+	/// maximum number of buffers
+	capacity: usize,
+	/// all allocated buffers
+	buffers: Vec<Buffer>,
+	/// maps a block to its buffer
+	index: BTreeMap<BlockKey, usize>,
+	/// maps the time of the last access to the buffer
+	lru: BTreeMap<u64, usize>,
+	/// logical clock to stamp the accesses
+	clock: u64,
+	/// cached devices by their identifier
+	devices: Vec<Arc<dyn BlockDevice>>,
+	/// block, which follows the last read of each device
+	next_read: BTreeMap<usize, u64>,
+	statistics: CacheStatistics,
+}
+
+impl BufferCache {
+	fn new(capacity: usize) -> Self {
+		BufferCache {
+			capacity,
+			buffers: Vec::new(),
+			index: BTreeMap::new(),
+			lru: BTreeMap::new(),
+			clock: 0,
+			devices: Vec::new(),
+			next_read: BTreeMap::new(),
+			statistics: CacheStatistics {
+				capacity,
+				..Default::default()
+			},
+		}
+	}
+
+	/// Returns the number of sectors of the block `key`,
+	/// which is only shorter at the end of the device
+	fn sectors(&self, key: BlockKey) -> usize {
+		let num_sectors = self.devices[key.0].num_sectors();
+
+		(num_sectors - key.1 * SECTORS_PER_BLOCK).min(SECTORS_PER_BLOCK) as usize
+	}
+
+	/// Returns the number of blocks of the device `id`
+	fn num_blocks(&self, id: usize) -> u64 {
+		self.devices[id].num_sectors().div_ceil(SECTORS_PER_BLOCK)
+	}
+
+	/// Mark the buffer `slot` as most recently used
+	fn touch(&mut self, slot: usize) {
+		self.lru.remove(&self.buffers[slot].stamp);
+		self.clock += 1;
+		self.buffers[slot].stamp = self.clock;
+		self.lru.insert(self.clock, slot);
+	}
+
+	/// Write the buffer `slot` back to its device, if it is modified
+	fn write_back(&mut self, slot: usize) -> io::Result<()> {
+		let buffer = &self.buffers[slot];
+		if !buffer.dirty {
+			return Ok(());
+		}
+
+		let len = self.sectors(buffer.key) * SECTOR_SIZE;
+		self.devices[buffer.key.0].write_sectors(
+			buffer.key.1 * SECTORS_PER_BLOCK,
+			&buffer.data.as_slice()[..len],
+		)?;
+
+		self.buffers[slot].dirty = false;
+		self.statistics.writebacks += 1;
+		self.statistics.dirty -= 1;
+
+		Ok(())
+	}
+
+	/// Returns an unused buffer for the block `key`. If the cache is
+	/// full, the least recently used block is written back and replaced.
+	fn allocate(&mut self, key: BlockKey) -> io::Result<usize> {
+		let slot = if self.buffers.len() < self.capacity {
+			self.buffers.push(Buffer {
+				key,
+				data: DmaBuffer::new(CACHE_BLOCK_SIZE),
+				dirty: false,
+				stamp: 0,
+			});
+			self.statistics.used += 1;
+			self.buffers.len() - 1
+		} else {
+			let (_, &slot) = self.lru.first_key_value().unwrap();
+			self.write_back(slot)?;
+
+			let buffer = &self.buffers[slot];
+			self.lru.remove(&buffer.stamp);
+			self.index.remove(&buffer.key);
+			self.statistics.evictions += 1;
+			slot
+		};
+
+		self.buffers[slot].key = key;
+		self.index.insert(key, slot);
+		self.touch(slot);
+
+		Ok(slot)
+	}
+
+	/// Returns the buffer of the block `key`. If the block isn't cached
+	/// and `load` is false, the content of the returned buffer is undefined.
+	fn get(&mut self, key: BlockKey, load: bool) -> io::Result<usize> {
+		if let Some(&slot) = self.index.get(&key) {
+			self.statistics.hits += 1;
+			self.touch(slot);
+			return Ok(slot);
+		}
+
+		self.statistics.misses += 1;
+		if !load {
+			return self.allocate(key);
+		}
+
+		// load the following blocks, which aren't cached, in advance,
+		// if the device is read sequentially
+		let mut count = 1;
+		if self.next_read.get(&key.0) == Some(&key.1) {
+			let limit = READ_AHEAD
+				.load(Ordering::Relaxed)
+				.min(self.capacity / 4)
+				.min((self.num_blocks(key.0) - key.1 - 1) as usize);
+
+			while count <= limit && !self.index.contains_key(&(key.0, key.1 + count as u64)) {
+				count += 1;
+			}
+		}
+
+		let sectors = (count - 1) * SECTORS_PER_BLOCK as usize
+			+ self.sectors((key.0, key.1 + count as u64 - 1));
+		let mut data = vec![0u8; sectors * SECTOR_SIZE];
+		self.devices[key.0].read_sectors(key.1 * SECTORS_PER_BLOCK, &mut data)?;
+
+		// allocate the requested block at last, which is therefore
+		// the most recently used block
+		for (i, chunk) in data.chunks(CACHE_BLOCK_SIZE).enumerate().rev() {
+			let slot = self.allocate((key.0, key.1 + i as u64))?;
+			self.buffers[slot].data.as_mut_slice()[..chunk.len()].copy_from_slice(chunk);
+		}
+		self.statistics.read_ahead += count as u64 - 1;
+
+		Ok(self.index[&key])
+	}
+
+	/// Read the sectors starting at `sector` of the device `id`
+	fn read(&mut self, id: usize, sector: u64, buf: &mut [u8]) -> io::Result<()> {
+		let mut done = 0;
+
+		while done < buf.len() {
+			let position = sector * SECTOR_SIZE as u64 + done as u64;
+			let block = position / CACHE_BLOCK_SIZE as u64;
+			let offset = (position % CACHE_BLOCK_SIZE as u64) as usize;
+			let len = (CACHE_BLOCK_SIZE - offset).min(buf.len() - done);
+
+			let slot = self.get((id, block), true)?;
+			buf[done..done + len]
+				.copy_from_slice(&self.buffers[slot].data.as_slice()[offset..offset + len]);
+
+			done += len;
+			self.next_read.insert(id, block + 1);
+		}
+
+		Ok(())
+	}
+
+	/// Write the sectors starting at `sector` of the device `id`
+	fn write(&mut self, id: usize, sector: u64, buf: &[u8]) -> io::Result<()> {
+		let mut done = 0;
+
+		while done < buf.len() {
+			let position = sector * SECTOR_SIZE as u64 + done as u64;
+			let block = position / CACHE_BLOCK_SIZE as u64;
+			let offset = (position % CACHE_BLOCK_SIZE as u64) as usize;
+			let len = (CACHE_BLOCK_SIZE - offset).min(buf.len() - done);
+
+			// a block, which is completely overwritten, hasn't to be loaded
+			let whole = offset == 0 && len == self.sectors((id, block)) * SECTOR_SIZE;
+			let slot = self.get((id, block), !whole)?;
+
+			let buffer = &mut self.buffers[slot];
+			buffer.data.as_mut_slice()[offset..offset + len]
+				.copy_from_slice(&buf[done..done + len]);
+			if !buffer.dirty {
+				buffer.dirty = true;
+				self.statistics.dirty += 1;
+			}
+
+			done += len;
+		}
+
+		Ok(())
+	}
+
+	/// Write all modified blocks of the device `id` back in ascending order
+	fn flush(&mut self, id: usize) -> io::Result<()> {
+		let slots: Vec<usize> = self
+			.index
+			.range((id, 0)..=(id, u64::MAX))
+			.map(|(_, &slot)| slot)
+			.collect();
+
+		for slot in slots {
+			self.write_back(slot)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Block device, whose accesses pass the buffer cache
+#[derive(Debug)]
+struct CachedDevice {
+	/// identifier of the device within the cache
+	id: usize,
+	/// driver of the device
+	dev: Arc<dyn BlockDevice>,
+}
+
+impl BlockDevice for CachedDevice {
+	fn num_sectors(&self) -> u64 {
+		self.dev.num_sectors()
+	}
+
+	fn is_read_only(&self) -> bool {
+		self.dev.is_read_only()
+	}
+
+	fn read_sectors(&self, sector: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_request(self, sector, buf.len())?;
+
+		CACHE.lock().as_mut().unwrap().read(self.id, sector, buf)
+	}
+
+	fn write_sectors(&self, sector: u64, buf: &[u8]) -> io::Result<()> {
+		if self.dev.is_read_only() {
+			return Err(io::Error::EROFS);
+		}
+		check_request(self, sector, buf.len())?;
+
+		CACHE.lock().as_mut().unwrap().write(self.id, sector, buf)
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		CACHE.lock().as_mut().unwrap().flush(self.id)?;
+		self.dev.flush()
+	}
+}
+
+/// Returns the device `dev`, whose accesses pass the buffer cache
+pub(crate) fn cached(dev: Arc<dyn BlockDevice>) -> Arc<dyn BlockDevice> {
+	let mut guard = CACHE.lock();
+	let cache = guard.as_mut().unwrap();
+
+	cache.devices.push(dev.clone());
+	Arc::new(CachedDevice {
+		id: cache.devices.len() - 1,
+		dev,
+	})
+}
+
+/// Write all modified blocks back and flush the devices
+pub fn sync() -> io::Result<()> {
+	let devices = CACHE.lock().as_ref().unwrap().devices.clone();
+	let mut result = Ok(());
+
+	for (id, dev) in devices.iter().enumerate() {
+		// continue with the other devices, if a device fails
+		if let Err(e) = CACHE
+			.lock()
+			.as_mut()
+			.unwrap()
+			.flush(id)
+			.and_then(|_| dev.flush())
+		{
+			warn!("Unable to synchronize block device {}: {:?}", id, e);
+			result = Err(e);
+		}
+	}
+
+	result
+}
+
+/// Returns the counters of the buffer cache
+pub fn statistics() -> CacheStatistics {
+	CACHE.lock().as_ref().unwrap().statistics
+}
+
+/// Set the number of blocks, which are loaded in advance by
+/// sequential reads. The value zero disables the read-ahead.
+pub fn set_read_ahead(blocks: usize) {
+	READ_AHEAD.store(blocks, Ordering::Relaxed);
+}
+
+/// Create the buffer cache, whose size depends on the main memory
+pub(crate) fn init() {
+	let capacity = (get_memory_size() / CACHE_MEMORY_SHARE / CACHE_BLOCK_SIZE)
+		.clamp(MIN_CACHE_BLOCKS, MAX_CACHE_BLOCKS);
+
+	info!(
+		"Buffer cache holds up to {} blocks of {} bytes",
+		capacity, CACHE_BLOCK_SIZE
+	);
+	*CACHE.lock() = Some(BufferCache::new(capacity));
+}
@@ -0,0 +1,154 @@
+//! Abstraction of block devices, which are accessed in units of sectors
+//!
+//! Drivers register their devices by a name, e.g. `ram0`. File systems
+//! receive the name (or the path `/dev/<name>`) as source of `mount`.
+//! In addition, the devfs publishes each device as `/dev/<name>`.
+//! All accesses to a registered device pass the buffer cache.
+
+mod ata;
+mod cache;
+mod ramdisk;
+mod virtio;
+
+pub use self::cache::{set_read_ahead, statistics, sync, CacheStatistics};
+pub(crate) use self::ramdisk::RAM_DISK;
+pub(crate) use self::virtio::VIRTIO_BLK_DRIVER;
+
+use crate::io;
+use crate::logging::*;
+use crate::synch::spinlock::*;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Size of a sector in bytes
+pub const SECTOR_SIZE: usize = 512;
+
+/// The trait `BlockDevice` specifies a device, which stores its
+/// data in sectors of `SECTOR_SIZE` bytes
+pub trait BlockDevice: core::fmt::Debug + core::marker::Send + core::marker::Sync {
+	/// Returns the number of sectors of the device
+	fn num_sectors(&self) -> u64;
+
+	/// Returns true, if the device doesn't support `write_sectors`
+	fn is_read_only(&self) -> bool {
+		true
+	}
+
+	/// Read the sectors starting at `sector` into `buf`,
+	/// whose length is a multiple of `SECTOR_SIZE`
+	fn read_sectors(&self, sector: u64, buf: &mut [u8]) -> io::Result<()>;
+
+	/// Write `buf`, whose length is a multiple of `SECTOR_SIZE`,
+	/// to the sectors starting at `sector`
+	fn write_sectors(&self, _sector: u64, _buf: &[u8]) -> io::Result<()> {
+		Err(io::Error::EROFS)
+	}
+
+	/// Write all buffered data persistently to the device
+	fn flush(&self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Check if the request to access `len` bytes starting at `sector`
+/// is aligned to sectors and within the device `dev`
+pub(crate) fn check_request(dev: &dyn BlockDevice, sector: u64, len: usize) -> io::Result<()> {
+	if !len.is_multiple_of(SECTOR_SIZE) {
+		return Err(io::Error::EINVAL);
+	}
+
+	match sector.checked_add((len / SECTOR_SIZE) as u64) {
+		Some(end) if end <= dev.num_sectors() => Ok(()),
+		_ => Err(io::Error::EIO),
+	}
+}
+
+/// Read `buf.len()` bytes starting at the byte `offset` of the device
+/// `dev`. In contrast to `read_sectors`, the request hasn't to be
+/// aligned to sectors.
+pub(crate) fn read_bytes(dev: &dyn BlockDevice, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+	let first = offset / SECTOR_SIZE as u64;
+	let skip = (offset % SECTOR_SIZE as u64) as usize;
+
+	if skip == 0 && buf.len().is_multiple_of(SECTOR_SIZE) {
+		return dev.read_sectors(first, buf);
+	}
+
+	let len = (skip + buf.len()).next_multiple_of(SECTOR_SIZE);
+	let mut sectors = vec![0u8; len];
+	dev.read_sectors(first, &mut sectors)?;
+	buf.copy_from_slice(&sectors[skip..skip + buf.len()]);
+
+	Ok(())
+}
+
+/// Write `buf` starting at the byte `offset` of the device `dev`.
+/// Sectors, which are only partially written, are read before.
+pub(crate) fn write_bytes(dev: &dyn BlockDevice, offset: u64, buf: &[u8]) -> io::Result<()> {
+	let first = offset / SECTOR_SIZE as u64;
+	let skip = (offset % SECTOR_SIZE as u64) as usize;
+
+	if skip == 0 && buf.len().is_multiple_of(SECTOR_SIZE) {
+		return dev.write_sectors(first, buf);
+	}
+
+	let len = (skip + buf.len()).next_multiple_of(SECTOR_SIZE);
+	let mut sectors = vec![0u8; len];
+	dev.read_sectors(first, &mut sectors)?;
+	sectors[skip..skip + buf.len()].copy_from_slice(buf);
+
+	dev.write_sectors(first, &sectors)
+}
+
+/// Registered block devices
+static BLOCK_DEVICES: Spinlock<BTreeMap<String, Arc<dyn BlockDevice>>> =
+	Spinlock::new(BTreeMap::new());
+
+/// Register the block device `dev` with the name `name`
+pub fn register_block_device(name: &str, dev: Arc<dyn BlockDevice>) -> io::Result<()> {
+	if name.is_empty() || name.contains('/') {
+		return Err(io::Error::EINVAL);
+	}
+
+	let mut devices = BLOCK_DEVICES.lock();
+	if devices.contains_key(name) {
+		return Err(io::Error::EEXIST);
+	}
+
+	info!(
+		"Register block device {} with {} sectors",
+		name,
+		dev.num_sectors()
+	);
+	let dev = cache::cached(dev);
+	crate::fs::register_block_node(name, dev.clone())?;
+	devices.insert(String::from(name), dev);
+
+	Ok(())
+}
+
+/// Returns the block device `name`. The prefix `/dev/` is optional.
+pub fn get_block_device(name: &str) -> io::Result<Arc<dyn BlockDevice>> {
+	let name = name.strip_prefix("/dev/").unwrap_or(name);
+
+	BLOCK_DEVICES
+		.lock()
+		.get(name)
+		.cloned()
+		.ok_or(io::Error::ENODEV)
+}
+
+/// Returns the names of all registered block devices
+pub fn block_devices() -> Vec<String> {
+	BLOCK_DEVICES.lock().keys().cloned().collect()
+}
+
+/// Register all block devices, which are available at boot time
+pub(crate) fn init() {
+	cache::init();
+	ramdisk::init();
+	ata::init();
+}
@@ -0,0 +1,88 @@
+//! Block device, which provides a disk image in memory
+//!
+//! The build script embeds the disk image, which is specified by the
+//! environment variable `EDUOS_DISK_IMAGE`. Without a disk image, the
+//! device isn't registered. The embedded image is immutable, written
+//! sectors are kept on the heap and replace the original sectors.
+
+use crate::drivers::block::{check_request, register_block_device, BlockDevice, SECTOR_SIZE};
+use crate::io;
+use crate::logging::*;
+use crate::synch::spinlock::*;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::include_bytes;
+
+/// Disk image, which is embedded by the build script
+static DISK_IMAGE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/disk.img"));
+
+/// Name of the RAM disk, which provides the embedded disk image
+pub(crate) const RAM_DISK: &str = "ram0";
+
+#[derive(Debug)]
+struct RamDisk {
+	/// original content of the disk
+	data: &'static [u8],
+	/// sectors, which are modified since the boot
+	written: Spinlock<BTreeMap<u64, Box<[u8]>>>,
+}
+
+impl BlockDevice for RamDisk {
+	fn num_sectors(&self) -> u64 {
+		(self.data.len() / SECTOR_SIZE) as u64
+	}
+
+	fn is_read_only(&self) -> bool {
+		false
+	}
+
+	fn read_sectors(&self, sector: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_request(self, sector, buf.len())?;
+
+		let written = self.written.lock();
+		for (index, chunk) in buf.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+			let current = sector + index as u64;
+
+			match written.get(&current) {
+				Some(data) => chunk.copy_from_slice(data),
+				None => {
+					let start = current as usize * SECTOR_SIZE;
+					chunk.copy_from_slice(&self.data[start..start + SECTOR_SIZE]);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	fn write_sectors(&self, sector: u64, buf: &[u8]) -> io::Result<()> {
+		check_request(self, sector, buf.len())?;
+
+		let mut written = self.written.lock();
+		for (index, chunk) in buf.chunks_exact(SECTOR_SIZE).enumerate() {
+			written.insert(sector + index as u64, Box::from(chunk));
+		}
+
+		Ok(())
+	}
+}
+
+pub(crate) fn init() {
+	if DISK_IMAGE.is_empty() {
+		return;
+	}
+
+	if !DISK_IMAGE.len().is_multiple_of(SECTOR_SIZE) {
+		warn!(
+			"Size of the disk image isn't a multiple of {} bytes, the remainder is ignored",
+			SECTOR_SIZE
+		);
+	}
+
+	let disk = RamDisk {
+		data: DISK_IMAGE,
+		written: Spinlock::new(BTreeMap::new()),
+	};
+	register_block_device(RAM_DISK, Arc::new(disk)).unwrap();
+}
@@ -0,0 +1,209 @@
+//! Driver for virtio block devices (virtio-blk)
+//!
+//! Each device has a single virtqueue and processes one request at
+//! a time. The devices are registered as `vda`, `vdb` and so on.
+
+use crate::drivers::block::{check_request, register_block_device, BlockDevice, SECTOR_SIZE};
+use crate::drivers::dma::DmaBuffer;
+use crate::drivers::pci::{PciDevice, PciDriver};
+use crate::drivers::virtio::*;
+use crate::io;
+use crate::synch::spinlock::*;
+use alloc::format;
+use alloc::sync::Arc;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Device ID of a transitional block device, which supports the legacy interface
+const VIRTIO_BLK_DEVICE_ID: u16 = 0x1001;
+
+/// Feature: the device is read-only
+const VIRTIO_BLK_F_RO: u32 = 1 << 5;
+/// Feature: the device has a write cache, which is flushed on request
+const VIRTIO_BLK_F_FLUSH: u32 = 1 << 9;
+
+// request types
+const VIRTIO_BLK_T_IN: u32 = 0;
+const VIRTIO_BLK_T_OUT: u32 = 1;
+const VIRTIO_BLK_T_FLUSH: u32 = 4;
+/// Status of a successful request
+const VIRTIO_BLK_S_OK: u8 = 0;
+
+/// Size of the request header
+const HEADER_SIZE: usize = 16;
+/// Offset of the status byte within the request buffer
+const STATUS_OFFSET: usize = HEADER_SIZE;
+/// Offset of the data within the request buffer
+const DATA_OFFSET: usize = 4096;
+/// Maximum number of bytes per request
+const MAX_TRANSFER: usize = 64 * 1024;
+
+/// State of a device, which is locked during a request
+#[derive(Debug)]
+struct VirtioBlkState {
+	queue: Virtqueue,
+	/// header, status and data of the current request
+	request: DmaBuffer,
+}
+
+#[derive(Debug)]
+struct VirtioBlk {
+	/// registers of the device
+	device: LegacyDevice,
+	/// number of sectors
+	sectors: u64,
+	/// the device rejects writes
+	read_only: bool,
+	/// the device has a write cache
+	flush: bool,
+	state: Spinlock<VirtioBlkState>,
+}
+
+impl VirtioBlk {
+	/// Initialize the device `dev` as described by the legacy interface
+	fn new(dev: &PciDevice) -> io::Result<Self> {
+		let (device, features) = LegacyDevice::new(dev, VIRTIO_BLK_F_RO | VIRTIO_BLK_F_FLUSH)?;
+		let queue = device.setup_queue(0, 3)?;
+		let sectors =
+			u64::from(device.read_config_u32(0)) | u64::from(device.read_config_u32(4)) << 32;
+		device.driver_ok();
+
+		Ok(VirtioBlk {
+			device,
+			sectors,
+			read_only: features & VIRTIO_BLK_F_RO != 0,
+			flush: features & VIRTIO_BLK_F_FLUSH != 0,
+			state: Spinlock::new(VirtioBlkState {
+				queue,
+				request: DmaBuffer::new(DATA_OFFSET + MAX_TRANSFER),
+			}),
+		})
+	}
+
+	/// Submit a request of type `kind` for `len` bytes starting at `sector`,
+	/// the data is exchanged in the request buffer
+	fn request(
+		&self,
+		state: &mut VirtioBlkState,
+		kind: u32,
+		sector: u64,
+		len: usize,
+	) -> io::Result<()> {
+		let VirtioBlkState { queue, request } = state;
+
+		unsafe {
+			write_volatile(request.as_ptr::<u32>(0), kind);
+			write_volatile(request.as_ptr::<u32>(4), 0);
+			write_volatile(request.as_ptr::<u64>(8), sector);
+			write_volatile(request.as_ptr::<u8>(STATUS_OFFSET), 0xFF);
+		}
+
+		queue.set_descriptor(
+			0,
+			request.physical_address(0),
+			HEADER_SIZE,
+			VIRTQ_DESC_F_NEXT,
+			1,
+		);
+		let status = if len > 0 {
+			let flags = if kind == VIRTIO_BLK_T_IN {
+				VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE
+			} else {
+				VIRTQ_DESC_F_NEXT
+			};
+			queue.set_descriptor(1, request.physical_address(DATA_OFFSET), len, flags, 2);
+			2
+		} else {
+			1
+		};
+		queue.set_descriptor(
+			status,
+			request.physical_address(STATUS_OFFSET),
+			1,
+			VIRTQ_DESC_F_WRITE,
+			0,
+		);
+		queue.push(0);
+		self.device.notify(0);
+		queue.wait_used()?;
+		self.device.acknowledge();
+
+		if unsafe { read_volatile(request.as_ptr::<u8>(STATUS_OFFSET)) } == VIRTIO_BLK_S_OK {
+			Ok(())
+		} else {
+			Err(io::Error::EIO)
+		}
+	}
+}
+
+impl BlockDevice for VirtioBlk {
+	fn num_sectors(&self) -> u64 {
+		self.sectors
+	}
+
+	fn is_read_only(&self) -> bool {
+		self.read_only
+	}
+
+	fn read_sectors(&self, sector: u64, buf: &mut [u8]) -> io::Result<()> {
+		check_request(self, sector, buf.len())?;
+
+		let mut state = self.state.lock();
+		for (index, chunk) in buf.chunks_mut(MAX_TRANSFER).enumerate() {
+			let first = sector + (index * MAX_TRANSFER / SECTOR_SIZE) as u64;
+
+			self.request(&mut state, VIRTIO_BLK_T_IN, first, chunk.len())?;
+			chunk
+				.copy_from_slice(&state.request.as_slice()[DATA_OFFSET..DATA_OFFSET + chunk.len()]);
+		}
+
+		Ok(())
+	}
+
+	fn write_sectors(&self, sector: u64, buf: &[u8]) -> io::Result<()> {
+		if self.read_only {
+			return Err(io::Error::EROFS);
+		}
+		check_request(self, sector, buf.len())?;
+
+		let mut state = self.state.lock();
+		for (index, chunk) in buf.chunks(MAX_TRANSFER).enumerate() {
+			let first = sector + (index * MAX_TRANSFER / SECTOR_SIZE) as u64;
+
+			state.request.as_mut_slice()[DATA_OFFSET..DATA_OFFSET + chunk.len()]
+				.copy_from_slice(chunk);
+			self.request(&mut state, VIRTIO_BLK_T_OUT, first, chunk.len())?;
+		}
+
+		Ok(())
+	}
+
+	fn flush(&self) -> io::Result<()> {
+		// the data is persistent after the write cache is flushed
+		if self.flush {
+			let mut state = self.state.lock();
+			self.request(&mut state, VIRTIO_BLK_T_FLUSH, 0, 0)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Number of virtio disks, which determines the name of the next disk
+static NUM_DISKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Initialize the virtio block device `dev`
+fn probe(dev: &PciDevice) -> io::Result<()> {
+	let disk = VirtioBlk::new(dev)?;
+	let index = NUM_DISKS.fetch_add(1, Ordering::Relaxed);
+	let name = format!("vd{}", (b'a' + index as u8) as char);
+
+	register_block_device(&name, Arc::new(disk))
+}
+
+/// Driver of virtio block devices
+pub(crate) const VIRTIO_BLK_DRIVER: PciDriver = PciDriver {
+	name: "virtio-blk",
+	ids: &[(VIRTIO_VENDOR_ID, VIRTIO_BLK_DEVICE_ID)],
+	probe,
+};
@@ -0,0 +1,76 @@
+//! Physically contiguous memory, which is shared with devices
+
+use crate::arch::mm::paging::{self, PageTableEntryFlags};
+use crate::arch::mm::{physicalmem, virtualmem};
+use crate::arch::mm::{PhysAddr, VirtAddr};
+use crate::arch::{BasePageSize, PageSize};
+
+/// Memory region, which is physically contiguous and mapped into
+/// the kernel space. Devices access the region by its physical address.
+#[derive(Debug)]
+pub(crate) struct DmaBuffer {
+	/// virtual address of the region
+	virtual_address: VirtAddr,
+	/// physical address of the region
+	physical_address: PhysAddr,
+	/// size of the region, which is a multiple of the page size
+	size: usize,
+}
+
+impl DmaBuffer {
+	/// Allocate a zeroed region of at least `size` bytes, which is aligned to a page
+	pub fn new(size: usize) -> Self {
+		let size = size.next_multiple_of(BasePageSize::SIZE);
+		let physical_address = physicalmem::allocate_aligned(size, BasePageSize::SIZE);
+		let virtual_address = virtualmem::allocate_aligned(size, BasePageSize::SIZE);
+
+		paging::map::<BasePageSize>(
+			virtual_address,
+			physical_address,
+			size / BasePageSize::SIZE,
+			PageTableEntryFlags::WRITABLE | PageTableEntryFlags::EXECUTE_DISABLE,
+		);
+
+		unsafe {
+			core::ptr::write_bytes(virtual_address.as_mut_ptr::<u8>(), 0, size);
+		}
+
+		DmaBuffer {
+			virtual_address,
+			physical_address,
+			size,
+		}
+	}
+
+	/// Returns the physical address of the byte at `offset`
+	pub fn physical_address(&self, offset: usize) -> u64 {
+		debug_assert!(offset < self.size);
+
+		self.physical_address.as_u64() + offset as u64
+	}
+
+	/// Returns a pointer to the byte at `offset`
+	pub fn as_ptr<T>(&self, offset: usize) -> *mut T {
+		debug_assert!(offset < self.size);
+
+		(self.virtual_address.as_usize() + offset) as *mut T
+	}
+
+	/// Returns the region as slice
+	pub fn as_slice(&self) -> &[u8] {
+		unsafe { core::slice::from_raw_parts(self.as_ptr(0), self.size) }
+	}
+
+	/// Returns the region as mutable slice
+	pub fn as_mut_slice(&mut self) -> &mut [u8] {
+		unsafe { core::slice::from_raw_parts_mut(self.as_ptr(0), self.size) }
+	}
+}
+
+impl Drop for DmaBuffer {
+	fn drop(&mut self) {
+		paging::unmap::<BasePageSize>(self.virtual_address, self.size / BasePageSize::SIZE);
+		virtualmem::deallocate(self.virtual_address, self.size);
+		physicalmem::deallocate(self.physical_address, self.size);
+	}
+}
@@ -0,0 +1,20 @@
+//! Device drivers of the PC platform
+
+pub mod block;
+mod dma;
+pub mod net;
+pub mod pci;
+mod virtio;
+
+use crate::drivers::pci::PciDriver;
+
+/// Drivers of PCI devices, which are bound to the devices by their IDs
+static PCI_DRIVERS: &[PciDriver] = &[block::VIRTIO_BLK_DRIVER, net::VIRTIO_NET_DRIVER];
+
+/// Initialize all drivers, must be called once, and only once
+pub(crate) fn init() {
+	pci::init();
+	block::init();
+	net::init();
+	pci::bind_drivers(PCI_DRIVERS);
+}
@@ -0,0 +1,59 @@
+//! Loopback device `lo`, which returns each transmitted frame to the receiver
+
+use crate::drivers::net::{register_network_device, NetworkDevice};
+use crate::io;
+use crate::logging::*;
+use crate::net::MacAddress;
+use crate::synch::spinlock::*;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Maximum number of frames, which wait for the receiver
+const LOOPBACK_QUEUE_LEN: usize = 256;
+
+#[derive(Debug)]
+struct Loopback {
+	/// transmitted frames, which aren't received yet
+	queue: Spinlock<VecDeque<Vec<u8>>>,
+}
+
+impl NetworkDevice for Loopback {
+	fn mac_address(&self) -> MacAddress {
+		[0; 6]
+	}
+
+	fn is_loopback(&self) -> bool {
+		true
+	}
+
+	fn send(&self, frame: &[u8]) -> io::Result<()> {
+		let mut queue = self.queue.lock();
+
+		// like a real device, the loopback device drops frames, if the queue is full
+		if queue.len() < LOOPBACK_QUEUE_LEN {
+			queue.push_back(Vec::from(frame));
+		}
+
+		Ok(())
+	}
+
+	fn receive(&self, buf: &mut [u8]) -> Option<usize> {
+		let frame = self.queue.lock().pop_front()?;
+		let len = frame.len().min(buf.len());
+		buf[..len].copy_from_slice(&frame[..len]);
+
+		Some(len)
+	}
+}
+
+/// Register the loopback device
+pub(crate) fn init() {
+	let device = Loopback {
+		queue: Spinlock::new(VecDeque::new()),
+	};
+
+	if let Err(e) = register_network_device("lo", Arc::new(device)) {
+		warn!("Unable to register the loopback device: {:?}", e);
+	}
+}
@@ -0,0 +1,54 @@
+//! Abstraction of network devices, which send and receive Ethernet frames
+//!
+//! Drivers register their devices by a name, e.g. `eth0`. The network
+//! stack creates an interface for each registered device. The devices
+//! are polled by the stack, i.e. they don't raise interrupts. The
+//! loopback device `lo` is always available.
+
+mod loopback;
+mod virtio;
+
+pub(crate) use self::virtio::VIRTIO_NET_DRIVER;
+
+use crate::io;
+use crate::logging::*;
+use crate::net::MacAddress;
+use alloc::sync::Arc;
+
+/// Maximum size of an Ethernet frame without the frame check sequence
+pub const MAX_FRAME_SIZE: usize = 1514;
+
+/// The trait `NetworkDevice` specifies a device, which
+/// transmits Ethernet frames
+pub trait NetworkDevice: core::fmt::Debug + core::marker::Send + core::marker::Sync {
+	/// Returns the hardware address of the device
+	fn mac_address(&self) -> MacAddress;
+
+	/// Returns true, if the device delivers its frames to the own stack
+	fn is_loopback(&self) -> bool {
+		false
+	}
+
+	/// Transmit the frame `frame`, which contains the Ethernet header
+	fn send(&self, frame: &[u8]) -> io::Result<()>;
+
+	/// Copy the next received frame into `buf`, which holds at least
+	/// `MAX_FRAME_SIZE` bytes. Returns the length of the frame or
+	/// `None`, if no frame is available.
+	fn receive(&self, buf: &mut [u8]) -> Option<usize>;
+}
+
+/// Register the network device `dev` by the name `name`
+pub fn register_network_device(name: &str, dev: Arc<dyn NetworkDevice>) -> io::Result<()> {
+	if name.is_empty() || name.contains('/') {
+		return Err(io::Error::EINVAL);
+	}
+
+	info!("Register network device {}", name);
+	crate::net::add_interface(name, dev)
+}
+
+/// Register the devices, which don't depend on the hardware
+pub(crate) fn init() {
+	loopback::init();
+}
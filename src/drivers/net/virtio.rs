@@ -0,0 +1,183 @@
+//! Driver for virtio network devices (virtio-net)
+//!
+//! Each device uses a receive and a transmit queue. All receive buffers
+//! are posted in advance and are reposted after their frame is copied.
+//! The devices are registered as `eth0`, `eth1` and so on.
+
+use crate::drivers::dma::DmaBuffer;
+use crate::drivers::net::{register_network_device, NetworkDevice, MAX_FRAME_SIZE};
+use crate::drivers::pci::{PciDevice, PciDriver};
+use crate::drivers::virtio::*;
+use crate::io;
+use crate::net::MacAddress;
+use crate::synch::spinlock::*;
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Device ID of a transitional network device, which supports the legacy interface
+const VIRTIO_NET_DEVICE_ID: u16 = 0x1000;
+
+/// Feature: the configuration contains the MAC address
+const VIRTIO_NET_F_MAC: u32 = 1 << 5;
+
+/// Index of the receive queue
+const RX_QUEUE: u16 = 0;
+/// Index of the transmit queue
+const TX_QUEUE: u16 = 1;
+
+/// Size of the header, which precedes each frame
+const HEADER_SIZE: usize = 10;
+/// Size of a receive or transmit buffer
+const BUFFER_SIZE: usize = 2048;
+/// Maximum number of buffers per queue
+const MAX_BUFFERS: usize = 32;
+
+/// Address of the device, if the configuration doesn't contain an address
+const DEFAULT_MAC: MacAddress = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+
+/// Queue and buffers of one direction
+#[derive(Debug)]
+struct VirtioNetQueue {
+	queue: Virtqueue,
+	/// one buffer per descriptor
+	buffers: DmaBuffer,
+	/// descriptors, which aren't owned by the device
+	free: Vec<u16>,
+}
+
+impl VirtioNetQueue {
+	fn new(queue: Virtqueue) -> Self {
+		let count = queue.size().min(MAX_BUFFERS);
+
+		VirtioNetQueue {
+			queue,
+			buffers: DmaBuffer::new(count * BUFFER_SIZE),
+			free: (0..count as u16).collect(),
+		}
+	}
+
+	/// Pass the buffer `id` with `len` bytes to the device
+	fn post(&mut self, id: u16, len: usize, flags: u16) {
+		let offset = usize::from(id) * BUFFER_SIZE;
+
+		self.queue.set_descriptor(
+			usize::from(id),
+			self.buffers.physical_address(offset),
+			len,
+			flags,
+			0,
+		);
+		self.queue.push(id);
+	}
+}
+
+#[derive(Debug)]
+struct VirtioNet {
+	/// registers of the device
+	device: LegacyDevice,
+	mac: MacAddress,
+	rx: Spinlock<VirtioNetQueue>,
+	tx: Spinlock<VirtioNetQueue>,
+}
+
+impl VirtioNet {
+	/// Initialize the device `dev` as described by the legacy interface
+	fn new(dev: &PciDevice) -> io::Result<Self> {
+		let (device, features) = LegacyDevice::new(dev, VIRTIO_NET_F_MAC)?;
+		let mut rx = VirtioNetQueue::new(device.setup_queue(RX_QUEUE, 1)?);
+		let tx = VirtioNetQueue::new(device.setup_queue(TX_QUEUE, 1)?);
+
+		let mut mac = DEFAULT_MAC;
+		if features & VIRTIO_NET_F_MAC != 0 {
+			for (offset, byte) in mac.iter_mut().enumerate() {
+				*byte = device.read_config_u8(offset as u16);
+			}
+		}
+
+		// the device owns all receive buffers
+		while let Some(id) = rx.free.pop() {
+			rx.post(id, BUFFER_SIZE, VIRTQ_DESC_F_WRITE);
+		}
+		device.driver_ok();
+		device.notify(RX_QUEUE);
+
+		Ok(VirtioNet {
+			device,
+			mac,
+			rx: Spinlock::new(rx),
+			tx: Spinlock::new(tx),
+		})
+	}
+}
+
+impl NetworkDevice for VirtioNet {
+	fn mac_address(&self) -> MacAddress {
+		self.mac
+	}
+
+	fn send(&self, frame: &[u8]) -> io::Result<()> {
+		if frame.len() > MAX_FRAME_SIZE {
+			return Err(io::Error::EMSGSIZE);
+		}
+
+		let mut tx = self.tx.lock();
+		// reclaim the buffers of transmitted frames
+		while let Some((id, _)) = tx.queue.pop_used() {
+			tx.free.push(id);
+		}
+		let id = match tx.free.pop() {
+			Some(id) => id,
+			None => tx.queue.wait_used()?.0,
+		};
+
+		let offset = usize::from(id) * BUFFER_SIZE;
+		let buffer = &mut tx.buffers.as_mut_slice()[offset..offset + BUFFER_SIZE];
+		buffer[..HEADER_SIZE].fill(0);
+		buffer[HEADER_SIZE..HEADER_SIZE + frame.len()].copy_from_slice(frame);
+
+		tx.post(id, HEADER_SIZE + frame.len(), 0);
+		self.device.notify(TX_QUEUE);
+
+		Ok(())
+	}
+
+	fn receive(&self, buf: &mut [u8]) -> Option<usize> {
+		let mut rx = self.rx.lock();
+		let (id, len) = rx.queue.pop_used()?;
+		self.device.acknowledge();
+
+		let offset = usize::from(id) * BUFFER_SIZE;
+		let len = len
+			.saturating_sub(HEADER_SIZE)
+			.min(BUFFER_SIZE - HEADER_SIZE)
+			.min(buf.len());
+		let start = offset + HEADER_SIZE;
+		buf[..len].copy_from_slice(&rx.buffers.as_slice()[start..start + len]);
+
+		rx.post(id, BUFFER_SIZE, VIRTQ_DESC_F_WRITE);
+		self.device.notify(RX_QUEUE);
+
+		Some(len)
+	}
+}
+
+/// Number of virtio network devices, which determines the name of the next device
+static NUM_DEVICES: AtomicUsize = AtomicUsize::new(0);
+
+/// Initialize the virtio network device `dev`
+fn probe(dev: &PciDevice) -> io::Result<()> {
+	let device = VirtioNet::new(dev)?;
+	let index = NUM_DEVICES.fetch_add(1, Ordering::Relaxed);
+	let name = format!("eth{}", index);
+
+	register_network_device(&name, Arc::new(device))
+}
+
+/// Driver of virtio network devices
+pub(crate) const VIRTIO_NET_DRIVER: PciDriver = PciDriver {
+	name: "virtio-net",
+	ids: &[(VIRTIO_VENDOR_ID, VIRTIO_NET_DEVICE_ID)],
+	probe,
+};
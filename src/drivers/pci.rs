@@ -0,0 +1,647 @@
+//! Enumerates the devices on the PCI bus
+//!
+//! The configuration space is accessed through the memory mapped
+//! configuration space (ECAM), if the ACPI table MCFG describes it.
+//! Otherwise, the legacy mechanism is used, i.e. the I/O ports `0xCF8`
+//! and `0xCFC`, which only provides the first 256 bytes of each function.
+//!
+//! The enumeration starts at the host bridges and follows the
+//! PCI-to-PCI bridges. Afterwards, each device is bound to the first
+//! driver of the driver table, which lists its vendor and device ID.
+
+use crate::arch::acpi;
+use crate::arch::mm::paging::{self, PageTableEntryFlags};
+use crate::arch::mm::{virtualmem, PhysAddr, VirtAddr};
+use crate::arch::{BasePageSize, PageSize};
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::DisabledPreemption;
+use crate::synch::spinlock::*;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ptr::{read_volatile, write_volatile};
+use x86::io::*;
+
+/// Port to select a register of the configuration space
+const CONFIG_ADDRESS: u16 = 0xCF8;
+/// Port to access the selected register
+const CONFIG_DATA: u16 = 0xCFC;
+/// Enables the access to the configuration space
+const CONFIG_ENABLE: u32 = 1 << 31;
+/// Size of the configuration space, which is accessible by the I/O ports
+const LEGACY_CONFIG_SIZE: u16 = 256;
+/// Size of the configuration space of a function, if ECAM is used
+const ECAM_CONFIG_SIZE: u16 = 4096;
+/// Size of the memory mapped configuration space of a bus
+const ECAM_BUS_SIZE: usize = 1 << 20;
+
+/// Number of devices per bus
+const PCI_MAX_DEVICE: u8 = 32;
+/// Number of functions per device
+const PCI_MAX_FUNCTION: u8 = 8;
+
+// offsets of the registers of the configuration space
+pub const PCI_VENDOR_ID: u16 = 0x00;
+pub const PCI_COMMAND: u16 = 0x04;
+pub const PCI_CLASS_REVISION: u16 = 0x08;
+pub const PCI_HEADER_TYPE: u16 = 0x0C;
+pub const PCI_BAR0: u16 = 0x10;
+pub const PCI_BUS_NUMBERS: u16 = 0x18;
+pub const PCI_CAPABILITY_LIST: u16 = 0x34;
+pub const PCI_INTERRUPT_LINE: u16 = 0x3C;
+
+/// Vendor ID of an absent function
+const PCI_NONE: u16 = 0xFFFF;
+/// The header type marks a device with several functions
+const PCI_MULTIFUNCTION: u32 = 0x80 << 16;
+/// Layout of the header, i.e. the header type without the multifunction bit
+const PCI_HEADER_LAYOUT: u32 = 0x7F << 16;
+/// Layout of the header of a PCI-to-PCI bridge
+const PCI_HEADER_BRIDGE: u32 = 0x01 << 16;
+/// Number of base address registers of a general device
+const PCI_NUM_BARS: u8 = 6;
+/// Number of base address registers of a PCI-to-PCI bridge
+const PCI_NUM_BRIDGE_BARS: u8 = 2;
+
+/// Class code of bridges
+const PCI_CLASS_BRIDGE: u8 = 0x06;
+/// Subclass of PCI-to-PCI bridges
+const PCI_SUBCLASS_PCI_BRIDGE: u8 = 0x04;
+
+/// Command register: the device responds to I/O accesses
+pub const PCI_COMMAND_IO: u16 = 1 << 0;
+/// Command register: the device responds to memory accesses
+pub const PCI_COMMAND_MEMORY: u16 = 1 << 1;
+/// Command register: the device is allowed to access the memory (DMA)
+pub const PCI_COMMAND_MASTER: u16 = 1 << 2;
+/// Command register: the device doesn't assert its legacy interrupt
+pub const PCI_COMMAND_INTX_DISABLE: u16 = 1 << 10;
+/// Status register (upper half of the command register): the capability list exists
+const PCI_STATUS_CAPABILITIES: u32 = 1 << (16 + 4);
+
+/// The base address register describes I/O ports
+const PCI_BAR_IO: u32 = 1 << 0;
+/// Type of a memory base address register: 64 bit address
+const PCI_BAR_MEMORY_64: u32 = 0x2 << 1;
+/// Mask of the type of a memory base address register
+const PCI_BAR_MEMORY_TYPE: u32 = 0x3 << 1;
+/// The memory region is prefetchable
+const PCI_BAR_PREFETCHABLE: u32 = 1 << 3;
+
+/// Capability ID of Message Signaled Interrupts
+pub const PCI_CAP_ID_MSI: u8 = 0x05;
+/// Capability ID of the extended version of MSI
+pub const PCI_CAP_ID_MSIX: u8 = 0x11;
+
+/// Devices, which are found at the boot time
+static DEVICES: Spinlock<Vec<PciDevice>> = Spinlock::new(Vec::new());
+
+/// Memory mapped configuration space, if the firmware provides it
+static ECAM: Spinlock<Option<Ecam>> = Spinlock::new(None);
+
+/// Memory mapped configuration space of the PCI segment group 0
+#[derive(Debug)]
+struct Ecam {
+	/// physical address of the configuration space of bus 0
+	base: u64,
+	/// first bus, which is described
+	start_bus: u8,
+	/// last bus, which is described
+	end_bus: u8,
+	/// buses, whose configuration space is already mapped
+	buses: BTreeMap<u8, VirtAddr>,
+}
+
+impl Ecam {
+	/// Returns the address of the configuration space of the bus `bus`,
+	/// which is mapped on the first access
+	fn bus_address(&mut self, bus: u8) -> Option<VirtAddr> {
+		if bus < self.start_bus || bus > self.end_bus {
+			return None;
+		}
+
+		let base = self.base;
+		let address = *self.buses.entry(bus).or_insert_with(|| {
+			let virtual_address = virtualmem::allocate(ECAM_BUS_SIZE);
+			let mut flags = PageTableEntryFlags::empty();
+			flags.device().writable().execute_disable();
+
+			paging::map::<BasePageSize>(
+				virtual_address,
+				PhysAddr(base + (u64::from(bus) << 20)),
+				ECAM_BUS_SIZE / BasePageSize::SIZE,
+				flags,
+			);
+
+			virtual_address
+		});
+
+		Some(address)
+	}
+}
+
+/// Position of a function on the PCI bus
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PciAddress {
+	pub bus: u8,
+	pub device: u8,
+	pub function: u8,
+}
+
+impl PciAddress {
+	pub const fn new(bus: u8, device: u8, function: u8) -> Self {
+		PciAddress {
+			bus,
+			device,
+			function,
+		}
+	}
+
+	fn config_address(&self, offset: u16) -> u32 {
+		CONFIG_ENABLE
+			| u32::from(self.bus) << 16
+			| u32::from(self.device) << 11
+			| u32::from(self.function) << 8
+			| u32::from(offset & 0xFC)
+	}
+
+	/// Returns the address of the register at `offset`, if ECAM is available
+	fn ecam_address(&self, offset: u16) -> Option<*mut u32> {
+		let bus = ECAM.lock().as_mut()?.bus_address(self.bus)?;
+		let offset = usize::from(self.device) << 15
+			| usize::from(self.function) << 12
+			| usize::from(offset & 0xFFC);
+
+		Some((bus.as_usize() + offset) as *mut u32)
+	}
+
+	/// Read the 32 bit register at `offset` of the configuration space.
+	/// Registers beyond the first 256 bytes require ECAM.
+	pub fn read_config(&self, offset: u16) -> u32 {
+		if offset >= ECAM_CONFIG_SIZE {
+			return u32::MAX;
+		}
+
+		if let Some(register) = self.ecam_address(offset) {
+			return unsafe { read_volatile(register) };
+		}
+
+		if offset >= LEGACY_CONFIG_SIZE {
+			return u32::MAX;
+		}
+
+		let _preemption = DisabledPreemption::new();
+
+		unsafe {
+			outl(CONFIG_ADDRESS, self.config_address(offset));
+			inl(CONFIG_DATA)
+		}
+	}
+
+	/// Write the 32 bit register at `offset` of the configuration space
+	pub fn write_config(&self, offset: u16, value: u32) {
+		if offset >= ECAM_CONFIG_SIZE {
+			return;
+		}
+
+		if let Some(register) = self.ecam_address(offset) {
+			unsafe {
+				write_volatile(register, value);
+			}
+			return;
+		}
+
+		if offset >= LEGACY_CONFIG_SIZE {
+			return;
+		}
+
+		let _preemption = DisabledPreemption::new();
+
+		unsafe {
+			outl(CONFIG_ADDRESS, self.config_address(offset));
+			outl(CONFIG_DATA, value);
+		}
+	}
+}
+
+impl core::fmt::Display for PciAddress {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{:02x}:{:02x}.{}", self.bus, self.device, self.function)
+	}
+}
+
+/// Resource, which is described by a base address register
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bar {
+	/// range of I/O ports
+	Io { port: u16, size: u32 },
+	/// region of the physical address space
+	Memory {
+		address: u64,
+		size: u64,
+		prefetchable: bool,
+	},
+}
+
+/// Memory region of a device, which is mapped into the kernel space
+#[derive(Debug)]
+pub struct MmioRegion {
+	/// first mapped page
+	virtual_address: VirtAddr,
+	/// size of the mapped pages
+	mapped_size: usize,
+	/// offset of the region within the first page
+	offset: usize,
+	/// size of the region
+	size: usize,
+}
+
+impl MmioRegion {
+	/// Map the region of `size` bytes at the physical address `address`
+	/// as uncached device memory
+	fn new(address: u64, size: usize) -> Self {
+		let offset = (address % BasePageSize::SIZE as u64) as usize;
+		let mapped_size = (offset + size).next_multiple_of(BasePageSize::SIZE);
+		let virtual_address = virtualmem::allocate(mapped_size);
+		let mut flags = PageTableEntryFlags::empty();
+		flags.device().writable().execute_disable();
+
+		paging::map::<BasePageSize>(
+			virtual_address,
+			PhysAddr(address - offset as u64),
+			mapped_size / BasePageSize::SIZE,
+			flags,
+		);
+
+		MmioRegion {
+			virtual_address,
+			mapped_size,
+			offset,
+			size,
+		}
+	}
+
+	/// Returns the size of the region
+	pub fn len(&self) -> usize {
+		self.size
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.size == 0
+	}
+
+	/// Returns a pointer to the register at `offset`
+	pub fn as_ptr<T>(&self, offset: usize) -> *mut T {
+		assert!(offset + core::mem::size_of::<T>() <= self.size);
+
+		(self.virtual_address.as_usize() + self.offset + offset) as *mut T
+	}
+
+	/// Read the register at `offset`
+	pub fn read<T: Copy>(&self, offset: usize) -> T {
+		unsafe { read_volatile(self.as_ptr(offset)) }
+	}
+
+	/// Write `value` to the register at `offset`
+	pub fn write<T: Copy>(&self, offset: usize, value: T) {
+		unsafe { write_volatile(self.as_ptr(offset), value) }
+	}
+}
+
+impl Drop for MmioRegion {
+	fn drop(&mut self) {
+		paging::unmap::<BasePageSize>(self.virtual_address, self.mapped_size / BasePageSize::SIZE);
+		virtualmem::deallocate(self.virtual_address, self.mapped_size);
+	}
+}
+
+/// Function of a device on the PCI bus
+#[derive(Copy, Clone, Debug)]
+pub struct PciDevice {
+	/// position on the bus
+	pub address: PciAddress,
+	pub vendor_id: u16,
+	pub device_id: u16,
+	pub class: u8,
+	pub subclass: u8,
+	pub prog_if: u8,
+	/// name of the driver, which is bound to the device
+	pub driver: Option<&'static str>,
+}
+
+impl PciDevice {
+	/// Returns true, if the function is a PCI-to-PCI bridge
+	fn is_bridge(&self) -> bool {
+		self.address.read_config(PCI_HEADER_TYPE) & PCI_HEADER_LAYOUT == PCI_HEADER_BRIDGE
+	}
+
+	/// Decode the base address register `index`. The size of the
+	/// resource is determined by writing all ones to the register.
+	/// A 64 bit memory address occupies the registers `index` and `index + 1`.
+	pub fn bar(&self, index: u8) -> Option<Bar> {
+		let num_bars = if self.is_bridge() {
+			PCI_NUM_BRIDGE_BARS
+		} else {
+			PCI_NUM_BARS
+		};
+		if index >= num_bars {
+			return None;
+		}
+
+		let offset = PCI_BAR0 + 4 * u16::from(index);
+		let bar = self.address.read_config(offset);
+		let is_64bit = bar & PCI_BAR_IO == 0 && bar & PCI_BAR_MEMORY_TYPE == PCI_BAR_MEMORY_64;
+		if is_64bit && index + 1 >= num_bars {
+			return None;
+		}
+
+		// the device must not decode the addresses during the probing
+		let _preemption = DisabledPreemption::new();
+		let command = self.address.read_config(PCI_COMMAND) & 0xFFFF;
+		self.address.write_config(
+			PCI_COMMAND,
+			command & !u32::from(PCI_COMMAND_IO | PCI_COMMAND_MEMORY),
+		);
+
+		let size_mask = |offset: u16| {
+			let value = self.address.read_config(offset);
+			self.address.write_config(offset, u32::MAX);
+			let mask = self.address.read_config(offset);
+			self.address.write_config(offset, value);
+			mask
+		};
+		let mask = size_mask(offset);
+		let upper = if is_64bit {
+			Some((self.address.read_config(offset + 4), size_mask(offset + 4)))
+		} else {
+			None
+		};
+
+		self.address.write_config(PCI_COMMAND, command);
+
+		if bar & PCI_BAR_IO != 0 {
+			if mask & !0x3 == 0 {
+				return None;
+			}
+			let size = (!(mask & !0x3) & 0xFFFF).wrapping_add(1);
+
+			return Some(Bar::Io {
+				port: (bar & !0x3) as u16,
+				size,
+			});
+		}
+
+		let (address, mask) = match upper {
+			Some((high, high_mask)) => (
+				u64::from(high) << 32 | u64::from(bar & !0xF),
+				u64::from(high_mask) << 32 | u64::from(mask & !0xF),
+			),
+			None => (
+				u64::from(bar & !0xF),
+				u64::from(mask & !0xF) | 0xFFFF_FFFF_0000_0000,
+			),
+		};
+		// the register isn't implemented
+		if mask == 0 || mask == 0xFFFF_FFFF_0000_0000 {
+			return None;
+		}
+
+		Some(Bar::Memory {
+			address,
+			size: (!mask).wrapping_add(1),
+			prefetchable: bar & PCI_BAR_PREFETCHABLE != 0,
+		})
+	}
+
+	/// Returns the first I/O port, which is described by the base address register `index`
+	pub fn io_bar(&self, index: u8) -> Option<u16> {
+		match self.bar(index)? {
+			Bar::Io { port, .. } if port != 0 => Some(port),
+			_ => None,
+		}
+	}
+
+	/// Map the memory region, which is described by the base address
+	/// register `index`, into the kernel space
+	pub fn map_bar(&self, index: u8) -> io::Result<MmioRegion> {
+		match self.bar(index) {
+			Some(Bar::Memory { address, size, .. }) if address != 0 => {
+				Ok(MmioRegion::new(address, size as usize))
+			}
+			_ => Err(io::Error::ENODEV),
+		}
+	}
+
+	/// Returns the legacy interrupt line, which is assigned by the firmware
+	pub fn interrupt_line(&self) -> u8 {
+		self.address.read_config(PCI_INTERRUPT_LINE) as u8
+	}
+
+	/// Set the bits `flags` of the command register
+	pub fn enable(&self, flags: u16) {
+		// writing ones to the status bits would clear them
+		let command = self.address.read_config(PCI_COMMAND) & 0xFFFF;
+		self.address
+			.write_config(PCI_COMMAND, command | u32::from(flags));
+	}
+
+	/// Clear the bits `flags` of the command register
+	pub fn disable(&self, flags: u16) {
+		let command = self.address.read_config(PCI_COMMAND) & 0xFFFF;
+		self.address
+			.write_config(PCI_COMMAND, command & !u32::from(flags));
+	}
+
+	/// Returns the ID and the offset of each capability
+	pub fn capabilities(&self) -> Vec<(u8, u16)> {
+		let mut capabilities = Vec::new();
+
+		if self.address.read_config(PCI_COMMAND) & PCI_STATUS_CAPABILITIES == 0 {
+			return capabilities;
+		}
+
+		let mut offset = (self.address.read_config(PCI_CAPABILITY_LIST) & 0xFC) as u16;
+		// the list is limited to the header, which protects against loops
+		while offset != 0 && capabilities.len() < 48 {
+			let header = self.address.read_config(offset);
+			capabilities.push((header as u8, offset));
+			offset = ((header >> 8) & 0xFC) as u16;
+		}
+
+		capabilities
+	}
+
+	/// Returns the offset of the capability `id`
+	pub fn find_capability(&self, id: u8) -> Option<u16> {
+		self.capabilities()
+			.into_iter()
+			.find(|(cap_id, _)| *cap_id == id)
+			.map(|(_, offset)| offset)
+	}
+}
+
+/// Driver of PCI devices, which is bound to all devices with a matching ID
+#[derive(Debug)]
+pub struct PciDriver {
+	/// name of the driver
+	pub name: &'static str,
+	/// pairs of vendor and device ID, which are supported by the driver
+	pub ids: &'static [(u16, u16)],
+	/// initialize the device, which is called for each matching device
+	pub probe: fn(&PciDevice) -> io::Result<()>,
+}
+
+impl PciDriver {
+	fn matches(&self, dev: &PciDevice) -> bool {
+		self.ids
+			.iter()
+			.any(|&(vendor_id, device_id)| dev.vendor_id == vendor_id && dev.device_id == device_id)
+	}
+}
+
+/// Returns the function at `address`, if it exists
+fn probe(address: PciAddress) -> Option<PciDevice> {
+	let id = address.read_config(PCI_VENDOR_ID);
+	if id as u16 == PCI_NONE {
+		return None;
+	}
+
+	let class = address.read_config(PCI_CLASS_REVISION);
+
+	Some(PciDevice {
+		address,
+		vendor_id: id as u16,
+		device_id: (id >> 16) as u16,
+		class: (class >> 24) as u8,
+		subclass: (class >> 16) as u8,
+		prog_if: (class >> 8) as u8,
+		driver: None,
+	})
+}
+
+/// Add all functions on the bus `bus` and on the buses behind its bridges
+fn scan_bus(bus: u8, devices: &mut Vec<PciDevice>) {
+	for device in 0..PCI_MAX_DEVICE {
+		let Some(dev) = probe(PciAddress::new(bus, device, 0)) else {
+			continue;
+		};
+
+		let functions = if dev.address.read_config(PCI_HEADER_TYPE) & PCI_MULTIFUNCTION != 0 {
+			PCI_MAX_FUNCTION
+		} else {
+			1
+		};
+
+		for function in 0..functions {
+			let Some(dev) = probe(PciAddress::new(bus, device, function)) else {
+				continue;
+			};
+			devices.push(dev);
+
+			if dev.class == PCI_CLASS_BRIDGE && dev.subclass == PCI_SUBCLASS_PCI_BRIDGE {
+				let secondary = (dev.address.read_config(PCI_BUS_NUMBERS) >> 8) as u8;
+				// the secondary bus follows the bus of the bridge
+				if secondary > bus {
+					scan_bus(secondary, devices);
+				}
+			}
+		}
+	}
+}
+
+/// Use the memory mapped configuration space, which is described by the ACPI table MCFG
+fn init_ecam() {
+	let Some(mcfg) = acpi::get_table(b"MCFG") else {
+		return;
+	};
+
+	// the allocation structures follow 8 reserved bytes
+	let entries = mcfg.body().get(8..).unwrap_or_default();
+	for entry in entries.chunks_exact(16) {
+		let base = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+		let segment = u16::from_le_bytes([entry[8], entry[9]]);
+		let (start_bus, end_bus) = (entry[10], entry[11]);
+
+		if segment == 0 {
+			info!(
+				"PCI configuration space at 0x{:x} (buses {}-{})",
+				base, start_bus, end_bus
+			);
+			*ECAM.lock() = Some(Ecam {
+				base,
+				start_bus,
+				end_bus,
+				buses: BTreeMap::new(),
+			});
+			return;
+		}
+	}
+}
+
+/// Returns all devices on the PCI bus
+pub fn devices() -> Vec<PciDevice> {
+	DEVICES.lock().clone()
+}
+
+/// Returns all devices with the vendor ID `vendor_id` and the device ID `device_id`
+pub fn find_devices(vendor_id: u16, device_id: u16) -> Vec<PciDevice> {
+	DEVICES
+		.lock()
+		.iter()
+		.filter(|dev| dev.vendor_id == vendor_id && dev.device_id == device_id)
+		.copied()
+		.collect()
+}
+
+/// Bind each device to the first driver of `drivers`, which supports the device
+pub(crate) fn bind_drivers(drivers: &[PciDriver]) {
+	for dev in devices() {
+		let Some(driver) = drivers.iter().find(|driver| driver.matches(&dev)) else {
+			continue;
+		};
+
+		// the lock isn't held, because drivers may look up other devices
+		match (driver.probe)(&dev) {
+			Ok(()) => {
+				info!("PCI {}: bound to driver {}", dev.address, driver.name);
+				if let Some(entry) = DEVICES
+					.lock()
+					.iter_mut()
+					.find(|entry| entry.address == dev.address)
+				{
+					entry.driver = Some(driver.name);
+				}
+			}
+			Err(e) => warn!(
+				"PCI {}: driver {} failed with {:?}",
+				dev.address, driver.name, e
+			),
+		}
+	}
+}
+
+/// Scan all buses, must be called once, and only once
+pub(crate) fn init() {
+	init_ecam();
+
+	let mut devices = DEVICES.lock();
+
+	// the functions of a multifunction host bridge are the host bridges of further buses
+	let host = PciAddress::new(0, 0, 0);
+	if probe(host).is_some() && host.read_config(PCI_HEADER_TYPE) & PCI_MULTIFUNCTION != 0 {
+		for function in 0..PCI_MAX_FUNCTION {
+			if probe(PciAddress::new(0, 0, function)).is_some() {
+				scan_bus(function, &mut devices);
+			}
+		}
+	} else {
+		scan_bus(0, &mut devices);
+	}
+
+	for dev in devices.iter() {
+		info!(
+			"PCI {}: {:04x}:{:04x} (class {:02x}:{:02x})",
+			dev.address, dev.vendor_id, dev.device_id, dev.class, dev.subclass
+		);
+	}
+}
@@ -0,0 +1,251 @@
+//! Common parts of the virtio drivers
+//!
+//! The drivers use the legacy interface of virtio, which is provided
+//! by the I/O ports of the first base address register. The used rings
+//! are polled, i.e. interrupts are suppressed.
+
+use crate::drivers::dma::DmaBuffer;
+use crate::drivers::pci::{PciDevice, PCI_COMMAND_IO, PCI_COMMAND_MASTER};
+use crate::io;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{fence, Ordering};
+use x86::io::*;
+
+/// Vendor ID of virtio devices
+pub(crate) const VIRTIO_VENDOR_ID: u16 = 0x1AF4;
+
+// offsets of the legacy registers
+const VIRTIO_PCI_HOST_FEATURES: u16 = 0x00;
+const VIRTIO_PCI_GUEST_FEATURES: u16 = 0x04;
+const VIRTIO_PCI_QUEUE_PFN: u16 = 0x08;
+const VIRTIO_PCI_QUEUE_NUM: u16 = 0x0C;
+const VIRTIO_PCI_QUEUE_SEL: u16 = 0x0E;
+const VIRTIO_PCI_QUEUE_NOTIFY: u16 = 0x10;
+const VIRTIO_PCI_STATUS: u16 = 0x12;
+const VIRTIO_PCI_ISR: u16 = 0x13;
+/// Offset of the device-specific configuration without MSI-X
+pub(crate) const VIRTIO_PCI_CONFIG: u16 = 0x14;
+
+// bits of the device status
+const VIRTIO_STATUS_ACKNOWLEDGE: u8 = 1;
+const VIRTIO_STATUS_DRIVER: u8 = 2;
+const VIRTIO_STATUS_DRIVER_OK: u8 = 4;
+const VIRTIO_STATUS_FAILED: u8 = 128;
+
+/// Alignment of the used ring in the legacy interface
+const VIRTIO_PCI_VRING_ALIGN: usize = 4096;
+/// The queue address is given as page frame number
+const VIRTIO_PCI_QUEUE_ADDR_SHIFT: u32 = 12;
+
+// flags of a descriptor
+pub(crate) const VIRTQ_DESC_F_NEXT: u16 = 1;
+pub(crate) const VIRTQ_DESC_F_WRITE: u16 = 2;
+/// The device doesn't need to signal used buffers by interrupts
+const VIRTQ_AVAIL_F_NO_INTERRUPT: u16 = 1;
+
+/// Size of a descriptor
+const DESC_SIZE: usize = 16;
+
+/// Number of polls of the used ring, before a request is aborted
+const VIRTIO_TIMEOUT: usize = 100_000_000;
+
+/// Virtqueue in the layout of the legacy interface, i.e. the
+/// descriptor table, the available and the used ring are stored
+/// in a physically contiguous region
+#[derive(Debug)]
+pub(crate) struct Virtqueue {
+	/// memory of the queue
+	memory: DmaBuffer,
+	/// number of descriptors
+	size: usize,
+	/// offset of the available ring
+	avail: usize,
+	/// offset of the used ring
+	used: usize,
+	/// index of the next entry of the available ring
+	avail_idx: u16,
+	/// index of the next entry of the used ring, which is consumed
+	used_idx: u16,
+}
+
+impl Virtqueue {
+	fn new(size: usize) -> Self {
+		let avail = size * DESC_SIZE;
+		let used = (avail + 6 + 2 * size).next_multiple_of(VIRTIO_PCI_VRING_ALIGN);
+		let memory = DmaBuffer::new(used + 6 + 8 * size);
+
+		let queue = Virtqueue {
+			memory,
+			size,
+			avail,
+			used,
+			avail_idx: 0,
+			used_idx: 0,
+		};
+		unsafe {
+			write_volatile(
+				queue.memory.as_ptr::<u16>(avail),
+				VIRTQ_AVAIL_F_NO_INTERRUPT,
+			);
+		}
+
+		queue
+	}
+
+	/// Returns the number of descriptors
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	pub fn set_descriptor(&mut self, index: usize, addr: u64, len: usize, flags: u16, next: u16) {
+		let offset = index * DESC_SIZE;
+
+		unsafe {
+			write_volatile(self.memory.as_ptr::<u64>(offset), addr);
+			write_volatile(self.memory.as_ptr::<u32>(offset + 8), len as u32);
+			write_volatile(self.memory.as_ptr::<u16>(offset + 12), flags);
+			write_volatile(self.memory.as_ptr::<u16>(offset + 14), next);
+		}
+	}
+
+	/// Publish the descriptor chain starting at `head` in the available ring
+	pub fn push(&mut self, head: u16) {
+		let slot = usize::from(self.avail_idx) % self.size;
+
+		unsafe {
+			write_volatile(self.memory.as_ptr::<u16>(self.avail + 4 + 2 * slot), head);
+			// the device has to see the entry before the new index
+			fence(Ordering::SeqCst);
+			self.avail_idx = self.avail_idx.wrapping_add(1);
+			write_volatile(self.memory.as_ptr::<u16>(self.avail + 2), self.avail_idx);
+			fence(Ordering::SeqCst);
+		}
+	}
+
+	/// Returns the head and the number of written bytes of the next
+	/// descriptor chain, which the device has returned in the used ring
+	pub fn pop_used(&mut self) -> Option<(u16, usize)> {
+		let idx = unsafe { read_volatile(self.memory.as_ptr::<u16>(self.used + 2)) };
+
+		if idx == self.used_idx {
+			return None;
+		}
+
+		fence(Ordering::SeqCst);
+		let slot = usize::from(self.used_idx) % self.size;
+		let (id, len) = unsafe {
+			(
+				read_volatile(self.memory.as_ptr::<u32>(self.used + 4 + 8 * slot)),
+				read_volatile(self.memory.as_ptr::<u32>(self.used + 8 + 8 * slot)),
+			)
+		};
+		self.used_idx = self.used_idx.wrapping_add(1);
+
+		Some((id as u16, len as usize))
+	}
+
+	/// Wait until the device returns a descriptor chain in the used ring
+	pub fn wait_used(&mut self) -> io::Result<(u16, usize)> {
+		for _ in 0..VIRTIO_TIMEOUT {
+			if let Some(used) = self.pop_used() {
+				return Ok(used);
+			}
+			core::hint::spin_loop();
+		}
+
+		Err(io::Error::EIO)
+	}
+}
+
+/// Registers of a device, which implements the legacy interface
+#[derive(Debug)]
+pub(crate) struct LegacyDevice {
+	/// first I/O port of the device
+	base: u16,
+}
+
+impl LegacyDevice {
+	/// Reset the device `dev` and negotiate the features, the driver
+	/// supports the features `supported`. Returns the device and the
+	/// negotiated features.
+	pub fn new(dev: &PciDevice, supported: u32) -> io::Result<(Self, u32)> {
+		let base = dev.io_bar(0).ok_or(io::Error::ENODEV)?;
+		dev.enable(PCI_COMMAND_IO | PCI_COMMAND_MASTER);
+
+		unsafe {
+			// reset the device and announce the driver
+			outb(base + VIRTIO_PCI_STATUS, 0);
+			outb(base + VIRTIO_PCI_STATUS, VIRTIO_STATUS_ACKNOWLEDGE);
+			outb(
+				base + VIRTIO_PCI_STATUS,
+				VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER,
+			);
+
+			let features = inl(base + VIRTIO_PCI_HOST_FEATURES) & supported;
+			outl(base + VIRTIO_PCI_GUEST_FEATURES, features);
+
+			Ok((LegacyDevice { base }, features))
+		}
+	}
+
+	/// Create the virtqueue `index`, which needs at least `min_size` descriptors
+	pub fn setup_queue(&self, index: u16, min_size: usize) -> io::Result<Virtqueue> {
+		unsafe {
+			outw(self.base + VIRTIO_PCI_QUEUE_SEL, index);
+			let size = usize::from(inw(self.base + VIRTIO_PCI_QUEUE_NUM));
+			if size < min_size {
+				self.fail();
+				return Err(io::Error::ENODEV);
+			}
+
+			let queue = Virtqueue::new(size);
+			outl(
+				self.base + VIRTIO_PCI_QUEUE_PFN,
+				(queue.memory.physical_address(0) >> VIRTIO_PCI_QUEUE_ADDR_SHIFT) as u32,
+			);
+
+			Ok(queue)
+		}
+	}
+
+	/// Read the byte at `offset` of the device-specific configuration
+	pub fn read_config_u8(&self, offset: u16) -> u8 {
+		unsafe { inb(self.base + VIRTIO_PCI_CONFIG + offset) }
+	}
+
+	/// Read the 32 bit value at `offset` of the device-specific configuration
+	pub fn read_config_u32(&self, offset: u16) -> u32 {
+		unsafe { inl(self.base + VIRTIO_PCI_CONFIG + offset) }
+	}
+
+	/// Announce that the driver is ready
+	pub fn driver_ok(&self) {
+		unsafe {
+			outb(
+				self.base + VIRTIO_PCI_STATUS,
+				VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER | VIRTIO_STATUS_DRIVER_OK,
+			);
+		}
+	}
+
+	/// Announce that the driver gave up the device
+	pub fn fail(&self) {
+		unsafe {
+			outb(self.base + VIRTIO_PCI_STATUS, VIRTIO_STATUS_FAILED);
+		}
+	}
+
+	/// Inform the device about new buffers in the virtqueue `index`
+	pub fn notify(&self, index: u16) {
+		unsafe {
+			outw(self.base + VIRTIO_PCI_QUEUE_NOTIFY, index);
+		}
+	}
+
+	/// Acknowledge a pending interrupt by reading the ISR
+	pub fn acknowledge(&self) {
+		unsafe {
+			inb(self.base + VIRTIO_PCI_ISR);
+		}
+	}
+}
@@ -1,7 +1,11 @@
 pub(crate) mod stdio;
 
+use crate::fs::{Attributes, DirEntry, NodeKind, TimeUpdate, Timestamps};
 use crate::io;
+use crate::net::{MessageFlags, Shutdown, SocketAddress};
 use crate::scheduler::get_io_interface;
+use alloc::string::String;
+use alloc::sync::Arc;
 
 pub type FileDescriptor = i32;
 
@@ -28,8 +32,18 @@ pub enum SeekFrom {
 
 /// Describes information about a file.
 pub struct FileStatus {
+	/// Inode number of the file
+	pub ino: u64,
+	/// Type of the file
+	pub kind: NodeKind,
+	/// Number of hard links to the file
+	pub nlink: usize,
 	/// Size of the file
 	pub file_size: usize,
+	/// Permission bits and owner of the file
+	pub attr: Attributes,
+	/// Time of the last access, modification and status change
+	pub times: Timestamps,
 }
 
 #[allow(dead_code)]
@@ -53,6 +67,95 @@ pub trait IoInterface: Sync + Send + core::fmt::Debug {
 	fn fstat(&self) -> io::Result<FileStatus> {
 		Err(io::Error::ENOSYS)
 	}
+
+	/// Returns the access mode of the object, i.e.
+	/// `O_RDONLY`, `O_WRONLY` or `O_RDWR`
+	fn access_mode(&self) -> OpenOption {
+		OpenOption::O_RDWR
+	}
+
+	/// `set_times` changes the access and the modification
+	/// time of the object referenced by the descriptor
+	fn set_times(&self, _atime: TimeUpdate, _mtime: TimeUpdate) -> io::Result<()> {
+		Err(io::Error::EPERM)
+	}
+
+	/// `readdir` returns the next entry of a directory or `None`,
+	/// if the end of the directory is reached
+	fn readdir(&self) -> io::Result<Option<DirEntry>> {
+		Err(io::Error::ENOTDIR)
+	}
+
+	/// `dir_path` returns the path of a directory, which is
+	/// required to change the working directory by `fchdir`
+	fn dir_path(&self) -> io::Result<String> {
+		Err(io::Error::ENOTDIR)
+	}
+
+	/// `fsync` writes the modified data of the object back to
+	/// the storage device. Objects without a device succeed.
+	fn fsync(&self) -> io::Result<()> {
+		Ok(())
+	}
+
+	/// `bind` assigns the address `addr` to a socket
+	fn bind(&self, _addr: SocketAddress) -> io::Result<()> {
+		Err(io::Error::ENOTSOCK)
+	}
+
+	/// `listen` marks a socket as passive, at most `backlog`
+	/// connections wait for `accept`
+	fn listen(&self, _backlog: usize) -> io::Result<()> {
+		Err(io::Error::ENOTSOCK)
+	}
+
+	/// `accept` waits for the next connection of a listening socket and
+	/// returns the socket of the connection and the address of the peer.
+	/// `nonblocking` determines the mode of the new socket.
+	fn accept(&self, _nonblocking: bool) -> io::Result<(Arc<dyn IoInterface>, SocketAddress)> {
+		Err(io::Error::ENOTSOCK)
+	}
+
+	/// `connect` connects a socket to the address `addr`
+	fn connect(&self, _addr: SocketAddress) -> io::Result<()> {
+		Err(io::Error::ENOTSOCK)
+	}
+
+	/// `sendto` sends `buf` to `addr` or, if no address is
+	/// given, to the peer of a connected socket
+	fn sendto(
+		&self,
+		_buf: &[u8],
+		_flags: MessageFlags,
+		_addr: Option<SocketAddress>,
+	) -> io::Result<usize> {
+		Err(io::Error::ENOTSOCK)
+	}
+
+	/// `recvfrom` receives data by a socket and returns
+	/// its length and, if known, the address of the sender
+	fn recvfrom(
+		&self,
+		_buf: &mut [u8],
+		_flags: MessageFlags,
+	) -> io::Result<(usize, Option<SocketAddress>)> {
+		Err(io::Error::ENOTSOCK)
+	}
+
+	/// `shutdown` closes the directions `how` of a connection
+	fn shutdown(&self, _how: Shutdown) -> io::Result<()> {
+		Err(io::Error::ENOTSOCK)
+	}
+
+	/// `local_address` returns the address, to which a socket is bound
+	fn local_address(&self) -> io::Result<SocketAddress> {
+		Err(io::Error::ENOTSOCK)
+	}
+
+	/// `peer_address` returns the address of the peer of a connected socket
+	fn peer_address(&self) -> io::Result<SocketAddress> {
+		Err(io::Error::ENOTSOCK)
+	}
 }
 
 bitflags! {
@@ -68,6 +171,38 @@ bitflags! {
 		const O_APPEND = 0o2000;
 		const O_DIRECT = 0o40000;
 		const O_DIRECTORY = 0o200_000;
+		const O_NOFOLLOW = 0o400_000;
+	}
+}
+
+impl OpenOption {
+	/// Mask to determine the access mode
+	const ACCESS_MODE: i32 = 0o0003;
+
+	/// Returns the access mode, which allows reading, if `readable`
+	/// is true, and writing, if `writeable` is true
+	pub fn access_mode(readable: bool, writeable: bool) -> OpenOption {
+		match (readable, writeable) {
+			(true, false) => OpenOption::O_RDONLY,
+			(false, true) => OpenOption::O_WRONLY,
+			_ => OpenOption::O_RDWR,
+		}
+	}
+
+	/// Returns true, if the access mode is one of
+	/// `O_RDONLY`, `O_WRONLY` or `O_RDWR`
+	pub fn has_valid_access_mode(&self) -> bool {
+		self.bits() & Self::ACCESS_MODE != Self::ACCESS_MODE
+	}
+
+	/// Returns true, if the access mode allows reading
+	pub fn is_readable(&self) -> bool {
+		self.bits() & Self::ACCESS_MODE != OpenOption::O_WRONLY.bits()
+	}
+
+	/// Returns true, if the access mode allows writing
+	pub fn is_writeable(&self) -> bool {
+		self.bits() & Self::ACCESS_MODE != OpenOption::O_RDONLY.bits()
 	}
 }
 
@@ -94,3 +229,7 @@ pub(crate) fn write(fd: FileDescriptor, buf: &[u8]) -> io::Result<usize> {
 pub(crate) fn fstat(fd: FileDescriptor) -> io::Result<FileStatus> {
 	get_io_interface(fd)?.fstat()
 }
+
+pub(crate) fn lseek(fd: FileDescriptor, offset: SeekFrom) -> io::Result<usize> {
+	get_io_interface(fd)?.seek(offset)
+}
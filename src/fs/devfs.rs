@@ -0,0 +1,594 @@
+//! Implements a device file system, which publishes all
+//! registered character and block devices
+
+use crate::arch::processor;
+#[cfg(not(feature = "vga"))]
+use crate::arch::serial;
+#[cfg(feature = "vga")]
+use crate::arch::vga;
+use crate::drivers::block::{read_bytes, write_bytes, BlockDevice, SECTOR_SIZE};
+use crate::fd::{FileStatus, IoInterface, OpenOption, SeekFrom};
+use crate::fs::{
+	next_ino, Attributes, DirEntry, FileSystem, Inode, NodeKind, TimeUpdate, Timestamps, VfsNode,
+};
+use crate::io;
+use crate::synch::spinlock::*;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Registered devices, which are published by each instance of the devfs
+static DEVICES: Spinlock<BTreeMap<String, Inode>> = Spinlock::new(BTreeMap::new());
+
+/// State of the software random number generator, which
+/// is used, if the processor doesn't support `rdrand`
+static RANDOM_STATE: SpinlockIrqSave<u64> = SpinlockIrqSave::new(0);
+
+/// Inode number, owner and timestamps of a device, which
+/// are shared by the inode and the open handles
+#[derive(Debug)]
+struct DeviceMetadata {
+	/// inode number of the device
+	ino: u64,
+	/// permission bits and owner of the device
+	attr: Spinlock<Attributes>,
+	/// time of the last access, modification and status change
+	times: Spinlock<Timestamps>,
+}
+
+impl DeviceMetadata {
+	fn new(kind: NodeKind) -> Arc<Self> {
+		Arc::new(DeviceMetadata {
+			ino: next_ino(),
+			attr: Spinlock::new(Attributes::default_for(kind)),
+			times: Spinlock::new(Timestamps::now()),
+		})
+	}
+
+	/// Returns the status of the device
+	fn stat(&self, kind: NodeKind, file_size: usize) -> FileStatus {
+		FileStatus {
+			ino: self.ino,
+			kind,
+			nlink: 1,
+			file_size,
+			attr: *self.attr.lock(),
+			times: *self.times.lock(),
+		}
+	}
+}
+
+/// Inode of a character device
+#[derive(Debug)]
+struct CharDevice {
+	/// inode number, owner and timestamps of the device
+	meta: Arc<DeviceMetadata>,
+	/// IO interface, which is shared by all open handles
+	dev: Arc<dyn IoInterface>,
+}
+
+impl VfsNode for CharDevice {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::CharDevice
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.meta.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		1
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		*self.meta.attr.lock()
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		*self.meta.attr.lock() = attr;
+		self.meta.times.lock().touch_change();
+		Ok(())
+	}
+
+	fn get_times(&self) -> Timestamps {
+		*self.meta.times.lock()
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.meta.times.lock().update(atime, mtime);
+		Ok(())
+	}
+
+	fn open(&self, opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(io::Error::EEXIST);
+		}
+
+		Ok(Arc::new(CharDeviceHandle {
+			meta: self.meta.clone(),
+			dev: self.dev.clone(),
+		}))
+	}
+}
+
+/// IO interface to an open character device, which forwards
+/// all operations to the device
+#[derive(Debug)]
+struct CharDeviceHandle {
+	/// inode number, owner and timestamps of the device
+	meta: Arc<DeviceMetadata>,
+	/// IO interface of the device
+	dev: Arc<dyn IoInterface>,
+}
+
+impl IoInterface for CharDeviceHandle {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		self.dev.read(buf)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		self.dev.write(buf)
+	}
+
+	fn seek(&self, offset: SeekFrom) -> io::Result<usize> {
+		self.dev.seek(offset)
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		Ok(self.meta.stat(NodeKind::CharDevice, 0))
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.meta.times.lock().update(atime, mtime);
+		Ok(())
+	}
+}
+
+/// Inode of a block device
+#[derive(Debug)]
+struct BlockDeviceNode {
+	/// inode number, owner and timestamps of the device
+	meta: Arc<DeviceMetadata>,
+	/// driver of the device
+	dev: Arc<dyn BlockDevice>,
+}
+
+impl VfsNode for BlockDeviceNode {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::BlockDevice
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.meta.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		1
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		*self.meta.attr.lock()
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		*self.meta.attr.lock() = attr;
+		self.meta.times.lock().touch_change();
+		Ok(())
+	}
+
+	fn get_times(&self) -> Timestamps {
+		*self.meta.times.lock()
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.meta.times.lock().update(atime, mtime);
+		Ok(())
+	}
+
+	fn open(&self, opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(io::Error::EEXIST);
+		}
+		if opt.is_writeable() && self.dev.is_read_only() {
+			return Err(io::Error::EROFS);
+		}
+
+		Ok(Arc::new(BlockDeviceHandle {
+			meta: self.meta.clone(),
+			dev: self.dev.clone(),
+			pos: Spinlock::new(0),
+		}))
+	}
+}
+
+/// IO interface to an open block device, which provides the
+/// content of the device as a file of fixed size
+#[derive(Debug)]
+struct BlockDeviceHandle {
+	/// inode number, owner and timestamps of the device
+	meta: Arc<DeviceMetadata>,
+	/// driver of the device
+	dev: Arc<dyn BlockDevice>,
+	/// position within the device
+	pos: Spinlock<u64>,
+}
+
+impl BlockDeviceHandle {
+	fn size(&self) -> u64 {
+		self.dev.num_sectors() * SECTOR_SIZE as u64
+	}
+}
+
+impl IoInterface for BlockDeviceHandle {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		let mut pos = self.pos.lock();
+		let len = self.size().saturating_sub(*pos).min(buf.len() as u64) as usize;
+
+		read_bytes(&*self.dev, *pos, &mut buf[..len])?;
+		*pos += len as u64;
+
+		Ok(len)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		let mut pos = self.pos.lock();
+		let len = self.size().saturating_sub(*pos).min(buf.len() as u64) as usize;
+
+		if len == 0 && !buf.is_empty() {
+			return Err(io::Error::ENOSPC);
+		}
+
+		write_bytes(&*self.dev, *pos, &buf[..len])?;
+		*pos += len as u64;
+
+		Ok(len)
+	}
+
+	fn seek(&self, style: SeekFrom) -> io::Result<usize> {
+		let mut pos = self.pos.lock();
+		let new_pos = match style {
+			SeekFrom::Start(n) => n as i64,
+			SeekFrom::End(n) => self.size() as i64 + n as i64,
+			SeekFrom::Current(n) => *pos as i64 + n as i64,
+		};
+
+		if new_pos >= 0 {
+			*pos = new_pos as u64;
+			Ok(new_pos as usize)
+		} else {
+			Err(io::Error::EINVAL)
+		}
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		Ok(self.meta.stat(NodeKind::BlockDevice, self.size() as usize))
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.meta.times.lock().update(atime, mtime);
+		Ok(())
+	}
+
+	fn fsync(&self) -> io::Result<()> {
+		self.dev.flush()
+	}
+}
+
+/// Root directory of the devfs, which lists all registered devices
+#[derive(Debug)]
+struct DevDirectory {
+	/// inode number of the directory
+	ino: u64,
+}
+
+impl VfsNode for DevDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		2
+	}
+
+	fn lookup(&self, name: &str) -> io::Result<Inode> {
+		DEVICES.lock().get(name).cloned().ok_or(io::Error::ENOENT)
+	}
+
+	/// Devices are only created by `register_char_device`
+	fn create(&self, _name: &str, _kind: NodeKind, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn symlink(&self, _name: &str, _target: &str, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn link(&self, _name: &str, _node: Inode) -> io::Result<()> {
+		Err(io::Error::EPERM)
+	}
+
+	fn remove(&self, _name: &str) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn replace(&self, _name: &str, _node: Inode) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn readdir(&self) -> io::Result<Vec<DirEntry>> {
+		Ok(DEVICES
+			.lock()
+			.iter()
+			.map(|(name, node)| DirEntry {
+				name: name.clone(),
+				kind: node.get_kind(),
+				ino: node.get_ino(),
+			})
+			.collect())
+	}
+}
+
+/// Instance of the device file system
+#[derive(Debug)]
+pub(crate) struct DevFs {
+	/// root directory of the file system
+	root: Inode,
+}
+
+impl DevFs {
+	pub fn new() -> Self {
+		DevFs {
+			root: Arc::new(DevDirectory { ino: next_ino() }),
+		}
+	}
+
+	/// Create a new instance of the devfs. The source isn't used.
+	pub fn create(_source: Option<&str>) -> io::Result<Arc<dyn FileSystem>> {
+		Ok(Arc::new(DevFs::new()))
+	}
+}
+
+impl FileSystem for DevFs {
+	fn name(&self) -> &'static str {
+		"devfs"
+	}
+
+	fn root(&self) -> Inode {
+		self.root.clone()
+	}
+}
+
+/// Insert the device node `node` as `/dev/<name>`
+fn insert_node(name: &str, node: Inode) -> io::Result<()> {
+	if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+		return Err(io::Error::EINVAL);
+	}
+
+	let mut devices = DEVICES.lock();
+	if devices.contains_key(name) {
+		return Err(io::Error::EEXIST);
+	}
+
+	devices.insert(String::from(name), node);
+
+	Ok(())
+}
+
+/// Publish the character device `dev` as `/dev/<name>`
+pub fn register_char_device(name: &str, dev: Arc<dyn IoInterface>) -> io::Result<()> {
+	insert_node(
+		name,
+		Arc::new(CharDevice {
+			meta: DeviceMetadata::new(NodeKind::CharDevice),
+			dev,
+		}),
+	)
+}
+
+/// Publish the block device `dev` as `/dev/<name>`, see `register_block_device`
+pub(crate) fn register_block_node(name: &str, dev: Arc<dyn BlockDevice>) -> io::Result<()> {
+	insert_node(
+		name,
+		Arc::new(BlockDeviceNode {
+			meta: DeviceMetadata::new(NodeKind::BlockDevice),
+			dev,
+		}),
+	)
+}
+
+/// Remove the character device `/dev/<name>`.
+/// Open handles to the device are still valid.
+pub fn unregister_char_device(name: &str) -> io::Result<()> {
+	let mut devices = DEVICES.lock();
+
+	// block devices stay published as long as they are registered
+	match devices.get(name) {
+		Some(node) if node.get_kind() == NodeKind::CharDevice => {
+			devices.remove(name);
+			Ok(())
+		}
+		_ => Err(io::Error::ENOENT),
+	}
+}
+
+/// Device, which discards all written data and signals the end of file on read
+#[derive(Debug)]
+struct NullDevice;
+
+impl IoInterface for NullDevice {
+	fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
+		Ok(0)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		Ok(buf.len())
+	}
+}
+
+/// Device, which discards all written data and returns zeros on read
+#[derive(Debug)]
+struct ZeroDevice;
+
+impl IoInterface for ZeroDevice {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		buf.fill(0);
+		Ok(buf.len())
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		Ok(buf.len())
+	}
+}
+
+/// Device, which returns zeros on read and is always full on write
+#[derive(Debug)]
+struct FullDevice;
+
+impl IoInterface for FullDevice {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		buf.fill(0);
+		Ok(buf.len())
+	}
+
+	fn write(&self, _buf: &[u8]) -> io::Result<usize> {
+		Err(io::Error::ENOSPC)
+	}
+}
+
+/// Returns a random number. Without `rdrand`, the numbers are generated
+/// by a xorshift generator, which is seeded by the time stamp counter.
+fn next_random() -> u64 {
+	if let Some(value) = processor::get_random() {
+		return value;
+	}
+
+	let mut state = RANDOM_STATE.lock();
+	if *state == 0 {
+		*state = processor::get_timestamp() | 1;
+	}
+
+	let mut x = *state;
+	x ^= x >> 12;
+	x ^= x << 25;
+	x ^= x >> 27;
+	*state = x;
+
+	x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+}
+
+/// Device, which returns random numbers on read. Written data
+/// is mixed into the state of the software generator.
+#[derive(Debug)]
+struct RandomDevice;
+
+impl IoInterface for RandomDevice {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		for chunk in buf.chunks_mut(8) {
+			let value = next_random().to_ne_bytes();
+			chunk.copy_from_slice(&value[..chunk.len()]);
+		}
+
+		Ok(buf.len())
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		let mut state = RANDOM_STATE.lock();
+
+		for (i, byte) in buf.iter().enumerate() {
+			*state ^= u64::from(*byte) << ((i % 8) * 8);
+		}
+		// the xorshift generator requires a state, which isn't zero
+		if *state == 0 {
+			*state = 1;
+		}
+
+		Ok(buf.len())
+	}
+}
+
+/// Copy the received bytes of the primary serial port to `buf`
+#[cfg(not(feature = "vga"))]
+fn read_serial(buf: &mut [u8]) -> io::Result<usize> {
+	let mut input = serial::COM1_INPUT.lock();
+	let mut len = 0;
+
+	while len < buf.len() {
+		match input.pop() {
+			Some(byte) => {
+				buf[len] = byte;
+				len += 1;
+			}
+			None => break,
+		}
+	}
+
+	if len > 0 {
+		Ok(len)
+	} else {
+		Err(io::Error::EAGAIN)
+	}
+}
+
+/// System console, which uses the same output device as the kernel messages
+#[derive(Debug)]
+struct ConsoleDevice;
+
+impl IoInterface for ConsoleDevice {
+	fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
+		cfg_if::cfg_if! {
+			if #[cfg(feature = "vga")] {
+				Err(io::Error::EAGAIN)
+			} else {
+				read_serial(_buf)
+			}
+		}
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		cfg_if::cfg_if! {
+			if #[cfg(feature = "vga")] {
+				vga::VGA_SCREEN.lock().write_bytes(buf);
+			} else {
+				serial::COM1.lock().write_bytes(buf);
+			}
+		}
+
+		Ok(buf.len())
+	}
+}
+
+/// Primary serial port. Reading doesn't block and returns
+/// `EAGAIN`, if no data is received.
+#[cfg(not(feature = "vga"))]
+#[derive(Debug)]
+struct SerialDevice;
+
+#[cfg(not(feature = "vga"))]
+impl IoInterface for SerialDevice {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		read_serial(buf)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		serial::COM1.lock().write_bytes(buf);
+
+		Ok(buf.len())
+	}
+}
+
+/// Register the default character devices
+pub(crate) fn init() {
+	register_char_device("null", Arc::new(NullDevice)).unwrap();
+	register_char_device("zero", Arc::new(ZeroDevice)).unwrap();
+	register_char_device("full", Arc::new(FullDevice)).unwrap();
+	register_char_device("random", Arc::new(RandomDevice)).unwrap();
+	register_char_device("urandom", Arc::new(RandomDevice)).unwrap();
+	register_char_device("console", Arc::new(ConsoleDevice)).unwrap();
+	#[cfg(not(feature = "vga"))]
+	register_char_device("ttyS0", Arc::new(SerialDevice)).unwrap();
+}
@@ -0,0 +1,2340 @@
+//! Implements a driver for the second extended file system (ext2)
+//!
+//! The driver supports the features `filetype`, `sparse_super` and
+//! `large_file`. All modifications are written through to the block
+//! device, i.e. the volume is consistent after each operation. Volumes
+//! with unknown features, which are compatible for reading, and volumes
+//! on read-only devices are mounted read-only.
+
+use crate::drivers::block::{get_block_device, read_bytes, write_bytes, BlockDevice, SECTOR_SIZE};
+use crate::fd::{FileStatus, IoInterface, OpenOption, SeekFrom};
+use crate::fs::{
+	next_ino, Attributes, DirEntry, FileSystem, Inode, NodeKind, TimeUpdate, Timestamps, VfsNode,
+	NAME_MAX,
+};
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::get_current_credentials;
+use crate::synch::spinlock::*;
+use crate::time::{realtime, Timespec};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Byte offset of the superblock on the device
+const SUPERBLOCK_OFFSET: u64 = 1024;
+/// Size of the superblock
+const SUPERBLOCK_SIZE: usize = 1024;
+/// Magic number of the superblock
+const EXT2_MAGIC: u16 = 0xEF53;
+/// Revision without dynamic inode sizes and feature flags
+const GOOD_OLD_REV: u32 = 0;
+/// Size of an inode in revision 0
+const GOOD_OLD_INODE_SIZE: usize = 128;
+/// First inode, which isn't reserved, in revision 0
+const GOOD_OLD_FIRST_INO: u32 = 11;
+/// Inode number of the root directory
+const ROOT_INO: u32 = 2;
+/// Size of a group descriptor
+const GROUP_DESC_SIZE: usize = 32;
+/// Maximum number of hard links to an inode
+const LINK_MAX: u16 = 32000;
+
+/// Directory entries store the file type
+const FEATURE_INCOMPAT_FILETYPE: u32 = 0x0002;
+/// Only some groups store copies of the superblock
+const FEATURE_RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+/// Files may be larger than 2 GiB
+const FEATURE_RO_COMPAT_LARGE_FILE: u32 = 0x0002;
+
+// offsets of the fields of the superblock
+const S_INODES_COUNT: usize = 0;
+const S_BLOCKS_COUNT: usize = 4;
+const S_R_BLOCKS_COUNT: usize = 8;
+const S_FREE_BLOCKS_COUNT: usize = 12;
+const S_FREE_INODES_COUNT: usize = 16;
+const S_FIRST_DATA_BLOCK: usize = 20;
+const S_LOG_BLOCK_SIZE: usize = 24;
+const S_BLOCKS_PER_GROUP: usize = 32;
+const S_INODES_PER_GROUP: usize = 40;
+const S_MTIME: usize = 44;
+const S_WTIME: usize = 48;
+const S_MNT_COUNT: usize = 52;
+const S_MAGIC: usize = 56;
+const S_REV_LEVEL: usize = 76;
+const S_FIRST_INO: usize = 84;
+const S_INODE_SIZE: usize = 88;
+const S_FEATURE_INCOMPAT: usize = 96;
+const S_FEATURE_RO_COMPAT: usize = 100;
+
+// offsets of the fields of a group descriptor
+const BG_BLOCK_BITMAP: usize = 0;
+const BG_INODE_BITMAP: usize = 4;
+const BG_INODE_TABLE: usize = 8;
+const BG_FREE_BLOCKS_COUNT: usize = 12;
+const BG_FREE_INODES_COUNT: usize = 14;
+const BG_USED_DIRS_COUNT: usize = 16;
+
+// offsets of the fields of an inode
+const I_MODE: usize = 0;
+const I_UID: usize = 2;
+const I_SIZE: usize = 4;
+const I_ATIME: usize = 8;
+const I_CTIME: usize = 12;
+const I_MTIME: usize = 16;
+const I_DTIME: usize = 20;
+const I_GID: usize = 24;
+const I_LINKS_COUNT: usize = 26;
+const I_BLOCKS: usize = 28;
+const I_FLAGS: usize = 32;
+const I_BLOCK: usize = 40;
+const I_FILE_ACL: usize = 104;
+const I_SIZE_HIGH: usize = 108;
+const I_UID_HIGH: usize = 120;
+const I_GID_HIGH: usize = 122;
+
+/// Number of direct block pointers of an inode
+const DIRECT_BLOCKS: usize = 12;
+/// Index of the indirect block within the block pointers
+const INDIRECT_BLOCK: usize = 12;
+/// Index of the double-indirect block within the block pointers
+const DOUBLE_INDIRECT_BLOCK: usize = 13;
+/// Index of the triple-indirect block within the block pointers
+const TRIPLE_INDIRECT_BLOCK: usize = 14;
+/// Size of the block pointers, which store the target of a fast symbolic link
+const FAST_SYMLINK_SIZE: usize = 60;
+/// Flag of a directory with a hash index, which isn't maintained by this driver
+const INDEX_FL: u32 = 0x1000;
+/// Offset of the reference counter within a block of extended attributes
+const XATTR_REFCOUNT: usize = 4;
+
+const S_IFMT: u16 = 0o170000;
+const S_IFREG: u16 = 0o100000;
+const S_IFDIR: u16 = 0o040000;
+const S_IFLNK: u16 = 0o120000;
+const S_IFCHR: u16 = 0o020000;
+const S_IFBLK: u16 = 0o060000;
+const S_IFSOCK: u16 = 0o140000;
+
+// file types of directory entries
+const FT_REG_FILE: u8 = 1;
+const FT_DIR: u8 = 2;
+const FT_CHRDEV: u8 = 3;
+const FT_BLKDEV: u8 = 4;
+const FT_SOCK: u8 = 6;
+const FT_SYMLINK: u8 = 7;
+
+/// Size of the header of a directory entry
+const DIR_ENTRY_HEADER: usize = 8;
+
+fn get_u16(buf: &[u8], offset: usize) -> u16 {
+	u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn get_u32(buf: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes([
+		buf[offset],
+		buf[offset + 1],
+		buf[offset + 2],
+		buf[offset + 3],
+	])
+}
+
+fn set_u16(buf: &mut [u8], offset: usize, value: u16) {
+	buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn set_u32(buf: &mut [u8], offset: usize, value: u32) {
+	buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Returns the size of a directory entry with a name of `len` bytes
+const fn entry_size(len: usize) -> usize {
+	(DIR_ENTRY_HEADER + len).next_multiple_of(4)
+}
+
+/// Returns the current time in the format of ext2
+fn now() -> u32 {
+	realtime().sec.clamp(0, i64::from(u32::MAX)) as u32
+}
+
+/// Convert a timestamp of ext2 to a point in time
+fn to_timespec(time: u32) -> Timespec {
+	Timespec::new(i64::from(time), 0)
+}
+
+/// Returns the type of a node with the mode `mode`. Pipes are
+/// presented as files, which cannot be opened.
+fn kind_of(mode: u16) -> NodeKind {
+	match mode & S_IFMT {
+		S_IFDIR => NodeKind::Directory,
+		S_IFLNK => NodeKind::Symlink,
+		S_IFCHR => NodeKind::CharDevice,
+		S_IFBLK => NodeKind::BlockDevice,
+		S_IFSOCK => NodeKind::Socket,
+		_ => NodeKind::File,
+	}
+}
+
+/// Returns the file type of a directory entry, which references a node with the mode `mode`
+fn file_type_of(mode: u16) -> u8 {
+	match mode & S_IFMT {
+		S_IFREG => FT_REG_FILE,
+		S_IFDIR => FT_DIR,
+		S_IFLNK => FT_SYMLINK,
+		S_IFCHR => FT_CHRDEV,
+		S_IFBLK => FT_BLKDEV,
+		S_IFSOCK => FT_SOCK,
+		_ => 0,
+	}
+}
+
+/// Copy of the first 128 bytes of an inode, which contain all fields used by the driver
+#[derive(Clone, Debug)]
+struct DiskInode {
+	raw: [u8; GOOD_OLD_INODE_SIZE],
+}
+
+impl DiskInode {
+	fn new(mode: u16, attr: &Attributes, links: u16) -> Self {
+		let mut inode = DiskInode {
+			raw: [0; GOOD_OLD_INODE_SIZE],
+		};
+		let time = now();
+
+		set_u16(&mut inode.raw, I_MODE, mode | attr.mode as u16);
+		inode.set_owner(attr.uid, attr.gid);
+		set_u32(&mut inode.raw, I_ATIME, time);
+		set_u32(&mut inode.raw, I_CTIME, time);
+		set_u32(&mut inode.raw, I_MTIME, time);
+		set_u16(&mut inode.raw, I_LINKS_COUNT, links);
+
+		inode
+	}
+
+	fn mode(&self) -> u16 {
+		get_u16(&self.raw, I_MODE)
+	}
+
+	fn is_dir(&self) -> bool {
+		self.mode() & S_IFMT == S_IFDIR
+	}
+
+	fn attributes(&self) -> Attributes {
+		let uid =
+			u32::from(get_u16(&self.raw, I_UID)) | u32::from(get_u16(&self.raw, I_UID_HIGH)) << 16;
+		let gid =
+			u32::from(get_u16(&self.raw, I_GID)) | u32::from(get_u16(&self.raw, I_GID_HIGH)) << 16;
+
+		Attributes::new(u32::from(self.mode()), uid, gid)
+	}
+
+	fn set_owner(&mut self, uid: u32, gid: u32) {
+		set_u16(&mut self.raw, I_UID, uid as u16);
+		set_u16(&mut self.raw, I_UID_HIGH, (uid >> 16) as u16);
+		set_u16(&mut self.raw, I_GID, gid as u16);
+		set_u16(&mut self.raw, I_GID_HIGH, (gid >> 16) as u16);
+	}
+
+	fn size(&self) -> u64 {
+		let size = u64::from(get_u32(&self.raw, I_SIZE));
+
+		// directories use the upper half for other purposes
+		if self.mode() & S_IFMT == S_IFREG {
+			size | u64::from(get_u32(&self.raw, I_SIZE_HIGH)) << 32
+		} else {
+			size
+		}
+	}
+
+	fn set_size(&mut self, size: u64) {
+		set_u32(&mut self.raw, I_SIZE, size as u32);
+		if self.mode() & S_IFMT == S_IFREG {
+			set_u32(&mut self.raw, I_SIZE_HIGH, (size >> 32) as u32);
+		}
+	}
+
+	fn links(&self) -> u16 {
+		get_u16(&self.raw, I_LINKS_COUNT)
+	}
+
+	fn set_links(&mut self, links: u16) {
+		set_u16(&mut self.raw, I_LINKS_COUNT, links);
+	}
+
+	/// Returns the number of allocated sectors including the indirect blocks
+	fn sectors(&self) -> u32 {
+		get_u32(&self.raw, I_BLOCKS)
+	}
+
+	fn set_sectors(&mut self, sectors: u32) {
+		set_u32(&mut self.raw, I_BLOCKS, sectors);
+	}
+
+	fn block(&self, index: usize) -> u32 {
+		get_u32(&self.raw, I_BLOCK + 4 * index)
+	}
+
+	fn set_block(&mut self, index: usize, block: u32) {
+		set_u32(&mut self.raw, I_BLOCK + 4 * index, block);
+	}
+
+	fn file_acl(&self) -> u32 {
+		get_u32(&self.raw, I_FILE_ACL)
+	}
+
+	fn times(&self) -> Timestamps {
+		Timestamps {
+			atime: to_timespec(get_u32(&self.raw, I_ATIME)),
+			mtime: to_timespec(get_u32(&self.raw, I_MTIME)),
+			ctime: to_timespec(get_u32(&self.raw, I_CTIME)),
+		}
+	}
+
+	/// Update the access time, if it is older than the modification
+	/// time or older than one day (see `relatime` of Linux). Returns
+	/// true, if the inode is modified.
+	fn touch_access(&mut self) -> bool {
+		let atime = get_u32(&self.raw, I_ATIME);
+		let time = now();
+
+		if atime <= get_u32(&self.raw, I_MTIME)
+			|| atime <= get_u32(&self.raw, I_CTIME)
+			|| time.saturating_sub(atime) >= 24 * 3600
+		{
+			set_u32(&mut self.raw, I_ATIME, time);
+			true
+		} else {
+			false
+		}
+	}
+
+	fn touch_modify(&mut self) {
+		let time = now();
+
+		set_u32(&mut self.raw, I_MTIME, time);
+		set_u32(&mut self.raw, I_CTIME, time);
+	}
+
+	fn touch_change(&mut self) {
+		set_u32(&mut self.raw, I_CTIME, now());
+	}
+}
+
+/// Entry of a directory on the device
+#[derive(Debug)]
+struct RawDirEntry {
+	/// inode number of the entry
+	ino: u32,
+	/// file type, if the volume supports the feature `filetype`
+	file_type: u8,
+	/// name of the entry
+	name: String,
+}
+
+/// State of a mounted ext2 volume
+#[derive(Debug)]
+struct Ext2Volume {
+	/// device, which stores the volume
+	device: Arc<dyn BlockDevice>,
+	/// size of a block in bytes
+	block_size: usize,
+	/// size of an inode in bytes
+	inode_size: usize,
+	/// total number of blocks
+	blocks_count: u32,
+	/// total number of inodes
+	inodes_count: u32,
+	/// number of the first block, which is managed by the groups
+	first_data_block: u32,
+	blocks_per_group: u32,
+	inodes_per_group: u32,
+	/// first inode, which isn't reserved
+	first_ino: u32,
+	/// number of block groups
+	group_count: u32,
+	/// directory entries store the file type
+	filetype: bool,
+	/// files may be larger than 2 GiB
+	large_file: bool,
+	/// the volume isn't modified
+	read_only: bool,
+	/// copy of the superblock
+	superblock: Vec<u8>,
+	/// copy of the blocks, which store the group descriptors
+	groups: Vec<u8>,
+	/// inode numbers of the virtual file system of all inodes, which
+	/// are already looked up, and the inverse mapping
+	inos: BTreeMap<u32, u64>,
+	disk_inos: BTreeMap<u64, u32>,
+	/// number of open handles per inode
+	open_handles: BTreeMap<u32, usize>,
+	/// inodes without links, which are freed after the last handle is closed
+	orphans: BTreeSet<u32>,
+}
+
+impl Ext2Volume {
+	/// Read the superblock and the group descriptors of `device`
+	fn open(device: Arc<dyn BlockDevice>) -> io::Result<Self> {
+		let mut superblock = vec![0u8; SUPERBLOCK_SIZE];
+		read_bytes(&*device, SUPERBLOCK_OFFSET, &mut superblock)?;
+
+		if get_u16(&superblock, S_MAGIC) != EXT2_MAGIC {
+			return Err(io::Error::EINVAL);
+		}
+
+		let log_block_size = get_u32(&superblock, S_LOG_BLOCK_SIZE);
+		if log_block_size > 6 {
+			return Err(io::Error::EINVAL);
+		}
+
+		let block_size = 1024usize << log_block_size;
+		let blocks_count = get_u32(&superblock, S_BLOCKS_COUNT);
+		let inodes_count = get_u32(&superblock, S_INODES_COUNT);
+		let first_data_block = get_u32(&superblock, S_FIRST_DATA_BLOCK);
+		let blocks_per_group = get_u32(&superblock, S_BLOCKS_PER_GROUP);
+		let inodes_per_group = get_u32(&superblock, S_INODES_PER_GROUP);
+		let (inode_size, first_ino, incompat, ro_compat) =
+			if get_u32(&superblock, S_REV_LEVEL) == GOOD_OLD_REV {
+				(GOOD_OLD_INODE_SIZE, GOOD_OLD_FIRST_INO, 0, 0)
+			} else {
+				(
+					usize::from(get_u16(&superblock, S_INODE_SIZE)),
+					get_u32(&superblock, S_FIRST_INO),
+					get_u32(&superblock, S_FEATURE_INCOMPAT),
+					get_u32(&superblock, S_FEATURE_RO_COMPAT),
+				)
+			};
+
+		let bits_per_block = 8 * block_size as u32;
+		if !inode_size.is_power_of_two()
+			|| !(GOOD_OLD_INODE_SIZE..=block_size).contains(&inode_size)
+			|| blocks_per_group == 0
+			|| blocks_per_group > bits_per_block
+			|| inodes_per_group == 0
+			|| inodes_per_group > bits_per_block
+			|| blocks_count <= first_data_block
+			|| u64::from(blocks_count) * block_size as u64
+				> device.num_sectors() * SECTOR_SIZE as u64
+		{
+			return Err(io::Error::EINVAL);
+		}
+
+		// features, which are incompatible, prevent mounting
+		if incompat & !FEATURE_INCOMPAT_FILETYPE != 0 {
+			warn!("ext2 volume uses unsupported features 0x{:x}", incompat);
+			return Err(io::Error::EINVAL);
+		}
+
+		let group_count = (blocks_count - first_data_block).div_ceil(blocks_per_group);
+		if u64::from(group_count) * u64::from(inodes_per_group) < u64::from(inodes_count) {
+			return Err(io::Error::EINVAL);
+		}
+
+		let mut groups =
+			vec![0u8; (group_count as usize * GROUP_DESC_SIZE).next_multiple_of(block_size)];
+		read_bytes(
+			&*device,
+			u64::from(first_data_block + 1) * block_size as u64,
+			&mut groups,
+		)?;
+
+		let read_only = device.is_read_only()
+			|| ro_compat & !(FEATURE_RO_COMPAT_SPARSE_SUPER | FEATURE_RO_COMPAT_LARGE_FILE) != 0;
+
+		let mut volume = Ext2Volume {
+			device,
+			block_size,
+			inode_size,
+			blocks_count,
+			inodes_count,
+			first_data_block,
+			blocks_per_group,
+			inodes_per_group,
+			first_ino,
+			group_count,
+			filetype: incompat & FEATURE_INCOMPAT_FILETYPE != 0,
+			large_file: ro_compat & FEATURE_RO_COMPAT_LARGE_FILE != 0,
+			read_only,
+			superblock,
+			groups,
+			inos: BTreeMap::new(),
+			disk_inos: BTreeMap::new(),
+			open_handles: BTreeMap::new(),
+			orphans: BTreeSet::new(),
+		};
+
+		if !volume.read_only {
+			let time = now();
+			let count = get_u16(&volume.superblock, S_MNT_COUNT);
+
+			set_u16(&mut volume.superblock, S_MNT_COUNT, count.wrapping_add(1));
+			set_u32(&mut volume.superblock, S_MTIME, time);
+			set_u32(&mut volume.superblock, S_WTIME, time);
+			volume.write_superblock()?;
+		}
+
+		Ok(volume)
+	}
+
+	fn check_writeable(&self) -> io::Result<()> {
+		if self.read_only {
+			Err(io::Error::EROFS)
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Returns the inode number of the virtual file system of the inode `ino`
+	fn vfs_ino(&mut self, ino: u32) -> u64 {
+		if let Some(vfs_ino) = self.inos.get(&ino) {
+			return *vfs_ino;
+		}
+
+		let vfs_ino = next_ino();
+		self.inos.insert(ino, vfs_ino);
+		self.disk_inos.insert(vfs_ino, ino);
+
+		vfs_ino
+	}
+
+	fn read_block(&self, block: u32, buf: &mut [u8]) -> io::Result<()> {
+		if block < self.first_data_block || block >= self.blocks_count {
+			return Err(io::Error::EIO);
+		}
+
+		read_bytes(
+			&*self.device,
+			u64::from(block) * self.block_size as u64,
+			buf,
+		)
+	}
+
+	fn write_block(&self, block: u32, buf: &[u8]) -> io::Result<()> {
+		if block < self.first_data_block || block >= self.blocks_count {
+			return Err(io::Error::EIO);
+		}
+
+		write_bytes(
+			&*self.device,
+			u64::from(block) * self.block_size as u64,
+			buf,
+		)
+	}
+
+	fn write_superblock(&self) -> io::Result<()> {
+		write_bytes(&*self.device, SUPERBLOCK_OFFSET, &self.superblock)
+	}
+
+	fn group_u32(&self, group: u32, offset: usize) -> u32 {
+		get_u32(&self.groups, group as usize * GROUP_DESC_SIZE + offset)
+	}
+
+	fn group_u16(&self, group: u32, offset: usize) -> u16 {
+		get_u16(&self.groups, group as usize * GROUP_DESC_SIZE + offset)
+	}
+
+	/// Add `delta` to the 16 bit counter at `offset` of the group descriptor `group`
+	fn add_group_u16(&mut self, group: u32, offset: usize, delta: i32) {
+		let value = (i32::from(self.group_u16(group, offset)) + delta) as u16;
+		set_u16(
+			&mut self.groups,
+			group as usize * GROUP_DESC_SIZE + offset,
+			value,
+		);
+	}
+
+	/// Add `delta` to the 32 bit counter at `offset` of the superblock
+	fn add_superblock_u32(&mut self, offset: usize, delta: i64) {
+		let value = (i64::from(get_u32(&self.superblock, offset)) + delta) as u32;
+		set_u32(&mut self.superblock, offset, value);
+	}
+
+	/// Write the block, which contains the descriptor of the group
+	/// `group`, and the superblock, which contains the summary
+	fn write_group(&self, group: u32) -> io::Result<()> {
+		let index = group as usize * GROUP_DESC_SIZE / self.block_size;
+		let start = index * self.block_size;
+
+		self.write_block(
+			self.first_data_block + 1 + index as u32,
+			&self.groups[start..start + self.block_size],
+		)?;
+		self.write_superblock()
+	}
+
+	/// Returns the number of blocks of the group `group`. The last group may be smaller.
+	fn blocks_in_group(&self, group: u32) -> u32 {
+		let first = self.first_data_block + group * self.blocks_per_group;
+
+		(self.blocks_count - first).min(self.blocks_per_group)
+	}
+
+	/// Find and set the first zero bit below `limit` of the bitmap, which is stored in `block`
+	fn allocate_bit(&self, block: u32, limit: u32) -> io::Result<Option<u32>> {
+		let mut bitmap = vec![0u8; self.block_size];
+		self.read_block(block, &mut bitmap)?;
+
+		for bit in 0..limit {
+			let (byte, mask) = ((bit / 8) as usize, 1u8 << (bit % 8));
+
+			if bitmap[byte] & mask == 0 {
+				bitmap[byte] |= mask;
+				self.write_block(block, &bitmap)?;
+				return Ok(Some(bit));
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Clear the bit `bit` of the bitmap, which is stored in `block`
+	fn free_bit(&self, block: u32, bit: u32) -> io::Result<()> {
+		let mut bitmap = vec![0u8; self.block_size];
+		self.read_block(block, &mut bitmap)?;
+
+		let (byte, mask) = ((bit / 8) as usize, 1u8 << (bit % 8));
+		if bitmap[byte] & mask == 0 {
+			warn!(
+				"ext2: bit {} of the bitmap in block {} is already free",
+				bit, block
+			);
+			return Err(io::Error::EIO);
+		}
+		bitmap[byte] &= !mask;
+
+		self.write_block(block, &bitmap)
+	}
+
+	/// Allocate a zeroed block, preferably in the group of the inode `ino`
+	fn allocate_block(&mut self, ino: u32) -> io::Result<u32> {
+		let free = get_u32(&self.superblock, S_FREE_BLOCKS_COUNT);
+		let reserved = get_u32(&self.superblock, S_R_BLOCKS_COUNT);
+
+		// the reserved blocks are only available for the superuser
+		if free == 0 || (free <= reserved && !get_current_credentials().is_superuser()) {
+			return Err(io::Error::ENOSPC);
+		}
+
+		let start = (ino - 1) / self.inodes_per_group;
+		for index in 0..self.group_count {
+			let group = (start + index) % self.group_count;
+			if self.group_u16(group, BG_FREE_BLOCKS_COUNT) == 0 {
+				continue;
+			}
+
+			let bitmap = self.group_u32(group, BG_BLOCK_BITMAP);
+			if let Some(bit) = self.allocate_bit(bitmap, self.blocks_in_group(group))? {
+				let block = self.first_data_block + group * self.blocks_per_group + bit;
+
+				self.add_group_u16(group, BG_FREE_BLOCKS_COUNT, -1);
+				self.add_superblock_u32(S_FREE_BLOCKS_COUNT, -1);
+				self.write_group(group)?;
+				self.write_block(block, &vec![0u8; self.block_size])?;
+
+				return Ok(block);
+			}
+		}
+
+		Err(io::Error::ENOSPC)
+	}
+
+	fn free_block(&mut self, block: u32) -> io::Result<()> {
+		if block < self.first_data_block || block >= self.blocks_count {
+			return Err(io::Error::EIO);
+		}
+
+		let group = (block - self.first_data_block) / self.blocks_per_group;
+		let bit = (block - self.first_data_block) % self.blocks_per_group;
+
+		self.free_bit(self.group_u32(group, BG_BLOCK_BITMAP), bit)?;
+		self.add_group_u16(group, BG_FREE_BLOCKS_COUNT, 1);
+		self.add_superblock_u32(S_FREE_BLOCKS_COUNT, 1);
+		self.write_group(group)
+	}
+
+	/// Allocate an inode. Files are placed in the group of their parent
+	/// `parent`, while directories are spread over the groups.
+	fn allocate_inode(&mut self, parent: u32, is_dir: bool) -> io::Result<u32> {
+		if get_u32(&self.superblock, S_FREE_INODES_COUNT) == 0 {
+			return Err(io::Error::ENOSPC);
+		}
+
+		let start = if is_dir {
+			(0..self.group_count)
+				.max_by_key(|group| self.group_u16(*group, BG_FREE_INODES_COUNT))
+				.unwrap_or(0)
+		} else {
+			(parent - 1) / self.inodes_per_group
+		};
+
+		for index in 0..self.group_count {
+			let group = (start + index) % self.group_count;
+			if self.group_u16(group, BG_FREE_INODES_COUNT) == 0 {
+				continue;
+			}
+
+			let bitmap = self.group_u32(group, BG_INODE_BITMAP);
+			if let Some(bit) = self.allocate_bit(bitmap, self.inodes_per_group)? {
+				let ino = group * self.inodes_per_group + bit + 1;
+
+				// the reserved inodes are marked in the bitmap
+				if ino < self.first_ino || ino > self.inodes_count {
+					return Err(io::Error::EIO);
+				}
+
+				self.add_group_u16(group, BG_FREE_INODES_COUNT, -1);
+				if is_dir {
+					self.add_group_u16(group, BG_USED_DIRS_COUNT, 1);
+				}
+				self.add_superblock_u32(S_FREE_INODES_COUNT, -1);
+				self.write_group(group)?;
+
+				// clear the complete inode including the extra fields of large inodes
+				write_bytes(
+					&*self.device,
+					self.inode_offset(ino)?,
+					&vec![0u8; self.inode_size],
+				)?;
+
+				return Ok(ino);
+			}
+		}
+
+		Err(io::Error::ENOSPC)
+	}
+
+	/// Returns the byte offset of the inode `ino` on the device
+	fn inode_offset(&self, ino: u32) -> io::Result<u64> {
+		if ino == 0 || ino > self.inodes_count {
+			return Err(io::Error::EIO);
+		}
+
+		let group = (ino - 1) / self.inodes_per_group;
+		let index = (ino - 1) % self.inodes_per_group;
+		let table = self.group_u32(group, BG_INODE_TABLE);
+
+		Ok(u64::from(table) * self.block_size as u64 + u64::from(index) * self.inode_size as u64)
+	}
+
+	fn read_inode(&self, ino: u32) -> io::Result<DiskInode> {
+		let mut inode = DiskInode {
+			raw: [0; GOOD_OLD_INODE_SIZE],
+		};
+		read_bytes(&*self.device, self.inode_offset(ino)?, &mut inode.raw)?;
+
+		Ok(inode)
+	}
+
+	fn write_inode(&self, ino: u32, inode: &DiskInode) -> io::Result<()> {
+		write_bytes(&*self.device, self.inode_offset(ino)?, &inode.raw)
+	}
+
+	/// Returns the maximum size of a file
+	fn max_file_size(&self) -> u64 {
+		let pointers = (self.block_size / 4) as u64;
+		let blocks = DIRECT_BLOCKS as u64 + pointers + pointers * pointers + pointers.pow(3);
+		let limit = if self.large_file {
+			u64::MAX
+		} else {
+			i32::MAX as u64
+		};
+
+		(blocks * self.block_size as u64).min(limit)
+	}
+
+	/// Returns the index of the block pointer of the inode, the indices
+	/// within the indirect blocks and the depth of the logical block `index`
+	fn block_path(&self, index: u64) -> io::Result<(usize, [usize; 3], usize)> {
+		let pointers = (self.block_size / 4) as u64;
+
+		if index < DIRECT_BLOCKS as u64 {
+			return Ok((index as usize, [0; 3], 0));
+		}
+
+		let index = index - DIRECT_BLOCKS as u64;
+		if index < pointers {
+			return Ok((INDIRECT_BLOCK, [index as usize, 0, 0], 1));
+		}
+
+		let index = index - pointers;
+		if index < pointers * pointers {
+			return Ok((
+				DOUBLE_INDIRECT_BLOCK,
+				[(index / pointers) as usize, (index % pointers) as usize, 0],
+				2,
+			));
+		}
+
+		let index = index - pointers * pointers;
+		if index < pointers.pow(3) {
+			return Ok((
+				TRIPLE_INDIRECT_BLOCK,
+				[
+					(index / (pointers * pointers)) as usize,
+					((index / pointers) % pointers) as usize,
+					(index % pointers) as usize,
+				],
+				3,
+			));
+		}
+
+		Err(io::Error::EFBIG)
+	}
+
+	/// Returns the block, which stores the logical block `index` of the
+	/// inode `ino`. If `allocate` is true, missing blocks are allocated
+	/// and the caller has to write `inode`. Otherwise, holes return `None`.
+	fn map_block(
+		&mut self,
+		ino: u32,
+		inode: &mut DiskInode,
+		index: u64,
+		allocate: bool,
+	) -> io::Result<Option<u32>> {
+		let (slot, path, depth) = self.block_path(index)?;
+		let sectors_per_block = (self.block_size / SECTOR_SIZE) as u32;
+
+		let mut block = inode.block(slot);
+		if block == 0 {
+			if !allocate {
+				return Ok(None);
+			}
+
+			block = self.allocate_block(ino)?;
+			inode.set_block(slot, block);
+			inode.set_sectors(inode.sectors() + sectors_per_block);
+		}
+
+		for offset in path.iter().take(depth) {
+			let mut table = vec![0u8; self.block_size];
+			self.read_block(block, &mut table)?;
+
+			let mut next = get_u32(&table, 4 * offset);
+			if next == 0 {
+				if !allocate {
+					return Ok(None);
+				}
+
+				next = self.allocate_block(ino)?;
+				inode.set_sectors(inode.sectors() + sectors_per_block);
+				set_u32(&mut table, 4 * offset, next);
+				self.write_block(block, &table)?;
+			}
+			block = next;
+		}
+
+		Ok(Some(block))
+	}
+
+	/// Free `block` and, if `depth` is larger than zero, all blocks,
+	/// which are referenced by the indirect block `block`
+	fn free_tree(&mut self, block: u32, depth: usize) -> io::Result<()> {
+		if depth > 0 {
+			let mut table = vec![0u8; self.block_size];
+			self.read_block(block, &mut table)?;
+
+			for offset in (0..self.block_size).step_by(4) {
+				let next = get_u32(&table, offset);
+				if next != 0 {
+					self.free_tree(next, depth - 1)?;
+				}
+			}
+		}
+
+		self.free_block(block)
+	}
+
+	/// Returns true, if the target of the symbolic link is stored in the block pointers
+	fn is_fast_symlink(&self, inode: &DiskInode) -> bool {
+		let acl_sectors = if inode.file_acl() != 0 {
+			(self.block_size / SECTOR_SIZE) as u32
+		} else {
+			0
+		};
+
+		inode.mode() & S_IFMT == S_IFLNK && inode.sectors() == acl_sectors
+	}
+
+	/// Free all data blocks of `inode`. The caller has to write `inode`.
+	fn free_data(&mut self, inode: &mut DiskInode) -> io::Result<()> {
+		if !self.is_fast_symlink(inode) {
+			for slot in 0..=TRIPLE_INDIRECT_BLOCK {
+				let block = inode.block(slot);
+				if block != 0 {
+					let depth = slot.saturating_sub(DIRECT_BLOCKS - 1);
+					self.free_tree(block, depth)?;
+				}
+			}
+		}
+
+		for slot in 0..=TRIPLE_INDIRECT_BLOCK {
+			inode.set_block(slot, 0);
+		}
+		inode.set_sectors(if inode.file_acl() != 0 {
+			(self.block_size / SECTOR_SIZE) as u32
+		} else {
+			0
+		});
+		inode.set_size(0);
+
+		Ok(())
+	}
+
+	/// Free the inode `ino` and its blocks
+	fn free_inode(&mut self, ino: u32, mut inode: DiskInode) -> io::Result<()> {
+		self.free_data(&mut inode)?;
+
+		// blocks of extended attributes may be shared by several inodes
+		let acl = inode.file_acl();
+		if acl != 0 {
+			let mut block = vec![0u8; self.block_size];
+			self.read_block(acl, &mut block)?;
+
+			let refcount = get_u32(&block, XATTR_REFCOUNT);
+			if refcount <= 1 {
+				self.free_block(acl)?;
+			} else {
+				set_u32(&mut block, XATTR_REFCOUNT, refcount - 1);
+				self.write_block(acl, &block)?;
+			}
+			set_u32(&mut inode.raw, I_FILE_ACL, 0);
+			inode.set_sectors(0);
+		}
+
+		set_u32(&mut inode.raw, I_DTIME, now());
+		inode.set_links(0);
+		self.write_inode(ino, &inode)?;
+
+		let group = (ino - 1) / self.inodes_per_group;
+		let bit = (ino - 1) % self.inodes_per_group;
+		self.free_bit(self.group_u32(group, BG_INODE_BITMAP), bit)?;
+		self.add_group_u16(group, BG_FREE_INODES_COUNT, 1);
+		if inode.is_dir() {
+			self.add_group_u16(group, BG_USED_DIRS_COUNT, -1);
+		}
+		self.add_superblock_u32(S_FREE_INODES_COUNT, 1);
+		self.write_group(group)
+	}
+
+	/// Read `buf.len()` bytes starting at `pos` of the content of the inode `ino`
+	fn read_data(
+		&mut self,
+		ino: u32,
+		inode: &mut DiskInode,
+		pos: u64,
+		buf: &mut [u8],
+	) -> io::Result<()> {
+		let mut done = 0;
+
+		while done < buf.len() {
+			let offset = pos + done as u64;
+			let within = (offset % self.block_size as u64) as usize;
+			let len = (self.block_size - within).min(buf.len() - done);
+
+			match self.map_block(ino, inode, offset / self.block_size as u64, false)? {
+				Some(block) => read_bytes(
+					&*self.device,
+					u64::from(block) * self.block_size as u64 + within as u64,
+					&mut buf[done..done + len],
+				)?,
+				// holes of sparse files contain zeros
+				None => buf[done..done + len].fill(0),
+			}
+			done += len;
+		}
+
+		Ok(())
+	}
+
+	/// Write `buf` starting at `pos` to the content of the inode `ino`.
+	/// Returns the number of written bytes, which is only smaller than
+	/// `buf.len()`, if the volume is full. The caller has to write `inode`.
+	fn write_data(
+		&mut self,
+		ino: u32,
+		inode: &mut DiskInode,
+		pos: u64,
+		buf: &[u8],
+	) -> io::Result<usize> {
+		let mut done = 0;
+
+		while done < buf.len() {
+			let offset = pos + done as u64;
+			let within = (offset % self.block_size as u64) as usize;
+			let len = (self.block_size - within).min(buf.len() - done);
+
+			let block = match self.map_block(ino, inode, offset / self.block_size as u64, true) {
+				Ok(block) => block.unwrap(),
+				Err(e) if done == 0 => return Err(e),
+				Err(_) => break,
+			};
+
+			if len == self.block_size {
+				self.write_block(block, &buf[done..done + len])?;
+			} else {
+				write_bytes(
+					&*self.device,
+					u64::from(block) * self.block_size as u64 + within as u64,
+					&buf[done..done + len],
+				)?;
+			}
+			done += len;
+		}
+
+		if pos + done as u64 > inode.size() {
+			inode.set_size(pos + done as u64);
+		}
+
+		Ok(done)
+	}
+
+	/// Read all entries of the directory `ino` including `.` and `..`
+	fn read_dir(&mut self, ino: u32, inode: &mut DiskInode) -> io::Result<Vec<RawDirEntry>> {
+		let mut entries = Vec::new();
+		let mut data = vec![0u8; self.block_size];
+		let blocks = inode.size() / self.block_size as u64;
+
+		for index in 0..blocks {
+			let Some(block) = self.map_block(ino, inode, index, false)? else {
+				continue;
+			};
+			self.read_block(block, &mut data)?;
+
+			let mut offset = 0;
+			while offset < self.block_size {
+				let (entry_ino, rec_len, name_len) = self.parse_entry(&data, offset)?;
+
+				if entry_ino != 0 {
+					let name =
+						&data[offset + DIR_ENTRY_HEADER..offset + DIR_ENTRY_HEADER + name_len];
+					entries.push(RawDirEntry {
+						ino: entry_ino,
+						file_type: if self.filetype { data[offset + 7] } else { 0 },
+						name: String::from_utf8_lossy(name).into_owned(),
+					});
+				}
+				offset += rec_len;
+			}
+		}
+
+		Ok(entries)
+	}
+
+	/// Returns the inode number, the record length and the length of the
+	/// name of the directory entry at `offset` of the block `data`
+	fn parse_entry(&self, data: &[u8], offset: usize) -> io::Result<(u32, usize, usize)> {
+		let rec_len = usize::from(get_u16(data, offset + 4));
+		let name_len = if self.filetype {
+			usize::from(data[offset + 6])
+		} else {
+			usize::from(get_u16(data, offset + 6))
+		};
+
+		if rec_len < DIR_ENTRY_HEADER
+			|| rec_len % 4 != 0
+			|| offset + rec_len > self.block_size
+			|| DIR_ENTRY_HEADER + name_len > rec_len
+		{
+			warn!("ext2: directory entry at offset {} is corrupted", offset);
+			return Err(io::Error::EIO);
+		}
+
+		Ok((get_u32(data, offset), rec_len, name_len))
+	}
+
+	/// Write a directory entry at `offset` of the block `data`
+	fn put_entry(
+		&self,
+		data: &mut [u8],
+		offset: usize,
+		ino: u32,
+		rec_len: usize,
+		name: &str,
+		file_type: u8,
+	) {
+		set_u32(data, offset, ino);
+		set_u16(data, offset + 4, rec_len as u16);
+		if self.filetype {
+			data[offset + 6] = name.len() as u8;
+			data[offset + 7] = file_type;
+		} else {
+			set_u16(data, offset + 6, name.len() as u16);
+		}
+		data[offset + DIR_ENTRY_HEADER..offset + DIR_ENTRY_HEADER + name.len()]
+			.copy_from_slice(name.as_bytes());
+	}
+
+	fn find_entry(
+		&mut self,
+		ino: u32,
+		inode: &mut DiskInode,
+		name: &str,
+	) -> io::Result<Option<u32>> {
+		Ok(self
+			.read_dir(ino, inode)?
+			.iter()
+			.find(|entry| entry.name == name)
+			.map(|entry| entry.ino))
+	}
+
+	/// Insert the entry `name`, which references `target`, into the
+	/// directory `ino`. The caller has to write `inode`.
+	fn add_entry(
+		&mut self,
+		ino: u32,
+		inode: &mut DiskInode,
+		name: &str,
+		target: u32,
+		file_type: u8,
+	) -> io::Result<()> {
+		let needed = entry_size(name.len());
+		let mut data = vec![0u8; self.block_size];
+		let blocks = inode.size() / self.block_size as u64;
+
+		// search an entry with enough unused space
+		for index in 0..blocks {
+			let Some(block) = self.map_block(ino, inode, index, false)? else {
+				continue;
+			};
+			self.read_block(block, &mut data)?;
+
+			let mut offset = 0;
+			while offset < self.block_size {
+				let (entry_ino, rec_len, name_len) = self.parse_entry(&data, offset)?;
+				let used = if entry_ino == 0 {
+					0
+				} else {
+					entry_size(name_len)
+				};
+
+				if rec_len - used >= needed {
+					if used != 0 {
+						set_u16(&mut data, offset + 4, used as u16);
+					}
+					self.put_entry(
+						&mut data,
+						offset + used,
+						target,
+						rec_len - used,
+						name,
+						file_type,
+					);
+
+					return self.write_block(block, &data);
+				}
+				offset += rec_len;
+			}
+		}
+
+		// append a new block to the directory
+		let block = self.map_block(ino, inode, blocks, true)?.unwrap();
+		data.fill(0);
+		self.put_entry(&mut data, 0, target, self.block_size, name, file_type);
+		self.write_block(block, &data)?;
+		inode.set_size(inode.size() + self.block_size as u64);
+
+		Ok(())
+	}
+
+	/// Let the entry `name` of the directory `ino` reference `target` and
+	/// return the previous inode number. The caller has to write `inode`.
+	fn set_entry(
+		&mut self,
+		ino: u32,
+		inode: &mut DiskInode,
+		name: &str,
+		target: u32,
+		file_type: u8,
+	) -> io::Result<u32> {
+		let mut data = vec![0u8; self.block_size];
+		let blocks = inode.size() / self.block_size as u64;
+
+		for index in 0..blocks {
+			let Some(block) = self.map_block(ino, inode, index, false)? else {
+				continue;
+			};
+			self.read_block(block, &mut data)?;
+
+			let mut offset = 0;
+			while offset < self.block_size {
+				let (entry_ino, rec_len, name_len) = self.parse_entry(&data, offset)?;
+				let entry_name =
+					&data[offset + DIR_ENTRY_HEADER..offset + DIR_ENTRY_HEADER + name_len];
+
+				if entry_ino != 0 && entry_name == name.as_bytes() {
+					self.put_entry(&mut data, offset, target, rec_len, name, file_type);
+					self.write_block(block, &data)?;
+
+					return Ok(entry_ino);
+				}
+
+				offset += rec_len;
+			}
+		}
+
+		Err(io::Error::ENOENT)
+	}
+
+	/// Remove the entry `name` from the directory `ino` and return the
+	/// referenced inode number. The caller has to write `inode`.
+	fn remove_entry(&mut self, ino: u32, inode: &mut DiskInode, name: &str) -> io::Result<u32> {
+		let mut data = vec![0u8; self.block_size];
+		let blocks = inode.size() / self.block_size as u64;
+
+		for index in 0..blocks {
+			let Some(block) = self.map_block(ino, inode, index, false)? else {
+				continue;
+			};
+			self.read_block(block, &mut data)?;
+
+			let mut previous = None;
+			let mut offset = 0;
+			while offset < self.block_size {
+				let (entry_ino, rec_len, name_len) = self.parse_entry(&data, offset)?;
+				let entry_name =
+					&data[offset + DIR_ENTRY_HEADER..offset + DIR_ENTRY_HEADER + name_len];
+
+				if entry_ino != 0 && entry_name == name.as_bytes() {
+					match previous {
+						// the previous entry absorbs the space of the removed entry
+						Some(previous) => {
+							let previous_len = get_u16(&data, previous + 4);
+							set_u16(&mut data, previous + 4, previous_len + rec_len as u16);
+						}
+						// the first entry of a block is marked as unused
+						None => set_u32(&mut data, offset, 0),
+					}
+					self.write_block(block, &data)?;
+
+					return Ok(entry_ino);
+				}
+
+				previous = Some(offset);
+				offset += rec_len;
+			}
+		}
+
+		Err(io::Error::ENOENT)
+	}
+
+	/// Returns the inode of the parent of the directory `ino`, i.e. the entry `..`
+	fn parent_of(&mut self, ino: u32, inode: &mut DiskInode) -> io::Result<u32> {
+		self.find_entry(ino, inode, "..")?.ok_or(io::Error::EIO)
+	}
+
+	/// Change the entry `..` of the directory `ino` to `parent`
+	fn set_parent(&mut self, ino: u32, inode: &mut DiskInode, parent: u32) -> io::Result<()> {
+		let block = self
+			.map_block(ino, inode, 0, false)?
+			.ok_or(io::Error::EIO)?;
+		let mut data = vec![0u8; self.block_size];
+		self.read_block(block, &mut data)?;
+
+		// `..` is the second entry of the first block
+		let (_, rec_len, _) = self.parse_entry(&data, 0)?;
+		let (_, _, name_len) = self.parse_entry(&data, rec_len)?;
+		if &data[rec_len + DIR_ENTRY_HEADER..rec_len + DIR_ENTRY_HEADER + name_len] != b".." {
+			return Err(io::Error::EIO);
+		}
+		set_u32(&mut data, rec_len, parent);
+
+		self.write_block(block, &data)
+	}
+
+	/// Add `delta` to the number of links of the inode `ino`
+	fn add_links(&mut self, ino: u32, delta: i32) -> io::Result<()> {
+		let mut inode = self.read_inode(ino)?;
+		inode.set_links((i32::from(inode.links()) + delta) as u16);
+		inode.touch_change();
+
+		self.write_inode(ino, &inode)
+	}
+
+	/// Mark the directory `ino` as modified. A hash index of the
+	/// directory isn't maintained and, consequently, invalidated.
+	fn touch_directory(&self, ino: u32, inode: &mut DiskInode) -> io::Result<()> {
+		let flags = get_u32(&inode.raw, I_FLAGS);
+		set_u32(&mut inode.raw, I_FLAGS, flags & !INDEX_FL);
+		inode.touch_modify();
+
+		self.write_inode(ino, inode)
+	}
+
+	fn lookup(&mut self, dir: u32, name: &str) -> io::Result<(u32, u16)> {
+		let mut inode = self.read_inode(dir)?;
+		let ino = self
+			.find_entry(dir, &mut inode, name)?
+			.ok_or(io::Error::ENOENT)?;
+
+		Ok((ino, self.read_inode(ino)?.mode()))
+	}
+
+	fn readdir(&mut self, dir: u32) -> io::Result<Vec<DirEntry>> {
+		let mut inode = self.read_inode(dir)?;
+		let entries = self.read_dir(dir, &mut inode)?;
+
+		if !self.read_only && inode.touch_access() {
+			self.write_inode(dir, &inode)?;
+		}
+
+		let mut result = Vec::with_capacity(entries.len());
+		for entry in entries {
+			if entry.name == "." || entry.name == ".." {
+				continue;
+			}
+
+			let kind = match entry.file_type {
+				FT_REG_FILE => NodeKind::File,
+				FT_DIR => NodeKind::Directory,
+				FT_SYMLINK => NodeKind::Symlink,
+				FT_CHRDEV => NodeKind::CharDevice,
+				FT_BLKDEV => NodeKind::BlockDevice,
+				FT_SOCK => NodeKind::Socket,
+				_ => kind_of(self.read_inode(entry.ino)?.mode()),
+			};
+
+			result.push(DirEntry {
+				name: entry.name,
+				kind,
+				ino: self.vfs_ino(entry.ino),
+			});
+		}
+
+		Ok(result)
+	}
+
+	/// Check if the entry `name` can be inserted into the directory `dir`
+	fn check_new_entry(&mut self, dir: u32, inode: &mut DiskInode, name: &str) -> io::Result<()> {
+		self.check_writeable()?;
+
+		if name.len() > NAME_MAX {
+			return Err(io::Error::ENAMETOOLONG);
+		}
+		if self.find_entry(dir, inode, name)?.is_some() {
+			return Err(io::Error::EEXIST);
+		}
+
+		Ok(())
+	}
+
+	/// Create the node `name` of type `kind` in the directory `dir`
+	fn create(
+		&mut self,
+		dir: u32,
+		name: &str,
+		kind: NodeKind,
+		attr: &Attributes,
+	) -> io::Result<(u32, u16)> {
+		let mut parent = self.read_inode(dir)?;
+		self.check_new_entry(dir, &mut parent, name)?;
+
+		let (mode, links, file_type) = match kind {
+			NodeKind::File => (S_IFREG, 1, FT_REG_FILE),
+			NodeKind::Directory => {
+				if parent.links() >= LINK_MAX {
+					return Err(io::Error::EMLINK);
+				}
+				(S_IFDIR, 2, FT_DIR)
+			}
+			// devices are only provided by the devfs
+			NodeKind::CharDevice | NodeKind::BlockDevice => return Err(io::Error::EPERM),
+			// symbolic links require a target, see `symlink`
+			NodeKind::Symlink => return Err(io::Error::EINVAL),
+			NodeKind::Socket => (S_IFSOCK, 1, FT_SOCK),
+		};
+
+		let ino = self.allocate_inode(dir, kind == NodeKind::Directory)?;
+		let mut inode = DiskInode::new(mode, attr, links);
+
+		let result = self.init_node(ino, &mut inode, dir).and_then(|_| {
+			self.write_inode(ino, &inode)?;
+			self.add_entry(dir, &mut parent, name, ino, file_type)
+		});
+		if let Err(e) = result {
+			self.free_inode(ino, inode)?;
+			return Err(e);
+		}
+
+		if kind == NodeKind::Directory {
+			parent.set_links(parent.links() + 1);
+		}
+		self.touch_directory(dir, &mut parent)?;
+
+		Ok((ino, inode.mode()))
+	}
+
+	/// Initialize the content of a new node, i.e. the entries `.` and `..` of a directory
+	fn init_node(&mut self, ino: u32, inode: &mut DiskInode, parent: u32) -> io::Result<()> {
+		if !inode.is_dir() {
+			return Ok(());
+		}
+
+		let block = self.map_block(ino, inode, 0, true)?.unwrap();
+		let mut data = vec![0u8; self.block_size];
+		let dot_len = entry_size(1);
+		self.put_entry(&mut data, 0, ino, dot_len, ".", FT_DIR);
+		self.put_entry(
+			&mut data,
+			dot_len,
+			parent,
+			self.block_size - dot_len,
+			"..",
+			FT_DIR,
+		);
+		self.write_block(block, &data)?;
+		inode.set_size(self.block_size as u64);
+
+		Ok(())
+	}
+
+	/// Create the symbolic link `name`, which references `target`, in the directory `dir`
+	fn symlink(
+		&mut self,
+		dir: u32,
+		name: &str,
+		target: &str,
+		attr: &Attributes,
+	) -> io::Result<(u32, u16)> {
+		let mut parent = self.read_inode(dir)?;
+		self.check_new_entry(dir, &mut parent, name)?;
+
+		if target.len() >= self.block_size {
+			return Err(io::Error::ENAMETOOLONG);
+		}
+
+		let ino = self.allocate_inode(dir, false)?;
+		let mut inode = DiskInode::new(S_IFLNK, attr, 1);
+
+		let result = if target.len() < FAST_SYMLINK_SIZE {
+			// short targets are stored in the block pointers
+			inode.raw[I_BLOCK..I_BLOCK + target.len()].copy_from_slice(target.as_bytes());
+			inode.set_size(target.len() as u64);
+			Ok(())
+		} else {
+			self.write_data(ino, &mut inode, 0, target.as_bytes())
+				.map(|_| ())
+		};
+
+		let result = result.and_then(|_| {
+			self.write_inode(ino, &inode)?;
+			self.add_entry(dir, &mut parent, name, ino, FT_SYMLINK)
+		});
+		if let Err(e) = result {
+			self.free_inode(ino, inode)?;
+			return Err(e);
+		}
+		self.touch_directory(dir, &mut parent)?;
+
+		Ok((ino, inode.mode()))
+	}
+
+	fn readlink(&mut self, ino: u32) -> io::Result<String> {
+		let mut inode = self.read_inode(ino)?;
+		let size = inode.size() as usize;
+
+		if size > self.block_size {
+			return Err(io::Error::EIO);
+		}
+
+		let target = if self.is_fast_symlink(&inode) {
+			if size > FAST_SYMLINK_SIZE {
+				return Err(io::Error::EIO);
+			}
+			inode.raw[I_BLOCK..I_BLOCK + size].to_vec()
+		} else {
+			let mut data = vec![0u8; size];
+			self.read_data(ino, &mut inode, 0, &mut data)?;
+			data
+		};
+
+		Ok(String::from_utf8_lossy(&target).into_owned())
+	}
+
+	/// Insert the existing inode `ino` as entry `name` into the directory
+	/// `dir`. A directory is moved, i.e. its entry `..` is updated.
+	fn link(&mut self, dir: u32, name: &str, ino: u32) -> io::Result<()> {
+		let mut parent = self.read_inode(dir)?;
+		self.check_new_entry(dir, &mut parent, name)?;
+
+		let mut inode = self.read_inode(ino)?;
+		if inode.links() >= LINK_MAX {
+			return Err(io::Error::EMLINK);
+		}
+
+		self.add_entry(dir, &mut parent, name, ino, file_type_of(inode.mode()))?;
+		inode.set_links(inode.links() + 1);
+		inode.touch_change();
+
+		if inode.is_dir() {
+			let old_parent = self.parent_of(ino, &mut inode)?;
+
+			if old_parent != dir {
+				self.set_parent(ino, &mut inode, dir)?;
+				parent.set_links(parent.links() + 1);
+				self.add_links(old_parent, -1)?;
+			}
+		}
+
+		self.write_inode(ino, &inode)?;
+		self.touch_directory(dir, &mut parent)
+	}
+
+	/// Remove the entry `name` from the directory `dir`. The inode is
+	/// freed, if it hasn't any links and isn't opened anymore.
+	fn remove(&mut self, dir: u32, name: &str) -> io::Result<(u32, u16)> {
+		self.check_writeable()?;
+
+		let mut parent = self.read_inode(dir)?;
+		let ino = self.remove_entry(dir, &mut parent, name)?;
+
+		self.release(dir, &mut parent, ino)
+	}
+
+	/// Let the existing entry `name` of the directory `dir` reference the
+	/// inode `ino`. The previous inode is released as by `remove`.
+	fn replace(&mut self, dir: u32, name: &str, ino: u32) -> io::Result<(u32, u16)> {
+		self.check_writeable()?;
+
+		let mut parent = self.read_inode(dir)?;
+		let mut inode = self.read_inode(ino)?;
+		if inode.links() >= LINK_MAX {
+			return Err(io::Error::EMLINK);
+		}
+
+		let previous = self.set_entry(dir, &mut parent, name, ino, file_type_of(inode.mode()))?;
+		inode.set_links(inode.links() + 1);
+		inode.touch_change();
+
+		if inode.is_dir() {
+			let old_parent = self.parent_of(ino, &mut inode)?;
+
+			if old_parent != dir {
+				self.set_parent(ino, &mut inode, dir)?;
+				parent.set_links(parent.links() + 1);
+				self.add_links(old_parent, -1)?;
+			}
+		}
+
+		self.write_inode(ino, &inode)?;
+		self.release(dir, &mut parent, previous)
+	}
+
+	/// Drop a link of the inode `ino`, whose entry has been removed from the
+	/// directory `dir`. The inode is freed, if it hasn't any links and isn't
+	/// opened anymore. `parent` is the inode of `dir`, which is written.
+	fn release(&mut self, dir: u32, parent: &mut DiskInode, ino: u32) -> io::Result<(u32, u16)> {
+		let mut inode = self.read_inode(ino)?;
+
+		inode.set_links(inode.links().saturating_sub(1));
+		// a removed directory keeps only the link `.`, while a moved
+		// directory is still referenced by its new parent
+		if inode.is_dir() && inode.links() <= 1 {
+			inode.set_links(0);
+			parent.set_links(parent.links().saturating_sub(1));
+		}
+		self.touch_directory(dir, parent)?;
+
+		if inode.links() == 0 && !self.open_handles.contains_key(&ino) {
+			self.free_inode(ino, inode.clone())?;
+		} else {
+			if inode.links() == 0 {
+				self.orphans.insert(ino);
+			}
+			inode.touch_change();
+			self.write_inode(ino, &inode)?;
+		}
+
+		Ok((ino, inode.mode()))
+	}
+
+	fn status(&mut self, ino: u32) -> io::Result<FileStatus> {
+		let inode = self.read_inode(ino)?;
+
+		Ok(FileStatus {
+			ino: self.vfs_ino(ino),
+			kind: kind_of(inode.mode()),
+			nlink: usize::from(inode.links()),
+			file_size: inode.size() as usize,
+			attr: inode.attributes(),
+			times: inode.times(),
+		})
+	}
+
+	fn set_attributes(&mut self, ino: u32, attr: &Attributes) -> io::Result<()> {
+		self.check_writeable()?;
+
+		let mut inode = self.read_inode(ino)?;
+		let mode = (inode.mode() & S_IFMT) | attr.mode as u16;
+		set_u16(&mut inode.raw, I_MODE, mode);
+		inode.set_owner(attr.uid, attr.gid);
+		inode.touch_change();
+
+		self.write_inode(ino, &inode)
+	}
+
+	fn set_times(&mut self, ino: u32, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.check_writeable()?;
+
+		let mut inode = self.read_inode(ino)?;
+		let mut times = inode.times();
+		times.update(atime, mtime);
+
+		let to_disk = |time: Timespec| time.sec.clamp(0, i64::from(u32::MAX)) as u32;
+		set_u32(&mut inode.raw, I_ATIME, to_disk(times.atime));
+		set_u32(&mut inode.raw, I_MTIME, to_disk(times.mtime));
+		set_u32(&mut inode.raw, I_CTIME, to_disk(times.ctime));
+
+		self.write_inode(ino, &inode)
+	}
+
+	/// Register an open handle to the file `ino`
+	fn open_inode(&mut self, ino: u32, opt: OpenOption) -> io::Result<()> {
+		let mut inode = self.read_inode(ino)?;
+
+		match inode.mode() & S_IFMT {
+			S_IFREG => {}
+			S_IFDIR => return Err(io::Error::EISDIR),
+			// the volume doesn't provide devices, pipes or sockets
+			_ => return Err(io::Error::ENXIO),
+		}
+
+		if opt.is_writeable() {
+			self.check_writeable()?;
+		}
+
+		if opt.contains(OpenOption::O_TRUNC) && opt.is_writeable() {
+			self.free_data(&mut inode)?;
+			inode.touch_modify();
+			self.write_inode(ino, &inode)?;
+		}
+
+		*self.open_handles.entry(ino).or_insert(0) += 1;
+
+		Ok(())
+	}
+
+	/// Unregister an open handle to the file `ino`. The last
+	/// handle frees the inode, if the file is already removed.
+	fn close_inode(&mut self, ino: u32) -> io::Result<()> {
+		let Some(count) = self.open_handles.get_mut(&ino) else {
+			return Ok(());
+		};
+
+		*count -= 1;
+		if *count == 0 {
+			self.open_handles.remove(&ino);
+
+			if self.orphans.remove(&ino) {
+				let inode = self.read_inode(ino)?;
+				self.free_inode(ino, inode)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn read(&mut self, ino: u32, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+		let mut inode = self.read_inode(ino)?;
+		let size = inode.size();
+
+		if pos >= size {
+			return Ok(0);
+		}
+
+		let len = ((size - pos) as usize).min(buf.len());
+		self.read_data(ino, &mut inode, pos, &mut buf[..len])?;
+
+		if !self.read_only && inode.touch_access() {
+			self.write_inode(ino, &inode)?;
+		}
+
+		Ok(len)
+	}
+
+	/// Write `buf` at `pos` or, if `append` is true, at the end of the file `ino`.
+	/// Returns the number of written bytes and the new position.
+	fn write(&mut self, ino: u32, pos: u64, append: bool, buf: &[u8]) -> io::Result<(usize, u64)> {
+		self.check_writeable()?;
+
+		let mut inode = self.read_inode(ino)?;
+		let pos = if append { inode.size() } else { pos };
+
+		if pos + buf.len() as u64 > self.max_file_size() {
+			return Err(io::Error::EFBIG);
+		}
+
+		let result = self.write_data(ino, &mut inode, pos, buf);
+		// allocated blocks are already referenced by the inode
+		inode.touch_modify();
+		self.write_inode(ino, &inode)?;
+
+		let len = result?;
+		Ok((len, pos + len as u64))
+	}
+}
+
+/// Create the node of the inode `ino` with the mode `mode`
+fn node(volume: &Arc<Spinlock<Ext2Volume>>, ino: u32, mode: u16) -> Inode {
+	let vfs_ino = volume.lock().vfs_ino(ino);
+
+	match kind_of(mode) {
+		NodeKind::Directory => Arc::new(Ext2Directory {
+			volume: volume.clone(),
+			ino,
+			vfs_ino,
+		}),
+		NodeKind::Symlink => Arc::new(Ext2Symlink {
+			volume: volume.clone(),
+			ino,
+			vfs_ino,
+		}),
+		kind => Arc::new(Ext2File {
+			volume: volume.clone(),
+			ino,
+			vfs_ino,
+			kind,
+		}),
+	}
+}
+
+#[derive(Debug)]
+struct Ext2Directory {
+	/// volume, which stores the directory
+	volume: Arc<Spinlock<Ext2Volume>>,
+	/// inode number on the volume
+	ino: u32,
+	/// inode number of the virtual file system
+	vfs_ino: u64,
+}
+
+impl VfsNode for Ext2Directory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.vfs_ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		self.stat().map_or(0, |status| status.nlink)
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		self.stat()
+			.map_or(Attributes::default_for(NodeKind::Directory), |status| {
+				status.attr
+			})
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		self.volume.lock().set_attributes(self.ino, &attr)
+	}
+
+	fn get_times(&self) -> Timestamps {
+		self.stat()
+			.map_or(Timestamps::default(), |status| status.times)
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.volume.lock().set_times(self.ino, atime, mtime)
+	}
+
+	fn stat(&self) -> io::Result<FileStatus> {
+		self.volume.lock().status(self.ino)
+	}
+
+	fn lookup(&self, name: &str) -> io::Result<Inode> {
+		let (ino, mode) = self.volume.lock().lookup(self.ino, name)?;
+
+		Ok(node(&self.volume, ino, mode))
+	}
+
+	fn create(&self, name: &str, kind: NodeKind, attr: Attributes) -> io::Result<Inode> {
+		let (ino, mode) = self.volume.lock().create(self.ino, name, kind, &attr)?;
+
+		Ok(node(&self.volume, ino, mode))
+	}
+
+	fn symlink(&self, name: &str, target: &str, attr: Attributes) -> io::Result<Inode> {
+		let (ino, mode) = self.volume.lock().symlink(self.ino, name, target, &attr)?;
+
+		Ok(node(&self.volume, ino, mode))
+	}
+
+	fn link(&self, name: &str, node: Inode) -> io::Result<()> {
+		let mut volume = self.volume.lock();
+		let ino = *volume
+			.disk_inos
+			.get(&node.get_ino())
+			.ok_or(io::Error::EXDEV)?;
+
+		volume.link(self.ino, name, ino)
+	}
+
+	fn remove(&self, name: &str) -> io::Result<Inode> {
+		let (ino, mode) = self.volume.lock().remove(self.ino, name)?;
+
+		Ok(node(&self.volume, ino, mode))
+	}
+
+	fn replace(&self, name: &str, target: Inode) -> io::Result<Inode> {
+		let mut volume = self.volume.lock();
+		let ino = *volume
+			.disk_inos
+			.get(&target.get_ino())
+			.ok_or(io::Error::EXDEV)?;
+		let (previous, mode) = volume.replace(self.ino, name, ino)?;
+		drop(volume);
+
+		Ok(node(&self.volume, previous, mode))
+	}
+
+	fn readdir(&self) -> io::Result<Vec<DirEntry>> {
+		self.volume.lock().readdir(self.ino)
+	}
+}
+
+#[derive(Debug)]
+struct Ext2Symlink {
+	/// volume, which stores the link
+	volume: Arc<Spinlock<Ext2Volume>>,
+	/// inode number on the volume
+	ino: u32,
+	/// inode number of the virtual file system
+	vfs_ino: u64,
+}
+
+impl VfsNode for Ext2Symlink {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Symlink
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.vfs_ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		self.stat().map_or(0, |status| status.nlink)
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		self.stat()
+			.map_or(Attributes::default_for(NodeKind::Symlink), |status| {
+				status.attr
+			})
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		self.volume.lock().set_attributes(self.ino, &attr)
+	}
+
+	fn get_times(&self) -> Timestamps {
+		self.stat()
+			.map_or(Timestamps::default(), |status| status.times)
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.volume.lock().set_times(self.ino, atime, mtime)
+	}
+
+	fn stat(&self) -> io::Result<FileStatus> {
+		self.volume.lock().status(self.ino)
+	}
+
+	fn readlink(&self) -> io::Result<String> {
+		self.volume.lock().readlink(self.ino)
+	}
+}
+
+#[derive(Debug)]
+struct Ext2File {
+	/// volume, which stores the file
+	volume: Arc<Spinlock<Ext2Volume>>,
+	/// inode number on the volume
+	ino: u32,
+	/// inode number of the virtual file system
+	vfs_ino: u64,
+	/// type of the node, devices and sockets cannot be opened
+	kind: NodeKind,
+}
+
+impl VfsNode for Ext2File {
+	fn get_kind(&self) -> NodeKind {
+		self.kind
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.vfs_ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		self.stat().map_or(0, |status| status.nlink)
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		self.stat()
+			.map_or(Attributes::default_for(self.kind), |status| status.attr)
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		self.volume.lock().set_attributes(self.ino, &attr)
+	}
+
+	fn get_times(&self) -> Timestamps {
+		self.stat()
+			.map_or(Timestamps::default(), |status| status.times)
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.volume.lock().set_times(self.ino, atime, mtime)
+	}
+
+	fn stat(&self) -> io::Result<FileStatus> {
+		self.volume.lock().status(self.ino)
+	}
+
+	fn open(&self, opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		self.volume.lock().open_inode(self.ino, opt)?;
+
+		Ok(Arc::new(Ext2FileHandle {
+			volume: self.volume.clone(),
+			ino: self.ino,
+			readable: opt.is_readable(),
+			writeable: opt.is_writeable(),
+			append: opt.contains(OpenOption::O_APPEND),
+			pos: Spinlock::new(0),
+		}))
+	}
+}
+
+/// IO interface to an open file
+///
+/// The handle keeps the inode alive, i.e. the content is
+/// still accessible after the file is removed.
+#[derive(Debug)]
+struct Ext2FileHandle {
+	/// volume, which stores the file
+	volume: Arc<Spinlock<Ext2Volume>>,
+	/// inode number on the volume
+	ino: u32,
+	/// the file is opened for reading
+	readable: bool,
+	/// the file is opened for writing
+	writeable: bool,
+	/// all writes append data to the end of the file
+	append: bool,
+	/// position within the file
+	pos: Spinlock<u64>,
+}
+
+impl IoInterface for Ext2FileHandle {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		if !self.readable {
+			return Err(io::Error::EBADF);
+		}
+
+		let mut pos_guard = self.pos.lock();
+		let len = self.volume.lock().read(self.ino, *pos_guard, buf)?;
+		*pos_guard += len as u64;
+
+		Ok(len)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		if !self.writeable {
+			return Err(io::Error::EBADF);
+		}
+
+		let mut pos_guard = self.pos.lock();
+		let (len, pos) = self
+			.volume
+			.lock()
+			.write(self.ino, *pos_guard, self.append, buf)?;
+		*pos_guard = pos;
+
+		Ok(len)
+	}
+
+	fn seek(&self, style: SeekFrom) -> io::Result<usize> {
+		let mut pos_guard = self.pos.lock();
+		let pos = match style {
+			SeekFrom::Start(n) => n as i64,
+			SeekFrom::End(n) => self.fstat()?.file_size as i64 + n as i64,
+			SeekFrom::Current(n) => *pos_guard as i64 + n as i64,
+		};
+
+		if pos >= 0 {
+			*pos_guard = pos as u64;
+			Ok(pos as usize)
+		} else {
+			Err(io::Error::EINVAL)
+		}
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		self.volume.lock().status(self.ino)
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.volume.lock().set_times(self.ino, atime, mtime)
+	}
+
+	fn fsync(&self) -> io::Result<()> {
+		// all changes are already passed to the device
+		let device = self.volume.lock().device.clone();
+		device.flush()
+	}
+
+	fn access_mode(&self) -> OpenOption {
+		OpenOption::access_mode(self.readable, self.writeable)
+	}
+}
+
+impl Drop for Ext2FileHandle {
+	fn drop(&mut self) {
+		if let Err(e) = self.volume.lock().close_inode(self.ino) {
+			warn!("ext2: unable to release inode {}: {:?}", self.ino, e);
+		}
+	}
+}
+
+/// Instance of a mounted ext2 volume
+#[derive(Debug)]
+pub(crate) struct Ext2Fs {
+	/// root directory of the volume
+	root: Inode,
+	/// device, which stores the volume
+	device: Arc<dyn BlockDevice>,
+}
+
+impl Ext2Fs {
+	/// Mount the ext2 volume, which is stored on the block device `source`
+	pub fn create(source: Option<&str>) -> io::Result<Arc<dyn FileSystem>> {
+		let device = get_block_device(source.ok_or(io::Error::EINVAL)?)?;
+		let volume = Ext2Volume::open(device.clone())?;
+
+		info!(
+			"Mount ext2 volume with {} blocks of {} bytes{}",
+			volume.blocks_count,
+			volume.block_size,
+			if volume.read_only { " (read-only)" } else { "" }
+		);
+
+		let volume = Arc::new(Spinlock::new(volume));
+		let root = node(&volume, ROOT_INO, S_IFDIR);
+
+		Ok(Arc::new(Ext2Fs { root, device }))
+	}
+}
+
+impl FileSystem for Ext2Fs {
+	fn name(&self) -> &'static str {
+		"ext2"
+	}
+
+	fn root(&self) -> Inode {
+		self.root.clone()
+	}
+
+	fn sync(&self) -> io::Result<()> {
+		self.device.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::drivers::block::check_request;
+
+	const BLOCK_SIZE: usize = 1024;
+	const BLOCKS_COUNT: u32 = 64;
+	const INODES_COUNT: u32 = 32;
+	// layout of the test volume
+	const BLOCK_BITMAP: u32 = 3;
+	const INODE_BITMAP: u32 = 4;
+	const INODE_TABLE: u32 = 5;
+	const ROOT_BLOCK: u32 = 9;
+
+	/// Block device, which stores its content in a vector
+	#[derive(Debug)]
+	struct MemoryDisk {
+		data: Spinlock<Vec<u8>>,
+	}
+
+	impl BlockDevice for MemoryDisk {
+		fn num_sectors(&self) -> u64 {
+			(self.data.lock().len() / SECTOR_SIZE) as u64
+		}
+
+		fn is_read_only(&self) -> bool {
+			false
+		}
+
+		fn read_sectors(&self, sector: u64, buf: &mut [u8]) -> io::Result<()> {
+			check_request(self, sector, buf.len())?;
+
+			let start = sector as usize * SECTOR_SIZE;
+			buf.copy_from_slice(&self.data.lock()[start..start + buf.len()]);
+
+			Ok(())
+		}
+
+		fn write_sectors(&self, sector: u64, buf: &[u8]) -> io::Result<()> {
+			check_request(self, sector, buf.len())?;
+
+			let start = sector as usize * SECTOR_SIZE;
+			self.data.lock()[start..start + buf.len()].copy_from_slice(buf);
+
+			Ok(())
+		}
+	}
+
+	/// Returns a volume of revision 0 with a single group, which
+	/// contains only the root directory
+	fn volume() -> Ext2Volume {
+		let mut image = vec![0u8; BLOCKS_COUNT as usize * BLOCK_SIZE];
+		let used_blocks = ROOT_BLOCK;
+
+		let superblock = &mut image[BLOCK_SIZE..2 * BLOCK_SIZE];
+		set_u32(superblock, S_INODES_COUNT, INODES_COUNT);
+		set_u32(superblock, S_BLOCKS_COUNT, BLOCKS_COUNT);
+		set_u32(
+			superblock,
+			S_FREE_BLOCKS_COUNT,
+			BLOCKS_COUNT - 1 - used_blocks,
+		);
+		set_u32(
+			superblock,
+			S_FREE_INODES_COUNT,
+			INODES_COUNT + 1 - GOOD_OLD_FIRST_INO,
+		);
+		set_u32(superblock, S_FIRST_DATA_BLOCK, 1);
+		set_u32(superblock, S_BLOCKS_PER_GROUP, BLOCKS_COUNT - 1);
+		set_u32(superblock, S_INODES_PER_GROUP, INODES_COUNT);
+		set_u16(superblock, S_MAGIC, EXT2_MAGIC);
+
+		let group = &mut image[2 * BLOCK_SIZE..3 * BLOCK_SIZE];
+		set_u32(group, BG_BLOCK_BITMAP, BLOCK_BITMAP);
+		set_u32(group, BG_INODE_BITMAP, INODE_BITMAP);
+		set_u32(group, BG_INODE_TABLE, INODE_TABLE);
+		set_u16(
+			group,
+			BG_FREE_BLOCKS_COUNT,
+			(BLOCKS_COUNT - 1 - used_blocks) as u16,
+		);
+		set_u16(
+			group,
+			BG_FREE_INODES_COUNT,
+			(INODES_COUNT + 1 - GOOD_OLD_FIRST_INO) as u16,
+		);
+		set_u16(group, BG_USED_DIRS_COUNT, 1);
+
+		// the blocks up to the root directory and the reserved inodes are in use
+		for bit in 0..used_blocks {
+			image[BLOCK_BITMAP as usize * BLOCK_SIZE + bit as usize / 8] |= 1 << (bit % 8);
+		}
+		for bit in 0..GOOD_OLD_FIRST_INO - 1 {
+			image[INODE_BITMAP as usize * BLOCK_SIZE + bit as usize / 8] |= 1 << (bit % 8);
+		}
+
+		let mut root = DiskInode::new(S_IFDIR, &Attributes::new(0o755, 0, 0), 2);
+		root.set_block(0, ROOT_BLOCK);
+		root.set_sectors((BLOCK_SIZE / SECTOR_SIZE) as u32);
+		root.set_size(BLOCK_SIZE as u64);
+		let offset =
+			INODE_TABLE as usize * BLOCK_SIZE + (ROOT_INO as usize - 1) * GOOD_OLD_INODE_SIZE;
+		image[offset..offset + GOOD_OLD_INODE_SIZE].copy_from_slice(&root.raw);
+
+		let entries = &mut image[ROOT_BLOCK as usize * BLOCK_SIZE..];
+		set_u32(entries, 0, ROOT_INO);
+		set_u16(entries, 4, 12);
+		set_u16(entries, 6, 1);
+		entries[8] = b'.';
+		set_u32(entries, 12, ROOT_INO);
+		set_u16(entries, 16, (BLOCK_SIZE - 12) as u16);
+		set_u16(entries, 18, 2);
+		entries[20..22].copy_from_slice(b"..");
+
+		let disk = MemoryDisk {
+			data: Spinlock::new(image),
+		};
+		Ext2Volume::open(Arc::new(disk)).unwrap()
+	}
+
+	fn free_blocks(volume: &Ext2Volume) -> u32 {
+		get_u32(&volume.superblock, S_FREE_BLOCKS_COUNT)
+	}
+
+	fn free_inodes(volume: &Ext2Volume) -> u32 {
+		get_u32(&volume.superblock, S_FREE_INODES_COUNT)
+	}
+
+	fn links(volume: &Ext2Volume, ino: u32) -> u16 {
+		volume.read_inode(ino).unwrap().links()
+	}
+
+	#[test_case]
+	fn block_path_levels() {
+		let volume = volume();
+		// a block of 1024 bytes contains 256 pointers
+		let pointers = 256u64;
+
+		assert_eq!(volume.block_path(11), Ok((11, [0, 0, 0], 0)));
+		assert_eq!(volume.block_path(12), Ok((INDIRECT_BLOCK, [0, 0, 0], 1)));
+		assert_eq!(
+			volume.block_path(12 + pointers - 1),
+			Ok((INDIRECT_BLOCK, [255, 0, 0], 1))
+		);
+		assert_eq!(
+			volume.block_path(12 + pointers),
+			Ok((DOUBLE_INDIRECT_BLOCK, [0, 0, 0], 2))
+		);
+		assert_eq!(
+			volume.block_path(12 + pointers + pointers + 1),
+			Ok((DOUBLE_INDIRECT_BLOCK, [1, 1, 0], 2))
+		);
+		assert_eq!(
+			volume.block_path(12 + pointers + pointers * pointers),
+			Ok((TRIPLE_INDIRECT_BLOCK, [0, 0, 0], 3))
+		);
+
+		let last = 12 + pointers + pointers * pointers + pointers.pow(3) - 1;
+		assert_eq!(
+			volume.block_path(last),
+			Ok((TRIPLE_INDIRECT_BLOCK, [255, 255, 255], 3))
+		);
+		assert_eq!(volume.block_path(last + 1), Err(io::Error::EFBIG));
+	}
+
+	#[test_case]
+	fn map_indirect_block() {
+		let mut volume = volume();
+		let (ino, _) = volume
+			.create(
+				ROOT_INO,
+				"file",
+				NodeKind::File,
+				&Attributes::new(0o644, 0, 0),
+			)
+			.unwrap();
+		let mut inode = volume.read_inode(ino).unwrap();
+		let free = free_blocks(&volume);
+
+		assert_eq!(volume.map_block(ino, &mut inode, 12, false), Ok(None));
+
+		// the indirect block and the data block are allocated
+		let block = volume.map_block(ino, &mut inode, 12, true).unwrap();
+		assert!(block.is_some());
+		assert_ne!(inode.block(INDIRECT_BLOCK), 0);
+		assert_ne!(Some(inode.block(INDIRECT_BLOCK)), block);
+		assert_eq!(free_blocks(&volume), free - 2);
+		assert_eq!(inode.sectors(), 2 * (BLOCK_SIZE / SECTOR_SIZE) as u32);
+
+		assert_eq!(volume.map_block(ino, &mut inode, 12, false), Ok(block));
+		assert_eq!(volume.map_block(ino, &mut inode, 13, false), Ok(None));
+
+		// the blocks are released with the data
+		volume.free_data(&mut inode).unwrap();
+		assert_eq!(free_blocks(&volume), free);
+	}
+
+	#[test_case]
+	fn map_double_indirect_block() {
+		let mut volume = volume();
+		let (ino, _) = volume
+			.create(
+				ROOT_INO,
+				"file",
+				NodeKind::File,
+				&Attributes::new(0o644, 0, 0),
+			)
+			.unwrap();
+		let mut inode = volume.read_inode(ino).unwrap();
+		let free = free_blocks(&volume);
+
+		let block = volume.map_block(ino, &mut inode, 12 + 256, true).unwrap();
+		assert!(block.is_some());
+		assert_eq!(inode.block(INDIRECT_BLOCK), 0);
+		assert_ne!(inode.block(DOUBLE_INDIRECT_BLOCK), 0);
+		assert_eq!(free_blocks(&volume), free - 3);
+		assert_eq!(
+			volume.map_block(ino, &mut inode, 12 + 256, false),
+			Ok(block)
+		);
+		assert_eq!(volume.map_block(ino, &mut inode, 12 + 257, false), Ok(None));
+
+		volume.free_data(&mut inode).unwrap();
+		assert_eq!(free_blocks(&volume), free);
+	}
+
+	#[test_case]
+	fn link_and_remove_file() {
+		let mut volume = volume();
+		let attr = Attributes::new(0o755, 0, 0);
+		let free = free_inodes(&volume);
+		let (dir, _) = volume
+			.create(ROOT_INO, "dir", NodeKind::Directory, &attr)
+			.unwrap();
+		let (file, _) = volume
+			.create(ROOT_INO, "file", NodeKind::File, &attr)
+			.unwrap();
+
+		assert_eq!(links(&volume, file), 1);
+		assert_eq!(volume.link(dir, "file", file), Ok(()));
+		assert_eq!(volume.link(dir, "file", file), Err(io::Error::EEXIST));
+		assert_eq!(links(&volume, file), 2);
+		// hard links to files don't change the links of the directories
+		assert_eq!(links(&volume, dir), 2);
+
+		assert_eq!(
+			volume.remove(ROOT_INO, "file").map(|(ino, _)| ino),
+			Ok(file)
+		);
+		assert_eq!(links(&volume, file), 1);
+		assert_eq!(volume.lookup(dir, "file").map(|(ino, _)| ino), Ok(file));
+
+		// the inode is freed with the last link
+		assert_eq!(volume.remove(dir, "file").map(|(ino, _)| ino), Ok(file));
+		assert_eq!(links(&volume, file), 0);
+		assert_eq!(free_inodes(&volume), free - 1);
+		assert_eq!(volume.remove(dir, "file"), Err(io::Error::ENOENT));
+	}
+
+	#[test_case]
+	fn link_and_remove_directory() {
+		let mut volume = volume();
+		let attr = Attributes::new(0o755, 0, 0);
+		let free = free_inodes(&volume);
+
+		let (dir, _) = volume
+			.create(ROOT_INO, "dir", NodeKind::Directory, &attr)
+			.unwrap();
+		// the entry `..` of the new directory links the root directory
+		assert_eq!(links(&volume, ROOT_INO), 3);
+		assert_eq!(links(&volume, dir), 2);
+
+		let (sub, _) = volume
+			.create(dir, "sub", NodeKind::Directory, &attr)
+			.unwrap();
+		assert_eq!(links(&volume, dir), 3);
+
+		// moving the directory changes the links of the old and the new parent
+		assert_eq!(volume.link(ROOT_INO, "moved", sub), Ok(()));
+		assert_eq!(volume.remove(dir, "sub").map(|(ino, _)| ino), Ok(sub));
+		assert_eq!(links(&volume, sub), 2);
+		assert_eq!(links(&volume, dir), 2);
+		assert_eq!(links(&volume, ROOT_INO), 4);
+
+		assert_eq!(
+			volume.remove(ROOT_INO, "moved").map(|(ino, _)| ino),
+			Ok(sub)
+		);
+		assert_eq!(volume.remove(ROOT_INO, "dir").map(|(ino, _)| ino), Ok(dir));
+		assert_eq!(links(&volume, ROOT_INO), 2);
+		assert_eq!(free_inodes(&volume), free);
+	}
+}
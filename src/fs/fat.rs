@@ -0,0 +1,791 @@
+//! Implements a read-only driver for the FAT32 file system
+//! including long file names (VFAT)
+//!
+//! Directories and the file allocation table are read on demand from
+//! the block device. FAT doesn't store owners and permission bits,
+//! consequently all nodes belong to the superuser.
+
+use crate::drivers::block::{get_block_device, read_bytes, BlockDevice, SECTOR_SIZE};
+use crate::fd::{FileStatus, IoInterface, OpenOption, SeekFrom};
+use crate::fs::{
+	next_ino, Attributes, DirEntry, FileSystem, Inode, NodeKind, TimeUpdate, Timestamps, VfsNode,
+};
+use crate::io;
+use crate::logging::*;
+use crate::synch::spinlock::*;
+use crate::time::{days_from_civil, Timespec};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Signature at the end of the boot sector
+const BOOT_SIGNATURE: u16 = 0xAA55;
+/// Size of an entry of a directory
+const DIR_ENTRY_SIZE: usize = 32;
+
+/// Attribute of a file, which isn't writeable
+const ATTR_READ_ONLY: u8 = 0x01;
+/// Attribute of the entry, which stores the label of the volume
+const ATTR_VOLUME_ID: u8 = 0x08;
+/// Attribute of a directory
+const ATTR_DIRECTORY: u8 = 0x10;
+/// Combination of attributes, which marks an entry of a long name
+const ATTR_LONG_NAME: u8 = 0x0F;
+/// Mask of the attributes, which are checked to detect an entry of a long name
+const ATTR_LONG_NAME_MASK: u8 = 0x3F;
+
+/// First byte of the name, which marks the end of a directory
+const END_OF_DIRECTORY: u8 = 0x00;
+/// First byte of the name of a deleted entry
+const DELETED_ENTRY: u8 = 0xE5;
+/// First byte of the name, which replaces a leading 0xE5 of a valid entry
+const ESCAPED_E5: u8 = 0x05;
+/// Flag of the entry, which marks a base name in lowercase
+const LOWERCASE_BASE: u8 = 0x08;
+/// Flag of the entry, which marks an extension in lowercase
+const LOWERCASE_EXT: u8 = 0x10;
+
+/// Flag of the sequence number of the last entry of a long name
+const LAST_LONG_ENTRY: u8 = 0x40;
+/// Mask of the sequence number of an entry of a long name
+const LONG_ENTRY_ORDER: u8 = 0x1F;
+/// Offsets of the UTF-16 characters within an entry of a long name
+const LONG_NAME_OFFSETS: [usize; 13] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// Mask of the valid bits of an entry of the allocation table
+const FAT_ENTRY_MASK: u32 = 0x0FFF_FFFF;
+/// Entries from this value on mark the end of a cluster chain
+const END_OF_CHAIN: u32 = 0x0FFF_FFF8;
+/// Number of the first data cluster
+const FIRST_CLUSTER: u32 = 2;
+
+/// Permission bits of all nodes. Write accesses fail with `EROFS`.
+const DEFAULT_MODE: u32 = 0o755;
+/// Write permissions of the owner, the group and others
+const S_IWUGO: u32 = 0o222;
+
+fn le16(buf: &[u8], offset: usize) -> u16 {
+	u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn le32(buf: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes([
+		buf[offset],
+		buf[offset + 1],
+		buf[offset + 2],
+		buf[offset + 3],
+	])
+}
+
+/// Convert a date and a time of FAT to a point in time. FAT
+/// stores the local time, which is considered to be UTC.
+fn to_timespec(date: u16, time: u16) -> Timespec {
+	if date == 0 {
+		return Timespec::default();
+	}
+
+	let year = 1980 + i64::from(date >> 9);
+	let month = i64::from((date >> 5) & 0x0F).clamp(1, 12);
+	let day = i64::from(date & 0x1F).max(1);
+	let seconds = i64::from(time >> 11) * 3600
+		+ i64::from((time >> 5) & 0x3F) * 60
+		+ i64::from(time & 0x1F) * 2;
+
+	Timespec::new(days_from_civil(year, month, day) * 86400 + seconds, 0)
+}
+
+/// Checksum of a short name, which is stored in the entries of the long name
+fn short_name_checksum(name: &[u8]) -> u8 {
+	name.iter()
+		.fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte))
+}
+
+/// Convert the short name of the entry `raw` to the format 8.3
+fn short_name(raw: &[u8]) -> String {
+	let flags = raw[12];
+	let mut name = String::new();
+
+	for (index, byte) in raw[0..8].iter().enumerate() {
+		let byte = if index == 0 && *byte == ESCAPED_E5 {
+			DELETED_ENTRY
+		} else {
+			*byte
+		};
+		name.push(char::from(if flags & LOWERCASE_BASE != 0 {
+			byte.to_ascii_lowercase()
+		} else {
+			byte
+		}));
+	}
+	let len = name.trim_end_matches(' ').len();
+	name.truncate(len);
+
+	let extension = raw[8..11]
+		.iter()
+		.map(|byte| {
+			char::from(if flags & LOWERCASE_EXT != 0 {
+				byte.to_ascii_lowercase()
+			} else {
+				*byte
+			})
+		})
+		.collect::<String>();
+	let extension = extension.trim_end_matches(' ');
+	if !extension.is_empty() {
+		name.push('.');
+		name.push_str(extension);
+	}
+
+	name
+}
+
+/// Collects the entries of a long name, which precede the entry of
+/// the short name in reverse order
+#[derive(Debug, Default)]
+struct LongName {
+	/// UTF-16 characters of the name
+	chars: Vec<u16>,
+	/// checksum of the short name, which belongs to the long name
+	checksum: u8,
+	/// sequence number of the next expected entry
+	next: u8,
+	/// false, if an entry is missing or doesn't match
+	valid: bool,
+}
+
+impl LongName {
+	/// Add the entry `raw` to the long name
+	fn push(&mut self, raw: &[u8]) {
+		let order = raw[0] & LONG_ENTRY_ORDER;
+
+		if raw[0] & LAST_LONG_ENTRY != 0 {
+			self.chars = vec![0xFFFF; usize::from(order) * LONG_NAME_OFFSETS.len()];
+			self.checksum = raw[13];
+			self.next = order;
+			self.valid = order != 0;
+		}
+
+		// the sequence numbers start at 1 => an entry with the number 0
+		// doesn't belong to a long name, even if the previous name is complete
+		if !self.valid || order == 0 || order != self.next || raw[13] != self.checksum {
+			self.valid = false;
+			return;
+		}
+
+		let start = usize::from(order - 1) * LONG_NAME_OFFSETS.len();
+		for (index, offset) in LONG_NAME_OFFSETS.iter().enumerate() {
+			self.chars[start + index] = le16(raw, *offset);
+		}
+		self.next = order - 1;
+	}
+
+	/// Discard the collected entries
+	fn reset(&mut self) {
+		self.valid = false;
+	}
+
+	/// Returns the long name, if it is complete and belongs to
+	/// the short name with the checksum `checksum`
+	fn take(&mut self, checksum: u8) -> Option<String> {
+		let valid = self.valid && self.next == 0 && self.checksum == checksum;
+		self.reset();
+
+		if !valid {
+			return None;
+		}
+
+		// the name is terminated by a zero and padded with 0xFFFF
+		let len = self
+			.chars
+			.iter()
+			.position(|c| *c == 0 || *c == 0xFFFF)
+			.unwrap_or(self.chars.len());
+		let name = char::decode_utf16(self.chars[..len].iter().cloned())
+			.map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+			.collect::<String>();
+
+		if name.is_empty() {
+			None
+		} else {
+			Some(name)
+		}
+	}
+}
+
+/// Parsed entry of a directory
+#[derive(Clone, Debug)]
+struct FatDirEntry {
+	/// long name or, if the entry doesn't have one, the short name
+	name: String,
+	/// short name in the format 8.3
+	short_name: String,
+	/// attributes of the entry, e.g. `ATTR_DIRECTORY`
+	attr: u8,
+	/// first cluster of the content
+	cluster: u32,
+	/// size of a file in bytes
+	size: u32,
+	/// position of the entry on the device, which identifies the node
+	pos: u64,
+	/// time of the last access and modification
+	times: Timestamps,
+}
+
+impl FatDirEntry {
+	fn kind(&self) -> NodeKind {
+		if self.attr & ATTR_DIRECTORY != 0 {
+			NodeKind::Directory
+		} else {
+			NodeKind::File
+		}
+	}
+
+	fn attributes(&self) -> Attributes {
+		if self.attr & ATTR_READ_ONLY != 0 {
+			Attributes::new(DEFAULT_MODE & !S_IWUGO, 0, 0)
+		} else {
+			Attributes::new(DEFAULT_MODE, 0, 0)
+		}
+	}
+
+	/// Returns true, if `name` references the entry. As
+	/// usual for FAT, the comparison ignores the case.
+	fn matches(&self, name: &str) -> bool {
+		self.name.eq_ignore_ascii_case(name) || self.short_name.eq_ignore_ascii_case(name)
+	}
+}
+
+/// State of a mounted FAT32 volume
+#[derive(Debug)]
+struct FatVolume {
+	/// device, which stores the volume
+	device: Arc<dyn BlockDevice>,
+	/// byte offset of the first allocation table
+	fat_offset: u64,
+	/// byte offset of the first data cluster
+	data_offset: u64,
+	/// size of a cluster in bytes
+	cluster_size: usize,
+	/// number of data clusters
+	cluster_count: u32,
+	/// first cluster of the root directory
+	root_cluster: u32,
+	/// inode numbers of all nodes, which are already looked up.
+	/// The key is the position of the entry on the device.
+	inodes: Spinlock<BTreeMap<u64, u64>>,
+}
+
+impl FatVolume {
+	/// Read the boot sector of `device` and check if it describes a FAT32 volume
+	fn open(device: Arc<dyn BlockDevice>) -> io::Result<Self> {
+		let mut boot = [0u8; SECTOR_SIZE];
+		device.read_sectors(0, &mut boot)?;
+
+		if le16(&boot, 510) != BOOT_SIGNATURE {
+			return Err(io::Error::EINVAL);
+		}
+
+		let bytes_per_sector = u64::from(le16(&boot, 11));
+		let sectors_per_cluster = u64::from(boot[13]);
+		let reserved_sectors = u64::from(le16(&boot, 14));
+		let num_fats = u64::from(boot[16]);
+		let root_entries = le16(&boot, 17);
+		let fat_size = u64::from(le32(&boot, 36));
+		let total_sectors = match le16(&boot, 19) {
+			0 => u64::from(le32(&boot, 32)),
+			sectors => u64::from(sectors),
+		};
+		let root_cluster = le32(&boot, 44);
+
+		if !bytes_per_sector.is_power_of_two()
+			|| !(512..=4096).contains(&bytes_per_sector)
+			|| !sectors_per_cluster.is_power_of_two()
+			|| num_fats == 0
+		{
+			return Err(io::Error::EINVAL);
+		}
+
+		// FAT12 and FAT16 have a root directory of a fixed size
+		// and store the size of the allocation table in 16 bit
+		if root_entries != 0 || le16(&boot, 22) != 0 || fat_size == 0 {
+			return Err(io::Error::EINVAL);
+		}
+
+		let data_sector = reserved_sectors + num_fats * fat_size;
+		if total_sectors <= data_sector
+			|| total_sectors * bytes_per_sector > device.num_sectors() * SECTOR_SIZE as u64
+		{
+			return Err(io::Error::EINVAL);
+		}
+
+		// the allocation table may be too small for all data sectors
+		let cluster_count = ((total_sectors - data_sector) / sectors_per_cluster)
+			.min(fat_size * bytes_per_sector / 4 - u64::from(FIRST_CLUSTER))
+			as u32;
+		if root_cluster < FIRST_CLUSTER || root_cluster >= FIRST_CLUSTER + cluster_count {
+			return Err(io::Error::EINVAL);
+		}
+
+		Ok(FatVolume {
+			device,
+			fat_offset: reserved_sectors * bytes_per_sector,
+			data_offset: data_sector * bytes_per_sector,
+			cluster_size: (sectors_per_cluster * bytes_per_sector) as usize,
+			cluster_count,
+			root_cluster,
+			inodes: Spinlock::new(BTreeMap::new()),
+		})
+	}
+
+	/// Returns true, if `cluster` is a valid data cluster
+	fn is_valid_cluster(&self, cluster: u32) -> bool {
+		cluster >= FIRST_CLUSTER && cluster - FIRST_CLUSTER < self.cluster_count
+	}
+
+	/// Returns the successor of `cluster` or `None` at the end of the chain
+	fn next_cluster(&self, cluster: u32) -> io::Result<Option<u32>> {
+		let mut entry = [0u8; 4];
+		read_bytes(
+			&*self.device,
+			self.fat_offset + 4 * u64::from(cluster),
+			&mut entry,
+		)?;
+
+		let next = u32::from_le_bytes(entry) & FAT_ENTRY_MASK;
+		if next >= END_OF_CHAIN {
+			Ok(None)
+		} else if self.is_valid_cluster(next) {
+			Ok(Some(next))
+		} else {
+			// free or bad clusters aren't part of a chain
+			Err(io::Error::EIO)
+		}
+	}
+
+	/// Returns all clusters of the chain, which starts at `first`
+	fn cluster_chain(&self, first: u32) -> io::Result<Vec<u32>> {
+		let mut chain = Vec::new();
+
+		// empty files don't have any cluster
+		if first == 0 {
+			return Ok(chain);
+		}
+		if !self.is_valid_cluster(first) {
+			return Err(io::Error::EIO);
+		}
+
+		let mut cluster = Some(first);
+		while let Some(current) = cluster {
+			// a damaged table may contain a cycle
+			if chain.len() >= self.cluster_count as usize {
+				return Err(io::Error::EIO);
+			}
+
+			chain.push(current);
+			cluster = self.next_cluster(current)?;
+		}
+
+		Ok(chain)
+	}
+
+	/// Returns the byte offset of `cluster` on the device
+	fn cluster_offset(&self, cluster: u32) -> u64 {
+		self.data_offset + u64::from(cluster - FIRST_CLUSTER) * self.cluster_size as u64
+	}
+
+	/// Read `buf.len()` bytes starting at `offset` of the
+	/// content, which is stored in the clusters `chain`
+	fn read_chain(&self, chain: &[u32], offset: usize, buf: &mut [u8]) -> io::Result<()> {
+		let mut done = 0;
+
+		while done < buf.len() {
+			let pos = offset + done;
+			let cluster = *chain.get(pos / self.cluster_size).ok_or(io::Error::EIO)?;
+			let within = pos % self.cluster_size;
+			let len = (self.cluster_size - within).min(buf.len() - done);
+
+			read_bytes(
+				&*self.device,
+				self.cluster_offset(cluster) + within as u64,
+				&mut buf[done..done + len],
+			)?;
+			done += len;
+		}
+
+		Ok(())
+	}
+
+	/// Returns the inode number of the node, whose entry is stored at `pos`
+	fn ino(&self, pos: u64) -> u64 {
+		*self.inodes.lock().entry(pos).or_insert_with(next_ino)
+	}
+
+	/// Read all entries of the directory, which starts at `cluster`,
+	/// without `.`, `..` and the label of the volume
+	fn read_directory(&self, cluster: u32) -> io::Result<Vec<FatDirEntry>> {
+		let chain = self.cluster_chain(cluster)?;
+		let mut data = vec![0u8; chain.len() * self.cluster_size];
+		self.read_chain(&chain, 0, &mut data)?;
+
+		let mut entries = Vec::new();
+		let mut long_name = LongName::default();
+
+		for (index, raw) in data.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+			match raw[0] {
+				END_OF_DIRECTORY => break,
+				DELETED_ENTRY => {
+					long_name.reset();
+					continue;
+				}
+				_ => {}
+			}
+
+			let attr = raw[11];
+			if attr & ATTR_LONG_NAME_MASK == ATTR_LONG_NAME {
+				long_name.push(raw);
+				continue;
+			}
+
+			let name = long_name.take(short_name_checksum(&raw[0..11]));
+			if attr & ATTR_VOLUME_ID != 0 {
+				continue;
+			}
+
+			let short_name = short_name(raw);
+			if short_name == "." || short_name == ".." {
+				continue;
+			}
+
+			let offset = index * DIR_ENTRY_SIZE;
+			let mtime = to_timespec(le16(raw, 24), le16(raw, 22));
+			entries.push(FatDirEntry {
+				name: name.unwrap_or_else(|| short_name.clone()),
+				short_name,
+				attr,
+				cluster: (u32::from(le16(raw, 20)) << 16) | u32::from(le16(raw, 26)),
+				size: le32(raw, 28),
+				pos: self.cluster_offset(chain[offset / self.cluster_size])
+					+ (offset % self.cluster_size) as u64,
+				times: Timestamps {
+					atime: to_timespec(le16(raw, 18), 0),
+					mtime,
+					ctime: mtime,
+				},
+			});
+		}
+
+		Ok(entries)
+	}
+
+	/// Create the node, which is described by `entry`
+	fn node(self: &Arc<Self>, entry: &FatDirEntry) -> Inode {
+		let ino = self.ino(entry.pos);
+
+		match entry.kind() {
+			NodeKind::Directory => Arc::new(FatDirectory {
+				volume: self.clone(),
+				ino,
+				cluster: entry.cluster,
+				attr: entry.attributes(),
+				times: entry.times,
+			}),
+			_ => Arc::new(FatFile {
+				volume: self.clone(),
+				ino,
+				cluster: entry.cluster,
+				size: entry.size as usize,
+				attr: entry.attributes(),
+				times: entry.times,
+			}),
+		}
+	}
+}
+
+#[derive(Debug)]
+struct FatDirectory {
+	/// volume, which stores the directory
+	volume: Arc<FatVolume>,
+	/// inode number of the directory
+	ino: u64,
+	/// first cluster of the entries
+	cluster: u32,
+	/// permission bits of the directory
+	attr: Attributes,
+	/// time of the last access and modification
+	times: Timestamps,
+}
+
+impl VfsNode for FatDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		// the entry in the parent directory, the entry `.` and
+		// the entries `..` of all subdirectories
+		2 + self
+			.volume
+			.read_directory(self.cluster)
+			.map_or(0, |entries| {
+				entries
+					.iter()
+					.filter(|entry| entry.kind() == NodeKind::Directory)
+					.count()
+			})
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		self.attr
+	}
+
+	fn set_attributes(&self, _attr: Attributes) -> io::Result<()> {
+		Err(io::Error::EROFS)
+	}
+
+	fn get_times(&self) -> Timestamps {
+		self.times
+	}
+
+	fn set_times(&self, _atime: TimeUpdate, _mtime: TimeUpdate) -> io::Result<()> {
+		Err(io::Error::EROFS)
+	}
+
+	fn lookup(&self, name: &str) -> io::Result<Inode> {
+		self.volume
+			.read_directory(self.cluster)?
+			.iter()
+			.find(|entry| entry.matches(name))
+			.map(|entry| self.volume.node(entry))
+			.ok_or(io::Error::ENOENT)
+	}
+
+	fn create(&self, _name: &str, _kind: NodeKind, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EROFS)
+	}
+
+	fn symlink(&self, _name: &str, _target: &str, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EROFS)
+	}
+
+	fn link(&self, _name: &str, _node: Inode) -> io::Result<()> {
+		Err(io::Error::EROFS)
+	}
+
+	fn remove(&self, _name: &str) -> io::Result<Inode> {
+		Err(io::Error::EROFS)
+	}
+
+	fn replace(&self, _name: &str, _node: Inode) -> io::Result<Inode> {
+		Err(io::Error::EROFS)
+	}
+
+	fn readdir(&self) -> io::Result<Vec<DirEntry>> {
+		Ok(self
+			.volume
+			.read_directory(self.cluster)?
+			.iter()
+			.map(|entry| DirEntry {
+				name: entry.name.clone(),
+				kind: entry.kind(),
+				ino: self.volume.ino(entry.pos),
+			})
+			.collect())
+	}
+}
+
+#[derive(Debug)]
+struct FatFile {
+	/// volume, which stores the file
+	volume: Arc<FatVolume>,
+	/// inode number of the file
+	ino: u64,
+	/// first cluster of the content
+	cluster: u32,
+	/// size of the file in bytes
+	size: usize,
+	/// permission bits of the file
+	attr: Attributes,
+	/// time of the last access and modification
+	times: Timestamps,
+}
+
+impl VfsNode for FatFile {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::File
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		1
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		self.attr
+	}
+
+	fn set_attributes(&self, _attr: Attributes) -> io::Result<()> {
+		Err(io::Error::EROFS)
+	}
+
+	fn get_times(&self) -> Timestamps {
+		self.times
+	}
+
+	fn set_times(&self, _atime: TimeUpdate, _mtime: TimeUpdate) -> io::Result<()> {
+		Err(io::Error::EROFS)
+	}
+
+	fn stat(&self) -> io::Result<FileStatus> {
+		Ok(FileStatus {
+			ino: self.ino,
+			kind: NodeKind::File,
+			nlink: 1,
+			file_size: self.size,
+			attr: self.attr,
+			times: self.times,
+		})
+	}
+
+	fn open(&self, opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		if opt.is_writeable() || opt.contains(OpenOption::O_TRUNC) {
+			return Err(io::Error::EROFS);
+		}
+
+		// the chain is determined once, because the volume isn't modified
+		let chain = self.volume.cluster_chain(self.cluster)?;
+		if chain.len() * self.volume.cluster_size < self.size {
+			return Err(io::Error::EIO);
+		}
+
+		Ok(Arc::new(FatFileHandle {
+			volume: self.volume.clone(),
+			ino: self.ino,
+			chain,
+			size: self.size,
+			attr: self.attr,
+			times: self.times,
+			pos: Spinlock::new(0),
+		}))
+	}
+}
+
+/// IO interface to an open file
+#[derive(Debug)]
+struct FatFileHandle {
+	/// volume, which stores the file
+	volume: Arc<FatVolume>,
+	/// inode number of the file
+	ino: u64,
+	/// clusters, which store the content
+	chain: Vec<u32>,
+	/// size of the file in bytes
+	size: usize,
+	/// permission bits of the file
+	attr: Attributes,
+	/// time of the last access and modification
+	times: Timestamps,
+	/// position within the file
+	pos: Spinlock<usize>,
+}
+
+impl IoInterface for FatFileHandle {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		let mut pos_guard = self.pos.lock();
+		let pos = *pos_guard;
+
+		if pos >= self.size {
+			return Ok(0);
+		}
+
+		let len = (self.size - pos).min(buf.len());
+		self.volume.read_chain(&self.chain, pos, &mut buf[..len])?;
+		*pos_guard = pos + len;
+
+		Ok(len)
+	}
+
+	fn write(&self, _buf: &[u8]) -> io::Result<usize> {
+		Err(io::Error::EBADF)
+	}
+
+	fn seek(&self, style: SeekFrom) -> io::Result<usize> {
+		let mut pos_guard = self.pos.lock();
+		let pos = match style {
+			SeekFrom::Start(n) => n as isize,
+			SeekFrom::End(n) => self.size as isize + n,
+			SeekFrom::Current(n) => *pos_guard as isize + n,
+		};
+
+		if pos >= 0 {
+			*pos_guard = pos as usize;
+			Ok(pos as usize)
+		} else {
+			Err(io::Error::EINVAL)
+		}
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		Ok(FileStatus {
+			ino: self.ino,
+			kind: NodeKind::File,
+			nlink: 1,
+			file_size: self.size,
+			attr: self.attr,
+			times: self.times,
+		})
+	}
+
+	fn access_mode(&self) -> OpenOption {
+		OpenOption::O_RDONLY
+	}
+}
+
+/// Instance of a mounted FAT32 volume
+#[derive(Debug)]
+pub(crate) struct FatFs {
+	/// root directory of the volume
+	root: Inode,
+}
+
+impl FatFs {
+	/// Mount the FAT32 volume, which is stored on the block device `source`
+	pub fn create(source: Option<&str>) -> io::Result<Arc<dyn FileSystem>> {
+		let device = get_block_device(source.ok_or(io::Error::EINVAL)?)?;
+		let volume = Arc::new(FatVolume::open(device)?);
+
+		info!(
+			"Mount FAT32 volume with {} clusters of {} bytes",
+			volume.cluster_count, volume.cluster_size
+		);
+
+		// the root directory doesn't have an entry, which stores its timestamps
+		let root = Arc::new(FatDirectory {
+			ino: next_ino(),
+			cluster: volume.root_cluster,
+			attr: Attributes::new(DEFAULT_MODE, 0, 0),
+			times: Timestamps::now(),
+			volume,
+		});
+
+		Ok(Arc::new(FatFs { root }))
+	}
+}
+
+impl FileSystem for FatFs {
+	fn name(&self) -> &'static str {
+		"vfat"
+	}
+
+	fn root(&self) -> Inode {
+		self.root.clone()
+	}
+}
@@ -0,0 +1,141 @@
+//! Unpacks a cpio archive in the "new ASCII" format (`newc`) into the
+//! virtual file system
+//!
+//! The archive is created by the build script from the directory
+//! `initramfs` and embedded into the kernel.
+
+use crate::fd::OpenOption;
+use crate::fs::vfs::Fs;
+use crate::fs::Vfs;
+use crate::io;
+use crate::logging::*;
+use alloc::string::String;
+
+/// Magic number of the `newc` format
+const MAGIC: &[u8] = b"070701";
+/// Size of the header of an entry
+const HEADER_SIZE: usize = 110;
+/// Name of the last entry of an archive
+const TRAILER: &str = "TRAILER!!!";
+
+/// Mask to determine the file type
+const S_IFMT: usize = 0o170000;
+/// File type of a directory
+const S_IFDIR: usize = 0o040000;
+/// File type of a regular file
+const S_IFREG: usize = 0o100000;
+/// File type of a symbolic link
+const S_IFLNK: usize = 0o120000;
+/// Write permissions of the owner, the group and others
+const S_IWUGO: usize = 0o222;
+/// Mask of all permission bits
+const S_IALLUGO: usize = 0o7777;
+
+/// Parse the header field with the index `index`, which
+/// is encoded as hexadecimal number with 8 digits
+fn parse_field(header: &[u8], index: usize) -> io::Result<usize> {
+	let start = MAGIC.len() + 8 * index;
+	let field = core::str::from_utf8(&header[start..start + 8]).map_err(|_| io::Error::EINVAL)?;
+
+	usize::from_str_radix(field, 16).map_err(|_| io::Error::EINVAL)
+}
+
+/// Create a writeable file `path` with the content `data`
+fn write_file(root: &mut Fs, path: &str, data: &[u8]) -> io::Result<()> {
+	let file = root.open(
+		path,
+		OpenOption::O_WRONLY | OpenOption::O_CREAT | OpenOption::O_TRUNC,
+		0o600,
+	)?;
+	let mut written = 0;
+
+	while written < data.len() {
+		written += file.write(&data[written..])?;
+	}
+
+	Ok(())
+}
+
+/// Unpack the archive `archive` into the file system `root`
+///
+/// Directories, which already exist, are merged with the archive.
+/// Files without write permissions reference the archive directly
+/// and are read-only, while all other files are copied to the heap.
+/// All entries receive the permission bits and the owner, which are
+/// stored in the archive.
+pub(crate) fn unpack(root: &mut Fs, archive: &'static [u8]) -> io::Result<()> {
+	let mut offset = 0;
+	let mut files = 0;
+
+	loop {
+		let header = archive
+			.get(offset..offset + HEADER_SIZE)
+			.ok_or(io::Error::EINVAL)?;
+		if &header[..MAGIC.len()] != MAGIC {
+			return Err(io::Error::EINVAL);
+		}
+
+		let mode = parse_field(header, 1)?;
+		let uid = parse_field(header, 2)? as u32;
+		let gid = parse_field(header, 3)? as u32;
+		let file_size = parse_field(header, 6)?;
+		let name_size = parse_field(header, 11)?;
+
+		// the name is terminated by a zero byte and the
+		// header and the name are aligned to 4 bytes
+		let name_start = offset + HEADER_SIZE;
+		let name = archive
+			.get(name_start..name_start + name_size.saturating_sub(1))
+			.ok_or(io::Error::EINVAL)?;
+		let name = core::str::from_utf8(name).map_err(|_| io::Error::EINVAL)?;
+		let data_start = align_up!(name_start + name_size, 4);
+		let data = archive
+			.get(data_start..data_start + file_size)
+			.ok_or(io::Error::EINVAL)?;
+		offset = align_up!(data_start + file_size, 4);
+
+		if name == TRAILER {
+			break;
+		}
+
+		let name = name.trim_start_matches("./").trim_start_matches('/');
+		if name.is_empty() || name == "." {
+			continue;
+		}
+
+		let mut path = String::from("/");
+		path.push_str(name);
+
+		match mode & S_IFMT {
+			S_IFDIR => match root.mkdir(&path, 0o700) {
+				Ok(()) | Err(io::Error::EEXIST) => {}
+				Err(e) => return Err(e),
+			},
+			S_IFREG => {
+				if mode & S_IWUGO == 0 {
+					root.mount_rom(&path, data)?;
+				} else {
+					write_file(root, &path, data)?;
+				}
+				files += 1;
+			}
+			S_IFLNK => {
+				let target = core::str::from_utf8(data).map_err(|_| io::Error::EINVAL)?;
+				root.symlink(target, &path)?;
+				root.chown(&path, uid, gid, false)?;
+				continue;
+			}
+			_ => {
+				warn!("Ignore {} with the unsupported mode {:o}", path, mode);
+				continue;
+			}
+		}
+
+		root.chmod(&path, (mode & S_IALLUGO) as u32)?;
+		root.chown(&path, uid, gid, false)?;
+	}
+
+	info!("Unpacked {} files from the initramfs", files);
+
+	Ok(())
+}
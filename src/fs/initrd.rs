@@ -25,11 +25,17 @@ impl RomHandle {
 		}
 	}
 
-	pub fn get_handle(&self, _opt: OpenOption) -> RomHandle {
-		RomHandle {
+	/// Create a new handle to the file content. The content of
+	/// a ROM file is immutable and only read-only opens are allowed.
+	pub fn get_handle(&self, opt: OpenOption) -> io::Result<RomHandle> {
+		if opt.is_writeable() {
+			return Err(io::Error::EROFS);
+		}
+
+		Ok(RomHandle {
 			pos: Spinlock::new(0),
 			data: self.data.clone(),
-		}
+		})
 	}
 
 	pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
@@ -101,8 +107,12 @@ impl Clone for RomHandle {
 
 #[derive(Debug)]
 pub(crate) struct RamHandle {
+	/// Is the file readable?
+	readable: bool,
 	/// Is the file writeable?
 	writeable: bool,
+	/// Starts each write at the end of the file?
+	append: bool,
 	/// Position within the file
 	pos: Spinlock<usize>,
 	/// File content
@@ -112,13 +122,19 @@ pub(crate) struct RamHandle {
 impl RamHandle {
 	pub fn new(writeable: bool) -> Self {
 		RamHandle {
+			readable: true,
 			writeable: writeable,
+			append: false,
 			pos: Spinlock::new(0),
 			data: Arc::new(RwSpinlock::new(Vec::new())),
 		}
 	}
 
 	pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		if !self.readable {
+			return Err(io::Error::EBADF);
+		}
+
 		let guard = self.data.read();
 		let vec = guard.deref();
 		let mut pos_guard = self.pos.lock();
@@ -149,7 +165,9 @@ impl RamHandle {
 		let mut guard = self.data.write();
 		let vec = guard.deref_mut();
 		let mut pos_guard = self.pos.lock();
-		let pos = *pos_guard;
+		// in append mode, the end of the file is determined
+		// while holding the lock of the content
+		let pos = if self.append { vec.len() } else { *pos_guard };
 
 		if pos + buf.len() > vec.len() {
 			vec.resize(pos + buf.len(), 0);
@@ -200,7 +218,7 @@ impl RamHandle {
 		let mut guard = self.data.write();
 		let vec = guard.deref_mut();
 		let mut pos_guard = self.pos.lock();
-		let pos = *pos_guard;
+		let pos = if self.append { vec.len() } else { *pos_guard };
 
 		if pos + s.len() > vec.len() {
 			vec.resize(pos + s.len(), 0);
@@ -212,12 +230,23 @@ impl RamHandle {
 		Ok(())
 	}
 
-	pub fn get_handle(&self, opt: OpenOption) -> RamHandle {
-		RamHandle {
-			writeable: opt.contains(OpenOption::O_RDWR),
+	/// Create a new handle to the file content with the access
+	/// mode of `opt`. With `O_TRUNC`, a writeable open discards
+	/// the current content.
+	pub fn get_handle(&self, opt: OpenOption) -> io::Result<RamHandle> {
+		let writeable = opt.is_writeable();
+
+		if writeable && opt.contains(OpenOption::O_TRUNC) {
+			self.data.write().clear();
+		}
+
+		Ok(RamHandle {
+			readable: opt.is_readable(),
+			writeable,
+			append: opt.contains(OpenOption::O_APPEND),
 			pos: Spinlock::new(0),
 			data: self.data.clone(),
-		}
+		})
 	}
 
 	pub fn len(&self) -> usize {
@@ -225,14 +254,155 @@ impl RamHandle {
 		let ref vec: &Vec<u8> = guard.deref();
 		vec.len() as usize
 	}
+
+	/// Returns the access mode, with which the handle has been opened
+	pub fn access_mode(&self) -> OpenOption {
+		OpenOption::access_mode(self.readable, self.writeable)
+	}
 }
 
 impl Clone for RamHandle {
 	fn clone(&self) -> Self {
 		RamHandle {
+			readable: self.readable,
 			writeable: self.writeable,
+			append: self.append,
 			pos: Spinlock::new(*self.pos.lock()),
 			data: self.data.clone(),
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::scheduler::remove_io_interface;
+
+	/// Returns a writeable file with the content `data`
+	fn file_with(data: &[u8]) -> RamHandle {
+		let file = RamHandle::new(true);
+		file.write(data).unwrap();
+
+		file
+	}
+
+	/// Returns the whole content of `file`
+	fn content(file: &RamHandle) -> Vec<u8> {
+		let handle = file.get_handle(OpenOption::O_RDONLY).unwrap();
+		let mut buf = [0u8; 64];
+		let len = handle.read(&mut buf).unwrap();
+
+		buf[..len].to_vec()
+	}
+
+	#[test_case]
+	fn open_read_only() {
+		let file = file_with(b"hello");
+		let handle = file.get_handle(OpenOption::O_RDONLY).unwrap();
+		let mut buf = [0u8; 8];
+
+		assert_eq!(handle.read(&mut buf), Ok(5));
+		assert_eq!(&buf[..5], b"hello");
+		assert_eq!(handle.write(b"x"), Err(io::Error::EBADF));
+		assert_eq!(content(&file), b"hello");
+	}
+
+	#[test_case]
+	fn open_write_only() {
+		let file = file_with(b"hello");
+		let handle = file.get_handle(OpenOption::O_WRONLY).unwrap();
+		let mut buf = [0u8; 8];
+
+		assert_eq!(handle.read(&mut buf), Err(io::Error::EBADF));
+		assert_eq!(handle.write(b"HE"), Ok(2));
+		assert_eq!(content(&file), b"HEllo");
+	}
+
+	#[test_case]
+	fn open_read_write() {
+		let file = file_with(b"hello");
+		let handle = file.get_handle(OpenOption::O_RDWR).unwrap();
+		let mut buf = [0u8; 2];
+
+		assert_eq!(handle.read(&mut buf), Ok(2));
+		assert_eq!(&buf, b"he");
+		// the write starts behind the read data
+		assert_eq!(handle.write(b"LL"), Ok(2));
+		assert_eq!(content(&file), b"heLLo");
+	}
+
+	#[test_case]
+	fn open_append() {
+		let file = file_with(b"hello");
+		let handle = file
+			.get_handle(OpenOption::O_WRONLY | OpenOption::O_APPEND)
+			.unwrap();
+
+		// each write starts at the end of the file, independent of the position
+		assert_eq!(handle.seek(SeekFrom::Start(0)), Ok(0));
+		assert_eq!(handle.write(b" world"), Ok(6));
+		file.get_handle(OpenOption::O_WRONLY)
+			.unwrap()
+			.write(b"H")
+			.unwrap();
+		assert_eq!(handle.write(b"!"), Ok(1));
+		assert_eq!(content(&file), b"Hello world!");
+	}
+
+	#[test_case]
+	fn open_truncate() {
+		let file = file_with(b"hello");
+
+		// a read-only open keeps the content
+		file.get_handle(OpenOption::O_RDONLY | OpenOption::O_TRUNC)
+			.unwrap();
+		assert_eq!(content(&file), b"hello");
+
+		let handle = file
+			.get_handle(OpenOption::O_WRONLY | OpenOption::O_TRUNC)
+			.unwrap();
+		assert_eq!(file.len(), 0);
+		assert_eq!(handle.write(b"bye"), Ok(3));
+		assert_eq!(content(&file), b"bye");
+	}
+
+	#[test_case]
+	fn open_exclusive() {
+		let path = "/tmp/initrd-open-exclusive";
+		let flags = OpenOption::O_WRONLY | OpenOption::O_CREAT | OpenOption::O_EXCL;
+
+		let fd = crate::fs::open(path, flags, 0o644).unwrap();
+		remove_io_interface(fd).unwrap();
+
+		assert_eq!(crate::fs::open(path, flags, 0o644), Err(io::Error::EEXIST));
+		// without `O_EXCL`, the existing file is opened
+		let fd = crate::fs::open(path, OpenOption::O_WRONLY | OpenOption::O_CREAT, 0o644).unwrap();
+		remove_io_interface(fd).unwrap();
+
+		crate::fs::unlink(path).unwrap();
+	}
+
+	#[test_case]
+	fn open_rom_writeable() {
+		let file = RomHandle::new(b"rom");
+
+		assert_eq!(
+			file.get_handle(OpenOption::O_WRONLY).unwrap_err(),
+			io::Error::EROFS
+		);
+		assert_eq!(
+			file.get_handle(OpenOption::O_RDWR).unwrap_err(),
+			io::Error::EROFS
+		);
+		assert_eq!(
+			file.get_handle(OpenOption::O_WRONLY | OpenOption::O_TRUNC)
+				.unwrap_err(),
+			io::Error::EROFS
+		);
+
+		let handle = file.get_handle(OpenOption::O_RDONLY).unwrap();
+		let mut buf = [0u8; 8];
+		assert_eq!(handle.read(&mut buf), Ok(3));
+		assert_eq!(&buf[..3], b"rom");
+	}
+}
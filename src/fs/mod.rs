@@ -2,111 +2,817 @@
 
 #![allow(dead_code)]
 
+mod devfs;
+mod ext2;
+mod fat;
+mod initramfs;
 mod initrd;
+mod procfs;
+mod ramfs;
 mod vfs;
 
-use crate::errno::*;
+pub(crate) use crate::fs::devfs::register_block_node;
+pub use crate::fs::devfs::{register_char_device, unregister_char_device};
+
+use crate::drivers::block::{get_block_device, RAM_DISK};
 use crate::fd::{self, FileDescriptor, OpenOption};
-use crate::fd::{IoInterface, SeekFrom};
+use crate::fd::{FileStatus, IoInterface, SeekFrom};
+use crate::fs::devfs::DevFs;
+use crate::fs::ext2::Ext2Fs;
+use crate::fs::fat::FatFs;
+use crate::fs::procfs::ProcFs;
+use crate::fs::ramfs::RamFs;
 use crate::fs::vfs::Fs;
 use crate::io;
 use crate::logging::*;
-use crate::scheduler::{insert_io_interface, remove_io_interface};
+use crate::scheduler::task::{Credentials, TaskId};
+use crate::scheduler::{
+	get_current_credentials, get_current_working_dir, get_io_interface, insert_io_interface,
+	remove_io_interface, set_current_working_dir,
+};
+use crate::time::{realtime, Timespec};
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::include_bytes;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// cpio archive, which is created by the build script
+static INITRAMFS: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/initramfs.cpio"));
+
+/// Maximum length of a file name
+pub(crate) const NAME_MAX: usize = 255;
+
+/// Maximum number of symbolic links, which are followed by a path lookup
+pub(crate) const SYMLOOP_MAX: usize = 40;
 
-static DEMO: &[u8] = include_bytes!("../../demo/hello");
+/// Counter to generate unique inode numbers
+static INODE_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Returns a new inode number, which is unique for all in-memory file systems
+pub(crate) fn next_ino() -> u64 {
+	INODE_COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Release the nodes of the procfs, which describe the exited task `id`
+pub(crate) fn release_task(id: TaskId) {
+	procfs::release_task(id);
+}
 
 /// Type of the VfsNode
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub(crate) enum NodeKind {
+pub enum NodeKind {
 	/// Node represent a file
 	File,
 	/// Node represent a directory
 	Directory,
+	/// Node represent a character device
+	CharDevice,
+	/// Node represent a block device
+	BlockDevice,
+	/// Node represent a symbolic link
+	Symlink,
+	/// Node represent a socket
+	Socket,
+}
+
+/// An entry of a directory
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+	/// Name of the entry
+	pub name: String,
+	/// Type of the entry
+	pub kind: NodeKind,
+	/// Inode number of the entry
+	pub ino: u64,
+}
+
+/// Set-user-ID bit, which changes the effective user ID on execution
+pub const S_ISUID: u32 = 0o4000;
+/// Set-group-ID bit, which changes the effective group ID on execution
+pub const S_ISGID: u32 = 0o2000;
+/// Sticky bit, which restricts the removal of entries of a directory to their owners
+pub const S_ISVTX: u32 = 0o1000;
+/// Mask of all permission bits
+pub const S_IALLUGO: u32 = 0o7777;
+/// Execute (or search) permission of the owner, the group and others
+pub const S_IXUGO: u32 = 0o111;
+
+/// Permission to read a file or the entries of a directory
+pub const MAY_READ: u32 = 4;
+/// Permission to write a file or to modify the entries of a directory
+pub const MAY_WRITE: u32 = 2;
+/// Permission to execute a file or to search a directory
+pub const MAY_EXEC: u32 = 1;
+
+/// Value of `uid` and `gid`, which keeps the current owner, see `chown`
+pub const KEEP_ID: u32 = u32::MAX;
+
+/// Permission bits and owner of a node
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Attributes {
+	/// Permission bits including the set-user-ID, set-group-ID and sticky bit
+	pub mode: u32,
+	/// User ID of the owner
+	pub uid: u32,
+	/// Group ID of the owner
+	pub gid: u32,
+}
+
+impl Attributes {
+	pub const fn new(mode: u32, uid: u32, gid: u32) -> Self {
+		Attributes {
+			mode: mode & S_IALLUGO,
+			uid,
+			gid,
+		}
+	}
+
+	/// Returns the default attributes of a node of type `kind`,
+	/// which belongs to the superuser
+	pub const fn default_for(kind: NodeKind) -> Self {
+		let mode = match kind {
+			NodeKind::File => 0o644,
+			NodeKind::Directory => 0o755,
+			NodeKind::CharDevice => 0o666,
+			NodeKind::BlockDevice => 0o660,
+			NodeKind::Symlink | NodeKind::Socket => 0o777,
+		};
+
+		Attributes::new(mode, 0, 0)
+	}
+
+	/// Returns the attributes of a new node, which is created by a task
+	/// with the credentials `cred`. The bits of `umask` are cleared.
+	pub(crate) fn for_new_node(mode: u32, umask: u32, cred: &Credentials) -> Self {
+		Attributes::new(mode & !umask, cred.euid, cred.egid)
+	}
+}
+
+/// Check if a task with the credentials `cred` is allowed to access
+/// a node of type `kind` with the attributes `attr`. `mask` is a
+/// combination of `MAY_READ`, `MAY_WRITE` and `MAY_EXEC`.
+///
+/// The permission bits of the owner are used, if the task owns the node.
+/// Otherwise, the bits of the group are used, if the task is member of
+/// the group. In all other cases, the bits of others are used. The
+/// superuser passes all checks, except for the execution of a file
+/// without any execute bit.
+pub(crate) fn check_permission(
+	kind: NodeKind,
+	attr: &Attributes,
+	cred: &Credentials,
+	mask: u32,
+) -> io::Result<()> {
+	if cred.is_superuser() {
+		if mask & MAY_EXEC == 0 || kind == NodeKind::Directory || attr.mode & S_IXUGO != 0 {
+			return Ok(());
+		}
+
+		return Err(io::Error::EACCES);
+	}
+
+	let bits = if cred.euid == attr.uid {
+		attr.mode >> 6
+	} else if cred.in_group(attr.gid) {
+		attr.mode >> 3
+	} else {
+		attr.mode
+	};
+
+	if bits & mask == mask {
+		Ok(())
+	} else {
+		Err(io::Error::EACCES)
+	}
+}
+
+/// Time of the last access, of the last modification of the
+/// content and of the last status change of a node
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timestamps {
+	/// Time of the last access
+	pub atime: Timespec,
+	/// Time of the last modification of the content
+	pub mtime: Timespec,
+	/// Time of the last change of the content or the attributes
+	pub ctime: Timespec,
+}
+
+impl Timestamps {
+	/// Returns timestamps, which are set to the current time
+	pub fn now() -> Self {
+		let now = realtime();
+
+		Timestamps {
+			atime: now,
+			mtime: now,
+			ctime: now,
+		}
+	}
+
+	/// Update the time of the last access
+	pub fn touch_access(&mut self) {
+		self.atime = realtime();
+	}
+
+	/// Update the modification and the change time,
+	/// because the content of the node is modified
+	pub fn touch_modify(&mut self) {
+		let now = realtime();
+
+		self.mtime = now;
+		self.ctime = now;
+	}
+
+	/// Update the change time, because the attributes or
+	/// the number of hard links of the node are changed
+	pub fn touch_change(&mut self) {
+		self.ctime = realtime();
+	}
+
+	/// Set the access and the modification time as specified by
+	/// `atime` and `mtime`. The change time is always updated.
+	pub fn update(&mut self, atime: TimeUpdate, mtime: TimeUpdate) {
+		let now = realtime();
+
+		match atime {
+			TimeUpdate::Now => self.atime = now,
+			TimeUpdate::Set(time) => self.atime = time,
+			TimeUpdate::Omit => {}
+		}
+		match mtime {
+			TimeUpdate::Now => self.mtime = now,
+			TimeUpdate::Set(time) => self.mtime = time,
+			TimeUpdate::Omit => {}
+		}
+		self.ctime = now;
+	}
+}
+
+/// New value of a timestamp, see `utimens`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeUpdate {
+	/// Set the timestamp to the current time
+	Now,
+	/// Keep the current timestamp
+	Omit,
+	/// Set the timestamp to the given time
+	Set(Timespec),
+}
+
+/// Check if a task with the credentials `cred` is allowed to change
+/// the timestamps of a node of type `kind` with the attributes `attr`
+///
+/// The owner and the superuser are allowed to set arbitrary timestamps.
+/// Setting both timestamps to the current time requires only the
+/// permission to write the node.
+pub(crate) fn check_set_times(
+	kind: NodeKind,
+	attr: &Attributes,
+	cred: &Credentials,
+	atime: TimeUpdate,
+	mtime: TimeUpdate,
+) -> io::Result<()> {
+	if cred.is_superuser() || cred.euid == attr.uid {
+		return Ok(());
+	}
+
+	let explicit = |update| matches!(update, TimeUpdate::Set(_));
+	if explicit(atime) || explicit(mtime) {
+		return Err(io::Error::EPERM);
+	}
+
+	check_permission(kind, attr, cred, MAY_WRITE)
 }
 
-/// VfsNode represents an internal node of the virtual file system.
-trait VfsNode: core::fmt::Debug + core::marker::Send + core::marker::Sync {
+/// Shared reference to an inode of the virtual file system
+pub(crate) type Inode = Arc<dyn VfsNode>;
+
+/// VfsNode represents an inode of the virtual file system. Each file,
+/// directory or any other node type implements this trait. Operations,
+/// which aren't supported by a node type, return an error.
+pub(crate) trait VfsNode:
+	core::fmt::Debug + core::marker::Send + core::marker::Sync
+{
 	/// Determines the current node type
 	fn get_kind(&self) -> NodeKind;
-}
 
-/// VfsNodeFile represents a file node of the virtual file system.
-trait VfsNodeFile: VfsNode + core::fmt::Debug + core::marker::Send + core::marker::Sync {
+	/// Determines the inode number of the node
+	fn get_ino(&self) -> u64;
+
+	/// Determines the number of hard links to the node
+	fn get_nlink(&self) -> usize;
+
+	/// Increment the number of hard links, because the node is
+	/// inserted into a directory
+	fn inc_nlink(&self) {}
+
+	/// Decrement the number of hard links, because the node is
+	/// removed from a directory
+	fn drop_nlink(&self) {}
+
+	/// Determines the permission bits and the owner of the node
+	fn get_attributes(&self) -> Attributes {
+		Attributes::default_for(self.get_kind())
+	}
+
+	/// Change the permission bits and the owner of the node
+	fn set_attributes(&self, _attr: Attributes) -> io::Result<()> {
+		Err(io::Error::EPERM)
+	}
+
+	/// Determines the timestamps of the node. Nodes without
+	/// stored timestamps report the current time.
+	fn get_times(&self) -> Timestamps {
+		Timestamps::now()
+	}
+
+	/// Change the access and the modification time of the node
+	fn set_times(&self, _atime: TimeUpdate, _mtime: TimeUpdate) -> io::Result<()> {
+		Err(io::Error::EPERM)
+	}
+
+	/// Returns the status of the node
+	fn stat(&self) -> io::Result<FileStatus> {
+		Ok(FileStatus {
+			ino: self.get_ino(),
+			kind: self.get_kind(),
+			nlink: self.get_nlink(),
+			file_size: 0,
+			attr: self.get_attributes(),
+			times: self.get_times(),
+		})
+	}
+
 	/// Create an IO interface to the current file
-	fn get_handle(&self, _opt: OpenOption) -> Result<Arc<dyn IoInterface>>;
-}
+	fn open(&self, _opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		Err(io::Error::EISDIR)
+	}
+
+	/// Returns the target of the current symbolic link
+	fn readlink(&self) -> io::Result<String> {
+		Err(io::Error::EINVAL)
+	}
 
-/// VfsNodeDirectory represents a directory node of the virtual file system.
-trait VfsNodeDirectory: VfsNode + core::fmt::Debug + core::marker::Send + core::marker::Sync {
-	/// Helper function to create a new dirctory node
-	fn traverse_mkdir(&mut self, _components: &mut Vec<&str>) -> Result<()>;
+	/// Returns the entry `name` of the current directory
+	fn lookup(&self, _name: &str) -> io::Result<Inode> {
+		Err(io::Error::ENOTDIR)
+	}
 
-	/// Helper function to print the current state of the file system
-	fn traverse_lsdir(&self, _tabs: String) -> Result<()>;
+	/// Create the new node `name` of type `kind` with the
+	/// attributes `attr` in the current directory
+	fn create(&self, _name: &str, _kind: NodeKind, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::ENOTDIR)
+	}
 
-	/// Helper function to open a file
-	fn traverse_open(
-		&mut self,
-		_components: &mut Vec<&str>,
-		_flags: OpenOption,
-	) -> Result<Arc<dyn IoInterface>>;
+	/// Create the symbolic link `name`, which references `target`
+	/// and is owned as specified by `attr`, in the current directory
+	fn symlink(&self, _name: &str, _target: &str, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::ENOTDIR)
+	}
 
-	/// Mound memory region as file
-	fn traverse_mount(&mut self, _components: &mut Vec<&str>, slice: &'static [u8]) -> Result<()>;
+	/// Insert the existing node `node` as entry `name` into the current directory
+	fn link(&self, _name: &str, _node: Inode) -> io::Result<()> {
+		Err(io::Error::ENOTDIR)
+	}
+
+	/// Remove the entry `name` from the current directory
+	fn remove(&self, _name: &str) -> io::Result<Inode> {
+		Err(io::Error::ENOTDIR)
+	}
+
+	/// Let the existing entry `name` of the current directory reference `node`
+	/// and return the previous node. If an error occurs, the entry is unchanged.
+	fn replace(&self, _name: &str, _node: Inode) -> io::Result<Inode> {
+		Err(io::Error::ENOTDIR)
+	}
+
+	/// Read the entries of the current directory without `.` and `..`
+	fn readdir(&self) -> io::Result<Vec<DirEntry>> {
+		Err(io::Error::ENOTDIR)
+	}
 }
 
+/// The trait `FileSystem` specifies an instance of a file system driver,
+/// which is able to be mounted on a directory of the virtual file system.
+pub(crate) trait FileSystem:
+	core::fmt::Debug + core::marker::Send + core::marker::Sync
+{
+	/// Returns the name of the file system type
+	fn name(&self) -> &'static str;
+
+	/// Returns the root directory of the file system
+	fn root(&self) -> Inode;
+
+	/// Write the modified data of the file system back to its device
+	fn sync(&self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Constructor of a file system, which receives the source of `mount`
+type FileSystemConstructor = fn(Option<&str>) -> io::Result<Arc<dyn FileSystem>>;
+
+/// File system types, which are able to be mounted by their name
+static FILE_SYSTEM_TYPES: &[(&str, FileSystemConstructor)] = &[
+	("ramfs", RamFs::create),
+	("tmpfs", RamFs::create),
+	("devfs", DevFs::create),
+	("proc", ProcFs::create),
+	("vfat", FatFs::create),
+	("ext2", Ext2Fs::create),
+];
+
 /// The trait `Vfs` specifies all operation on the virtual file systems.
 trait Vfs: core::fmt::Debug + core::marker::Send + core::marker::Sync {
-	/// Create a directory node at the location `path`.
-	fn mkdir(&mut self, path: &String) -> Result<()>;
+	/// Create a directory node with the permission bits `mode` at the location `path`.
+	fn mkdir(&mut self, path: &str, mode: u32) -> io::Result<()>;
+
+	/// Remove the file `path`
+	fn unlink(&mut self, path: &str) -> io::Result<()>;
+
+	/// Remove the empty directory `path`
+	fn rmdir(&mut self, path: &str) -> io::Result<()>;
+
+	/// Rename the file or directory `old_path` to `new_path`
+	fn rename(&mut self, old_path: &str, new_path: &str) -> io::Result<()>;
 
-	/// Print the current state of the file system
-	fn lsdir(&self) -> Result<()>;
+	/// Read the entries of the directory `path` without `.` and `..`
+	fn readdir(&mut self, path: &str) -> io::Result<Vec<DirEntry>>;
+
+	/// Returns the normalized path of the directory `path`
+	fn realpath_directory(&mut self, path: &str) -> io::Result<String>;
+
+	/// Create the symbolic link `path`, which references `target`
+	fn symlink(&mut self, target: &str, path: &str) -> io::Result<()>;
+
+	/// Returns the target of the symbolic link `path`
+	fn readlink(&mut self, path: &str) -> io::Result<String>;
+
+	/// Create the socket node `path` and return its inode number
+	fn mksock(&mut self, path: &str, mode: u32) -> io::Result<u64>;
+
+	/// Returns the inode number of the socket node `path`, which
+	/// the current task is allowed to write
+	fn find_socket(&mut self, path: &str) -> io::Result<u64>;
+
+	/// Create the new name `new_path` for the existing file `old_path`. If
+	/// `follow` is true and `old_path` is a symbolic link, the target is linked.
+	fn link(&mut self, old_path: &str, new_path: &str, follow: bool) -> io::Result<()>;
+
+	/// Returns the status of the file `path`. If `follow` is true and
+	/// `path` is a symbolic link, the status of the target is returned.
+	fn stat(&mut self, path: &str, follow: bool) -> io::Result<FileStatus>;
+
+	/// Change the permission bits of the file `path`
+	fn chmod(&mut self, path: &str, mode: u32) -> io::Result<()>;
+
+	/// Change the owner of the file `path`. If `follow` is true and
+	/// `path` is a symbolic link, the owner of the target is changed.
+	fn chown(&mut self, path: &str, uid: u32, gid: u32, follow: bool) -> io::Result<()>;
+
+	/// Check if the real user of the current task is allowed to
+	/// access the file `path` as specified by `mask`
+	fn access(&mut self, path: &str, mask: u32) -> io::Result<()>;
+
+	/// Check if the current task is allowed to execute the
+	/// file `path` and return the attributes of the file
+	fn check_executable(&mut self, path: &str) -> io::Result<Attributes>;
+
+	/// Change the access and the modification time of the file `path`. If
+	/// `follow` is true and `path` is a symbolic link, the target is changed.
+	fn utimens(
+		&mut self,
+		path: &str,
+		atime: TimeUpdate,
+		mtime: TimeUpdate,
+		follow: bool,
+	) -> io::Result<()>;
 
 	/// Open a file with the path `path`.
 	/// `path` must be an absolute path to the file, while `flags` defined
-	fn open(&mut self, path: &str, flags: OpenOption) -> Result<Arc<dyn IoInterface>>;
+	/// if the file is writeable or created with the permission bits `mode`.
+	fn open(
+		&mut self,
+		path: &str,
+		flags: OpenOption,
+		mode: u32,
+	) -> io::Result<Arc<dyn IoInterface>>;
+
+	/// Mount the file system `fs` on the directory `path`
+	fn mount(&mut self, path: &str, fs: Arc<dyn FileSystem>) -> io::Result<()>;
+
+	/// Unmount the file system, which is mounted on `path`
+	fn umount(&mut self, path: &str) -> io::Result<()>;
 
 	/// Mound memory region as file
-	fn mount(&mut self, path: &String, slice: &'static [u8]) -> Result<()>;
+	fn mount_rom(&mut self, path: &str, slice: &'static [u8]) -> io::Result<()>;
 }
 
 /// Entrypoint of the file system
 static mut VFS_ROOT: Option<Fs> = None;
 
+/// Helper function to print the directory `path` and its subdirectories
+fn traverse_lsdir(path: &str, tabs: &str) -> io::Result<()> {
+	let mut tabs = String::from(tabs);
+
+	tabs.push_str("  ");
+	for entry in readdir(path)? {
+		info!("{}{} ({:?})", tabs, entry.name, entry.kind);
+
+		if entry.kind == NodeKind::Directory {
+			let mut subdir = String::from(path.trim_end_matches('/'));
+			subdir.push('/');
+			subdir.push_str(&entry.name);
+
+			traverse_lsdir(&subdir, &tabs)?;
+		}
+	}
+
+	Ok(())
+}
+
 /// List the current state of file system
-pub fn lsdir() -> Result<()> {
-	unsafe { VFS_ROOT.as_mut().unwrap().lsdir() }
+pub fn lsdir() -> io::Result<()> {
+	info!("/");
+
+	traverse_lsdir("/", "")
 }
 
-/// Create a directory with the path `path`.
-/// `path` must be a absolete path to the direcory.
-pub fn mkdir(path: &String) -> Result<()> {
-	unsafe { VFS_ROOT.as_mut().unwrap().mkdir(path) }
+/// Read the entries of the directory `path`.
+/// A relative `path` starts at the working directory of the current task.
+/// The entries `.` and `..` aren't part of the result.
+pub fn readdir(path: &str) -> io::Result<Vec<DirEntry>> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().readdir(&path) }
 }
 
-/// Open a file with the path `path`.
-/// `path` must be an absolute path to the file, while `flags` defined
-/// if the file is writeable or created on demand.
-pub fn open(name: &str, flags: OpenOption) -> io::Result<FileDescriptor> {
-	debug!("Open {}, {:?}", name, flags);
+/// Create a directory with the path `path` and the permission bits
+/// `mode`, which are masked by the file mode creation mask.
+/// The parent directory has to exist.
+pub fn mkdir(path: &str, mode: u32) -> io::Result<()> {
+	let path = absolute_path(path)?;
 
-	let fs = unsafe { VFS_ROOT.as_mut().unwrap() };
-	if let Ok(file) = fs.open(name, flags) {
-		let fd = insert_io_interface(file)?;
-		Ok(fd)
-	} else {
-		Err(io::Error::EINVAL)
+	unsafe { VFS_ROOT.as_mut().unwrap().mkdir(&path, mode) }
+}
+
+/// Remove the file `path`.
+/// Open handles to the file are still able to access the content.
+pub fn unlink(path: &str) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().unlink(&path) }
+}
+
+/// Remove the directory `path`, which has to be empty.
+pub fn rmdir(path: &str) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().rmdir(&path) }
+}
+
+/// Rename the file or directory `old_path` to `new_path`, which
+/// may be part of another directory. An existing file `new_path` or
+/// an existing empty directory `new_path` is replaced.
+pub fn rename(old_path: &str, new_path: &str) -> io::Result<()> {
+	let old_path = absolute_path(old_path)?;
+	let new_path = absolute_path(new_path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().rename(&old_path, &new_path) }
+}
+
+/// Change the working directory of the current task to `path`
+pub fn chdir(path: &str) -> io::Result<()> {
+	let path = absolute_path(path)?;
+	let cwd = unsafe { VFS_ROOT.as_mut().unwrap().realpath_directory(&path)? };
+
+	set_current_working_dir(cwd);
+
+	Ok(())
+}
+
+/// Change the working directory of the current task to
+/// the directory, which is referenced by `fd`
+///
+/// The path of an open directory follows renames of the directory. If
+/// the directory has been removed, `ENOENT` is returned, even if a new
+/// directory with the same name exists.
+pub fn fchdir(fd: FileDescriptor) -> io::Result<()> {
+	let handle = get_io_interface(fd)?;
+	let path = handle.dir_path()?;
+	let cwd = unsafe { VFS_ROOT.as_mut().unwrap().realpath_directory(&path)? };
+
+	if stat(&cwd, true)?.ino != handle.fstat()?.ino {
+		return Err(io::Error::ENOENT);
+	}
+
+	set_current_working_dir(cwd);
+
+	Ok(())
+}
+
+/// Create the symbolic link `path`, which references `target`.
+/// `target` isn't checked and may be a relative path.
+pub fn symlink(target: &str, path: &str) -> io::Result<()> {
+	if target.is_empty() {
+		return Err(io::Error::ENOENT);
 	}
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().symlink(target, &path) }
+}
+
+/// Returns the target of the symbolic link `path`
+pub fn readlink(path: &str) -> io::Result<String> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().readlink(&path) }
+}
+
+/// Create the node `path`, to which a socket is bound. The node
+/// receives the permission bits `mode`, which are masked by the file
+/// mode creation mask. Returns the inode number of the node, which
+/// identifies the socket.
+pub(crate) fn mksock(path: &str, mode: u32) -> io::Result<u64> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().mksock(&path, mode) }
+}
+
+/// Returns the inode number of the socket node `path`, e.g. to connect
+/// to the socket. Symbolic links are followed.
+pub(crate) fn find_socket(path: &str) -> io::Result<u64> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().find_socket(&path) }
+}
+
+/// Create the hard link `new_path` to the existing file `old_path`.
+/// If `follow` is true and `old_path` is a symbolic link, the
+/// target of the symbolic link is linked.
+pub fn link(old_path: &str, new_path: &str, follow: bool) -> io::Result<()> {
+	let old_path = absolute_path(old_path)?;
+	let new_path = absolute_path(new_path)?;
+
+	unsafe {
+		VFS_ROOT
+			.as_mut()
+			.unwrap()
+			.link(&old_path, &new_path, follow)
+	}
+}
+
+/// Returns the status of the file `path`. If `follow` is true
+/// and `path` is a symbolic link, the target is examined.
+pub fn stat(path: &str, follow: bool) -> io::Result<FileStatus> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().stat(&path, follow) }
+}
+
+/// Change the permission bits of the file `path` to `mode`.
+/// Only the owner and the superuser are allowed to change them.
+pub fn chmod(path: &str, mode: u32) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().chmod(&path, mode) }
+}
+
+/// Change the owner of the file `path` to the user `uid` and the group
+/// `gid`. `KEEP_ID` keeps the current user or group. If `follow` is
+/// true and `path` is a symbolic link, the target is changed.
+pub fn chown(path: &str, uid: u32, gid: u32, follow: bool) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().chown(&path, uid, gid, follow) }
+}
+
+/// Check if the real user of the current task is allowed to access the
+/// file `path`. `mask` is a combination of `MAY_READ`, `MAY_WRITE` and
+/// `MAY_EXEC`. If `mask` is zero, only the existence is checked.
+pub fn access(path: &str, mask: u32) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().access(&path, mask) }
+}
+
+/// Check if the current task is allowed to execute the file `path`
+/// and return its attributes, e.g. to evaluate the set-user-ID bit
+pub(crate) fn check_executable(path: &str) -> io::Result<Attributes> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().check_executable(&path) }
+}
+
+/// Change the access time of the file `path` to `atime` and its
+/// modification time to `mtime`. If `follow` is true and `path` is a
+/// symbolic link, the target is changed.
+pub fn utimens(path: &str, atime: TimeUpdate, mtime: TimeUpdate, follow: bool) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe {
+		VFS_ROOT
+			.as_mut()
+			.unwrap()
+			.utimens(&path, atime, mtime, follow)
+	}
+}
+
+/// Change the access and the modification time of the
+/// file, which is referenced by `fd`, see `utimens`
+pub fn futimens(fd: FileDescriptor, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+	let obj = get_io_interface(fd)?;
+
+	if atime == TimeUpdate::Omit && mtime == TimeUpdate::Omit {
+		return Ok(());
+	}
+
+	let status = obj.fstat()?;
+	check_set_times(
+		status.kind,
+		&status.attr,
+		&get_current_credentials(),
+		atime,
+		mtime,
+	)?;
+
+	obj.set_times(atime, mtime)
+}
+
+/// Write all modified blocks of the buffer cache back to the devices
+pub fn sync() -> io::Result<()> {
+	crate::drivers::block::sync()
+}
+
+/// Write the modified data of the file, which is referenced by `fd`,
+/// back to the device
+pub fn fsync(fd: FileDescriptor) -> io::Result<()> {
+	get_io_interface(fd)?.fsync()
+}
+
+/// Write the modified data of the file system, which contains the
+/// file or directory referenced by `fd`, back to the device. The disk
+/// file systems flush the whole device by `fsync` of a file, while
+/// `fsync` of a directory writes back its file system. Consequently,
+/// `fsync` is sufficient.
+pub fn syncfs(fd: FileDescriptor) -> io::Result<()> {
+	fsync(fd)
+}
+
+/// Returns the absolute path of the working directory of the current task
+pub fn getcwd() -> String {
+	get_current_working_dir()
+}
+
+/// Open a file with the path `path`.
+/// A relative `path` starts at the working directory of the current
+/// task, while `flags` defined if the file is writeable or created
+/// on demand. A new file receives the permission bits `mode`, which
+/// are masked by the file mode creation mask.
+pub fn open(name: &str, flags: OpenOption, mode: u32) -> io::Result<FileDescriptor> {
+	debug!("Open {}, {:?}, {:o}", name, flags, mode);
+
+	let path = absolute_path(name)?;
+	let fs = unsafe { VFS_ROOT.as_mut().unwrap() };
+	let file = fs.open(&path, flags, mode)?;
+
+	insert_io_interface(file)
 }
 
 /// Mount slice to to `path`
-pub fn mount(path: &String, slice: &'static [u8]) -> Result<()> {
-	unsafe { VFS_ROOT.as_mut().unwrap().mount(path, slice) }
+pub fn mount_rom(path: &str, slice: &'static [u8]) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().mount_rom(&path, slice) }
+}
+
+/// Mount the file system `fs` on the directory `path`
+pub(crate) fn mount(path: &str, fs: Arc<dyn FileSystem>) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().mount(&path, fs) }
+}
+
+/// Create a file system of the type `fstype` and mount it on the
+/// directory `path`. `source` specifies the origin of the file
+/// system, e.g. a block device.
+pub fn mount_by_type(source: Option<&str>, path: &str, fstype: &str) -> io::Result<()> {
+	let constructor = FILE_SYSTEM_TYPES
+		.iter()
+		.find(|(name, _)| *name == fstype)
+		.map(|(_, constructor)| constructor)
+		.ok_or(io::Error::ENODEV)?;
+
+	mount(path, constructor(source)?)
+}
+
+/// Unmount the file system, which is mounted on `path`
+pub fn umount(path: &str) -> io::Result<()> {
+	let path = absolute_path(path)?;
+
+	unsafe { VFS_ROOT.as_mut().unwrap().umount(&path) }
 }
 
 /// Help function to check if the argument is an abolute path
@@ -120,6 +826,41 @@ fn check_path(path: &str) -> bool {
 	false
 }
 
+/// Help function to prepend the working directory of the
+/// current task to a relative path
+fn absolute_path(path: &str) -> io::Result<String> {
+	if path.is_empty() {
+		Err(io::Error::ENOENT)
+	} else if check_path(path) {
+		Ok(String::from(path))
+	} else {
+		let mut absolute = get_current_working_dir();
+
+		if !absolute.ends_with('/') {
+			absolute.push('/');
+		}
+		absolute.push_str(path);
+
+		Ok(absolute)
+	}
+}
+
+/// Help function to create an absolute path from normalized components
+fn to_path(components: &[String]) -> String {
+	let mut path = String::new();
+
+	for component in components {
+		path.push('/');
+		path.push_str(component);
+	}
+
+	if path.is_empty() {
+		path.push('/');
+	}
+
+	path
+}
+
 #[derive(Debug)]
 pub struct File {
 	fd: FileDescriptor,
@@ -129,7 +870,7 @@ pub struct File {
 impl File {
 	/// Attempts to create a file in read-write mode.
 	pub fn create(path: &str) -> io::Result<Self> {
-		let fd = open(path, OpenOption::O_RDWR | OpenOption::O_CREAT)?;
+		let fd = open(path, OpenOption::O_RDWR | OpenOption::O_CREAT, 0o666)?;
 
 		Ok(File {
 			fd,
@@ -139,7 +880,7 @@ impl File {
 
 	/// Attempts to open a file in read-write mode.
 	pub fn open(path: &str) -> io::Result<Self> {
-		let fd = open(path, OpenOption::O_RDWR)?;
+		let fd = open(path, OpenOption::O_RDWR, 0)?;
 
 		Ok(File {
 			fd,
@@ -172,24 +913,42 @@ impl Drop for File {
 }
 
 pub(crate) fn init() {
-	let mut root = Fs::new();
-
-	root.mkdir(&String::from("/bin")).unwrap();
-	root.mkdir(&String::from("/dev")).unwrap();
-
-	if DEMO.len() > 0 {
-		info!(
-			"Found mountable file at 0x{:x} (len 0x{:x})",
-			DEMO.as_ptr() as u64,
-			DEMO.len()
-		);
-		root.mount(&String::from("/bin/demo"), &DEMO)
-			.expect("Unable to mount file");
+	let mut root = Fs::new(Arc::new(RamFs::new()));
+
+	root.mkdir("/bin", 0o755).unwrap();
+	root.mkdir("/dev", 0o755).unwrap();
+	root.mkdir("/proc", 0o555).unwrap();
+	root.mkdir("/tmp", 0o755).unwrap();
+	root.mount("/tmp", Arc::new(RamFs::new())).unwrap();
+	// everybody is allowed to create files in /tmp, but
+	// only the owner is allowed to remove them
+	root.chmod("/tmp", 0o777 | S_ISVTX).unwrap();
+
+	devfs::init();
+	root.mount("/dev", Arc::new(DevFs::new())).unwrap();
+	root.mount("/proc", Arc::new(ProcFs::new())).unwrap();
+
+	info!(
+		"Found initramfs at 0x{:x} (len 0x{:x})",
+		INITRAMFS.as_ptr() as u64,
+		INITRAMFS.len()
+	);
+	initramfs::unpack(&mut root, INITRAMFS).expect("Unable to unpack the initramfs");
+
+	// the disk image of the build is mounted on /mnt, the
+	// file system is detected by probing the known drivers
+	if get_block_device(RAM_DISK).is_ok() {
+		root.mkdir("/mnt", 0o755).unwrap();
+		match Ext2Fs::create(Some(RAM_DISK)).or_else(|_| FatFs::create(Some(RAM_DISK))) {
+			Ok(fs) => root.mount("/mnt", fs).unwrap(),
+			Err(e) => warn!("Unable to mount the disk image {}: {:?}", RAM_DISK, e),
+		}
 	}
 
-	root.lsdir().unwrap();
 	//info!("root {:?}", root);
 	unsafe {
 		VFS_ROOT = Some(root);
 	}
+
+	lsdir().unwrap();
 }
@@ -0,0 +1,671 @@
+//! Implements a process file system, which generates the
+//! content of its files on read
+//!
+//! The file system provides the following files:
+//! - `/proc/<pid>/status`: state of the task `pid`
+//! - `/proc/<pid>/maps`: memory regions of the user space of the task `pid`
+//! - `/proc/<pid>/fd/`: open file descriptors of the task `pid`
+//! - `/proc/bcache`: usage and counters of the buffer cache
+//! - `/proc/meminfo`: usage of the physical memory and of the kernel heap
+//! - `/proc/interrupts`: number of received interrupts per vector
+//! - `/proc/uptime`: time since the boot and time of the idle task in seconds
+//! - `/proc/self`: symbolic link to the directory of the current process
+
+use crate::arch::irq::{get_irq_counters, get_timer_ticks};
+use crate::arch::mm::{get_memory_size, physicalmem};
+use crate::consts::{HEAP_SIZE, TIMER_FREQ};
+use crate::drivers::block::statistics;
+use crate::fd::{FileDescriptor, FileStatus, IoInterface, OpenOption, SeekFrom};
+use crate::fs::{next_ino, Attributes, DirEntry, FileSystem, Inode, NodeKind, Timestamps, VfsNode};
+use crate::io;
+use crate::mm::get_free_heap_size;
+use crate::scheduler::task::{TaskId, TaskInfo, TaskStatus};
+use crate::scheduler::{for_each_task, get_current_tgid, get_file_descriptors, get_memory_regions};
+use crate::synch::spinlock::*;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// Files in the root directory, which don't belong to a task
+const GLOBAL_FILES: [&str; 4] = ["bcache", "interrupts", "meminfo", "uptime"];
+
+/// Files in the directory of a task
+const TASK_FILES: [&str; 3] = ["fd", "maps", "status"];
+
+/// Permission bits of generated files, which are readable by everybody
+const FILE_MODE: u32 = 0o444;
+
+/// Permission bits of directories, which are searchable by everybody
+const DIRECTORY_MODE: u32 = 0o555;
+
+/// Returns the attributes of a node of the task `id`, which
+/// belongs to the effective user and group of the task
+fn task_attributes(id: TaskId, mode: u32) -> Attributes {
+	match get_task_info(id) {
+		Some(info) => Attributes::new(mode, info.credentials.euid, info.credentials.egid),
+		None => Attributes::new(mode, 0, 0),
+	}
+}
+
+/// Identifies a node of the procfs independent of the lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NodeId {
+	SelfLink,
+	File(Generator),
+	TaskDirectory(TaskId),
+	FdDirectory(TaskId),
+	Fd(TaskId, FileDescriptor),
+}
+
+impl NodeId {
+	/// Returns the task, which is described by the node
+	fn task(&self) -> Option<TaskId> {
+		match *self {
+			NodeId::SelfLink => None,
+			NodeId::File(Generator::Status(id)) | NodeId::File(Generator::Maps(id)) => Some(id),
+			NodeId::File(_) => None,
+			NodeId::TaskDirectory(id) | NodeId::FdDirectory(id) | NodeId::Fd(id, _) => Some(id),
+		}
+	}
+}
+
+/// Inode numbers of the nodes, which have been looked up so far
+static INODES: Spinlock<BTreeMap<NodeId, u64>> = Spinlock::new(BTreeMap::new());
+
+/// Returns the inode number of the node `id`. The nodes are created by
+/// each lookup, but the same node always receives the same number.
+fn ino_of(id: NodeId) -> u64 {
+	*INODES.lock().entry(id).or_insert_with(next_ino)
+}
+
+/// Forget the inode numbers of the nodes, which describe the exited task `id`
+pub(crate) fn release_task(id: TaskId) {
+	INODES.lock().retain(|node, _| node.task() != Some(id));
+}
+
+/// Returns a snapshot of the task `id`
+fn get_task_info(id: TaskId) -> Option<TaskInfo> {
+	let mut result = None;
+
+	for_each_task(|info| {
+		if info.id == id {
+			result = Some(info.clone());
+		}
+	});
+
+	result
+}
+
+/// Describes, how the content of a file is generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Generator {
+	BufferCache,
+	MemInfo,
+	Interrupts,
+	Uptime,
+	Status(TaskId),
+	Maps(TaskId),
+}
+
+impl Generator {
+	/// Create the current content of the file
+	fn generate(&self) -> io::Result<String> {
+		let mut s = String::new();
+
+		match *self {
+			Generator::BufferCache => {
+				let stats = statistics();
+				let lines = [
+					("Capacity:", stats.capacity as u64),
+					("Used:", stats.used as u64),
+					("Dirty:", stats.dirty as u64),
+					("Hits:", stats.hits),
+					("Misses:", stats.misses),
+					("ReadAhead:", stats.read_ahead),
+					("Writebacks:", stats.writebacks),
+					("Evictions:", stats.evictions),
+				];
+
+				for (name, value) in lines.iter() {
+					writeln!(s, "{:<16}{:>10}", name, value).unwrap();
+				}
+			}
+			Generator::MemInfo => {
+				let lines = [
+					("MemTotal:", get_memory_size()),
+					("MemFree:", physicalmem::get_free_size()),
+					("HeapTotal:", HEAP_SIZE),
+					("HeapFree:", get_free_heap_size()),
+				];
+
+				for (name, size) in lines.iter() {
+					writeln!(s, "{:<16}{:>10} kB", name, size >> 10).unwrap();
+				}
+			}
+			Generator::Interrupts => {
+				for (int_no, name, count) in get_irq_counters() {
+					writeln!(s, "{:>4}: {:>10}  {}", int_no, count, name).unwrap();
+				}
+			}
+			Generator::Uptime => {
+				let uptime = get_timer_ticks() * 100 / u64::from(TIMER_FREQ);
+				let mut idle = 0;
+
+				for_each_task(|info| {
+					if info.status == TaskStatus::Idle {
+						idle += info.runtime / 10;
+					}
+				});
+
+				writeln!(
+					s,
+					"{}.{:02} {}.{:02}",
+					uptime / 100,
+					uptime % 100,
+					idle / 100,
+					idle % 100
+				)
+				.unwrap();
+			}
+			Generator::Status(id) => {
+				let info = get_task_info(id).ok_or(io::Error::ESRCH)?;
+				let mut threads = 0;
+
+				for_each_task(|other| {
+					if other.tgid == info.tgid {
+						threads += 1;
+					}
+				});
+
+				writeln!(s, "Name:\t{}", info.name).unwrap();
+				writeln!(s, "State:\t{:?}", info.status).unwrap();
+				writeln!(s, "Tgid:\t{}", info.tgid).unwrap();
+				writeln!(s, "Pid:\t{}", info.id).unwrap();
+				let cred = &info.credentials;
+				writeln!(s, "Uid:\t{}\t{}\t{}", cred.uid, cred.euid, cred.suid).unwrap();
+				writeln!(s, "Gid:\t{}\t{}\t{}", cred.gid, cred.egid, cred.sgid).unwrap();
+				write!(s, "Groups:").unwrap();
+				for gid in cred.groups.iter() {
+					write!(s, "\t{}", gid).unwrap();
+				}
+				writeln!(s).unwrap();
+				writeln!(s, "Threads:\t{}", threads).unwrap();
+				writeln!(s, "Priority:\t{}", info.prio).unwrap();
+				writeln!(s, "Runtime:\t{} ms", info.runtime).unwrap();
+				writeln!(s, "StackUsage:\t{} bytes", info.stack_usage).unwrap();
+				if let Some(high_water) = info.stack_high_water {
+					writeln!(s, "StackHighWater:\t{} bytes", high_water).unwrap();
+				}
+			}
+			Generator::Maps(id) => {
+				for region in get_memory_regions(id).ok_or(io::Error::ESRCH)? {
+					writeln!(
+						s,
+						"{:016x}-{:016x} {} 00000000 00:00 0 {}",
+						region.start, region.end, region.permissions, region.name
+					)
+					.unwrap();
+				}
+			}
+		}
+
+		Ok(s)
+	}
+}
+
+/// Content of an open file and the position within the content
+#[derive(Debug)]
+struct ProcBuffer {
+	data: Vec<u8>,
+	pos: usize,
+}
+
+/// IO interface to a generated file
+///
+/// The content is generated by each read at the beginning of
+/// the file. Consequently, a seek to the start of the file
+/// followed by a read returns the current state.
+#[derive(Debug)]
+struct ProcFileHandle {
+	ino: u64,
+	generator: Generator,
+	buffer: Spinlock<ProcBuffer>,
+}
+
+impl IoInterface for ProcFileHandle {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		let mut guard = self.buffer.lock();
+
+		if guard.pos == 0 {
+			guard.data = self.generator.generate()?.into_bytes();
+		}
+
+		let pos = guard.pos;
+		if pos >= guard.data.len() {
+			return Ok(0);
+		}
+
+		let len = core::cmp::min(buf.len(), guard.data.len() - pos);
+		buf[..len].copy_from_slice(&guard.data[pos..pos + len]);
+		guard.pos = pos + len;
+
+		Ok(len)
+	}
+
+	fn write(&self, _buf: &[u8]) -> io::Result<usize> {
+		Err(io::Error::EBADF)
+	}
+
+	fn seek(&self, style: SeekFrom) -> io::Result<usize> {
+		let mut guard = self.buffer.lock();
+		let pos = match style {
+			SeekFrom::Start(n) => n as isize,
+			SeekFrom::End(n) => guard.data.len() as isize + n,
+			SeekFrom::Current(n) => guard.pos as isize + n,
+		};
+
+		if pos >= 0 {
+			guard.pos = pos as usize;
+			Ok(pos as usize)
+		} else {
+			Err(io::Error::EINVAL)
+		}
+	}
+
+	/// The size of a generated file is unknown in advance
+	fn fstat(&self) -> io::Result<FileStatus> {
+		Ok(FileStatus {
+			ino: self.ino,
+			kind: NodeKind::File,
+			nlink: 1,
+			file_size: 0,
+			attr: Attributes::new(FILE_MODE, 0, 0),
+			times: Timestamps::now(),
+		})
+	}
+
+	fn access_mode(&self) -> OpenOption {
+		OpenOption::O_RDONLY
+	}
+}
+
+/// Inode of a generated file
+#[derive(Debug)]
+struct ProcFile {
+	ino: u64,
+	generator: Generator,
+}
+
+impl ProcFile {
+	fn new(generator: Generator) -> Self {
+		ProcFile {
+			ino: ino_of(NodeId::File(generator)),
+			generator,
+		}
+	}
+}
+
+impl VfsNode for ProcFile {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::File
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		1
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		Attributes::new(FILE_MODE, 0, 0)
+	}
+
+	fn open(&self, opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		if opt.is_writeable() || opt.contains(OpenOption::O_TRUNC) {
+			return Err(io::Error::EACCES);
+		}
+
+		Ok(Arc::new(ProcFileHandle {
+			ino: self.ino,
+			generator: self.generator,
+			buffer: Spinlock::new(ProcBuffer {
+				data: Vec::new(),
+				pos: 0,
+			}),
+		}))
+	}
+}
+
+/// Inode of an open file descriptor in `/proc/<pid>/fd/`
+///
+/// Opening the inode returns the IO interface of the file descriptor.
+#[derive(Debug)]
+struct FdNode {
+	ino: u64,
+	obj: Arc<dyn IoInterface>,
+	/// only the owner of the task is able to open the file descriptor
+	attr: Attributes,
+}
+
+impl VfsNode for FdNode {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::File
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		1
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		self.attr
+	}
+
+	/// The file descriptor is shared => the access mode has to be granted
+	/// by the file descriptor and the file isn't created or truncated
+	fn open(&self, opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		let mode = self.obj.access_mode();
+
+		if opt.intersects(OpenOption::O_CREAT | OpenOption::O_TRUNC)
+			|| (opt.is_readable() && !mode.is_readable())
+			|| (opt.is_writeable() && !mode.is_writeable())
+		{
+			return Err(io::Error::EACCES);
+		}
+
+		Ok(self.obj.clone())
+	}
+}
+
+/// Symbolic link `/proc/self`, which references the directory of the current process
+#[derive(Debug)]
+struct ProcSelf {
+	ino: u64,
+}
+
+impl VfsNode for ProcSelf {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Symlink
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		1
+	}
+
+	fn readlink(&self) -> io::Result<String> {
+		Ok(get_current_tgid().to_string())
+	}
+}
+
+/// Create the entries of a directory by looking up each name
+fn collect_entries(node: &dyn VfsNode, names: &[String]) -> Vec<DirEntry> {
+	names
+		.iter()
+		.filter_map(|name| {
+			node.lookup(name).ok().map(|child| DirEntry {
+				name: name.clone(),
+				kind: child.get_kind(),
+				ino: child.get_ino(),
+			})
+		})
+		.collect()
+}
+
+/// Directory `/proc/<pid>/fd/`
+#[derive(Debug)]
+struct FdDirectory {
+	ino: u64,
+	id: TaskId,
+}
+
+impl VfsNode for FdDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		2
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		task_attributes(self.id, 0o500)
+	}
+
+	fn lookup(&self, name: &str) -> io::Result<Inode> {
+		let fd: FileDescriptor = name.parse().map_err(|_| io::Error::ENOENT)?;
+		let obj = get_file_descriptors(self.id)
+			.ok_or(io::Error::ENOENT)?
+			.remove(&fd)
+			.ok_or(io::Error::ENOENT)?;
+
+		Ok(Arc::new(FdNode {
+			ino: ino_of(NodeId::Fd(self.id, fd)),
+			obj,
+			attr: task_attributes(self.id, 0o600),
+		}))
+	}
+
+	fn create(&self, _name: &str, _kind: NodeKind, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn symlink(&self, _name: &str, _target: &str, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn link(&self, _name: &str, _node: Inode) -> io::Result<()> {
+		Err(io::Error::EPERM)
+	}
+
+	fn remove(&self, _name: &str) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn replace(&self, _name: &str, _node: Inode) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn readdir(&self) -> io::Result<Vec<DirEntry>> {
+		let names: Vec<String> = get_file_descriptors(self.id)
+			.ok_or(io::Error::ENOENT)?
+			.keys()
+			.map(|fd| fd.to_string())
+			.collect();
+
+		Ok(collect_entries(self, &names))
+	}
+}
+
+/// Directory `/proc/<pid>/`
+#[derive(Debug)]
+struct TaskDirectory {
+	ino: u64,
+	id: TaskId,
+}
+
+impl VfsNode for TaskDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		3
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		task_attributes(self.id, DIRECTORY_MODE)
+	}
+
+	fn lookup(&self, name: &str) -> io::Result<Inode> {
+		match name {
+			"fd" => Ok(Arc::new(FdDirectory {
+				ino: ino_of(NodeId::FdDirectory(self.id)),
+				id: self.id,
+			})),
+			"maps" => Ok(Arc::new(ProcFile::new(Generator::Maps(self.id)))),
+			"status" => Ok(Arc::new(ProcFile::new(Generator::Status(self.id)))),
+			_ => Err(io::Error::ENOENT),
+		}
+	}
+
+	fn create(&self, _name: &str, _kind: NodeKind, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn symlink(&self, _name: &str, _target: &str, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn link(&self, _name: &str, _node: Inode) -> io::Result<()> {
+		Err(io::Error::EPERM)
+	}
+
+	fn remove(&self, _name: &str) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn replace(&self, _name: &str, _node: Inode) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn readdir(&self) -> io::Result<Vec<DirEntry>> {
+		let names: Vec<String> = TASK_FILES.iter().map(|name| name.to_string()).collect();
+
+		Ok(collect_entries(self, &names))
+	}
+}
+
+/// Root directory of the procfs, which lists all processes
+#[derive(Debug)]
+struct ProcDirectory {
+	ino: u64,
+}
+
+impl ProcDirectory {
+	fn task_directory(id: TaskId) -> io::Result<Inode> {
+		get_task_info(id).ok_or(io::Error::ENOENT)?;
+
+		Ok(Arc::new(TaskDirectory {
+			ino: ino_of(NodeId::TaskDirectory(id)),
+			id,
+		}))
+	}
+}
+
+impl VfsNode for ProcDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		2
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		Attributes::new(DIRECTORY_MODE, 0, 0)
+	}
+
+	fn lookup(&self, name: &str) -> io::Result<Inode> {
+		match name {
+			"bcache" => Ok(Arc::new(ProcFile::new(Generator::BufferCache))),
+			"interrupts" => Ok(Arc::new(ProcFile::new(Generator::Interrupts))),
+			"meminfo" => Ok(Arc::new(ProcFile::new(Generator::MemInfo))),
+			"uptime" => Ok(Arc::new(ProcFile::new(Generator::Uptime))),
+			"self" => Ok(Arc::new(ProcSelf {
+				ino: ino_of(NodeId::SelfLink),
+			})),
+			_ => {
+				let id: u32 = name.parse().map_err(|_| io::Error::ENOENT)?;
+				Self::task_directory(TaskId::from(id))
+			}
+		}
+	}
+
+	fn create(&self, _name: &str, _kind: NodeKind, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn symlink(&self, _name: &str, _target: &str, _attr: Attributes) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn link(&self, _name: &str, _node: Inode) -> io::Result<()> {
+		Err(io::Error::EPERM)
+	}
+
+	fn remove(&self, _name: &str) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	fn replace(&self, _name: &str, _node: Inode) -> io::Result<Inode> {
+		Err(io::Error::EPERM)
+	}
+
+	/// Lists the global files, `self` and the directories of all
+	/// processes. The threads of a process aren't listed, but
+	/// their directories are accessible.
+	fn readdir(&self) -> io::Result<Vec<DirEntry>> {
+		let mut names: Vec<String> = GLOBAL_FILES.iter().map(|name| name.to_string()).collect();
+		names.push(String::from("self"));
+		for_each_task(|info| {
+			if info.id == info.tgid {
+				names.push(info.id.to_string());
+			}
+		});
+
+		Ok(collect_entries(self, &names))
+	}
+}
+
+/// Instance of the process file system
+#[derive(Debug)]
+pub(crate) struct ProcFs {
+	/// root directory of the file system
+	root: Inode,
+}
+
+impl ProcFs {
+	pub fn new() -> Self {
+		ProcFs {
+			root: Arc::new(ProcDirectory { ino: next_ino() }),
+		}
+	}
+
+	/// Create a new instance of the procfs. The source isn't used.
+	pub fn create(_source: Option<&str>) -> io::Result<Arc<dyn FileSystem>> {
+		Ok(Arc::new(ProcFs::new()))
+	}
+}
+
+impl FileSystem for ProcFs {
+	fn name(&self) -> &'static str {
+		"proc"
+	}
+
+	fn root(&self) -> Inode {
+		self.root.clone()
+	}
+}
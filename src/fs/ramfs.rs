@@ -0,0 +1,577 @@
+//! Implements an in-memory file system
+
+use crate::fd::OpenOption;
+use crate::fd::{FileStatus, IoInterface};
+use crate::fs::initrd::{RamHandle, RomHandle};
+use crate::fs::{
+	next_ino, Attributes, DirEntry, FileSystem, Inode, NodeKind, SeekFrom, TimeUpdate, Timestamps,
+	VfsNode,
+};
+use crate::io;
+use crate::synch::spinlock::*;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug)]
+struct RamDirectory {
+	/// inode number of the directory
+	ino: u64,
+	/// permission bits and owner of the directory
+	attr: Spinlock<Attributes>,
+	/// time of the last access, modification and status change
+	times: Spinlock<Timestamps>,
+	/// in principle, a map with all entries of the current directory
+	children: Spinlock<BTreeMap<String, Inode>>,
+}
+
+impl RamDirectory {
+	pub fn new(attr: Attributes) -> Self {
+		RamDirectory {
+			ino: next_ino(),
+			attr: Spinlock::new(attr),
+			times: Spinlock::new(Timestamps::now()),
+			children: Spinlock::new(BTreeMap::new()),
+		}
+	}
+}
+
+impl VfsNode for RamDirectory {
+	/// Returns the node type
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		// the entry in the parent directory, the entry `.` and
+		// the entries `..` of all subdirectories
+		2 + self
+			.children
+			.lock()
+			.values()
+			.filter(|node| node.get_kind() == NodeKind::Directory)
+			.count()
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		*self.attr.lock()
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		*self.attr.lock() = attr;
+		self.times.lock().touch_change();
+		Ok(())
+	}
+
+	fn get_times(&self) -> Timestamps {
+		*self.times.lock()
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.times.lock().update(atime, mtime);
+		Ok(())
+	}
+
+	fn lookup(&self, name: &str) -> io::Result<Inode> {
+		self.children
+			.lock()
+			.get(name)
+			.cloned()
+			.ok_or(io::Error::ENOENT)
+	}
+
+	fn create(&self, name: &str, kind: NodeKind, attr: Attributes) -> io::Result<Inode> {
+		let mut guard = self.children.lock();
+
+		if guard.contains_key(name) {
+			return Err(io::Error::EEXIST);
+		}
+
+		let node: Inode = match kind {
+			NodeKind::File => Arc::new(RamFile::new(attr)),
+			NodeKind::Directory => Arc::new(RamDirectory::new(attr)),
+			// devices are only provided by the devfs
+			NodeKind::CharDevice | NodeKind::BlockDevice => return Err(io::Error::EPERM),
+			// symbolic links require a target, see `symlink`
+			NodeKind::Symlink => return Err(io::Error::EINVAL),
+			NodeKind::Socket => Arc::new(RamSocket::new(attr)),
+		};
+		node.inc_nlink();
+		guard.insert(String::from(name), node.clone());
+		self.times.lock().touch_modify();
+
+		Ok(node)
+	}
+
+	fn symlink(&self, name: &str, target: &str, attr: Attributes) -> io::Result<Inode> {
+		let mut guard = self.children.lock();
+
+		if guard.contains_key(name) {
+			return Err(io::Error::EEXIST);
+		}
+
+		let node: Inode = Arc::new(RamSymlink::new(target, attr));
+		node.inc_nlink();
+		guard.insert(String::from(name), node.clone());
+		self.times.lock().touch_modify();
+
+		Ok(node)
+	}
+
+	fn link(&self, name: &str, node: Inode) -> io::Result<()> {
+		let mut guard = self.children.lock();
+
+		if guard.contains_key(name) {
+			return Err(io::Error::EEXIST);
+		}
+
+		node.inc_nlink();
+		guard.insert(String::from(name), node);
+		self.times.lock().touch_modify();
+
+		Ok(())
+	}
+
+	fn remove(&self, name: &str) -> io::Result<Inode> {
+		let node = self.children.lock().remove(name).ok_or(io::Error::ENOENT)?;
+		node.drop_nlink();
+		self.times.lock().touch_modify();
+
+		Ok(node)
+	}
+
+	fn replace(&self, name: &str, node: Inode) -> io::Result<Inode> {
+		let mut guard = self.children.lock();
+		let entry = guard.get_mut(name).ok_or(io::Error::ENOENT)?;
+
+		node.inc_nlink();
+		let previous = core::mem::replace(entry, node);
+		previous.drop_nlink();
+		self.times.lock().touch_modify();
+
+		Ok(previous)
+	}
+
+	fn readdir(&self) -> io::Result<Vec<DirEntry>> {
+		self.times.lock().touch_access();
+
+		Ok(self
+			.children
+			.lock()
+			.iter()
+			.map(|(name, node)| DirEntry {
+				name: name.clone(),
+				kind: node.get_kind(),
+				ino: node.get_ino(),
+			})
+			.collect())
+	}
+}
+
+/// Symbolic link, which stores the path of its target
+#[derive(Debug)]
+struct RamSymlink {
+	/// inode number of the link
+	ino: u64,
+	/// number of directory entries, which reference the link
+	nlink: AtomicUsize,
+	/// owner of the link, the permission bits aren't used
+	attr: Spinlock<Attributes>,
+	/// time of the last access, modification and status change
+	times: Spinlock<Timestamps>,
+	/// path of the target
+	target: String,
+}
+
+impl RamSymlink {
+	pub fn new(target: &str, attr: Attributes) -> Self {
+		RamSymlink {
+			ino: next_ino(),
+			nlink: AtomicUsize::new(0),
+			attr: Spinlock::new(attr),
+			times: Spinlock::new(Timestamps::now()),
+			target: String::from(target),
+		}
+	}
+}
+
+impl VfsNode for RamSymlink {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Symlink
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		self.nlink.load(Ordering::SeqCst)
+	}
+
+	fn inc_nlink(&self) {
+		self.nlink.fetch_add(1, Ordering::SeqCst);
+		self.times.lock().touch_change();
+	}
+
+	fn drop_nlink(&self) {
+		self.nlink.fetch_sub(1, Ordering::SeqCst);
+		self.times.lock().touch_change();
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		*self.attr.lock()
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		*self.attr.lock() = attr;
+		self.times.lock().touch_change();
+		Ok(())
+	}
+
+	fn get_times(&self) -> Timestamps {
+		*self.times.lock()
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.times.lock().update(atime, mtime);
+		Ok(())
+	}
+
+	fn stat(&self) -> io::Result<FileStatus> {
+		Ok(FileStatus {
+			ino: self.ino,
+			kind: NodeKind::Symlink,
+			nlink: self.get_nlink(),
+			file_size: self.target.len(),
+			attr: self.get_attributes(),
+			times: self.get_times(),
+		})
+	}
+
+	fn readlink(&self) -> io::Result<String> {
+		self.times.lock().touch_access();
+
+		Ok(self.target.clone())
+	}
+}
+
+/// Node of a socket, which is bound to the path of the node. The
+/// socket itself is managed by the network stack.
+#[derive(Debug)]
+struct RamSocket {
+	/// inode number of the socket
+	ino: u64,
+	/// number of directory entries, which reference the socket
+	nlink: AtomicUsize,
+	/// permission bits and owner of the socket
+	attr: Spinlock<Attributes>,
+	/// time of the last access, modification and status change
+	times: Spinlock<Timestamps>,
+}
+
+impl RamSocket {
+	pub fn new(attr: Attributes) -> Self {
+		RamSocket {
+			ino: next_ino(),
+			nlink: AtomicUsize::new(0),
+			attr: Spinlock::new(attr),
+			times: Spinlock::new(Timestamps::now()),
+		}
+	}
+}
+
+impl VfsNode for RamSocket {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Socket
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		self.nlink.load(Ordering::SeqCst)
+	}
+
+	fn inc_nlink(&self) {
+		self.nlink.fetch_add(1, Ordering::SeqCst);
+		self.times.lock().touch_change();
+	}
+
+	fn drop_nlink(&self) {
+		self.nlink.fetch_sub(1, Ordering::SeqCst);
+		self.times.lock().touch_change();
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		*self.attr.lock()
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		*self.attr.lock() = attr;
+		self.times.lock().touch_change();
+		Ok(())
+	}
+
+	fn get_times(&self) -> Timestamps {
+		*self.times.lock()
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.times.lock().update(atime, mtime);
+		Ok(())
+	}
+
+	fn open(&self, _opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		// sockets are accessed by `connect` instead of `open`
+		Err(io::Error::ENXIO)
+	}
+}
+
+/// Enumeration of possible methods to seek within an I/O object.
+#[derive(Debug, Clone)]
+enum DataHandle {
+	RAM(RamHandle),
+	ROM(RomHandle),
+}
+
+#[derive(Debug)]
+pub(crate) struct RamFile {
+	/// inode number of the file
+	ino: u64,
+	/// number of directory entries, which reference the file.
+	/// The counter is shared with all open handles.
+	nlink: Arc<AtomicUsize>,
+	/// permission bits and owner of the file, which
+	/// are shared with all open handles
+	attr: Arc<Spinlock<Attributes>>,
+	/// time of the last access, modification and status
+	/// change, which are shared with all open handles
+	times: Arc<Spinlock<Timestamps>>,
+	/// File content
+	data: DataHandle,
+}
+
+impl RamFile {
+	pub fn new(attr: Attributes) -> Self {
+		RamFile {
+			ino: next_ino(),
+			nlink: Arc::new(AtomicUsize::new(0)),
+			attr: Arc::new(Spinlock::new(attr)),
+			times: Arc::new(Spinlock::new(Timestamps::now())),
+			data: DataHandle::RAM(RamHandle::new(true)),
+		}
+	}
+
+	/// Create a read-only file, which references `slice`
+	pub fn new_from_rom(slice: &'static [u8]) -> Self {
+		RamFile {
+			ino: next_ino(),
+			nlink: Arc::new(AtomicUsize::new(0)),
+			attr: Arc::new(Spinlock::new(Attributes::new(0o444, 0, 0))),
+			times: Arc::new(Spinlock::new(Timestamps::now())),
+			data: DataHandle::ROM(RomHandle::new(slice)),
+		}
+	}
+}
+
+impl VfsNode for RamFile {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::File
+	}
+
+	fn get_ino(&self) -> u64 {
+		self.ino
+	}
+
+	fn get_nlink(&self) -> usize {
+		self.nlink.load(Ordering::SeqCst)
+	}
+
+	fn inc_nlink(&self) {
+		self.nlink.fetch_add(1, Ordering::SeqCst);
+		self.times.lock().touch_change();
+	}
+
+	fn drop_nlink(&self) {
+		self.nlink.fetch_sub(1, Ordering::SeqCst);
+		self.times.lock().touch_change();
+	}
+
+	fn get_attributes(&self) -> Attributes {
+		*self.attr.lock()
+	}
+
+	fn set_attributes(&self, attr: Attributes) -> io::Result<()> {
+		*self.attr.lock() = attr;
+		self.times.lock().touch_change();
+		Ok(())
+	}
+
+	fn get_times(&self) -> Timestamps {
+		*self.times.lock()
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.times.lock().update(atime, mtime);
+		Ok(())
+	}
+
+	fn stat(&self) -> io::Result<FileStatus> {
+		let file_size = match self.data {
+			DataHandle::RAM(ref data) => data.len(),
+			DataHandle::ROM(ref data) => data.len(),
+		};
+
+		Ok(FileStatus {
+			ino: self.ino,
+			kind: NodeKind::File,
+			nlink: self.get_nlink(),
+			file_size,
+			attr: self.get_attributes(),
+			times: self.get_times(),
+		})
+	}
+
+	fn open(&self, opt: OpenOption) -> io::Result<Arc<dyn IoInterface>> {
+		let data = match self.data {
+			DataHandle::RAM(ref data) => DataHandle::RAM(data.get_handle(opt)?),
+			DataHandle::ROM(ref data) => DataHandle::ROM(data.get_handle(opt)?),
+		};
+
+		if opt.contains(OpenOption::O_TRUNC) && opt.is_writeable() {
+			self.times.lock().touch_modify();
+		}
+
+		Ok(Arc::new(RamFileHandle {
+			ino: self.ino,
+			nlink: self.nlink.clone(),
+			attr: self.attr.clone(),
+			times: self.times.clone(),
+			data,
+		}))
+	}
+}
+
+/// IO interface to an open file
+///
+/// The handle references the file content. Consequently, the
+/// content is still accessible after the file is removed.
+#[derive(Debug)]
+struct RamFileHandle {
+	/// inode number of the file
+	ino: u64,
+	/// number of directory entries, which reference the file
+	nlink: Arc<AtomicUsize>,
+	/// permission bits and owner of the file
+	attr: Arc<Spinlock<Attributes>>,
+	/// time of the last access, modification and status change
+	times: Arc<Spinlock<Timestamps>>,
+	/// File content and position within the file
+	data: DataHandle,
+}
+
+impl fmt::Write for RamFileHandle {
+	fn write_str(&mut self, s: &str) -> core::fmt::Result {
+		match self.data {
+			DataHandle::RAM(ref mut data) => data.write_str(s),
+			_ => Err(core::fmt::Error),
+		}
+	}
+}
+
+impl IoInterface for RamFileHandle {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		let len = match self.data {
+			DataHandle::RAM(ref data) => data.read(buf)?,
+			DataHandle::ROM(ref data) => data.read(buf)?,
+		};
+		self.times.lock().touch_access();
+
+		Ok(len)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		let len = match self.data {
+			DataHandle::RAM(ref data) => data.write(buf)?,
+			_ => return Err(io::Error::EBADF),
+		};
+		self.times.lock().touch_modify();
+
+		Ok(len)
+	}
+
+	fn seek(&self, style: SeekFrom) -> io::Result<usize> {
+		match self.data {
+			DataHandle::RAM(ref data) => data.seek(style),
+			DataHandle::ROM(ref data) => data.seek(style),
+		}
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		let file_size = match self.data {
+			DataHandle::RAM(ref data) => data.len(),
+			DataHandle::ROM(ref data) => data.len(),
+		};
+
+		Ok(FileStatus {
+			ino: self.ino,
+			kind: NodeKind::File,
+			nlink: self.nlink.load(Ordering::SeqCst),
+			file_size,
+			attr: *self.attr.lock(),
+			times: *self.times.lock(),
+		})
+	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.times.lock().update(atime, mtime);
+		Ok(())
+	}
+
+	fn access_mode(&self) -> OpenOption {
+		match self.data {
+			DataHandle::RAM(ref data) => data.access_mode(),
+			DataHandle::ROM(_) => OpenOption::O_RDONLY,
+		}
+	}
+}
+
+/// Instance of the in-memory file system
+#[derive(Debug)]
+pub(crate) struct RamFs {
+	/// root directory of the file system
+	root: Inode,
+}
+
+impl RamFs {
+	pub fn new() -> Self {
+		RamFs {
+			root: Arc::new(RamDirectory::new(Attributes::default_for(
+				NodeKind::Directory,
+			))),
+		}
+	}
+
+	/// Create an empty file system. The source isn't used.
+	pub fn create(_source: Option<&str>) -> io::Result<Arc<dyn FileSystem>> {
+		Ok(Arc::new(RamFs::new()))
+	}
+}
+
+impl FileSystem for RamFs {
+	fn name(&self) -> &'static str {
+		"ramfs"
+	}
+
+	fn root(&self) -> Inode {
+		self.root.clone()
+	}
+}
@@ -1,301 +1,970 @@
-//! Implements a simple virtual file system
+//! Implements the virtual file system, which combines all
+//! mounted file systems to a single name space
 
-use crate::errno::*;
 use crate::fd::OpenOption;
 use crate::fd::{FileStatus, IoInterface};
-use crate::fs::initrd::{RamHandle, RomHandle};
-use crate::fs::{check_path, NodeKind, SeekFrom, Vfs, VfsNode, VfsNodeDirectory, VfsNodeFile};
+use crate::fs::ramfs::RamFile;
+use crate::fs::{
+	check_path, check_permission, check_set_times, to_path, Attributes, DirEntry, FileSystem,
+	Inode, NodeKind, SeekFrom, TimeUpdate, Vfs, KEEP_ID, MAY_EXEC, MAY_READ, MAY_WRITE, NAME_MAX,
+	SYMLOOP_MAX, S_ISGID, S_ISUID, S_ISVTX,
+};
 use crate::io;
 use crate::logging::*;
+use crate::scheduler::task::Credentials;
+use crate::scheduler::{get_current_credentials, get_current_umask};
 use crate::synch::spinlock::*;
-use alloc::boxed::Box;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::string::String;
-use alloc::sync::Arc;
+use alloc::sync::{Arc, Weak};
 use alloc::vec::Vec;
-use core::any::Any;
-use core::fmt;
 
+/// Normalized path of an open directory
+type SharedPath = Arc<Spinlock<Vec<String>>>;
+
+/// IO interface to read the entries of a directory
+///
+/// The entries are collected, when the directory is opened.
+/// The position is the index of the next entry.
 #[derive(Debug)]
-struct VfsDirectory {
-	/// in principle, a map with all entries of the current directory
-	children: BTreeMap<String, Box<dyn Any + core::marker::Send + core::marker::Sync>>,
+struct VfsDirectoryHandle {
+	/// the opened directory
+	directory: Inode,
+	/// file system, which contains the directory
+	fs: Arc<dyn FileSystem>,
+	/// normalized path of the directory, which is updated, if
+	/// the directory or one of its ancestors is renamed
+	path: SharedPath,
+	/// entries of the directory including `.` and `..`
+	entries: Vec<DirEntry>,
+	/// index of the next entry
+	pos: Spinlock<usize>,
 }
 
-impl VfsDirectory {
-	pub fn new() -> Self {
-		VfsDirectory {
-			children: BTreeMap::new(),
+impl VfsDirectoryHandle {
+	/// Create an IO interface to read the entries of `directory`, which
+	/// is named by `path` and belongs to `fs`. `parent` is the inode
+	/// number of the parent directory.
+	fn new(
+		directory: &Inode,
+		fs: Arc<dyn FileSystem>,
+		path: SharedPath,
+		parent: u64,
+		opt: OpenOption,
+	) -> io::Result<Self> {
+		if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR | OpenOption::O_CREAT) {
+			return Err(io::Error::EISDIR);
 		}
+
+		let mut children = directory.readdir()?;
+		let mut entries = Vec::with_capacity(children.len() + 2);
+		entries.push(DirEntry {
+			name: String::from("."),
+			kind: NodeKind::Directory,
+			ino: directory.get_ino(),
+		});
+		entries.push(DirEntry {
+			name: String::from(".."),
+			kind: NodeKind::Directory,
+			ino: parent,
+		});
+		entries.append(&mut children);
+
+		Ok(VfsDirectoryHandle {
+			directory: directory.clone(),
+			fs,
+			path,
+			entries,
+			pos: Spinlock::new(0),
+		})
 	}
+}
+
+impl IoInterface for VfsDirectoryHandle {
+	fn read(&self, _buf: &mut [u8]) -> io::Result<usize> {
+		Err(io::Error::EISDIR)
+	}
+
+	fn seek(&self, style: SeekFrom) -> io::Result<usize> {
+		let mut pos_guard = self.pos.lock();
+		let pos = match style {
+			SeekFrom::Start(n) => n as isize,
+			SeekFrom::End(n) => self.entries.len() as isize + n,
+			SeekFrom::Current(n) => *pos_guard as isize + n,
+		};
 
-	fn get_mut<T: VfsNode + Any>(&mut self, name: &String) -> Option<&mut T> {
-		if let Some(b) = self.children.get_mut(name) {
-			return b.downcast_mut::<T>();
+		if pos >= 0 {
+			*pos_guard = pos as usize;
+			Ok(pos as usize)
+		} else {
+			Err(io::Error::EINVAL)
 		}
-		None
 	}
 
-	fn get<T: VfsNode + Any>(&mut self, name: &String) -> Option<&T> {
-		if let Some(b) = self.children.get_mut(name) {
-			return b.downcast_ref::<T>();
+	fn readdir(&self) -> io::Result<Option<DirEntry>> {
+		let mut pos_guard = self.pos.lock();
+		let entry = self.entries.get(*pos_guard).cloned();
+
+		if entry.is_some() {
+			*pos_guard += 1;
 		}
-		None
+
+		Ok(entry)
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		self.directory.stat()
 	}
+
+	fn set_times(&self, atime: TimeUpdate, mtime: TimeUpdate) -> io::Result<()> {
+		self.directory.set_times(atime, mtime)
+	}
+
+	fn dir_path(&self) -> io::Result<String> {
+		Ok(to_path(&self.path.lock()))
+	}
+
+	/// Directories are modified by the operations on the name space.
+	/// Consequently, the whole file system is written back.
+	fn fsync(&self) -> io::Result<()> {
+		self.fs.sync()
+	}
+
+	fn access_mode(&self) -> OpenOption {
+		OpenOption::O_RDONLY
+	}
+}
+
+/// Check if a task with the credentials `cred` is allowed
+/// to create or remove entries of the directory `directory`
+fn may_modify(directory: &Inode, cred: &Credentials) -> io::Result<()> {
+	check_permission(
+		NodeKind::Directory,
+		&directory.get_attributes(),
+		cred,
+		MAY_WRITE | MAY_EXEC,
+	)
 }
 
-impl VfsNode for VfsDirectory {
-	/// Returns the node type
-	fn get_kind(&self) -> NodeKind {
-		NodeKind::Directory
+/// Check if a task with the credentials `cred` is allowed to remove
+/// the entry `node` from the directory `directory`. If the sticky bit
+/// of the directory is set, only the owner of the entry, the owner
+/// of the directory and the superuser are able to remove the entry.
+fn may_delete(directory: &Inode, node: &Inode, cred: &Credentials) -> io::Result<()> {
+	may_modify(directory, cred)?;
+
+	let attr = directory.get_attributes();
+	if attr.mode & S_ISVTX != 0
+		&& !cred.is_superuser()
+		&& cred.euid != attr.uid
+		&& cred.euid != node.get_attributes().uid
+	{
+		return Err(io::Error::EPERM);
 	}
+
+	Ok(())
 }
 
-impl VfsNodeDirectory for VfsDirectory {
-	fn traverse_mkdir(&mut self, components: &mut Vec<&str>) -> Result<()> {
-		if let Some(component) = components.pop() {
-			let node_name = String::from(component);
+/// Returns the attributes of a new node of type `kind`, which is created
+/// in the directory `parent` with the permission bits `mode`. The file
+/// mode creation mask isn't applied to symbolic links. A directory with
+/// the set-group-ID bit passes its group to the new node.
+fn new_attributes(parent: &Inode, kind: NodeKind, mode: u32, cred: &Credentials) -> Attributes {
+	let umask = if kind == NodeKind::Symlink {
+		0
+	} else {
+		get_current_umask()
+	};
+	let mut attr = Attributes::for_new_node(mode, umask, cred);
+	let parent_attr = parent.get_attributes();
+
+	if parent_attr.mode & S_ISGID != 0 {
+		attr.gid = parent_attr.gid;
+		if kind == NodeKind::Directory {
+			attr.mode |= S_ISGID;
+		}
+	}
 
-			{
-				if let Some(directory) = self.get_mut::<VfsDirectory>(&node_name) {
-					return directory.traverse_mkdir(components);
+	attr
+}
+
+/// Entrypoint of the virtual file system
+#[derive(Debug)]
+pub(crate) struct Fs {
+	/// mount table, which maps the normalized path of a mount point
+	/// to the mounted file system. The root file system is mounted
+	/// on the empty path.
+	mounts: Spinlock<BTreeMap<Vec<String>, Arc<dyn FileSystem>>>,
+	/// paths of the open directories
+	directories: Spinlock<Vec<Weak<Spinlock<Vec<String>>>>>,
+	/// serializes all modifications of the name space
+	lock: Spinlock<()>,
+}
+
+impl Fs {
+	pub fn new(root: Arc<dyn FileSystem>) -> Fs {
+		let mut mounts = BTreeMap::new();
+		mounts.insert(Vec::new(), root);
+
+		Fs {
+			mounts: Spinlock::new(mounts),
+			directories: Spinlock::new(Vec::new()),
+			lock: Spinlock::new(()),
+		}
+	}
+
+	/// Returns the root directory of the name space
+	fn root(&self) -> Inode {
+		self.mounts.lock().get(&Vec::new()).unwrap().root()
+	}
+
+	/// Returns the root directory of the file system, which is mounted
+	/// on `components`. Otherwise, `node` itself is returned.
+	fn cross_mount_point(&self, components: &[String], node: Inode) -> Inode {
+		match self.mounts.lock().get(components) {
+			Some(fs) => fs.root(),
+			None => node,
+		}
+	}
+
+	/// Register the path of an open directory, which has to be
+	/// updated, if the directory is moved
+	fn register_directory(&self, path: &SharedPath) {
+		let mut directories = self.directories.lock();
+
+		directories.retain(|path| path.strong_count() > 0);
+		directories.push(Arc::downgrade(path));
+	}
+
+	/// Replace the prefix `old` of the paths of all open directories by `new`
+	fn move_directories(&self, old: &[String], new: &[String]) {
+		for path in self.directories.lock().iter().filter_map(Weak::upgrade) {
+			let mut path = path.lock();
+
+			if path.starts_with(old) {
+				let rest = path.split_off(old.len());
+				*path = new.to_vec();
+				path.extend(rest);
+			}
+		}
+	}
+
+	/// Returns true, if `components` is a mount point or contains a mount point
+	fn is_busy(&self, components: &[String]) -> bool {
+		self.mounts
+			.lock()
+			.keys()
+			.any(|key| key.starts_with(components))
+	}
+
+	/// Returns the mount point of the file system, which contains `components`
+	fn mount_point_of(&self, components: &[String]) -> Vec<String> {
+		let mounts = self.mounts.lock();
+
+		(0..=components.len())
+			.rev()
+			.map(|len| &components[..len])
+			.find(|prefix| mounts.contains_key(*prefix))
+			.unwrap()
+			.to_vec()
+	}
+
+	/// Normalize the absolute path `path` and return its components
+	/// in regular order. The entries `.` and `..` are resolved and
+	/// each component, which is followed by further components, has
+	/// to be an existing directory. The lookup crosses mount points
+	/// and follows symbolic links. A symbolic link as last component
+	/// is only followed, if `follow` is true or the path ends with a slash.
+	/// Each directory, which is searched, requires execute permission
+	/// for the credentials `cred`.
+	fn resolve(&self, path: &str, follow: bool, cred: &Credentials) -> io::Result<Vec<String>> {
+		let mut pending: VecDeque<String> = path
+			.split('/')
+			.filter(|s| !s.is_empty())
+			.map(String::from)
+			.collect();
+		let mut resolved: Vec<String> = Vec::with_capacity(pending.len());
+		let mut parents: Vec<Inode> = Vec::new();
+		let mut directory = self.root();
+		let mut symlinks = 0;
+
+		while let Some(component) = pending.pop_front() {
+			match component.as_str() {
+				"." => {}
+				".." => {
+					// the root directory is its own parent
+					if let Some(parent) = parents.pop() {
+						directory = parent;
+						resolved.pop();
+					}
+				}
+				name => {
+					if name.len() > NAME_MAX {
+						return Err(io::Error::ENAMETOOLONG);
+					}
+
+					let is_last = pending.is_empty();
+
+					check_permission(
+						NodeKind::Directory,
+						&directory.get_attributes(),
+						cred,
+						MAY_EXEC,
+					)?;
+
+					match directory.lookup(name) {
+						Ok(node) => match node.get_kind() {
+							NodeKind::Directory => {
+								if !is_last {
+									resolved.push(component);
+									let node = self.cross_mount_point(&resolved, node);
+									parents.push(core::mem::replace(&mut directory, node));
+									continue;
+								}
+							}
+							NodeKind::Symlink if !is_last || follow || path.ends_with('/') => {
+								symlinks += 1;
+								if symlinks > SYMLOOP_MAX {
+									return Err(io::Error::ELOOP);
+								}
+
+								// continue the lookup with the target of the link
+								let target = node.readlink()?;
+								if target.starts_with('/') {
+									resolved.clear();
+									parents.clear();
+									directory = self.root();
+								}
+								for name in target.split('/').filter(|s| !s.is_empty()).rev() {
+									pending.push_front(String::from(name));
+								}
+								continue;
+							}
+							_ => {
+								// only directories are able to have further components
+								if !is_last || path.ends_with('/') {
+									return Err(io::Error::ENOTDIR);
+								}
+							}
+						},
+						// the last component may be created by the caller
+						Err(io::Error::ENOENT) if is_last => {}
+						Err(e) => return Err(e),
+					}
+
+					resolved.push(component);
 				}
 			}
+		}
+
+		Ok(resolved)
+	}
+
+	/// Returns the node, which is named by the normalized `components`
+	fn walk(&self, components: &[String]) -> io::Result<Inode> {
+		let mut node = self.root();
 
-			let mut directory = Box::new(VfsDirectory::new());
-			let result = directory.traverse_mkdir(components);
-			self.children.insert(node_name, directory);
+		for (i, component) in components.iter().enumerate() {
+			node = node.lookup(component)?;
+			node = self.cross_mount_point(&components[..=i], node);
+		}
+
+		Ok(node)
+	}
+}
 
-			result
+impl Vfs for Fs {
+	fn mkdir(&mut self, path: &str, mode: u32) -> io::Result<()> {
+		if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let mut components = self.resolve(path, false, &cred)?;
+			// the root directory already exists
+			let name = components.pop().ok_or(io::Error::EEXIST)?;
+			let parent = self.walk(&components)?;
+
+			may_modify(&parent, &cred)?;
+			let attr = new_attributes(&parent, NodeKind::Directory, mode, &cred);
+			parent.create(&name, NodeKind::Directory, attr).map(|_| ())
 		} else {
-			Ok(())
+			Err(io::Error::EINVAL)
 		}
 	}
 
-	fn traverse_lsdir(&self, mut tabs: String) -> Result<()> {
-		tabs.push_str("  ");
-		for (name, node) in self.children.iter() {
-			if let Some(directory) = node.downcast_ref::<VfsDirectory>() {
-				info!("{}{} ({:?})", tabs, name, self.get_kind());
-				directory.traverse_lsdir(tabs.clone())?;
-			} else if let Some(file) = node.downcast_ref::<VfsFile>() {
-				info!("{}{} ({:?})", tabs, name, file.get_kind());
-			} else {
-				info!("{}{} (Unknown))", tabs, name);
-			}
+	fn readdir(&mut self, path: &str) -> io::Result<Vec<DirEntry>> {
+		if check_path(path) {
+			let cred = get_current_credentials();
+			let components = self.resolve(path, true, &cred)?;
+			let directory = self.walk(&components)?;
+
+			check_permission(
+				directory.get_kind(),
+				&directory.get_attributes(),
+				&cred,
+				MAY_READ,
+			)?;
+			directory.readdir()
+		} else {
+			Err(io::Error::EINVAL)
 		}
+	}
+
+	fn unlink(&mut self, path: &str) -> io::Result<()> {
+		if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let mut components = self.resolve(path, false, &cred)?;
+			// the root directory isn't a file
+			let name = components.pop().ok_or(io::Error::EISDIR)?;
+			let parent = self.walk(&components)?;
+			let node = parent.lookup(&name)?;
+
+			if node.get_kind() == NodeKind::Directory {
+				return Err(io::Error::EISDIR);
+			}
+
+			may_delete(&parent, &node, &cred)?;
 
-		Ok(())
+			// open handles keep a reference to the content
+			parent.remove(&name).map(|_| ())
+		} else {
+			Err(io::Error::EINVAL)
+		}
 	}
 
-	fn traverse_open(
-		&mut self,
-		components: &mut Vec<&str>,
-		flags: OpenOption,
-	) -> Result<Arc<dyn IoInterface>> {
-		if let Some(component) = components.pop() {
-			let node_name = String::from(component);
-
-			if components.is_empty() == true {
-				// reach endpoint => reach file
-				if let Some(file) = self.get_mut::<VfsFile>(&node_name) {
-					return file.get_handle(flags);
-				}
+	fn rmdir(&mut self, path: &str) -> io::Result<()> {
+		if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let mut components = self.resolve(path, false, &cred)?;
+
+			// mount points, their ancestors and the root directory cannot be removed
+			if self.is_busy(&components) {
+				return Err(io::Error::EBUSY);
 			}
 
-			if components.is_empty() == true {
-				if flags.contains(OpenOption::O_CREAT) {
-					// Create file on demand
-					let file = Box::new(VfsFile::new());
-					let result = file.get_handle(flags);
-					self.children.insert(node_name, file);
+			let name = components.pop().unwrap();
+			let parent = self.walk(&components)?;
+			let node = parent.lookup(&name)?;
 
-					result
-				} else {
-					Err(Error::InvalidArgument)
-				}
-			} else {
-				// traverse to the directories to the endpoint
-				if let Some(directory) = self.get_mut::<VfsDirectory>(&node_name) {
-					directory.traverse_open(components, flags)
-				} else {
-					Err(Error::InvalidArgument)
-				}
+			if node.get_kind() != NodeKind::Directory {
+				return Err(io::Error::ENOTDIR);
+			} else if !node.readdir()?.is_empty() {
+				return Err(io::Error::ENOTEMPTY);
 			}
+
+			may_delete(&parent, &node, &cred)?;
+
+			parent.remove(&name).map(|_| ())
 		} else {
-			Err(Error::InvalidArgument)
+			Err(io::Error::EINVAL)
 		}
 	}
 
-	fn traverse_mount(&mut self, components: &mut Vec<&str>, slice: &'static [u8]) -> Result<()> {
-		if let Some(component) = components.pop() {
-			let node_name = String::from(component);
+	fn rename(&mut self, old_path: &str, new_path: &str) -> io::Result<()> {
+		if check_path(old_path) && check_path(new_path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let mut old_dir = self.resolve(old_path, false, &cred)?;
+			let mut new_dir = self.resolve(new_path, false, &cred)?;
+
+			// mount points, their ancestors and the root directory
+			// cannot be moved or replaced
+			if self.is_busy(&old_dir) || self.is_busy(&new_dir) {
+				return Err(io::Error::EBUSY);
+			}
 
-			if components.is_empty() == true {
-				// Create file on demand
-				let file = Box::new(VfsFile::new_from_rom(slice));
-				self.children.insert(node_name, file);
+			let old_name = old_dir.pop().unwrap();
+			let new_name = new_dir.pop().unwrap();
 
-				Ok(())
-			} else {
-				// traverse to the directories to the endpoint
-				if let Some(directory) = self.get_mut::<VfsDirectory>(&node_name) {
-					directory.traverse_mount(components, slice)
-				} else {
-					Err(Error::InvalidArgument)
+			// a node cannot be moved to another file system
+			if self.mount_point_of(&old_dir) != self.mount_point_of(&new_dir) {
+				return Err(io::Error::EXDEV);
+			}
+
+			// check all preconditions before the name space is modified
+			let old_parent = self.walk(&old_dir)?;
+			let new_parent = self.walk(&new_dir)?;
+			let node = old_parent.lookup(&old_name)?;
+			let is_dir = node.get_kind() == NodeKind::Directory;
+
+			may_delete(&old_parent, &node, &cred)?;
+			may_modify(&new_parent, &cred)?;
+
+			if old_dir == new_dir && old_name == new_name {
+				return Ok(());
+			}
+
+			// a directory cannot become a subdirectory of itself
+			if is_dir
+				&& new_dir.len() > old_dir.len()
+				&& new_dir.starts_with(&old_dir)
+				&& new_dir[old_dir.len()] == old_name
+			{
+				return Err(io::Error::EINVAL);
+			}
+
+			match new_parent.lookup(&new_name) {
+				Ok(target) => {
+					// both entries reference the same node
+					if target.get_ino() == node.get_ino() {
+						return Ok(());
+					}
+
+					match (is_dir, target.get_kind() == NodeKind::Directory) {
+						(false, true) => return Err(io::Error::EISDIR),
+						(true, false) => return Err(io::Error::ENOTDIR),
+						(true, true) if !target.readdir()?.is_empty() => {
+							return Err(io::Error::ENOTEMPTY)
+						}
+						_ => {}
+					}
+
+					may_delete(&new_parent, &target, &cred)?;
+					// the target is kept, if the entry cannot be replaced
+					new_parent.replace(&new_name, node)?;
 				}
+				Err(io::Error::ENOENT) => new_parent.link(&new_name, node)?,
+				Err(e) => return Err(e),
 			}
+
+			old_parent.remove(&old_name)?;
+
+			// open directories below the moved directory keep their paths
+			if is_dir {
+				old_dir.push(old_name);
+				new_dir.push(new_name);
+				self.move_directories(&old_dir, &new_dir);
+			}
+
+			Ok(())
 		} else {
-			Err(Error::InvalidArgument)
+			Err(io::Error::EINVAL)
 		}
 	}
-}
 
-/// Enumeration of possible methods to seek within an I/O object.
-#[derive(Debug, Clone)]
-enum DataHandle {
-	RAM(RamHandle),
-	ROM(RomHandle),
-}
+	fn realpath_directory(&mut self, path: &str) -> io::Result<String> {
+		if check_path(path) {
+			let cred = get_current_credentials();
+			let components = self.resolve(path, true, &cred)?;
+			let directory = self.walk(&components)?;
 
-#[derive(Debug, Clone)]
-struct VfsFile {
-	/// File content
-	data: DataHandle,
-}
+			if directory.get_kind() != NodeKind::Directory {
+				return Err(io::Error::ENOTDIR);
+			}
 
-impl VfsFile {
-	pub fn new() -> Self {
-		VfsFile {
-			data: DataHandle::RAM(RamHandle::new(true)),
+			// the working directory requires search permission
+			check_permission(
+				NodeKind::Directory,
+				&directory.get_attributes(),
+				&cred,
+				MAY_EXEC,
+			)?;
+
+			Ok(to_path(&components))
+		} else {
+			Err(io::Error::EINVAL)
 		}
 	}
 
-	pub fn new_from_rom(slice: &'static [u8]) -> Self {
-		VfsFile {
-			data: DataHandle::ROM(RomHandle::new(slice)),
+	fn symlink(&mut self, target: &str, path: &str) -> io::Result<()> {
+		if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let mut components = self.resolve(path, false, &cred)?;
+			// the root directory already exists
+			let name = components.pop().ok_or(io::Error::EEXIST)?;
+			let parent = self.walk(&components)?;
+
+			may_modify(&parent, &cred)?;
+			let attr = new_attributes(&parent, NodeKind::Symlink, 0o777, &cred);
+			parent.symlink(&name, target, attr).map(|_| ())
+		} else {
+			Err(io::Error::EINVAL)
 		}
 	}
-}
 
-impl VfsNode for VfsFile {
-	fn get_kind(&self) -> NodeKind {
-		NodeKind::File
+	fn readlink(&mut self, path: &str) -> io::Result<String> {
+		if check_path(path) {
+			let components = self.resolve(path, false, &get_current_credentials())?;
+
+			self.walk(&components)?.readlink()
+		} else {
+			Err(io::Error::EINVAL)
+		}
 	}
-}
 
-impl VfsNodeFile for VfsFile {
-	fn get_handle(&self, opt: OpenOption) -> Result<Arc<dyn IoInterface>> {
-		match self.data {
-			DataHandle::RAM(ref data) => Ok(Arc::new(VfsFile {
-				data: DataHandle::RAM(data.get_handle(opt)),
-			})),
-			DataHandle::ROM(ref data) => Ok(Arc::new(VfsFile {
-				data: DataHandle::ROM(data.get_handle(opt)),
-			})),
+	fn mksock(&mut self, path: &str, mode: u32) -> io::Result<u64> {
+		if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let mut components = self.resolve(path, false, &cred)?;
+			// an existing node, including the root directory, occupies the address
+			let name = components.pop().ok_or(io::Error::EADDRINUSE)?;
+			let parent = self.walk(&components)?;
+
+			may_modify(&parent, &cred)?;
+			let attr = new_attributes(&parent, NodeKind::Socket, mode, &cred);
+			match parent.create(&name, NodeKind::Socket, attr) {
+				Ok(node) => Ok(node.get_ino()),
+				Err(io::Error::EEXIST) => Err(io::Error::EADDRINUSE),
+				Err(e) => Err(e),
+			}
+		} else {
+			Err(io::Error::EINVAL)
 		}
 	}
-}
 
-impl fmt::Write for VfsFile {
-	fn write_str(&mut self, s: &str) -> core::fmt::Result {
-		match self.data {
-			DataHandle::RAM(ref mut data) => data.write_str(s),
-			_ => Err(core::fmt::Error),
+	fn find_socket(&mut self, path: &str) -> io::Result<u64> {
+		if check_path(path) {
+			let cred = get_current_credentials();
+			let components = self.resolve(path, true, &cred)?;
+			let node = self.walk(&components)?;
+
+			// nobody listens on other nodes
+			if node.get_kind() != NodeKind::Socket {
+				return Err(io::Error::ECONNREFUSED);
+			}
+			check_permission(NodeKind::Socket, &node.get_attributes(), &cred, MAY_WRITE)?;
+
+			Ok(node.get_ino())
+		} else {
+			Err(io::Error::EINVAL)
 		}
 	}
-}
 
-impl IoInterface for VfsFile {
-	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
-		match self.data {
-			DataHandle::RAM(ref data) => data.read(buf),
-			DataHandle::ROM(ref data) => data.read(buf),
+	fn link(&mut self, old_path: &str, new_path: &str, follow: bool) -> io::Result<()> {
+		if check_path(old_path) && check_path(new_path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let old_components = self.resolve(old_path, follow, &cred)?;
+			let mut new_dir = self.resolve(new_path, false, &cred)?;
+			let node = self.walk(&old_components)?;
+
+			// hard links to directories aren't allowed
+			if node.get_kind() == NodeKind::Directory {
+				return Err(io::Error::EPERM);
+			}
+
+			// the root directory already exists
+			let new_name = new_dir.pop().ok_or(io::Error::EEXIST)?;
+
+			// a hard link cannot reference a node of another file system
+			if self.mount_point_of(&old_components) != self.mount_point_of(&new_dir) {
+				return Err(io::Error::EXDEV);
+			}
+
+			let new_parent = self.walk(&new_dir)?;
+			may_modify(&new_parent, &cred)?;
+			new_parent.link(&new_name, node)
+		} else {
+			Err(io::Error::EINVAL)
 		}
 	}
 
-	fn write(&self, buf: &[u8]) -> io::Result<usize> {
-		match self.data {
-			DataHandle::RAM(ref data) => data.write(buf),
-			_ => Err(io::Error::EBADF),
+	fn stat(&mut self, path: &str, follow: bool) -> io::Result<FileStatus> {
+		if check_path(path) {
+			let components = self.resolve(path, follow, &get_current_credentials())?;
+
+			self.walk(&components)?.stat()
+		} else {
+			Err(io::Error::EINVAL)
 		}
 	}
 
-	fn seek(&self, style: SeekFrom) -> io::Result<usize> {
-		match self.data {
-			DataHandle::RAM(ref data) => data.seek(style),
-			DataHandle::ROM(ref data) => data.seek(style),
+	fn chmod(&mut self, path: &str, mode: u32) -> io::Result<()> {
+		if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let components = self.resolve(path, true, &cred)?;
+			let node = self.walk(&components)?;
+			let mut attr = node.get_attributes();
+
+			// only the owner is allowed to change the permissions
+			if !cred.is_superuser() && cred.euid != attr.uid {
+				return Err(io::Error::EPERM);
+			}
+
+			attr.mode = Attributes::new(mode, 0, 0).mode;
+			// the set-group-ID bit requires a membership of the group
+			if !cred.is_superuser() && !cred.in_group(attr.gid) {
+				attr.mode &= !S_ISGID;
+			}
+
+			node.set_attributes(attr)
+		} else {
+			Err(io::Error::EINVAL)
 		}
 	}
 
-	fn fstat(&self) -> io::Result<FileStatus> {
-		let file_size = match self.data {
-			DataHandle::RAM(ref data) => data.len(),
-			DataHandle::ROM(ref data) => data.len(),
-		};
+	fn chown(&mut self, path: &str, uid: u32, gid: u32, follow: bool) -> io::Result<()> {
+		if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let components = self.resolve(path, follow, &cred)?;
+			let node = self.walk(&components)?;
+			let mut attr = node.get_attributes();
+
+			if !cred.is_superuser() {
+				// only the superuser is allowed to pass a node to another user
+				if uid != KEEP_ID && uid != attr.uid {
+					return Err(io::Error::EPERM);
+				}
 
-		Ok(FileStatus { file_size })
+				// the owner is allowed to change the group to one of its groups
+				if gid != KEEP_ID
+					&& gid != attr.gid
+					&& (cred.euid != attr.uid || !cred.in_group(gid))
+				{
+					return Err(io::Error::EPERM);
+				}
+			}
+
+			if uid != KEEP_ID {
+				attr.uid = uid;
+			}
+			if gid != KEEP_ID {
+				attr.gid = gid;
+			}
+
+			// a new owner doesn't inherit the privileges of the previous owner
+			if !cred.is_superuser() && node.get_kind() != NodeKind::Directory {
+				attr.mode &= !(S_ISUID | S_ISGID);
+			}
+
+			node.set_attributes(attr)
+		} else {
+			Err(io::Error::EINVAL)
+		}
 	}
-}
 
-/// Entrypoint of the in-memory file system
-#[derive(Debug)]
-pub(crate) struct Fs {
-	handle: Spinlock<VfsDirectory>,
-}
+	fn access(&mut self, path: &str, mask: u32) -> io::Result<()> {
+		if check_path(path) {
+			// the real user and group IDs are checked
+			let cred = get_current_credentials().real();
+			let components = self.resolve(path, true, &cred)?;
+			let node = self.walk(&components)?;
 
-impl Fs {
-	pub fn new() -> Fs {
-		Fs {
-			handle: Spinlock::new(VfsDirectory::new()),
+			check_permission(node.get_kind(), &node.get_attributes(), &cred, mask)
+		} else {
+			Err(io::Error::EINVAL)
 		}
 	}
-}
 
-impl Vfs for Fs {
-	fn mkdir(&mut self, path: &String) -> Result<()> {
+	fn check_executable(&mut self, path: &str) -> io::Result<Attributes> {
 		if check_path(path) {
-			let mut components: Vec<&str> = path.split("/").collect();
+			let cred = get_current_credentials();
+			let components = self.resolve(path, true, &cred)?;
+			let node = self.walk(&components)?;
+			let attr = node.get_attributes();
+
+			if node.get_kind() != NodeKind::File {
+				return Err(io::Error::EACCES);
+			}
 
-			components.reverse();
-			components.pop();
+			check_permission(NodeKind::File, &attr, &cred, MAY_EXEC)?;
 
-			self.handle.lock().traverse_mkdir(&mut components)
+			Ok(attr)
 		} else {
-			Err(Error::InvalidFsPath)
+			Err(io::Error::EINVAL)
 		}
 	}
 
-	fn lsdir(&self) -> Result<()> {
-		info!("/");
+	fn utimens(
+		&mut self,
+		path: &str,
+		atime: TimeUpdate,
+		mtime: TimeUpdate,
+		follow: bool,
+	) -> io::Result<()> {
+		if check_path(path) {
+			let cred = get_current_credentials();
+			let components = self.resolve(path, follow, &cred)?;
+			let node = self.walk(&components)?;
+
+			if atime == TimeUpdate::Omit && mtime == TimeUpdate::Omit {
+				return Ok(());
+			}
+
+			check_set_times(node.get_kind(), &node.get_attributes(), &cred, atime, mtime)?;
 
-		self.handle.lock().traverse_lsdir(String::from(""))
+			node.set_times(atime, mtime)
+		} else {
+			Err(io::Error::EINVAL)
+		}
 	}
 
-	fn open(&mut self, path: &str, flags: OpenOption) -> Result<Arc<dyn IoInterface>> {
+	fn open(
+		&mut self,
+		path: &str,
+		flags: OpenOption,
+		mode: u32,
+	) -> io::Result<Arc<dyn IoInterface>> {
+		if !flags.has_valid_access_mode() {
+			Err(io::Error::EINVAL)
+		} else if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			// an exclusive creation doesn't follow a symbolic link
+			let follow = !flags.contains(OpenOption::O_NOFOLLOW)
+				&& !flags.contains(OpenOption::O_CREAT | OpenOption::O_EXCL);
+			let mut components = self.resolve(path, follow, &cred)?;
+			let mut created = false;
+
+			let (parent, node) = if let Some(name) = components.pop() {
+				let parent = self.walk(&components)?;
+
+				let node = match parent.lookup(&name) {
+					Ok(node) => {
+						// an exclusive creation fails for existing nodes
+						if flags.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+							return Err(io::Error::EEXIST);
+						}
+
+						node
+					}
+					Err(io::Error::ENOENT)
+						if flags.contains(OpenOption::O_CREAT)
+							&& !flags.contains(OpenOption::O_DIRECTORY) =>
+					{
+						// Create file on demand
+						may_modify(&parent, &cred)?;
+						let attr = new_attributes(&parent, NodeKind::File, mode, &cred);
+						created = true;
+						parent.create(&name, NodeKind::File, attr)?
+					}
+					Err(e) => return Err(e),
+				};
+
+				components.push(name);
+				let node = self.cross_mount_point(&components, node);
+
+				(parent, node)
+			} else if flags.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+				return Err(io::Error::EEXIST);
+			} else {
+				// the root directory is its own parent
+				(self.root(), self.root())
+			};
+
+			// a new file is accessible independent of its permissions
+			if !created {
+				let mask = if flags.contains(OpenOption::O_RDWR) {
+					MAY_READ | MAY_WRITE
+				} else if flags.contains(OpenOption::O_WRONLY) {
+					MAY_WRITE
+				} else {
+					MAY_READ
+				};
+
+				check_permission(node.get_kind(), &node.get_attributes(), &cred, mask)?;
+			}
+
+			if node.get_kind() == NodeKind::Symlink {
+				// the symbolic link isn't followed
+				Err(io::Error::ELOOP)
+			} else if node.get_kind() == NodeKind::Directory {
+				let mount_point = self.mount_point_of(&components);
+				let fs = self.mounts.lock()[&mount_point].clone();
+				let handle = VfsDirectoryHandle::new(
+					&node,
+					fs,
+					Arc::new(Spinlock::new(components)),
+					parent.get_ino(),
+					flags,
+				)?;
+				self.register_directory(&handle.path);
+
+				Ok(Arc::new(handle))
+			} else if flags.contains(OpenOption::O_DIRECTORY) {
+				Err(io::Error::ENOTDIR)
+			} else {
+				node.open(flags)
+			}
+		} else {
+			Err(io::Error::EINVAL)
+		}
+	}
+
+	fn mount(&mut self, path: &str, fs: Arc<dyn FileSystem>) -> io::Result<()> {
 		if check_path(path) {
-			let mut components: Vec<&str> = path.split("/").collect();
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+
+			if !cred.is_superuser() {
+				return Err(io::Error::EPERM);
+			}
+
+			let components = self.resolve(path, true, &cred)?;
+
+			if self.walk(&components)?.get_kind() != NodeKind::Directory {
+				return Err(io::Error::ENOTDIR);
+			}
+
+			let mut mounts = self.mounts.lock();
+			if mounts.contains_key(&components) {
+				return Err(io::Error::EBUSY);
+			}
 
-			components.reverse();
-			components.pop();
+			info!("Mount {} on {}", fs.name(), to_path(&components));
+			mounts.insert(components, fs);
 
-			self.handle.lock().traverse_open(&mut components, flags)
+			Ok(())
 		} else {
-			Err(Error::InvalidFsPath)
+			Err(io::Error::EINVAL)
 		}
 	}
 
-	/// Mound memory region as file
-	fn mount(&mut self, path: &String, slice: &'static [u8]) -> Result<()> {
+	fn umount(&mut self, path: &str) -> io::Result<()> {
 		if check_path(path) {
-			let mut components: Vec<&str> = path.split("/").collect();
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+
+			if !cred.is_superuser() {
+				return Err(io::Error::EPERM);
+			}
 
-			components.reverse();
-			components.pop();
+			let components = self.resolve(path, true, &cred)?;
+			let mut mounts = self.mounts.lock();
 
-			self.handle.lock().traverse_mount(&mut components, slice)
+			if !mounts.contains_key(&components) {
+				return Err(io::Error::EINVAL);
+			}
+
+			// the root file system and file systems with
+			// further mount points cannot be unmounted
+			if components.is_empty()
+				|| mounts
+					.keys()
+					.any(|key| key.len() > components.len() && key.starts_with(&components))
+			{
+				return Err(io::Error::EBUSY);
+			}
+
+			mounts[&components].sync()?;
+			let fs = mounts.remove(&components).unwrap();
+			info!("Unmount {} from {}", fs.name(), to_path(&components));
+
+			Ok(())
+		} else {
+			Err(io::Error::EINVAL)
+		}
+	}
+
+	/// Mound memory region as file
+	fn mount_rom(&mut self, path: &str, slice: &'static [u8]) -> io::Result<()> {
+		if check_path(path) {
+			let _guard = self.lock.lock();
+			let cred = get_current_credentials();
+			let mut components = self.resolve(path, false, &cred)?;
+			// the root directory cannot be replaced by a file
+			let name = components.pop().ok_or(io::Error::EISDIR)?;
+			let parent = self.walk(&components)?;
+
+			may_modify(&parent, &cred)?;
+			parent.link(&name, Arc::new(RamFile::new_from_rom(slice)))
 		} else {
-			Err(Error::InvalidFsPath)
+			Err(io::Error::EINVAL)
 		}
 	}
 }
@@ -8,6 +8,7 @@ use num_derive::{FromPrimitive, ToPrimitive};
 pub enum Error {
 	ENOENT = crate::errno::ENOENT as isize,
 	ENOSYS = crate::errno::ENOSYS as isize,
+	ERANGE = crate::errno::ERANGE as isize,
 	EIO = crate::errno::EIO as isize,
 	EBADF = crate::errno::EBADF as isize,
 	EISDIR = crate::errno::EISDIR as isize,
@@ -23,6 +24,37 @@ pub enum Error {
 	EADDRINUSE = crate::errno::EADDRINUSE as isize,
 	EOVERFLOW = crate::errno::EOVERFLOW as isize,
 	ENOTSOCK = crate::errno::ENOTSOCK as isize,
+	ENAMETOOLONG = crate::errno::ENAMETOOLONG as isize,
+	ENOTEMPTY = crate::errno::ENOTEMPTY as isize,
+	EBUSY = crate::errno::EBUSY as isize,
+	EROFS = crate::errno::EROFS as isize,
+	EXDEV = crate::errno::EXDEV as isize,
+	ENODEV = crate::errno::ENODEV as isize,
+	EPERM = crate::errno::EPERM as isize,
+	ENOSPC = crate::errno::ENOSPC as isize,
+	EACCES = crate::errno::EACCES as isize,
+	ESRCH = crate::errno::ESRCH as isize,
+	ELOOP = crate::errno::ELOOP as isize,
+	ENXIO = crate::errno::ENXIO as isize,
+	EFBIG = crate::errno::EFBIG as isize,
+	EMLINK = crate::errno::EMLINK as isize,
+	EPIPE = crate::errno::EPIPE as isize,
+	EDESTADDRREQ = crate::errno::EDESTADDRREQ as isize,
+	EMSGSIZE = crate::errno::EMSGSIZE as isize,
+	EPROTOTYPE = crate::errno::EPROTOTYPE as isize,
+	ENOPROTOOPT = crate::errno::ENOPROTOOPT as isize,
+	EPROTONOSUPPORT = crate::errno::EPROTONOSUPPORT as isize,
+	EOPNOTSUPP = crate::errno::EOPNOTSUPP as isize,
+	EAFNOSUPPORT = crate::errno::EAFNOSUPPORT as isize,
+	EADDRNOTAVAIL = crate::errno::EADDRNOTAVAIL as isize,
+	ENETUNREACH = crate::errno::ENETUNREACH as isize,
+	ECONNRESET = crate::errno::ECONNRESET as isize,
+	EISCONN = crate::errno::EISCONN as isize,
+	ETIMEDOUT = crate::errno::ETIMEDOUT as isize,
+	ECONNREFUSED = crate::errno::ECONNREFUSED as isize,
+	EALREADY = crate::errno::EALREADY as isize,
+	EINPROGRESS = crate::errno::EINPROGRESS as isize,
+	ENOMEM = crate::errno::ENOMEM as isize,
 }
 
 pub type Result<T> = result::Result<T, Error>;
@@ -4,10 +4,14 @@
 #![feature(specialization)]
 #![feature(map_try_insert)]
 #![feature(int_lowest_highest_one)]
+#![feature(custom_test_frameworks)]
+#![test_runner(crate::test_runner)]
+#![reexport_test_harness_main = "test_main"]
 #![allow(clippy::module_inception)]
 #![allow(incomplete_features)]
 #![allow(static_mut_refs)]
 #![no_std]
+#![cfg_attr(test, no_main)]
 
 extern crate alloc;
 #[cfg(target_arch = "x86_64")]
@@ -31,14 +35,17 @@ pub mod arch;
 pub mod collections;
 pub mod console;
 pub mod consts;
+pub mod drivers;
 pub mod errno;
 pub mod fd;
 pub mod fs;
 pub mod io;
 pub mod mm;
+pub mod net;
 pub mod scheduler;
 pub mod synch;
 pub mod syscall;
+pub mod time;
 
 #[repr(align(256))]
 struct Arena([u8; HEAP_SIZE]);
@@ -61,11 +68,49 @@ pub fn init() {
 	crate::arch::init();
 	crate::mm::init();
 	crate::scheduler::init();
+	crate::arch::acpi::init();
+	crate::drivers::init();
 	crate::fs::init();
+	crate::net::init();
+}
+
+/// A test, which is marked by `#[test_case]`
+pub trait Testable {
+	fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+	fn run(&self) {
+		print!("test {} ... ", core::any::type_name::<T>());
+		self();
+		println!("ok");
+	}
+}
+
+/// Run all tests, which are marked by `#[test_case]`. A failing
+/// test panics, which turns off the system with an error code.
+pub fn test_runner(tests: &[&dyn Testable]) {
+	println!("running {} tests", tests.len());
+
+	for test in tests {
+		test.run();
+	}
+
+	println!("test result: ok. {} passed", tests.len());
+}
+
+/// Entry point of the kernel, which runs the tests of the library
+#[cfg(test)]
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+	init();
+
+	test_main();
+
+	0
 }
 
 /// This function is called on panic.
-#[cfg(not(test))]
 #[panic_handler]
 pub fn panic(info: &PanicInfo) -> ! {
 	print!("[!!!PANIC!!!] ");
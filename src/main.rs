@@ -1,5 +1,8 @@
 #![no_std] // don't link the Rust standard library
-#![cfg_attr(not(test), no_main)] // disable all Rust-level entry points
+#![no_main] // disable all Rust-level entry points
+#![feature(custom_test_frameworks)]
+#![test_runner(eduos_rs::test_runner)]
+#![reexport_test_harness_main = "test_main"]
 #![cfg_attr(test, allow(dead_code, unused_macros, unused_imports))]
 
 #[macro_use]
@@ -9,6 +12,7 @@ extern crate alloc;
 use alloc::string::String;
 use eduos_rs::arch;
 use eduos_rs::arch::load_application;
+use eduos_rs::fs;
 use eduos_rs::scheduler;
 use eduos_rs::scheduler::task::NORMAL_PRIORITY;
 use eduos_rs::{LogLevel, LOGGER};
@@ -38,16 +42,30 @@ pub extern "C" fn main() -> i32 {
 	println!("Hello from eduOS-rs!");
 
 	for _i in 0..2 {
-		scheduler::spawn(foo, NORMAL_PRIORITY).unwrap();
+		scheduler::spawn_named("foo", foo, NORMAL_PRIORITY).unwrap();
 	}
-	scheduler::spawn(create_user_foo, NORMAL_PRIORITY).unwrap();
+	scheduler::spawn_named("loader", create_user_foo, NORMAL_PRIORITY).unwrap();
 
 	// enable interrupts => enable preemptive multitasking
 	arch::irq::irq_enable();
 
 	scheduler::reschedule();
 
+	// write the buffer cache back, before the system is turned off
+	let _ = fs::sync();
+
 	println!("Shutdown system!");
 
 	0
 }
+
+/// Entry point of the kernel, which runs the tests of the binary
+#[cfg(test)]
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+	eduos_rs::init();
+
+	test_main();
+
+	0
+}
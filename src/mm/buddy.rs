@@ -41,6 +41,15 @@ impl<const ORDER: usize> BuddySystem<ORDER> {
 		}
 	}
 
+	/// Returns the number of bytes, which are still available
+	pub fn free_size(&self) -> usize {
+		self.free_list
+			.iter()
+			.enumerate()
+			.map(|(order, list)| list.iter().count() << order)
+			.sum()
+	}
+
 	/// Allocates memory as described by the given `layout`.
 	///
 	/// Returns as result a pointer to newly-allocated memory,
@@ -161,6 +170,11 @@ impl<const ORDER: usize> LockedHeap<ORDER> {
 			self.0.lock().init(start, len);
 		}
 	}
+
+	/// Returns the number of bytes, which are still available
+	pub fn free_size(&self) -> usize {
+		self.0.lock().free_size()
+	}
 }
 
 impl<const ORDER: usize> fmt::Debug for LockedHeap<ORDER> {
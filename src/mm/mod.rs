@@ -7,7 +7,6 @@ use crate::logging::*;
 pub(crate) mod buddy;
 pub(crate) mod linked_list;
 
-#[cfg(not(test))]
 use alloc::alloc::Layout;
 
 pub(crate) fn init() {
@@ -16,7 +15,11 @@ pub(crate) fn init() {
 	arch::mm::init();
 }
 
-#[cfg(not(test))]
+/// Returns the number of bytes, which are still available on the kernel heap
+pub(crate) fn get_free_heap_size() -> usize {
+	crate::ALLOCATOR.free_size()
+}
+
 #[alloc_error_handler]
 pub fn rust_oom(layout: Layout) -> ! {
 	println!(
@@ -0,0 +1,184 @@
+//! Address Resolution Protocol (ARP), which maps IPv4 addresses to MAC addresses
+
+use crate::consts::TIMER_FREQ;
+use crate::io;
+use crate::net::{now, MacAddress, NetworkStack, BROADCAST_MAC, ETHERTYPE_ARP, ETHERTYPE_IPV4};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::net::Ipv4Addr;
+
+/// Hardware type of Ethernet
+const ARP_HTYPE_ETHERNET: u16 = 1;
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+/// Size of an ARP packet for Ethernet and IPv4
+const ARP_PACKET_SIZE: usize = 28;
+
+/// Lifetime of a cache entry in timer ticks
+const ARP_ENTRY_LIFETIME: u64 = 300 * TIMER_FREQ as u64;
+/// Interval between two requests for the same address in timer ticks
+const ARP_REQUEST_INTERVAL: u64 = TIMER_FREQ as u64;
+/// Number of requests, before the waiting packets are dropped
+const ARP_MAX_REQUESTS: u32 = 3;
+/// Maximum number of packets, which wait for the resolution of an address
+const ARP_MAX_PENDING: usize = 16;
+
+/// Pending resolution of an address
+#[derive(Debug)]
+struct Request {
+	/// interface, which sends the requests
+	interface: usize,
+	/// time of the last request
+	sent: u64,
+	/// number of sent requests
+	count: u32,
+	/// IPv4 packets, which wait for the resolution
+	packets: Vec<Vec<u8>>,
+}
+
+/// Mapping of IPv4 addresses to MAC addresses
+#[derive(Debug)]
+pub(super) struct ArpCache {
+	/// MAC address and expiration time of each known address
+	entries: BTreeMap<Ipv4Addr, (MacAddress, u64)>,
+	/// addresses, which are currently resolved
+	requests: BTreeMap<Ipv4Addr, Request>,
+}
+
+impl ArpCache {
+	pub const fn new() -> Self {
+		ArpCache {
+			entries: BTreeMap::new(),
+			requests: BTreeMap::new(),
+		}
+	}
+}
+
+/// Send an ARP packet of the type `operation` by the interface `index`
+fn send_packet(
+	stack: &NetworkStack,
+	index: usize,
+	operation: u16,
+	target_mac: MacAddress,
+	target: Ipv4Addr,
+) -> io::Result<()> {
+	let iface = &stack.interfaces[index];
+	let mut packet = Vec::with_capacity(ARP_PACKET_SIZE);
+
+	packet.extend_from_slice(&ARP_HTYPE_ETHERNET.to_be_bytes());
+	packet.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+	packet.push(6);
+	packet.push(4);
+	packet.extend_from_slice(&operation.to_be_bytes());
+	packet.extend_from_slice(&iface.mac);
+	packet.extend_from_slice(&iface.address.octets());
+	packet.extend_from_slice(&target_mac);
+	packet.extend_from_slice(&target.octets());
+
+	let dst = if operation == ARP_REQUEST {
+		BROADCAST_MAC
+	} else {
+		target_mac
+	};
+	iface.send_frame(dst, ETHERTYPE_ARP, &packet)
+}
+
+/// Send the IPv4 packet `packet` by the interface `index` to `next_hop`. If
+/// the MAC address of `next_hop` is unknown, the packet waits for its resolution.
+pub(super) fn send(
+	stack: &mut NetworkStack,
+	index: usize,
+	next_hop: Ipv4Addr,
+	packet: Vec<u8>,
+) -> io::Result<()> {
+	let now = now();
+
+	if let Some((mac, expires)) = stack.arp.entries.get(&next_hop) {
+		if *expires > now {
+			return stack.interfaces[index].send_frame(*mac, ETHERTYPE_IPV4, &packet);
+		}
+	}
+
+	if let Some(request) = stack.arp.requests.get_mut(&next_hop) {
+		if request.packets.len() >= ARP_MAX_PENDING {
+			return Err(io::Error::ENOBUFS);
+		}
+		request.packets.push(packet);
+
+		return Ok(());
+	}
+
+	stack.arp.requests.insert(
+		next_hop,
+		Request {
+			interface: index,
+			sent: now,
+			count: 1,
+			packets: alloc::vec![packet],
+		},
+	);
+	send_packet(stack, index, ARP_REQUEST, [0; 6], next_hop)
+}
+
+/// Process the ARP packet `packet`, which is received by the interface `index`
+pub(super) fn receive(stack: &mut NetworkStack, index: usize, packet: &[u8]) {
+	if packet.len() < ARP_PACKET_SIZE
+		|| u16::from_be_bytes([packet[0], packet[1]]) != ARP_HTYPE_ETHERNET
+		|| u16::from_be_bytes([packet[2], packet[3]]) != ETHERTYPE_IPV4
+		|| packet[4] != 6
+		|| packet[5] != 4
+	{
+		return;
+	}
+
+	let operation = u16::from_be_bytes([packet[6], packet[7]]);
+	let sender_mac: MacAddress = packet[8..14].try_into().unwrap();
+	let sender = Ipv4Addr::new(packet[14], packet[15], packet[16], packet[17]);
+	let target = Ipv4Addr::new(packet[24], packet[25], packet[26], packet[27]);
+	let address = stack.interfaces[index].address;
+
+	if sender.is_unspecified() {
+		return;
+	}
+
+	// learn the sender, if the packet is addressed to us or the sender is already known
+	let for_us = !address.is_unspecified() && target == address;
+	if for_us || stack.arp.entries.contains_key(&sender) {
+		stack
+			.arp
+			.entries
+			.insert(sender, (sender_mac, now() + ARP_ENTRY_LIFETIME));
+
+		if let Some(request) = stack.arp.requests.remove(&sender) {
+			let iface = &stack.interfaces[request.interface];
+			for packet in request.packets {
+				let _ = iface.send_frame(sender_mac, ETHERTYPE_IPV4, &packet);
+			}
+		}
+	}
+
+	if for_us && operation == ARP_REQUEST {
+		let _ = send_packet(stack, index, ARP_REPLY, sender_mac, sender);
+	}
+}
+
+/// Repeat unanswered requests and remove expired entries
+pub(super) fn poll(stack: &mut NetworkStack, now: u64) {
+	stack.arp.entries.retain(|_, (_, expires)| *expires > now);
+	stack.arp.requests.retain(|_, request| {
+		now < request.sent + ARP_REQUEST_INTERVAL || request.count < ARP_MAX_REQUESTS
+	});
+
+	let mut repeat = Vec::new();
+	for (addr, request) in stack.arp.requests.iter_mut() {
+		if now >= request.sent + ARP_REQUEST_INTERVAL {
+			request.sent = now;
+			request.count += 1;
+			repeat.push((request.interface, *addr));
+		}
+	}
+
+	for (index, addr) in repeat {
+		let _ = send_packet(stack, index, ARP_REQUEST, [0; 6], addr);
+	}
+}
@@ -0,0 +1,64 @@
+//! Internet Control Message Protocol (ICMP)
+//!
+//! The stack answers echo requests and reports unreachable UDP ports.
+
+use crate::net::ip::{checksum, IP_PROTOCOL_ICMP};
+use crate::net::NetworkStack;
+use alloc::vec::Vec;
+use core::net::Ipv4Addr;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_DEST_UNREACHABLE: u8 = 3;
+const ICMP_ECHO_REQUEST: u8 = 8;
+/// Code of the message "destination unreachable", if no socket uses the port
+const ICMP_PORT_UNREACHABLE: u8 = 3;
+
+/// Size of the ICMP header
+const ICMP_HEADER_SIZE: usize = 8;
+
+/// Returns a message of the type `kind` with the code `code`. `rest`
+/// contains the second word of the header and the data.
+fn build(kind: u8, code: u8, rest: &[u8]) -> Vec<u8> {
+	let mut message = Vec::with_capacity(4 + rest.len());
+	message.push(kind);
+	message.push(code);
+	message.extend_from_slice(&[0, 0]);
+	message.extend_from_slice(rest);
+
+	let sum = checksum(&message, 0);
+	message[2..4].copy_from_slice(&sum.to_be_bytes());
+
+	message
+}
+
+/// Process the ICMP message `message`, which `src` sends to `dst`
+pub(super) fn receive(stack: &mut NetworkStack, src: Ipv4Addr, dst: Ipv4Addr, message: &[u8]) {
+	if message.len() < ICMP_HEADER_SIZE || checksum(message, 0) != 0 {
+		return;
+	}
+
+	if message[0] == ICMP_ECHO_REQUEST && message[1] == 0 {
+		let reply = build(ICMP_ECHO_REPLY, 0, &message[4..]);
+		let _ = stack.send_ipv4(dst, src, IP_PROTOCOL_ICMP, &reply);
+	}
+}
+
+/// Report to `dst`, that the UDP datagram in `packet` isn't delivered,
+/// because no socket is bound to the destination port
+pub(super) fn port_unreachable(
+	stack: &mut NetworkStack,
+	src: Ipv4Addr,
+	dst: Ipv4Addr,
+	packet: &[u8],
+) {
+	// the message contains the IP header and the first 8 bytes of the payload
+	let header_len = usize::from(packet[0] & 0xF) * 4;
+	let len = packet.len().min(header_len + 8);
+
+	let mut rest = Vec::with_capacity(4 + len);
+	rest.extend_from_slice(&[0; 4]);
+	rest.extend_from_slice(&packet[..len]);
+
+	let message = build(ICMP_DEST_UNREACHABLE, ICMP_PORT_UNREACHABLE, &rest);
+	let _ = stack.send_ipv4(src, dst, IP_PROTOCOL_ICMP, &message);
+}
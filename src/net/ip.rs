@@ -0,0 +1,130 @@
+//! Internet Protocol version 4 (IPv4) without options and fragmentation
+
+use crate::io;
+use crate::net::{icmp, tcp, udp, NetworkStack};
+use alloc::vec::Vec;
+use core::net::Ipv4Addr;
+
+pub(super) const IP_PROTOCOL_ICMP: u8 = 1;
+pub(super) const IP_PROTOCOL_TCP: u8 = 6;
+pub(super) const IP_PROTOCOL_UDP: u8 = 17;
+
+/// Size of the header without options
+pub(super) const IPV4_HEADER_SIZE: usize = 20;
+/// Maximum size of a packet, which fits into an Ethernet frame
+pub(super) const IPV4_MTU: usize = 1500;
+/// Time to live of outgoing packets
+const IPV4_DEFAULT_TTL: u8 = 64;
+/// Flag "don't fragment"
+const IPV4_FLAG_DF: u16 = 0x4000;
+/// Mask of the flag "more fragments" and the fragment offset
+const IPV4_FRAGMENT_MASK: u16 = 0x3FFF;
+
+/// Returns the Internet checksum of `data`. `initial` is a sum of
+/// 16 bit words, which are added in advance, e.g. a pseudo header.
+pub(super) fn checksum(data: &[u8], initial: u32) -> u16 {
+	let mut sum = initial;
+
+	let mut chunks = data.chunks_exact(2);
+	for chunk in chunks.by_ref() {
+		sum += u32::from(u16::from_be_bytes([chunk[0], chunk[1]]));
+	}
+	if let [last] = chunks.remainder() {
+		sum += u32::from(*last) << 8;
+	}
+
+	while sum > 0xFFFF {
+		sum = (sum & 0xFFFF) + (sum >> 16);
+	}
+
+	!(sum as u16)
+}
+
+/// Returns the sum of the pseudo header, which is part
+/// of the checksums of TCP and UDP
+pub(super) fn pseudo_header_sum(src: Ipv4Addr, dst: Ipv4Addr, protocol: u8, len: usize) -> u32 {
+	let src = src.to_bits();
+	let dst = dst.to_bits();
+
+	(src >> 16) + (src & 0xFFFF) + (dst >> 16) + (dst & 0xFFFF) + u32::from(protocol) + len as u32
+}
+
+/// Returns a packet, which contains the header and `payload`
+pub(super) fn build(
+	src: Ipv4Addr,
+	dst: Ipv4Addr,
+	protocol: u8,
+	id: u16,
+	payload: &[u8],
+) -> io::Result<Vec<u8>> {
+	let len = IPV4_HEADER_SIZE + payload.len();
+	if len > IPV4_MTU {
+		return Err(io::Error::EMSGSIZE);
+	}
+
+	let mut packet = Vec::with_capacity(len);
+	packet.push(0x45);
+	packet.push(0);
+	packet.extend_from_slice(&(len as u16).to_be_bytes());
+	packet.extend_from_slice(&id.to_be_bytes());
+	packet.extend_from_slice(&IPV4_FLAG_DF.to_be_bytes());
+	packet.push(IPV4_DEFAULT_TTL);
+	packet.push(protocol);
+	packet.extend_from_slice(&[0, 0]);
+	packet.extend_from_slice(&src.octets());
+	packet.extend_from_slice(&dst.octets());
+
+	let sum = checksum(&packet, 0);
+	packet[10..12].copy_from_slice(&sum.to_be_bytes());
+	packet.extend_from_slice(payload);
+
+	Ok(packet)
+}
+
+/// Process the IPv4 packet `packet`, which is received by the interface `index`
+pub(super) fn receive(stack: &mut NetworkStack, index: usize, packet: &[u8]) {
+	if packet.len() < IPV4_HEADER_SIZE || packet[0] >> 4 != 4 {
+		return;
+	}
+
+	let header_len = usize::from(packet[0] & 0xF) * 4;
+	let total_len = usize::from(u16::from_be_bytes([packet[2], packet[3]]));
+	if header_len < IPV4_HEADER_SIZE
+		|| total_len < header_len
+		|| total_len > packet.len()
+		|| checksum(&packet[..header_len], 0) != 0
+	{
+		return;
+	}
+
+	// fragments aren't reassembled
+	if u16::from_be_bytes([packet[6], packet[7]]) & IPV4_FRAGMENT_MASK != 0 {
+		return;
+	}
+
+	let protocol = packet[9];
+	let src = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+	let dst = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+
+	let iface = &stack.interfaces[index];
+	let broadcast = dst.is_broadcast() || dst == iface.broadcast();
+	// the loopback interface receives the packets to all local addresses
+	let local = dst == iface.address
+		|| (iface.loopback && (iface.contains(dst) || stack.is_local_address(dst)));
+	if !broadcast && !local {
+		return;
+	}
+
+	let packet = &packet[..total_len];
+	let payload = &packet[header_len..];
+	match protocol {
+		IP_PROTOCOL_ICMP if !broadcast => icmp::receive(stack, src, dst, payload),
+		IP_PROTOCOL_UDP => {
+			if !udp::receive(stack, src, dst, payload) && !broadcast {
+				icmp::port_unreachable(stack, dst, src, packet);
+			}
+		}
+		IP_PROTOCOL_TCP if !broadcast => tcp::receive(stack, src, dst, payload),
+		_ => {}
+	}
+}
@@ -0,0 +1,585 @@
+//! Minimal TCP/IP stack
+//!
+//! The stack supports ARP, IPv4 without fragmentation, ICMP echo, UDP
+//! and basic TCP. Each registered network device becomes an interface
+//! with a static IPv4 configuration. The loopback interface `lo` uses the
+//! address 127.0.0.1/8 and carries the traffic to all local addresses.
+//! The first Ethernet interface uses the defaults of QEMU's user-mode
+//! network, i.e. the address 10.0.2.15/24 and the gateway 10.0.2.2.
+//! The devices aren't interrupt-driven, they are polled, whenever a
+//! socket operation waits for the network. In addition, the network
+//! task polls the devices and drives the timers of the stack, e.g. the
+//! retransmissions of TCP, while no socket operation is running. The task
+//! is only started, if an Ethernet interface exists. Sockets
+//! of the address family `AF_UNIX` connect tasks without the IP stack.
+
+mod arp;
+mod icmp;
+mod ip;
+mod socket;
+mod tcp;
+mod udp;
+mod unix;
+
+use crate::arch::irq::get_timer_ticks;
+use crate::drivers::net::{NetworkDevice, MAX_FRAME_SIZE};
+use crate::fd::{FileDescriptor, IoInterface};
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::task::NORMAL_PRIORITY;
+use crate::scheduler::{
+	get_io_interface, insert_io_interface, remove_io_interface, reschedule, sleep, spawn_named,
+};
+use crate::synch::spinlock::*;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+/// Address family of local sockets, which are bound to paths
+pub const AF_UNIX: i32 = 1;
+/// Address family of IPv4 sockets
+pub const AF_INET: i32 = 2;
+
+/// Connection-based byte stream (TCP)
+pub const SOCK_STREAM: i32 = 1;
+/// Connectionless datagrams (UDP)
+pub const SOCK_DGRAM: i32 = 2;
+/// Flag of the socket type: operations don't wait
+pub const SOCK_NONBLOCK: i32 = 0o4000;
+/// Flag of the socket type: close the descriptor on `exec` (ignored)
+pub const SOCK_CLOEXEC: i32 = 0o2000000;
+
+/// Protocol number of TCP
+pub const IPPROTO_TCP: i32 = 6;
+/// Protocol number of UDP
+pub const IPPROTO_UDP: i32 = 17;
+
+/// Hardware address of an Ethernet device
+pub type MacAddress = [u8; 6];
+
+/// Destination address of Ethernet broadcasts
+const BROADCAST_MAC: MacAddress = [0xFF; 6];
+/// Size of the header of an Ethernet frame
+const ETHERNET_HEADER_SIZE: usize = 14;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+
+/// Configuration of the loopback interface
+const LOOPBACK_ADDRESS: Ipv4Addr = Ipv4Addr::LOCALHOST;
+const LOOPBACK_NETMASK: Ipv4Addr = Ipv4Addr::new(255, 0, 0, 0);
+
+/// Default configuration of the first Ethernet interface (QEMU user-mode network)
+const DEFAULT_ADDRESS: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 15);
+const DEFAULT_NETMASK: Ipv4Addr = Ipv4Addr::new(255, 255, 255, 0);
+const DEFAULT_GATEWAY: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 2);
+
+/// Maximum number of frames, which are processed per interface and poll
+const POLL_BUDGET: usize = 64;
+
+/// Interval in timer ticks, in which the network task polls the network
+const POLL_INTERVAL: u64 = 10;
+
+/// First port, which is automatically assigned to sockets
+const EPHEMERAL_PORT_FIRST: u16 = 49152;
+
+/// Handle of a socket within the network stack
+type SocketHandle = usize;
+
+/// Address of a socket
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SocketAddress {
+	/// IPv4 address and port
+	Inet(SocketAddrV4),
+	/// Path of a local socket, which is empty, if the socket isn't bound
+	Unix(String),
+}
+
+/// Directions of a connection, which are shut down by `shutdown`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shutdown {
+	Read,
+	Write,
+	Both,
+}
+
+bitflags! {
+	/// Flags of `sendto` and `recvfrom`
+	#[derive(Debug, Copy, Clone)]
+	pub struct MessageFlags: i32 {
+		/// Receive data without removing it from the queue
+		const MSG_PEEK = 0x02;
+		/// Return `EAGAIN` instead of waiting
+		const MSG_DONTWAIT = 0x40;
+	}
+}
+
+/// Network interface, i.e. a device with an IPv4 configuration
+#[derive(Debug)]
+struct Interface {
+	name: String,
+	device: Arc<dyn NetworkDevice>,
+	/// the device returns all frames to the stack
+	loopback: bool,
+	mac: MacAddress,
+	address: Ipv4Addr,
+	netmask: Ipv4Addr,
+	gateway: Option<Ipv4Addr>,
+}
+
+impl Interface {
+	/// Returns true, if `addr` is part of the subnet of the interface
+	fn contains(&self, addr: Ipv4Addr) -> bool {
+		let mask = self.netmask.to_bits();
+
+		!self.address.is_unspecified() && addr.to_bits() & mask == self.address.to_bits() & mask
+	}
+
+	/// Returns the broadcast address of the subnet
+	fn broadcast(&self) -> Ipv4Addr {
+		Ipv4Addr::from_bits(self.address.to_bits() | !self.netmask.to_bits())
+	}
+
+	/// Send `payload` of the type `ethertype` to the device `dst`
+	fn send_frame(&self, dst: MacAddress, ethertype: u16, payload: &[u8]) -> io::Result<()> {
+		let mut frame = Vec::with_capacity(ETHERNET_HEADER_SIZE + payload.len());
+		frame.extend_from_slice(&dst);
+		frame.extend_from_slice(&self.mac);
+		frame.extend_from_slice(&ethertype.to_be_bytes());
+		frame.extend_from_slice(payload);
+
+		self.device.send(&frame)
+	}
+}
+
+/// Transport protocols, which have their own port numbers
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Protocol {
+	Udp,
+	Tcp,
+}
+
+/// State of the network stack, i.e. all interfaces and sockets
+#[derive(Debug)]
+struct NetworkStack {
+	interfaces: Vec<Interface>,
+	arp: arp::ArpCache,
+	udp: BTreeMap<SocketHandle, udp::UdpSocket>,
+	tcp: BTreeMap<SocketHandle, tcp::Tcb>,
+	next_handle: SocketHandle,
+	next_port: u16,
+	next_ip_id: u16,
+}
+
+impl NetworkStack {
+	const fn new() -> Self {
+		NetworkStack {
+			interfaces: Vec::new(),
+			arp: arp::ArpCache::new(),
+			udp: BTreeMap::new(),
+			tcp: BTreeMap::new(),
+			next_handle: 0,
+			next_port: EPHEMERAL_PORT_FIRST,
+			next_ip_id: 0,
+		}
+	}
+
+	fn allocate_handle(&mut self) -> SocketHandle {
+		self.next_handle += 1;
+		self.next_handle
+	}
+
+	/// Returns true, if `addr` is one of the addresses of the interfaces
+	fn is_local_address(&self, addr: Ipv4Addr) -> bool {
+		self.interfaces.iter().any(|iface| iface.address == addr)
+	}
+
+	/// Returns true, if a socket of `protocol` is bound to `addr`
+	fn is_port_in_use(&self, protocol: Protocol, addr: SocketAddrV4) -> bool {
+		let conflicts = |local: &SocketAddrV4| {
+			local.port() == addr.port()
+				&& (local.ip().is_unspecified()
+					|| addr.ip().is_unspecified()
+					|| local.ip() == addr.ip())
+		};
+
+		match protocol {
+			Protocol::Udp => self.udp.values().any(|socket| conflicts(&socket.local)),
+			Protocol::Tcp => self
+				.tcp
+				.values()
+				.any(|tcb| tcb.is_bound() && conflicts(&tcb.local)),
+		}
+	}
+
+	/// Returns a free port of `protocol`
+	fn allocate_port(&mut self, protocol: Protocol) -> io::Result<u16> {
+		for _ in EPHEMERAL_PORT_FIRST..=u16::MAX {
+			let port = self.next_port;
+			self.next_port = port.checked_add(1).unwrap_or(EPHEMERAL_PORT_FIRST);
+
+			let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port);
+			if !self.is_port_in_use(protocol, addr) {
+				return Ok(port);
+			}
+		}
+
+		Err(io::Error::EADDRINUSE)
+	}
+
+	/// Check that `addr` may be bound by a socket of `protocol`. Returns
+	/// the address, which is completed by an ephemeral port.
+	fn check_bind(&mut self, protocol: Protocol, addr: SocketAddrV4) -> io::Result<SocketAddrV4> {
+		if !addr.ip().is_unspecified() && !self.is_local_address(*addr.ip()) {
+			return Err(io::Error::EADDRNOTAVAIL);
+		}
+
+		if addr.port() == 0 {
+			Ok(SocketAddrV4::new(*addr.ip(), self.allocate_port(protocol)?))
+		} else if self.is_port_in_use(protocol, addr) {
+			Err(io::Error::EADDRINUSE)
+		} else {
+			Ok(addr)
+		}
+	}
+
+	/// Returns the index of the interface and the next hop to reach `dst`
+	fn route(&self, dst: Ipv4Addr) -> io::Result<(usize, Ipv4Addr)> {
+		let configured = |iface: &Interface| !iface.address.is_unspecified() && !iface.loopback;
+
+		// local destinations are reached by the loopback interface
+		if dst.is_loopback() || self.is_local_address(dst) {
+			if let Some(index) = self.interfaces.iter().position(|iface| iface.loopback) {
+				return Ok((index, dst));
+			}
+		}
+
+		if dst.is_broadcast() {
+			if let Some(index) = self.interfaces.iter().position(configured) {
+				return Ok((index, dst));
+			}
+		} else if let Some(index) = self.interfaces.iter().position(|iface| iface.contains(dst)) {
+			return Ok((index, dst));
+		} else if let Some(index) = self
+			.interfaces
+			.iter()
+			.position(|iface| configured(iface) && iface.gateway.is_some())
+		{
+			return Ok((index, self.interfaces[index].gateway.unwrap()));
+		}
+
+		Err(io::Error::ENETUNREACH)
+	}
+
+	/// Returns the address of the interface, which sends the packets to `dst`.
+	/// Packets to a local address are sent from this address.
+	fn source_address(&self, dst: Ipv4Addr) -> io::Result<Ipv4Addr> {
+		let (index, _) = self.route(dst)?;
+
+		if self.is_local_address(dst) {
+			Ok(dst)
+		} else {
+			Ok(self.interfaces[index].address)
+		}
+	}
+
+	/// Send `payload` of the transport protocol `protocol` from `src` to `dst`.
+	/// If `src` is unspecified, the address of the outgoing interface is used.
+	fn send_ipv4(
+		&mut self,
+		src: Ipv4Addr,
+		dst: Ipv4Addr,
+		protocol: u8,
+		payload: &[u8],
+	) -> io::Result<()> {
+		let (index, next_hop) = self.route(dst)?;
+		let src = if src.is_unspecified() {
+			self.source_address(dst)?
+		} else {
+			src
+		};
+
+		self.next_ip_id = self.next_ip_id.wrapping_add(1);
+		let packet = ip::build(src, dst, protocol, self.next_ip_id, payload)?;
+
+		let iface = &self.interfaces[index];
+		if iface.loopback {
+			// the loopback device doesn't need the address resolution
+			iface.send_frame(iface.mac, ETHERTYPE_IPV4, &packet)
+		} else if dst.is_broadcast() || dst == iface.broadcast() {
+			iface.send_frame(BROADCAST_MAC, ETHERTYPE_IPV4, &packet)
+		} else {
+			arp::send(self, index, next_hop, packet)
+		}
+	}
+
+	/// Process the Ethernet frame `frame`, which is received by the interface `index`
+	fn receive_frame(&mut self, index: usize, frame: &[u8]) {
+		if frame.len() < ETHERNET_HEADER_SIZE {
+			return;
+		}
+
+		let dst = &frame[0..6];
+		if dst != self.interfaces[index].mac && dst != BROADCAST_MAC {
+			return;
+		}
+
+		let payload = &frame[ETHERNET_HEADER_SIZE..];
+		match u16::from_be_bytes([frame[12], frame[13]]) {
+			ETHERTYPE_ARP => arp::receive(self, index, payload),
+			ETHERTYPE_IPV4 => ip::receive(self, index, payload),
+			_ => {}
+		}
+	}
+}
+
+/// State of the network stack
+static STACK: Spinlock<NetworkStack> = Spinlock::new(NetworkStack::new());
+
+/// Returns the current time in timer ticks
+fn now() -> u64 {
+	get_timer_ticks()
+}
+
+/// Create an interface for the network device `dev`
+pub(crate) fn add_interface(name: &str, dev: Arc<dyn NetworkDevice>) -> io::Result<()> {
+	let mut stack = STACK.lock();
+
+	if stack.interfaces.iter().any(|iface| iface.name == name) {
+		return Err(io::Error::EEXIST);
+	}
+
+	let mac = dev.mac_address();
+	let loopback = dev.is_loopback();
+	let (address, netmask, gateway) = if loopback {
+		(LOOPBACK_ADDRESS, LOOPBACK_NETMASK, None)
+	} else if stack.interfaces.iter().all(|iface| iface.loopback) {
+		(DEFAULT_ADDRESS, DEFAULT_NETMASK, Some(DEFAULT_GATEWAY))
+	} else {
+		(Ipv4Addr::UNSPECIFIED, Ipv4Addr::UNSPECIFIED, None)
+	};
+
+	info!(
+		"Interface {}: MAC {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}, address {}, netmask {}",
+		name, mac[0], mac[1], mac[2], mac[3], mac[4], mac[5], address, netmask
+	);
+
+	stack.interfaces.push(Interface {
+		name: String::from(name),
+		device: dev,
+		loopback,
+		mac,
+		address,
+		netmask,
+		gateway,
+	});
+
+	Ok(())
+}
+
+/// Assign the address `address` with the subnet mask `netmask` and the
+/// default gateway `gateway` to the interface `name`
+pub fn configure(
+	name: &str,
+	address: Ipv4Addr,
+	netmask: Ipv4Addr,
+	gateway: Option<Ipv4Addr>,
+) -> io::Result<()> {
+	let mut stack = STACK.lock();
+	let iface = stack
+		.interfaces
+		.iter_mut()
+		.find(|iface| iface.name == name)
+		.ok_or(io::Error::ENODEV)?;
+
+	iface.address = address;
+	iface.netmask = netmask;
+	iface.gateway = gateway;
+	info!(
+		"Interface {}: address {}, netmask {}",
+		name, address, netmask
+	);
+
+	Ok(())
+}
+
+/// Process all received frames and the timers of the protocols
+pub fn poll() {
+	poll_stack(&mut STACK.lock());
+}
+
+/// Receive the pending frames of all interfaces and drive the timers of `stack`
+fn poll_stack(stack: &mut NetworkStack) {
+	let mut frame = vec![0u8; MAX_FRAME_SIZE];
+
+	for index in 0..stack.interfaces.len() {
+		for _ in 0..POLL_BUDGET {
+			let Some(len) = stack.interfaces[index].device.receive(&mut frame) else {
+				break;
+			};
+			stack.receive_frame(index, &frame[..len]);
+		}
+	}
+
+	let now = now();
+	arp::poll(stack, now);
+	tcp::poll(stack, now);
+}
+
+/// Kernel task, which polls the network independent of the socket operations
+extern "C" fn network_task() {
+	loop {
+		// if the stack is in use, the network is polled in the next interval
+		if let Some(mut stack) = STACK.try_lock() {
+			poll_stack(&mut stack);
+		}
+
+		sleep(POLL_INTERVAL);
+	}
+}
+
+/// Start the network task, if an Ethernet interface exists. The traffic
+/// of the loopback interface is processed by the socket operations.
+pub(crate) fn init() {
+	if STACK.lock().interfaces.iter().all(|iface| iface.loopback) {
+		return;
+	}
+
+	if let Err(e) = spawn_named("network", network_task, NORMAL_PRIORITY) {
+		warn!("Unable to start the network task: {:?}", e);
+	}
+}
+
+/// Poll the network and evaluate `f`, until it returns a result. If
+/// `nonblocking` is true, `EAGAIN` is returned instead of waiting.
+fn wait_for<T, F>(nonblocking: bool, mut f: F) -> io::Result<T>
+where
+	F: FnMut(&mut NetworkStack) -> Option<io::Result<T>>,
+{
+	loop {
+		poll();
+
+		if let Some(result) = f(&mut STACK.lock()) {
+			return result;
+		} else if nonblocking {
+			return Err(io::Error::EAGAIN);
+		}
+
+		reschedule();
+	}
+}
+
+/// Create a socket of the address family `domain` with the type `kind`
+/// and the protocol `protocol`. Returns the file descriptor of the socket.
+pub(crate) fn socket(domain: i32, kind: i32, protocol: i32) -> io::Result<FileDescriptor> {
+	let nonblocking = kind & SOCK_NONBLOCK != 0;
+
+	let socket: Arc<dyn IoInterface> = match domain {
+		AF_INET => match (kind & !(SOCK_NONBLOCK | SOCK_CLOEXEC), protocol) {
+			(SOCK_STREAM, 0 | IPPROTO_TCP) => Arc::new(socket::InetSocket::new_tcp(nonblocking)),
+			(SOCK_DGRAM, 0 | IPPROTO_UDP) => Arc::new(socket::InetSocket::new_udp(nonblocking)),
+			(SOCK_STREAM | SOCK_DGRAM, _) => return Err(io::Error::EPROTONOSUPPORT),
+			_ => return Err(io::Error::EINVAL),
+		},
+		AF_UNIX => match (kind & !(SOCK_NONBLOCK | SOCK_CLOEXEC), protocol) {
+			(SOCK_STREAM, 0) => Arc::new(unix::UnixStream::new(nonblocking)),
+			(SOCK_DGRAM, 0) => Arc::new(unix::UnixDatagram::new(nonblocking)),
+			(SOCK_STREAM | SOCK_DGRAM, _) => return Err(io::Error::EPROTONOSUPPORT),
+			_ => return Err(io::Error::EINVAL),
+		},
+		_ => return Err(io::Error::EAFNOSUPPORT),
+	};
+
+	insert_io_interface(socket)
+}
+
+/// Create a pair of connected sockets of the address family `domain`
+/// with the type `kind` and the protocol `protocol`. Only `AF_UNIX`
+/// is supported. Returns the file descriptors of both sockets.
+pub(crate) fn socketpair(
+	domain: i32,
+	kind: i32,
+	protocol: i32,
+) -> io::Result<(FileDescriptor, FileDescriptor)> {
+	let nonblocking = kind & SOCK_NONBLOCK != 0;
+
+	let (first, second): (Arc<dyn IoInterface>, Arc<dyn IoInterface>) = match domain {
+		AF_UNIX => match (kind & !(SOCK_NONBLOCK | SOCK_CLOEXEC), protocol) {
+			(SOCK_STREAM, 0) => {
+				let (first, second) = unix::UnixStream::pair(nonblocking);
+				(Arc::new(first), Arc::new(second))
+			}
+			(SOCK_DGRAM, 0) => {
+				let (first, second) = unix::UnixDatagram::pair(nonblocking);
+				(Arc::new(first), Arc::new(second))
+			}
+			(SOCK_STREAM | SOCK_DGRAM, _) => return Err(io::Error::EPROTONOSUPPORT),
+			_ => return Err(io::Error::EINVAL),
+		},
+		AF_INET => return Err(io::Error::EOPNOTSUPP),
+		_ => return Err(io::Error::EAFNOSUPPORT),
+	};
+
+	let first = insert_io_interface(first)?;
+	match insert_io_interface(second) {
+		Ok(second) => Ok((first, second)),
+		Err(e) => {
+			let _ = remove_io_interface(first);
+			Err(e)
+		}
+	}
+}
+
+pub(crate) fn bind(fd: FileDescriptor, addr: SocketAddress) -> io::Result<()> {
+	get_io_interface(fd)?.bind(addr)
+}
+
+pub(crate) fn listen(fd: FileDescriptor, backlog: i32) -> io::Result<()> {
+	get_io_interface(fd)?.listen(backlog.max(0) as usize)
+}
+
+/// Wait for a connection of the listening socket `fd`. Returns the
+/// descriptor of the new socket and the address of the peer.
+pub(crate) fn accept(
+	fd: FileDescriptor,
+	nonblocking: bool,
+) -> io::Result<(FileDescriptor, SocketAddress)> {
+	let (socket, addr) = get_io_interface(fd)?.accept(nonblocking)?;
+
+	Ok((insert_io_interface(socket)?, addr))
+}
+
+pub(crate) fn connect(fd: FileDescriptor, addr: SocketAddress) -> io::Result<()> {
+	get_io_interface(fd)?.connect(addr)
+}
+
+pub(crate) fn sendto(
+	fd: FileDescriptor,
+	buf: &[u8],
+	flags: MessageFlags,
+	addr: Option<SocketAddress>,
+) -> io::Result<usize> {
+	get_io_interface(fd)?.sendto(buf, flags, addr)
+}
+
+pub(crate) fn recvfrom(
+	fd: FileDescriptor,
+	buf: &mut [u8],
+	flags: MessageFlags,
+) -> io::Result<(usize, Option<SocketAddress>)> {
+	get_io_interface(fd)?.recvfrom(buf, flags)
+}
+
+pub(crate) fn shutdown(fd: FileDescriptor, how: Shutdown) -> io::Result<()> {
+	get_io_interface(fd)?.shutdown(how)
+}
+
+pub(crate) fn getsockname(fd: FileDescriptor) -> io::Result<SocketAddress> {
+	get_io_interface(fd)?.local_address()
+}
+
+pub(crate) fn getpeername(fd: FileDescriptor) -> io::Result<SocketAddress> {
+	get_io_interface(fd)?.peer_address()
+}
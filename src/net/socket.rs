@@ -0,0 +1,232 @@
+//! Sockets of the address family `AF_INET`, which are used by file descriptors
+
+use crate::fd::{FileStatus, IoInterface};
+use crate::fs::{Attributes, NodeKind, Timestamps};
+use crate::io;
+use crate::net::{
+	poll, tcp, udp, wait_for, MessageFlags, Protocol, Shutdown, SocketAddress, SocketHandle, STACK,
+};
+use alloc::sync::Arc;
+use core::net::SocketAddrV4;
+
+/// Returns the IPv4 address of `addr`
+fn inet_address(addr: SocketAddress) -> io::Result<SocketAddrV4> {
+	match addr {
+		SocketAddress::Inet(addr) => Ok(addr),
+		_ => Err(io::Error::EAFNOSUPPORT),
+	}
+}
+
+/// TCP or UDP socket
+#[derive(Debug)]
+pub(super) struct InetSocket {
+	protocol: Protocol,
+	/// handle of the socket within the network stack
+	handle: SocketHandle,
+	/// operations return `EAGAIN` instead of waiting
+	nonblocking: bool,
+}
+
+impl InetSocket {
+	pub fn new_tcp(nonblocking: bool) -> Self {
+		InetSocket {
+			protocol: Protocol::Tcp,
+			handle: tcp::create(&mut STACK.lock()),
+			nonblocking,
+		}
+	}
+
+	pub fn new_udp(nonblocking: bool) -> Self {
+		InetSocket {
+			protocol: Protocol::Udp,
+			handle: udp::create(&mut STACK.lock()),
+			nonblocking,
+		}
+	}
+
+	/// Returns true, if the operation with the flags `flags` doesn't wait
+	fn is_nonblocking(&self, flags: MessageFlags) -> bool {
+		self.nonblocking || flags.contains(MessageFlags::MSG_DONTWAIT)
+	}
+}
+
+impl Drop for InetSocket {
+	fn drop(&mut self) {
+		let mut stack = STACK.lock();
+
+		match self.protocol {
+			Protocol::Tcp => tcp::close(&mut stack, self.handle),
+			Protocol::Udp => udp::close(&mut stack, self.handle),
+		}
+	}
+}
+
+impl IoInterface for InetSocket {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		self.recvfrom(buf, MessageFlags::empty())
+			.map(|(len, _)| len)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		self.sendto(buf, MessageFlags::empty(), None)
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		Ok(FileStatus {
+			ino: self.handle as u64,
+			kind: NodeKind::Socket,
+			nlink: 1,
+			file_size: 0,
+			attr: Attributes::default_for(NodeKind::Socket),
+			times: Timestamps::now(),
+		})
+	}
+
+	fn bind(&self, addr: SocketAddress) -> io::Result<()> {
+		let addr = inet_address(addr)?;
+		let mut stack = STACK.lock();
+
+		match self.protocol {
+			Protocol::Tcp => tcp::bind(&mut stack, self.handle, addr),
+			Protocol::Udp => udp::bind(&mut stack, self.handle, addr),
+		}
+	}
+
+	fn listen(&self, backlog: usize) -> io::Result<()> {
+		match self.protocol {
+			Protocol::Tcp => tcp::listen(&mut STACK.lock(), self.handle, backlog),
+			Protocol::Udp => Err(io::Error::EOPNOTSUPP),
+		}
+	}
+
+	fn accept(&self, nonblocking: bool) -> io::Result<(Arc<dyn IoInterface>, SocketAddress)> {
+		if self.protocol != Protocol::Tcp {
+			return Err(io::Error::EOPNOTSUPP);
+		}
+
+		let (handle, addr) = wait_for(self.nonblocking, |stack| tcp::accept(stack, self.handle))?;
+		let socket = InetSocket {
+			protocol: Protocol::Tcp,
+			handle,
+			nonblocking,
+		};
+
+		Ok((Arc::new(socket), SocketAddress::Inet(addr)))
+	}
+
+	fn connect(&self, addr: SocketAddress) -> io::Result<()> {
+		let addr = inet_address(addr)?;
+
+		match self.protocol {
+			Protocol::Tcp => {
+				tcp::connect(&mut STACK.lock(), self.handle, addr)?;
+				if self.nonblocking {
+					poll();
+					return Err(io::Error::EINPROGRESS);
+				}
+
+				wait_for(false, |stack| tcp::connect_result(stack, self.handle))
+			}
+			Protocol::Udp => udp::connect(&mut STACK.lock(), self.handle, addr),
+		}
+	}
+
+	fn sendto(
+		&self,
+		buf: &[u8],
+		flags: MessageFlags,
+		addr: Option<SocketAddress>,
+	) -> io::Result<usize> {
+		let nonblocking = self.is_nonblocking(flags);
+
+		match self.protocol {
+			// the destination of a connection is fixed
+			Protocol::Tcp => {
+				let mut written = 0;
+
+				while written < buf.len() {
+					match wait_for(nonblocking, |stack| {
+						tcp::send(stack, self.handle, &buf[written..])
+					}) {
+						Ok(len) => written += len,
+						Err(e) if written == 0 => return Err(e),
+						Err(_) => break,
+					}
+				}
+
+				Ok(written)
+			}
+			Protocol::Udp => {
+				let addr = addr.map(inet_address).transpose()?;
+				let len = udp::send(&mut STACK.lock(), self.handle, buf, addr)?;
+				// send the datagram, if the address of the next hop is resolved
+				poll();
+
+				Ok(len)
+			}
+		}
+	}
+
+	fn recvfrom(
+		&self,
+		buf: &mut [u8],
+		flags: MessageFlags,
+	) -> io::Result<(usize, Option<SocketAddress>)> {
+		let nonblocking = self.is_nonblocking(flags);
+		let peek = flags.contains(MessageFlags::MSG_PEEK);
+
+		match self.protocol {
+			Protocol::Tcp => {
+				let len = wait_for(nonblocking, |stack| {
+					tcp::recv(stack, self.handle, buf, peek)
+				})?;
+				Ok((len, None))
+			}
+			Protocol::Udp => {
+				let (len, addr) = wait_for(nonblocking, |stack| {
+					udp::recv(stack, self.handle, buf, peek)
+				})?;
+				Ok((len, Some(SocketAddress::Inet(addr))))
+			}
+		}
+	}
+
+	fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+		let mut stack = STACK.lock();
+
+		match self.protocol {
+			Protocol::Tcp => tcp::shutdown(&mut stack, self.handle, how),
+			Protocol::Udp => {
+				if stack.udp[&self.handle].remote.is_none() {
+					return Err(io::Error::ENOTCONN);
+				}
+				if how != Shutdown::Write {
+					udp::shutdown_read(&mut stack, self.handle);
+				}
+				Ok(())
+			}
+		}
+	}
+
+	fn local_address(&self) -> io::Result<SocketAddress> {
+		let stack = STACK.lock();
+
+		let addr = match self.protocol {
+			Protocol::Tcp => tcp::local_address(&stack, self.handle),
+			Protocol::Udp => stack.udp[&self.handle].local,
+		};
+
+		Ok(SocketAddress::Inet(addr))
+	}
+
+	fn peer_address(&self) -> io::Result<SocketAddress> {
+		let stack = STACK.lock();
+
+		let addr = match self.protocol {
+			Protocol::Tcp => tcp::peer_address(&stack, self.handle)?,
+			Protocol::Udp => stack.udp[&self.handle].remote.ok_or(io::Error::ENOTCONN)?,
+		};
+
+		Ok(SocketAddress::Inet(addr))
+	}
+}
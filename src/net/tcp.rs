@@ -0,0 +1,1186 @@
+//! Transmission Control Protocol (TCP)
+//!
+//! The implementation provides the connection management and a reliable,
+//! ordered byte stream. Lost segments are retransmitted after a timeout,
+//! which is doubled on each retry. Segments, which arrive out of order,
+//! are dropped and retransmitted by the peer. Congestion control,
+//! selective acknowledgements and urgent data aren't supported.
+
+use crate::arch::processor::{get_random, get_timestamp};
+use crate::consts::TIMER_FREQ;
+use crate::io;
+use crate::net::ip::{checksum, pseudo_header_sum, IPV4_HEADER_SIZE, IPV4_MTU, IP_PROTOCOL_TCP};
+use crate::net::{now, NetworkStack, Protocol, Shutdown, SocketHandle};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::net::{Ipv4Addr, SocketAddrV4};
+use core::ops::Range;
+
+// flags of a segment
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+
+// options
+const TCP_OPTION_END: u8 = 0;
+const TCP_OPTION_NOP: u8 = 1;
+const TCP_OPTION_MSS: u8 = 2;
+
+/// Size of the header without options
+const TCP_HEADER_SIZE: usize = 20;
+/// Maximum segment size, which is announced to the peer
+const TCP_MSS: usize = IPV4_MTU - IPV4_HEADER_SIZE - TCP_HEADER_SIZE;
+/// Maximum segment size, if the peer doesn't announce one
+const TCP_DEFAULT_MSS: usize = 536;
+/// Size of the send and of the receive buffer of a connection
+const TCP_BUFFER_SIZE: usize = 16 * 1024;
+
+/// Initial retransmission timeout in timer ticks
+const TCP_INITIAL_RTO: u64 = TIMER_FREQ as u64;
+/// Maximum retransmission timeout in timer ticks
+const TCP_MAX_RTO: u64 = 30 * TIMER_FREQ as u64;
+/// Number of retransmissions, before the connection is aborted
+const TCP_MAX_RETRIES: u32 = 8;
+/// Duration of the state TIME-WAIT in timer ticks
+const TCP_TIME_WAIT: u64 = 2 * TIMER_FREQ as u64;
+/// Time in timer ticks, after which a closed socket in FIN-WAIT-2 is released
+const TCP_FIN_WAIT2_TIMEOUT: u64 = 60 * TIMER_FREQ as u64;
+/// Maximum number of pending connections of a listening socket
+const TCP_MAX_BACKLOG: usize = 128;
+
+/// States of a connection, see RFC 793
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+	Closed,
+	Listen,
+	SynSent,
+	SynReceived,
+	Established,
+	FinWait1,
+	FinWait2,
+	CloseWait,
+	Closing,
+	LastAck,
+	TimeWait,
+}
+
+/// Returns true, if the sequence number `a` precedes `b`
+fn seq_lt(a: u32, b: u32) -> bool {
+	(a.wrapping_sub(b) as i32) < 0
+}
+
+/// Returns true, if the sequence number `a` precedes or equals `b`
+fn seq_le(a: u32, b: u32) -> bool {
+	(a.wrapping_sub(b) as i32) <= 0
+}
+
+/// Returns an initial sequence number, which is hard to guess
+fn initial_sequence_number() -> u32 {
+	get_random().unwrap_or_else(get_timestamp) as u32
+}
+
+/// Received segment
+struct Segment<'a> {
+	src_port: u16,
+	dst_port: u16,
+	seq: u32,
+	ack: u32,
+	flags: u8,
+	window: usize,
+	/// maximum segment size, which is announced by the peer
+	mss: Option<usize>,
+	data: &'a [u8],
+}
+
+impl<'a> Segment<'a> {
+	/// Parse the segment `segment`, which `src` sends to `dst`
+	fn parse(src: Ipv4Addr, dst: Ipv4Addr, segment: &'a [u8]) -> Option<Self> {
+		if segment.len() < TCP_HEADER_SIZE
+			|| checksum(
+				segment,
+				pseudo_header_sum(src, dst, IP_PROTOCOL_TCP, segment.len()),
+			) != 0
+		{
+			return None;
+		}
+
+		let header_len = usize::from(segment[12] >> 4) * 4;
+		if header_len < TCP_HEADER_SIZE || header_len > segment.len() {
+			return None;
+		}
+
+		let mut mss = None;
+		let mut options = &segment[TCP_HEADER_SIZE..header_len];
+		while let Some(&kind) = options.first() {
+			match kind {
+				TCP_OPTION_END => break,
+				TCP_OPTION_NOP => options = &options[1..],
+				_ => {
+					let len = usize::from(*options.get(1)?);
+					if len < 2 || len > options.len() {
+						return None;
+					}
+					if kind == TCP_OPTION_MSS && len == 4 {
+						mss = Some(usize::from(u16::from_be_bytes([options[2], options[3]])));
+					}
+					options = &options[len..];
+				}
+			}
+		}
+
+		Some(Segment {
+			src_port: u16::from_be_bytes([segment[0], segment[1]]),
+			dst_port: u16::from_be_bytes([segment[2], segment[3]]),
+			seq: u32::from_be_bytes(segment[4..8].try_into().unwrap()),
+			ack: u32::from_be_bytes(segment[8..12].try_into().unwrap()),
+			flags: segment[13],
+			window: usize::from(u16::from_be_bytes([segment[14], segment[15]])),
+			mss,
+			data: &segment[header_len..],
+		})
+	}
+
+	/// Returns the number of sequence numbers, which the segment occupies
+	fn len(&self) -> u32 {
+		let mut len = self.data.len() as u32;
+		if self.flags & TCP_SYN != 0 {
+			len += 1;
+		}
+		if self.flags & TCP_FIN != 0 {
+			len += 1;
+		}
+
+		len
+	}
+}
+
+/// Returns a segment with the given header fields. SYN segments
+/// announce the maximum segment size of the stack.
+fn build_segment(
+	local: SocketAddrV4,
+	remote: SocketAddrV4,
+	seq: u32,
+	ack: u32,
+	flags: u8,
+	window: usize,
+	data: &[u8],
+) -> Vec<u8> {
+	let header_len = if flags & TCP_SYN != 0 {
+		TCP_HEADER_SIZE + 4
+	} else {
+		TCP_HEADER_SIZE
+	};
+	let len = header_len + data.len();
+
+	let mut segment = Vec::with_capacity(len);
+	segment.extend_from_slice(&local.port().to_be_bytes());
+	segment.extend_from_slice(&remote.port().to_be_bytes());
+	segment.extend_from_slice(&seq.to_be_bytes());
+	segment.extend_from_slice(&ack.to_be_bytes());
+	segment.push((header_len as u8 / 4) << 4);
+	segment.push(flags);
+	segment.extend_from_slice(&(window.min(usize::from(u16::MAX)) as u16).to_be_bytes());
+	segment.extend_from_slice(&[0, 0, 0, 0]);
+	if flags & TCP_SYN != 0 {
+		segment.extend_from_slice(&[TCP_OPTION_MSS, 4]);
+		segment.extend_from_slice(&(TCP_MSS as u16).to_be_bytes());
+	}
+	segment.extend_from_slice(data);
+
+	let sum = checksum(
+		&segment,
+		pseudo_header_sum(*local.ip(), *remote.ip(), IP_PROTOCOL_TCP, len),
+	);
+	segment[16..18].copy_from_slice(&sum.to_be_bytes());
+
+	segment
+}
+
+/// Transmission Control Block, i.e. the state of a connection
+#[derive(Debug)]
+pub(super) struct Tcb {
+	state: State,
+	pub local: SocketAddrV4,
+	remote: SocketAddrV4,
+	/// the socket owns the local port, i.e. it is bound or connected
+	bound: bool,
+	/// initial send sequence number
+	iss: u32,
+	/// oldest unacknowledged sequence number
+	snd_una: u32,
+	/// next sequence number, which is sent
+	snd_nxt: u32,
+	/// highest sequence number, which has been sent
+	snd_max: u32,
+	/// receive window of the peer
+	snd_wnd: usize,
+	/// next sequence number, which is expected from the peer
+	rcv_nxt: u32,
+	/// window, which is announced by the last segment
+	rcv_wnd: usize,
+	/// maximum segment size of the peer
+	mss: usize,
+	/// data, which isn't acknowledged, starting at `snd_una`
+	send_buffer: VecDeque<u8>,
+	/// received data, which isn't read by the application
+	recv_buffer: VecDeque<u8>,
+	/// the application has closed the sending direction
+	fin_queued: bool,
+	/// the FIN is sent, it follows the data of the send buffer
+	fin_sent: bool,
+	/// the peer has closed its sending direction
+	fin_received: bool,
+	/// the application has closed the receiving direction
+	read_shutdown: bool,
+	/// time of the next retransmission
+	retransmit_at: Option<u64>,
+	/// current retransmission timeout
+	rto: u64,
+	/// number of retransmissions without progress
+	retries: u32,
+	/// time, when the connection is closed in TIME-WAIT or FIN-WAIT-2
+	close_at: Option<u64>,
+	/// error, which is reported by the next operation
+	error: Option<io::Error>,
+	/// no socket of the application refers to the connection, i.e. the
+	/// socket is closed or the connection isn't established yet
+	orphan: bool,
+	/// listening socket of a connection, which isn't accepted
+	listener: Option<SocketHandle>,
+	/// maximum number of pending connections of a listening socket
+	backlog: usize,
+	/// established connections, which aren't accepted
+	accept_queue: VecDeque<SocketHandle>,
+}
+
+impl Tcb {
+	fn new() -> Self {
+		Tcb {
+			state: State::Closed,
+			local: SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+			remote: SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+			bound: false,
+			iss: 0,
+			snd_una: 0,
+			snd_nxt: 0,
+			snd_max: 0,
+			snd_wnd: 0,
+			rcv_nxt: 0,
+			rcv_wnd: 0,
+			mss: TCP_DEFAULT_MSS,
+			send_buffer: VecDeque::new(),
+			recv_buffer: VecDeque::new(),
+			fin_queued: false,
+			fin_sent: false,
+			fin_received: false,
+			read_shutdown: false,
+			retransmit_at: None,
+			rto: TCP_INITIAL_RTO,
+			retries: 0,
+			close_at: None,
+			error: None,
+			orphan: false,
+			listener: None,
+			backlog: 0,
+			accept_queue: VecDeque::new(),
+		}
+	}
+
+	/// Returns true, if the socket prevents other sockets from binding its port
+	pub fn is_bound(&self) -> bool {
+		self.bound && !self.orphan
+	}
+
+	/// Returns true, if the connection is synchronized, i.e. both SYNs are acknowledged
+	fn is_synchronized(&self) -> bool {
+		!matches!(
+			self.state,
+			State::Closed | State::Listen | State::SynSent | State::SynReceived
+		)
+	}
+
+	/// Returns the free space of the receive buffer
+	fn window(&self) -> usize {
+		TCP_BUFFER_SIZE - self.recv_buffer.len()
+	}
+
+	/// Returns true, if `segment` continues the received data. Segments,
+	/// which arrive out of order, aren't acceptable.
+	fn accepts(&self, segment: &Segment<'_>) -> bool {
+		let len = segment.len();
+
+		if len == 0 {
+			segment.seq == self.rcv_nxt
+		} else {
+			seq_le(segment.seq, self.rcv_nxt) && seq_lt(self.rcv_nxt, segment.seq.wrapping_add(len))
+		}
+	}
+
+	/// Start the next connection by sending a SYN
+	fn open(&mut self, remote: SocketAddrV4) {
+		self.remote = remote;
+		self.iss = initial_sequence_number();
+		self.snd_una = self.iss;
+		self.snd_nxt = self.iss.wrapping_add(1);
+		self.snd_max = self.snd_nxt;
+		self.retransmit_at = Some(now() + self.rto);
+	}
+
+	/// Arm the retransmission timer, if it isn't running
+	fn arm_timer(&mut self) {
+		if self.retransmit_at.is_none() {
+			self.retransmit_at = Some(now() + self.rto);
+		}
+	}
+
+	/// Close the connection and report `error` to the application
+	fn abort(&mut self, error: io::Error) {
+		self.state = State::Closed;
+		self.retransmit_at = None;
+		self.close_at = None;
+		self.send_buffer.clear();
+		if !self.orphan {
+			self.error = Some(error);
+		}
+	}
+}
+
+/// Send a segment of the connection `handle` with the sequence number
+/// `seq` and the flags `flags`. `range` specifies the data within the
+/// send buffer.
+fn transmit(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	seq: u32,
+	flags: u8,
+	range: Range<usize>,
+) {
+	let Some(tcb) = stack.tcp.get_mut(&handle) else {
+		return;
+	};
+
+	let window = tcb.window();
+	tcb.rcv_wnd = window;
+	let ack = if flags & TCP_ACK != 0 { tcb.rcv_nxt } else { 0 };
+	let data: Vec<u8> = tcb.send_buffer.range(range).copied().collect();
+
+	let mut end = seq.wrapping_add(data.len() as u32);
+	if flags & (TCP_SYN | TCP_FIN) != 0 {
+		end = end.wrapping_add(1);
+	}
+	if seq_lt(tcb.snd_max, end) {
+		tcb.snd_max = end;
+	}
+
+	let segment = build_segment(tcb.local, tcb.remote, seq, ack, flags, window, &data);
+	let (src, dst) = (*tcb.local.ip(), *tcb.remote.ip());
+	// lost segments are repeated by the retransmission
+	let _ = stack.send_ipv4(src, dst, IP_PROTOCOL_TCP, &segment);
+}
+
+/// Acknowledge the received data of the connection `handle`
+fn send_ack(stack: &mut NetworkStack, handle: SocketHandle) {
+	if let Some(tcb) = stack.tcp.get(&handle) {
+		transmit(stack, handle, tcb.snd_nxt, TCP_ACK, 0..0);
+	}
+}
+
+/// Reject the segment `segment`, which `remote` sends to `local`
+fn send_reset(
+	stack: &mut NetworkStack,
+	local: SocketAddrV4,
+	remote: SocketAddrV4,
+	segment: &Segment<'_>,
+) {
+	if segment.flags & TCP_RST != 0 {
+		return;
+	}
+
+	let reset = if segment.flags & TCP_ACK != 0 {
+		build_segment(local, remote, segment.ack, 0, TCP_RST, 0, &[])
+	} else {
+		let ack = segment.seq.wrapping_add(segment.len());
+		build_segment(local, remote, 0, ack, TCP_RST | TCP_ACK, 0, &[])
+	};
+
+	let _ = stack.send_ipv4(*local.ip(), *remote.ip(), IP_PROTOCOL_TCP, &reset);
+}
+
+/// Send new data and the FIN of the connection `handle`, as far as the window of the peer allows
+fn output(stack: &mut NetworkStack, handle: SocketHandle) {
+	loop {
+		let Some(tcb) = stack.tcp.get_mut(&handle) else {
+			return;
+		};
+		if !tcb.is_synchronized() || tcb.state == State::TimeWait {
+			return;
+		}
+
+		let in_flight = tcb.snd_nxt.wrapping_sub(tcb.snd_una) as usize;
+		let unsent = tcb.send_buffer.len().saturating_sub(in_flight);
+		let usable = tcb.snd_wnd.saturating_sub(in_flight);
+
+		if unsent > 0 && usable > 0 {
+			let len = unsent.min(usable).min(tcb.mss);
+			let seq = tcb.snd_nxt;
+			let flags = if len == unsent {
+				TCP_ACK | TCP_PSH
+			} else {
+				TCP_ACK
+			};
+
+			tcb.snd_nxt = seq.wrapping_add(len as u32);
+			tcb.arm_timer();
+			transmit(stack, handle, seq, flags, in_flight..in_flight + len);
+		} else if unsent == 0 && tcb.fin_queued && !tcb.fin_sent {
+			let seq = tcb.snd_nxt;
+
+			tcb.snd_nxt = seq.wrapping_add(1);
+			tcb.fin_sent = true;
+			tcb.arm_timer();
+			transmit(stack, handle, seq, TCP_FIN | TCP_ACK, 0..0);
+		} else {
+			// a zero window is probed by the retransmission timer
+			if unsent > 0 {
+				tcb.arm_timer();
+			}
+			return;
+		}
+	}
+}
+
+/// Repeat the unacknowledged segments of the connection `handle`
+fn retransmit(stack: &mut NetworkStack, handle: SocketHandle, now: u64) {
+	let Some(tcb) = stack.tcp.get_mut(&handle) else {
+		return;
+	};
+
+	tcb.retries += 1;
+	if tcb.retries > TCP_MAX_RETRIES {
+		tcb.abort(io::Error::ETIMEDOUT);
+		return;
+	}
+	tcb.rto = (tcb.rto * 2).min(TCP_MAX_RTO);
+	tcb.retransmit_at = Some(now + tcb.rto);
+
+	match tcb.state {
+		State::SynSent => {
+			let iss = tcb.iss;
+			transmit(stack, handle, iss, TCP_SYN, 0..0);
+		}
+		State::SynReceived => {
+			let iss = tcb.iss;
+			transmit(stack, handle, iss, TCP_SYN | TCP_ACK, 0..0);
+		}
+		_ => {
+			// go back to the oldest unacknowledged byte
+			tcb.snd_nxt = tcb.snd_una;
+			tcb.fin_sent = false;
+
+			if tcb.snd_wnd == 0 && !tcb.send_buffer.is_empty() {
+				// probe the window by a single byte
+				let seq = tcb.snd_una;
+				tcb.snd_nxt = seq.wrapping_add(1);
+				transmit(stack, handle, seq, TCP_ACK, 0..1);
+			} else {
+				output(stack, handle);
+			}
+		}
+	}
+}
+
+/// Release the connection `handle`, if it is closed and the application
+/// doesn't use it anymore
+fn release(stack: &mut NetworkStack, handle: SocketHandle) {
+	if stack
+		.tcp
+		.get(&handle)
+		.is_some_and(|tcb| tcb.state == State::Closed && tcb.orphan)
+	{
+		stack.tcp.remove(&handle);
+	}
+}
+
+/// Process a segment, which is sent to the listening socket `handle`
+fn receive_listen(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	local: SocketAddrV4,
+	remote: SocketAddrV4,
+	segment: &Segment<'_>,
+) {
+	if segment.flags & TCP_RST != 0 {
+		return;
+	} else if segment.flags & TCP_ACK != 0 {
+		send_reset(stack, local, remote, segment);
+		return;
+	} else if segment.flags & TCP_SYN == 0 {
+		return;
+	}
+
+	// drop the SYN, if the backlog is full
+	let pending = stack
+		.tcp
+		.values()
+		.filter(|tcb| tcb.listener == Some(handle))
+		.count();
+	if pending >= stack.tcp[&handle].backlog {
+		return;
+	}
+
+	let mut tcb = Tcb::new();
+	tcb.state = State::SynReceived;
+	tcb.local = local;
+	tcb.open(remote);
+	tcb.rcv_nxt = segment.seq.wrapping_add(1);
+	tcb.snd_wnd = segment.window;
+	tcb.mss = segment.mss.unwrap_or(TCP_DEFAULT_MSS).clamp(1, TCP_MSS);
+	tcb.listener = Some(handle);
+	tcb.orphan = true;
+
+	let iss = tcb.iss;
+	let child = stack.allocate_handle();
+	stack.tcp.insert(child, tcb);
+	transmit(stack, child, iss, TCP_SYN | TCP_ACK, 0..0);
+}
+
+/// Process a segment, which answers the SYN of the connection `handle`
+fn receive_syn_sent(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	local: SocketAddrV4,
+	remote: SocketAddrV4,
+	segment: &Segment<'_>,
+) {
+	let tcb = stack.tcp.get_mut(&handle).unwrap();
+
+	if segment.flags & TCP_ACK != 0
+		&& (seq_le(segment.ack, tcb.iss) || seq_lt(tcb.snd_max, segment.ack))
+	{
+		send_reset(stack, local, remote, segment);
+		return;
+	}
+
+	if segment.flags & TCP_RST != 0 {
+		if segment.flags & TCP_ACK != 0 {
+			tcb.abort(io::Error::ECONNREFUSED);
+		}
+		return;
+	}
+
+	// simultaneous open isn't supported
+	if segment.flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK {
+		tcb.rcv_nxt = segment.seq.wrapping_add(1);
+		tcb.snd_una = segment.ack;
+		tcb.snd_wnd = segment.window;
+		tcb.mss = segment.mss.unwrap_or(TCP_DEFAULT_MSS).clamp(1, TCP_MSS);
+		tcb.state = State::Established;
+		tcb.retransmit_at = None;
+		tcb.retries = 0;
+		tcb.rto = TCP_INITIAL_RTO;
+
+		send_ack(stack, handle);
+	}
+}
+
+/// Process a segment of the connection `handle`, which has received a SYN
+fn receive_synchronized(stack: &mut NetworkStack, handle: SocketHandle, segment: &Segment<'_>) {
+	let now = now();
+	let tcb = stack.tcp.get_mut(&handle).unwrap();
+
+	// the peer repeats its SYN, because our SYN-ACK is lost
+	if tcb.state == State::SynReceived
+		&& segment.flags & TCP_SYN != 0
+		&& segment.seq.wrapping_add(1) == tcb.rcv_nxt
+	{
+		let iss = tcb.iss;
+		transmit(stack, handle, iss, TCP_SYN | TCP_ACK, 0..0);
+		return;
+	}
+
+	if !tcb.accepts(segment) {
+		if segment.flags & TCP_RST == 0 {
+			send_ack(stack, handle);
+		}
+		return;
+	}
+
+	if segment.flags & TCP_RST != 0 {
+		tcb.abort(io::Error::ECONNRESET);
+		release(stack, handle);
+		return;
+	}
+
+	if segment.flags & TCP_SYN != 0 {
+		send_ack(stack, handle);
+		return;
+	} else if segment.flags & TCP_ACK == 0 {
+		return;
+	}
+
+	if tcb.state == State::SynReceived {
+		if seq_lt(tcb.snd_una, segment.ack) && seq_le(segment.ack, tcb.snd_max) {
+			tcb.state = State::Established;
+			tcb.snd_una = segment.ack;
+			tcb.retransmit_at = None;
+			tcb.retries = 0;
+			tcb.rto = TCP_INITIAL_RTO;
+			tcb.orphan = false;
+
+			if let Some(listener) = tcb.listener {
+				if let Some(listener) = stack.tcp.get_mut(&listener) {
+					listener.accept_queue.push_back(handle);
+				}
+			}
+		} else {
+			let (local, remote) = (tcb.local, tcb.remote);
+			send_reset(stack, local, remote, segment);
+			return;
+		}
+	}
+
+	let tcb = stack.tcp.get_mut(&handle).unwrap();
+	if seq_lt(tcb.snd_max, segment.ack) {
+		// the segment acknowledges data, which hasn't been sent
+		send_ack(stack, handle);
+		return;
+	}
+
+	if seq_lt(tcb.snd_una, segment.ack) {
+		let data_end = tcb.snd_una.wrapping_add(tcb.send_buffer.len() as u32);
+		let fin_acked = tcb.fin_queued && segment.ack == data_end.wrapping_add(1);
+		let acked = if fin_acked {
+			tcb.send_buffer.len()
+		} else {
+			segment.ack.wrapping_sub(tcb.snd_una) as usize
+		};
+
+		tcb.send_buffer.drain(..acked);
+		tcb.snd_una = segment.ack;
+		if seq_lt(tcb.snd_nxt, segment.ack) {
+			tcb.snd_nxt = segment.ack;
+		}
+		tcb.retries = 0;
+		tcb.rto = TCP_INITIAL_RTO;
+		tcb.retransmit_at = if tcb.snd_una == tcb.snd_max {
+			None
+		} else {
+			Some(now + tcb.rto)
+		};
+
+		if fin_acked {
+			tcb.fin_sent = true;
+			match tcb.state {
+				State::FinWait1 => {
+					tcb.state = State::FinWait2;
+					if tcb.orphan {
+						tcb.close_at = Some(now + TCP_FIN_WAIT2_TIMEOUT);
+					}
+				}
+				State::Closing => {
+					tcb.state = State::TimeWait;
+					tcb.close_at = Some(now + TCP_TIME_WAIT);
+				}
+				State::LastAck => {
+					tcb.state = State::Closed;
+					release(stack, handle);
+					return;
+				}
+				_ => {}
+			}
+		}
+	}
+	if seq_le(tcb.snd_una, segment.ack) {
+		tcb.snd_wnd = segment.window;
+	}
+
+	let mut ack_now = false;
+	let mut complete = true;
+	if !segment.data.is_empty()
+		&& matches!(
+			tcb.state,
+			State::Established | State::FinWait1 | State::FinWait2
+		) {
+		// skip data, which has already been received
+		let skip = (tcb.rcv_nxt.wrapping_sub(segment.seq) as usize).min(segment.data.len());
+		let data = &segment.data[skip..];
+
+		let accepted = if tcb.read_shutdown {
+			data.len()
+		} else {
+			let accepted = data.len().min(tcb.window());
+			tcb.recv_buffer.extend(&data[..accepted]);
+			accepted
+		};
+
+		tcb.rcv_nxt = tcb.rcv_nxt.wrapping_add(accepted as u32);
+		complete = accepted == data.len();
+		ack_now = true;
+	}
+
+	if segment.flags & TCP_FIN != 0 && complete {
+		if !tcb.fin_received {
+			tcb.fin_received = true;
+			tcb.rcv_nxt = tcb.rcv_nxt.wrapping_add(1);
+
+			match tcb.state {
+				State::Established => tcb.state = State::CloseWait,
+				State::FinWait1 => tcb.state = State::Closing,
+				State::FinWait2 => {
+					tcb.state = State::TimeWait;
+					tcb.close_at = Some(now + TCP_TIME_WAIT);
+				}
+				_ => {}
+			}
+		} else if tcb.state == State::TimeWait {
+			tcb.close_at = Some(now + TCP_TIME_WAIT);
+		}
+		ack_now = true;
+	}
+
+	if ack_now {
+		send_ack(stack, handle);
+	}
+	output(stack, handle);
+}
+
+/// Process the segment `segment`, which `src` sends to `dst`
+pub(super) fn receive(stack: &mut NetworkStack, src: Ipv4Addr, dst: Ipv4Addr, segment: &[u8]) {
+	let Some(segment) = Segment::parse(src, dst, segment) else {
+		return;
+	};
+	let local = SocketAddrV4::new(dst, segment.dst_port);
+	let remote = SocketAddrV4::new(src, segment.src_port);
+
+	let connection = stack.tcp.iter().find(|(_, tcb)| {
+		tcb.state != State::Closed
+			&& tcb.state != State::Listen
+			&& tcb.local == local
+			&& tcb.remote == remote
+	});
+	let handle = connection
+		.or_else(|| {
+			stack.tcp.iter().find(|(_, tcb)| {
+				tcb.state == State::Listen
+					&& tcb.local.port() == local.port()
+					&& (tcb.local.ip().is_unspecified() || *tcb.local.ip() == dst)
+			})
+		})
+		.map(|(handle, tcb)| (*handle, tcb.state));
+
+	match handle {
+		Some((handle, State::Listen)) => receive_listen(stack, handle, local, remote, &segment),
+		Some((handle, State::SynSent)) => receive_syn_sent(stack, handle, local, remote, &segment),
+		Some((handle, _)) => receive_synchronized(stack, handle, &segment),
+		None => send_reset(stack, local, remote, &segment),
+	}
+}
+
+/// Process the timers of all connections
+pub(super) fn poll(stack: &mut NetworkStack, now: u64) {
+	let handles: Vec<SocketHandle> = stack.tcp.keys().copied().collect();
+
+	for handle in handles {
+		let Some(tcb) = stack.tcp.get_mut(&handle) else {
+			continue;
+		};
+
+		if tcb.close_at.is_some_and(|time| now >= time) {
+			tcb.state = State::Closed;
+			tcb.close_at = None;
+			tcb.retransmit_at = None;
+		} else if tcb.retransmit_at.is_some_and(|time| now >= time) {
+			retransmit(stack, handle, now);
+		}
+
+		release(stack, handle);
+	}
+}
+
+/// Create a socket, which isn't bound
+pub(super) fn create(stack: &mut NetworkStack) -> SocketHandle {
+	let handle = stack.allocate_handle();
+	stack.tcp.insert(handle, Tcb::new());
+
+	handle
+}
+
+/// Close the socket `handle`. Established connections are shut down
+/// in the background, before the socket is released.
+pub(super) fn close(stack: &mut NetworkStack, handle: SocketHandle) {
+	let Some(tcb) = stack.tcp.get_mut(&handle) else {
+		return;
+	};
+
+	match tcb.state {
+		State::Listen => {
+			// reset all connections, which aren't accepted
+			let children: Vec<SocketHandle> = stack
+				.tcp
+				.iter()
+				.filter(|(_, tcb)| tcb.listener == Some(handle))
+				.map(|(child, _)| *child)
+				.collect();
+			for child in children {
+				let tcb = &stack.tcp[&child];
+				let reset = build_segment(tcb.local, tcb.remote, tcb.snd_nxt, 0, TCP_RST, 0, &[]);
+				let (src, dst) = (*tcb.local.ip(), *tcb.remote.ip());
+				let _ = stack.send_ipv4(src, dst, IP_PROTOCOL_TCP, &reset);
+				stack.tcp.remove(&child);
+			}
+			stack.tcp.remove(&handle);
+		}
+		State::Closed | State::SynSent => {
+			stack.tcp.remove(&handle);
+		}
+		_ => {
+			tcb.orphan = true;
+			tcb.read_shutdown = true;
+			tcb.recv_buffer.clear();
+			if tcb.state == State::FinWait2 {
+				tcb.close_at = Some(now() + TCP_FIN_WAIT2_TIMEOUT);
+			}
+			close_send(stack, handle);
+		}
+	}
+}
+
+/// Queue a FIN behind the data of the send buffer
+fn close_send(stack: &mut NetworkStack, handle: SocketHandle) {
+	let tcb = stack.tcp.get_mut(&handle).unwrap();
+
+	match tcb.state {
+		State::Established => tcb.state = State::FinWait1,
+		State::CloseWait => tcb.state = State::LastAck,
+		_ => return,
+	}
+	tcb.fin_queued = true;
+
+	output(stack, handle);
+}
+
+pub(super) fn bind(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	addr: SocketAddrV4,
+) -> io::Result<()> {
+	let tcb = &stack.tcp[&handle];
+	if tcb.bound || tcb.state != State::Closed {
+		return Err(io::Error::EINVAL);
+	}
+
+	let addr = stack.check_bind(Protocol::Tcp, addr)?;
+	let tcb = stack.tcp.get_mut(&handle).unwrap();
+	tcb.local = addr;
+	tcb.bound = true;
+
+	Ok(())
+}
+
+/// Accept connections on the socket `handle`, at most `backlog`
+/// connections wait for `accept`
+pub(super) fn listen(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	backlog: usize,
+) -> io::Result<()> {
+	let tcb = &stack.tcp[&handle];
+
+	match tcb.state {
+		State::Closed if tcb.remote.port() == 0 => {
+			if !tcb.bound {
+				bind(stack, handle, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))?;
+			}
+			stack.tcp.get_mut(&handle).unwrap().state = State::Listen;
+		}
+		State::Listen => {}
+		_ => return Err(io::Error::EINVAL),
+	}
+
+	stack.tcp.get_mut(&handle).unwrap().backlog = backlog.clamp(1, TCP_MAX_BACKLOG);
+
+	Ok(())
+}
+
+/// Send a SYN to `addr` to establish a connection
+pub(super) fn connect(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	addr: SocketAddrV4,
+) -> io::Result<()> {
+	if addr.ip().is_unspecified() || addr.ip().is_broadcast() || addr.port() == 0 {
+		return Err(io::Error::EINVAL);
+	}
+
+	let tcb = &stack.tcp[&handle];
+	match tcb.state {
+		State::Closed if tcb.remote.port() == 0 => {}
+		State::Closed => return Err(io::Error::EISCONN),
+		State::SynSent => return Err(io::Error::EALREADY),
+		State::Listen => return Err(io::Error::EINVAL),
+		_ => return Err(io::Error::EISCONN),
+	}
+
+	let src = stack.source_address(*addr.ip())?;
+	let local = if tcb.bound {
+		tcb.local
+	} else {
+		SocketAddrV4::new(src, stack.allocate_port(Protocol::Tcp)?)
+	};
+
+	let tcb = stack.tcp.get_mut(&handle).unwrap();
+	tcb.local = if local.ip().is_unspecified() {
+		SocketAddrV4::new(src, local.port())
+	} else {
+		local
+	};
+	tcb.bound = true;
+	tcb.state = State::SynSent;
+	tcb.open(addr);
+
+	let iss = tcb.iss;
+	transmit(stack, handle, iss, TCP_SYN, 0..0);
+
+	Ok(())
+}
+
+/// Returns the result of `connect`, if the handshake of the connection `handle` is finished
+pub(super) fn connect_result(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+) -> Option<io::Result<()>> {
+	let tcb = stack.tcp.get_mut(&handle)?;
+
+	match tcb.state {
+		State::SynSent => None,
+		State::Closed => Some(Err(tcb.error.take().unwrap_or(io::Error::ECONNREFUSED))),
+		_ => Some(Ok(())),
+	}
+}
+
+/// Returns the next established connection of the listening socket `handle`
+/// and the address of the peer or `None`, if no connection is available.
+pub(super) fn accept(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+) -> Option<io::Result<(SocketHandle, SocketAddrV4)>> {
+	let tcb = stack.tcp.get_mut(&handle)?;
+	if tcb.state != State::Listen {
+		return Some(Err(io::Error::EINVAL));
+	}
+
+	let child = tcb.accept_queue.pop_front()?;
+	let tcb = stack.tcp.get_mut(&child)?;
+	tcb.listener = None;
+
+	Some(Ok((child, tcb.remote)))
+}
+
+/// Append `buf` to the send buffer of the connection `handle`. Returns
+/// the number of appended bytes or `None`, if the buffer is full.
+pub(super) fn send(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	buf: &[u8],
+) -> Option<io::Result<usize>> {
+	let tcb = stack.tcp.get_mut(&handle)?;
+
+	if let Some(error) = tcb.error.take() {
+		return Some(Err(error));
+	}
+
+	match tcb.state {
+		State::SynSent | State::SynReceived => None,
+		State::Established | State::CloseWait => {
+			let len = buf.len().min(TCP_BUFFER_SIZE - tcb.send_buffer.len());
+			if len == 0 {
+				return None;
+			}
+
+			tcb.send_buffer.extend(&buf[..len]);
+			output(stack, handle);
+
+			Some(Ok(len))
+		}
+		State::Closed | State::Listen if tcb.remote.port() == 0 => Some(Err(io::Error::ENOTCONN)),
+		_ => Some(Err(io::Error::EPIPE)),
+	}
+}
+
+/// Copy received data of the connection `handle` into `buf`. Returns the
+/// number of bytes, zero at the end of the stream, or `None`, if no data
+/// is available.
+pub(super) fn recv(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	buf: &mut [u8],
+	peek: bool,
+) -> Option<io::Result<usize>> {
+	let tcb = stack.tcp.get_mut(&handle)?;
+
+	if !tcb.recv_buffer.is_empty() {
+		let len = buf.len().min(tcb.recv_buffer.len());
+		for (dst, src) in buf.iter_mut().zip(tcb.recv_buffer.iter()) {
+			*dst = *src;
+		}
+
+		if !peek {
+			tcb.recv_buffer.drain(..len);
+
+			// announce the larger window, if the peer may wait for it
+			if tcb.rcv_wnd < tcb.mss && tcb.window() >= tcb.mss && tcb.is_synchronized() {
+				send_ack(stack, handle);
+			}
+		}
+
+		return Some(Ok(len));
+	}
+
+	if let Some(error) = tcb.error.take() {
+		return Some(Err(error));
+	} else if tcb.fin_received || tcb.read_shutdown {
+		return Some(Ok(0));
+	}
+
+	match tcb.state {
+		State::Closed | State::Listen if tcb.remote.port() == 0 => Some(Err(io::Error::ENOTCONN)),
+		State::Closed => Some(Ok(0)),
+		_ => None,
+	}
+}
+
+/// Shut down the directions `how` of the connection `handle`
+pub(super) fn shutdown(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	how: Shutdown,
+) -> io::Result<()> {
+	let tcb = stack.tcp.get_mut(&handle).unwrap();
+	if !tcb.is_synchronized() && tcb.state != State::SynReceived {
+		return Err(io::Error::ENOTCONN);
+	}
+
+	if how != Shutdown::Write {
+		tcb.read_shutdown = true;
+		tcb.recv_buffer.clear();
+	}
+	if how != Shutdown::Read {
+		close_send(stack, handle);
+	}
+
+	Ok(())
+}
+
+/// Returns the local address of the socket `handle`
+pub(super) fn local_address(stack: &NetworkStack, handle: SocketHandle) -> SocketAddrV4 {
+	stack.tcp[&handle].local
+}
+
+/// Returns the address of the peer of the connection `handle`
+pub(super) fn peer_address(stack: &NetworkStack, handle: SocketHandle) -> io::Result<SocketAddrV4> {
+	let tcb = &stack.tcp[&handle];
+
+	match tcb.state {
+		State::Closed | State::Listen => Err(io::Error::ENOTCONN),
+		_ => Ok(tcb.remote),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Returns a segment with the sequence number `seq`, the flags `flags` and the content `data`
+	fn segment(seq: u32, flags: u8, data: &[u8]) -> Segment<'_> {
+		Segment {
+			src_port: 1234,
+			dst_port: 80,
+			seq,
+			ack: 0,
+			flags,
+			window: TCP_BUFFER_SIZE,
+			mss: None,
+			data,
+		}
+	}
+
+	/// Returns an established connection, which expects the sequence number `rcv_nxt`
+	fn connection(rcv_nxt: u32) -> Tcb {
+		let mut tcb = Tcb::new();
+		tcb.state = State::Established;
+		tcb.rcv_nxt = rcv_nxt;
+
+		tcb
+	}
+
+	#[test_case]
+	fn sequence_numbers() {
+		assert!(seq_lt(1, 2));
+		assert!(!seq_lt(2, 1));
+		assert!(!seq_lt(2, 2));
+		assert!(seq_le(1, 2));
+		assert!(seq_le(2, 2));
+		assert!(!seq_le(3, 2));
+	}
+
+	#[test_case]
+	fn sequence_numbers_wrap_around() {
+		assert!(seq_lt(u32::MAX, 0));
+		assert!(seq_lt(u32::MAX - 10, 10));
+		assert!(!seq_lt(10, u32::MAX - 10));
+		assert!(seq_le(u32::MAX, 0));
+		assert!(!seq_le(0, u32::MAX));
+		// numbers, which are half of the sequence space apart, precede each other
+		assert!(seq_lt(0, 0x8000_0000));
+		assert!(seq_lt(0x8000_0000, 0));
+	}
+
+	#[test_case]
+	fn accept_continuing_segments() {
+		let tcb = connection(1000);
+
+		assert!(tcb.accepts(&segment(1000, TCP_ACK, b"")));
+		assert!(tcb.accepts(&segment(1000, TCP_ACK, b"data")));
+		assert!(tcb.accepts(&segment(1000, TCP_ACK | TCP_FIN, b"")));
+		// retransmitted segments, which contain new data, are accepted
+		assert!(tcb.accepts(&segment(998, TCP_ACK, b"data")));
+	}
+
+	#[test_case]
+	fn reject_old_and_future_segments() {
+		let tcb = connection(1000);
+
+		assert!(!tcb.accepts(&segment(999, TCP_ACK, b"")));
+		assert!(!tcb.accepts(&segment(1001, TCP_ACK, b"")));
+		// the data has already been received
+		assert!(!tcb.accepts(&segment(996, TCP_ACK, b"data")));
+		// segments, which arrive out of order, are dropped
+		assert!(!tcb.accepts(&segment(1001, TCP_ACK, b"data")));
+	}
+
+	#[test_case]
+	fn accept_segments_wrap_around() {
+		let tcb = connection(2);
+
+		assert!(tcb.accepts(&segment(u32::MAX - 1, TCP_ACK, b"data!")));
+		assert!(!tcb.accepts(&segment(u32::MAX - 2, TCP_ACK, b"data")));
+
+		let tcb = connection(u32::MAX);
+		assert!(tcb.accepts(&segment(u32::MAX, TCP_ACK, b"data")));
+		assert!(tcb.accepts(&segment(u32::MAX - 1, TCP_ACK | TCP_FIN, b"d")));
+		assert!(!tcb.accepts(&segment(0, TCP_ACK, b"data")));
+	}
+
+	#[test_case]
+	fn receive_window() {
+		let mut tcb = connection(0);
+
+		assert_eq!(tcb.window(), TCP_BUFFER_SIZE);
+		tcb.recv_buffer.extend(&[0u8; 100]);
+		assert_eq!(tcb.window(), TCP_BUFFER_SIZE - 100);
+		tcb.recv_buffer.resize(TCP_BUFFER_SIZE, 0);
+		assert_eq!(tcb.window(), 0);
+	}
+}
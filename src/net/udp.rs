@@ -0,0 +1,229 @@
+//! User Datagram Protocol (UDP)
+
+use crate::io;
+use crate::net::ip::{checksum, pseudo_header_sum, IPV4_HEADER_SIZE, IPV4_MTU, IP_PROTOCOL_UDP};
+use crate::net::{NetworkStack, Protocol, SocketHandle};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+/// Size of the UDP header
+const UDP_HEADER_SIZE: usize = 8;
+/// Maximum size of a datagram, which fits into an IPv4 packet
+const UDP_MAX_PAYLOAD: usize = IPV4_MTU - IPV4_HEADER_SIZE - UDP_HEADER_SIZE;
+/// Maximum number of datagrams in the receive queue of a socket
+const UDP_QUEUE_LEN: usize = 64;
+
+/// State of a UDP socket
+#[derive(Debug)]
+pub(super) struct UdpSocket {
+	/// local address, the port is zero, if the socket isn't bound
+	pub local: SocketAddrV4,
+	/// default destination, which is set by `connect`
+	pub remote: Option<SocketAddrV4>,
+	/// received datagrams and their senders
+	queue: VecDeque<(SocketAddrV4, Vec<u8>)>,
+	/// the receiving direction is shut down
+	read_shutdown: bool,
+}
+
+impl UdpSocket {
+	fn new() -> Self {
+		UdpSocket {
+			local: SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+			remote: None,
+			queue: VecDeque::new(),
+			read_shutdown: false,
+		}
+	}
+
+	/// Returns true, if the socket accepts a datagram from `src` to `dst`
+	fn accepts(&self, src: SocketAddrV4, dst: SocketAddrV4) -> bool {
+		self.local.port() == dst.port()
+			&& (self.local.ip().is_unspecified() || self.local.ip() == dst.ip())
+			&& self.remote.is_none_or(|remote| remote == src)
+	}
+}
+
+/// Create a socket, which isn't bound
+pub(super) fn create(stack: &mut NetworkStack) -> SocketHandle {
+	let handle = stack.allocate_handle();
+	stack.udp.insert(handle, UdpSocket::new());
+
+	handle
+}
+
+/// Release the socket `handle`
+pub(super) fn close(stack: &mut NetworkStack, handle: SocketHandle) {
+	stack.udp.remove(&handle);
+}
+
+pub(super) fn bind(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	addr: SocketAddrV4,
+) -> io::Result<()> {
+	if stack.udp[&handle].local.port() != 0 {
+		return Err(io::Error::EINVAL);
+	}
+
+	let addr = stack.check_bind(Protocol::Udp, addr)?;
+	stack.udp.get_mut(&handle).unwrap().local = addr;
+
+	Ok(())
+}
+
+/// Bind the socket `handle` to an ephemeral port, if it isn't bound
+fn autobind(stack: &mut NetworkStack, handle: SocketHandle) -> io::Result<()> {
+	if stack.udp[&handle].local.port() == 0 {
+		bind(stack, handle, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))?;
+	}
+
+	Ok(())
+}
+
+/// Set the default destination of the socket `handle`. In addition,
+/// only datagrams of this peer are received.
+pub(super) fn connect(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	addr: SocketAddrV4,
+) -> io::Result<()> {
+	stack.route(*addr.ip())?;
+	autobind(stack, handle)?;
+
+	let socket = stack.udp.get_mut(&handle).unwrap();
+	socket.remote = Some(addr);
+	// datagrams of other senders are dropped
+	socket.queue.retain(|(src, _)| *src == addr);
+
+	Ok(())
+}
+
+/// Send `buf` as datagram to `dst` or, if `dst` isn't given, to the connected peer
+pub(super) fn send(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	buf: &[u8],
+	dst: Option<SocketAddrV4>,
+) -> io::Result<usize> {
+	let dst = dst
+		.or(stack.udp[&handle].remote)
+		.ok_or(io::Error::EDESTADDRREQ)?;
+	if buf.len() > UDP_MAX_PAYLOAD {
+		return Err(io::Error::EMSGSIZE);
+	}
+
+	autobind(stack, handle)?;
+	let local = stack.udp[&handle].local;
+	let src = if local.ip().is_unspecified() {
+		stack.source_address(*dst.ip())?
+	} else {
+		*local.ip()
+	};
+
+	let len = UDP_HEADER_SIZE + buf.len();
+	let mut datagram = Vec::with_capacity(len);
+	datagram.extend_from_slice(&local.port().to_be_bytes());
+	datagram.extend_from_slice(&dst.port().to_be_bytes());
+	datagram.extend_from_slice(&(len as u16).to_be_bytes());
+	datagram.extend_from_slice(&[0, 0]);
+	datagram.extend_from_slice(buf);
+
+	// a checksum of zero means, that the checksum isn't computed
+	let sum = match checksum(
+		&datagram,
+		pseudo_header_sum(src, *dst.ip(), IP_PROTOCOL_UDP, len),
+	) {
+		0 => 0xFFFF,
+		sum => sum,
+	};
+	datagram[6..8].copy_from_slice(&sum.to_be_bytes());
+
+	stack.send_ipv4(src, *dst.ip(), IP_PROTOCOL_UDP, &datagram)?;
+
+	Ok(buf.len())
+}
+
+/// Copy the next datagram of the socket `handle` into `buf`. Returns
+/// `None`, if no datagram is available. Otherwise, the length of the
+/// datagram and its sender are returned. The rest of the datagram, which
+/// doesn't fit into `buf`, is discarded.
+pub(super) fn recv(
+	stack: &mut NetworkStack,
+	handle: SocketHandle,
+	buf: &mut [u8],
+	peek: bool,
+) -> Option<io::Result<(usize, SocketAddrV4)>> {
+	let socket = stack.udp.get_mut(&handle)?;
+
+	let Some((src, data)) = socket.queue.front() else {
+		if socket.read_shutdown {
+			return Some(Ok((0, SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))));
+		}
+		return None;
+	};
+
+	let len = data.len().min(buf.len());
+	buf[..len].copy_from_slice(&data[..len]);
+	let src = *src;
+	if !peek {
+		socket.queue.pop_front();
+	}
+
+	Some(Ok((len, src)))
+}
+
+/// Stop receiving datagrams by the socket `handle`
+pub(super) fn shutdown_read(stack: &mut NetworkStack, handle: SocketHandle) {
+	if let Some(socket) = stack.udp.get_mut(&handle) {
+		socket.read_shutdown = true;
+		socket.queue.clear();
+	}
+}
+
+/// Deliver the datagram `datagram`, which `src` sends to `dst`.
+/// Returns false, if no socket is bound to the destination port.
+pub(super) fn receive(
+	stack: &mut NetworkStack,
+	src: Ipv4Addr,
+	dst: Ipv4Addr,
+	datagram: &[u8],
+) -> bool {
+	if datagram.len() < UDP_HEADER_SIZE {
+		return true;
+	}
+
+	let len = usize::from(u16::from_be_bytes([datagram[4], datagram[5]]));
+	if len < UDP_HEADER_SIZE || len > datagram.len() {
+		return true;
+	}
+
+	let datagram = &datagram[..len];
+	let sum = u16::from_be_bytes([datagram[6], datagram[7]]);
+	if sum != 0 && checksum(datagram, pseudo_header_sum(src, dst, IP_PROTOCOL_UDP, len)) != 0 {
+		return true;
+	}
+
+	let src = SocketAddrV4::new(src, u16::from_be_bytes([datagram[0], datagram[1]]));
+	let dst = SocketAddrV4::new(dst, u16::from_be_bytes([datagram[2], datagram[3]]));
+
+	// prefer a connected socket
+	let socket = stack
+		.udp
+		.values_mut()
+		.filter(|socket| socket.accepts(src, dst))
+		.max_by_key(|socket| socket.remote.is_some());
+
+	match socket {
+		Some(socket) => {
+			if !socket.read_shutdown && socket.queue.len() < UDP_QUEUE_LEN {
+				socket
+					.queue
+					.push_back((src, Vec::from(&datagram[UDP_HEADER_SIZE..])));
+			}
+			true
+		}
+		None => false,
+	}
+}
@@ -0,0 +1,677 @@
+//! Sockets of the address family `AF_UNIX`
+//!
+//! The sockets exchange their data within the kernel. `bind` creates a
+//! node of the type socket in the file system, other sockets find the
+//! bound socket by the inode number of this node. A stream connection
+//! consists of two pipes, one for each direction. Tasks, which wait for
+//! their peer, yield the processor until the peer makes progress.
+
+use crate::fd::{FileStatus, IoInterface};
+use crate::fs::{self, Attributes, NodeKind, Timestamps};
+use crate::io;
+use crate::net::{MessageFlags, Shutdown, SocketAddress};
+use crate::scheduler::reschedule;
+use crate::synch::spinlock::*;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::mem;
+
+/// Capacity of each direction of a stream connection
+const UNIX_STREAM_BUFFER_SIZE: usize = 16 * 1024;
+/// Maximum size of a datagram
+const UNIX_DATAGRAM_MAX_SIZE: usize = 16 * 1024;
+/// Maximum number of datagrams in the receive queue of a socket
+const UNIX_DATAGRAM_QUEUE_LEN: usize = 64;
+/// Maximum number of connections, which wait for `accept`
+const UNIX_MAX_BACKLOG: usize = 128;
+
+/// Socket, which is bound to a node of the file system
+#[derive(Clone, Debug)]
+enum BoundSocket {
+	Stream(Weak<Spinlock<StreamState>>),
+	Datagram(Weak<Spinlock<DatagramState>>),
+}
+
+/// Bound sockets by the inode number of their node
+static BOUND_SOCKETS: Spinlock<BTreeMap<u64, BoundSocket>> = Spinlock::new(BTreeMap::new());
+
+/// Returns the address of a socket, which isn't bound
+fn unnamed() -> SocketAddress {
+	SocketAddress::Unix(String::new())
+}
+
+/// Returns the path of `addr`
+fn unix_path(addr: &SocketAddress) -> io::Result<&str> {
+	match addr {
+		SocketAddress::Unix(path) if !path.is_empty() => Ok(path),
+		_ => Err(io::Error::EINVAL),
+	}
+}
+
+/// Create the node `path` and register `socket` by its inode number
+fn bind_node(path: &str, socket: BoundSocket) -> io::Result<u64> {
+	let ino = fs::mksock(path, 0o777)?;
+	BOUND_SOCKETS.lock().insert(ino, socket);
+
+	Ok(ino)
+}
+
+/// Returns the socket, which is bound to the node `path`
+fn find_bound(path: &str) -> io::Result<BoundSocket> {
+	let ino = fs::find_socket(path)?;
+
+	BOUND_SOCKETS
+		.lock()
+		.get(&ino)
+		.cloned()
+		.ok_or(io::Error::ECONNREFUSED)
+}
+
+/// Evaluate `f`, until it returns a result. If `nonblocking`
+/// is true, `EAGAIN` is returned instead of waiting.
+fn wait_for<T, F>(nonblocking: bool, mut f: F) -> io::Result<T>
+where
+	F: FnMut() -> Option<io::Result<T>>,
+{
+	loop {
+		if let Some(result) = f() {
+			return result;
+		} else if nonblocking {
+			return Err(io::Error::EAGAIN);
+		}
+
+		reschedule();
+	}
+}
+
+/// Returns the status of a socket with the inode number `ino`
+fn socket_status(ino: u64) -> FileStatus {
+	FileStatus {
+		ino,
+		kind: NodeKind::Socket,
+		nlink: 1,
+		file_size: 0,
+		attr: Attributes::default_for(NodeKind::Socket),
+		times: Timestamps::now(),
+	}
+}
+
+/// One direction of a stream connection
+#[derive(Debug, Default)]
+struct Pipe {
+	data: VecDeque<u8>,
+	/// the writer doesn't send further data
+	write_closed: bool,
+	/// the reader doesn't receive further data
+	read_closed: bool,
+}
+
+type SharedPipe = Arc<Spinlock<Pipe>>;
+
+#[derive(Debug)]
+enum Connection {
+	Unconnected,
+	Listening {
+		backlog: usize,
+		/// established connections, which aren't accepted
+		pending: VecDeque<Arc<Spinlock<StreamState>>>,
+	},
+	Connected {
+		rx: SharedPipe,
+		tx: SharedPipe,
+		peer: SocketAddress,
+	},
+}
+
+impl Connection {
+	/// Close both directions of a connection
+	fn close(self) {
+		match self {
+			Connection::Listening { pending, .. } => {
+				for state in pending {
+					mem::replace(&mut state.lock().connection, Connection::Unconnected).close();
+				}
+			}
+			Connection::Connected { rx, tx, .. } => {
+				rx.lock().read_closed = true;
+				tx.lock().write_closed = true;
+			}
+			Connection::Unconnected => {}
+		}
+	}
+}
+
+#[derive(Debug)]
+struct StreamState {
+	/// address, which is reported by `getsockname`
+	local: SocketAddress,
+	/// inode number of the node, if the socket itself is bound
+	bound: Option<u64>,
+	connection: Connection,
+}
+
+impl StreamState {
+	fn connected(
+		local: SocketAddress,
+		rx: SharedPipe,
+		tx: SharedPipe,
+		peer: SocketAddress,
+	) -> Self {
+		StreamState {
+			local,
+			bound: None,
+			connection: Connection::Connected { rx, tx, peer },
+		}
+	}
+}
+
+/// Socket of the type `SOCK_STREAM`
+#[derive(Debug)]
+pub(super) struct UnixStream {
+	state: Arc<Spinlock<StreamState>>,
+	/// inode number, if the socket isn't bound
+	ino: u64,
+	/// operations return `EAGAIN` instead of waiting
+	nonblocking: bool,
+}
+
+impl UnixStream {
+	fn with_state(state: StreamState, nonblocking: bool) -> Self {
+		UnixStream {
+			state: Arc::new(Spinlock::new(state)),
+			ino: fs::next_ino(),
+			nonblocking,
+		}
+	}
+
+	pub fn new(nonblocking: bool) -> Self {
+		let state = StreamState {
+			local: unnamed(),
+			bound: None,
+			connection: Connection::Unconnected,
+		};
+
+		Self::with_state(state, nonblocking)
+	}
+
+	/// Returns two sockets, which are connected to each other
+	pub fn pair(nonblocking: bool) -> (Self, Self) {
+		let first: SharedPipe = Arc::default();
+		let second: SharedPipe = Arc::default();
+
+		(
+			Self::with_state(
+				StreamState::connected(unnamed(), first.clone(), second.clone(), unnamed()),
+				nonblocking,
+			),
+			Self::with_state(
+				StreamState::connected(unnamed(), second, first, unnamed()),
+				nonblocking,
+			),
+		)
+	}
+
+	/// Returns the pipes of the connection
+	fn pipes(&self) -> io::Result<(SharedPipe, SharedPipe)> {
+		match &self.state.lock().connection {
+			Connection::Connected { rx, tx, .. } => Ok((rx.clone(), tx.clone())),
+			_ => Err(io::Error::ENOTCONN),
+		}
+	}
+}
+
+impl Drop for UnixStream {
+	fn drop(&mut self) {
+		let mut state = self.state.lock();
+
+		if let Some(ino) = state.bound.take() {
+			BOUND_SOCKETS.lock().remove(&ino);
+		}
+		mem::replace(&mut state.connection, Connection::Unconnected).close();
+	}
+}
+
+impl IoInterface for UnixStream {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		self.recvfrom(buf, MessageFlags::empty())
+			.map(|(len, _)| len)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		self.sendto(buf, MessageFlags::empty(), None)
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		Ok(socket_status(self.state.lock().bound.unwrap_or(self.ino)))
+	}
+
+	fn bind(&self, addr: SocketAddress) -> io::Result<()> {
+		let path = unix_path(&addr)?;
+		if self.state.lock().bound.is_some() {
+			return Err(io::Error::EINVAL);
+		}
+
+		let ino = bind_node(path, BoundSocket::Stream(Arc::downgrade(&self.state)))?;
+		let mut state = self.state.lock();
+		state.bound = Some(ino);
+		state.local = addr;
+
+		Ok(())
+	}
+
+	fn listen(&self, backlog: usize) -> io::Result<()> {
+		let mut state = self.state.lock();
+		if state.bound.is_none() {
+			return Err(io::Error::EINVAL);
+		}
+
+		let backlog = backlog.clamp(1, UNIX_MAX_BACKLOG);
+		match &mut state.connection {
+			Connection::Unconnected => {
+				state.connection = Connection::Listening {
+					backlog,
+					pending: VecDeque::new(),
+				};
+				Ok(())
+			}
+			Connection::Listening {
+				backlog: current, ..
+			} => {
+				*current = backlog;
+				Ok(())
+			}
+			Connection::Connected { .. } => Err(io::Error::EINVAL),
+		}
+	}
+
+	fn accept(&self, nonblocking: bool) -> io::Result<(Arc<dyn IoInterface>, SocketAddress)> {
+		let state = wait_for(self.nonblocking, || {
+			match &mut self.state.lock().connection {
+				Connection::Listening { pending, .. } => pending.pop_front().map(Ok),
+				_ => Some(Err(io::Error::EINVAL)),
+			}
+		})?;
+
+		let peer = match &state.lock().connection {
+			Connection::Connected { peer, .. } => peer.clone(),
+			// the peer has already closed the connection
+			_ => unnamed(),
+		};
+		let socket = UnixStream {
+			state,
+			ino: fs::next_ino(),
+			nonblocking,
+		};
+
+		Ok((Arc::new(socket), peer))
+	}
+
+	fn connect(&self, addr: SocketAddress) -> io::Result<()> {
+		let BoundSocket::Stream(listener) = find_bound(unix_path(&addr)?)? else {
+			return Err(io::Error::EPROTOTYPE);
+		};
+		let listener = listener.upgrade().ok_or(io::Error::ECONNREFUSED)?;
+
+		let local = {
+			let state = self.state.lock();
+			match state.connection {
+				Connection::Unconnected => state.local.clone(),
+				Connection::Listening { .. } => return Err(io::Error::EINVAL),
+				Connection::Connected { .. } => return Err(io::Error::EISCONN),
+			}
+		};
+
+		let to_server: SharedPipe = Arc::default();
+		let to_client: SharedPipe = Arc::default();
+		let peer = wait_for(self.nonblocking, || {
+			let mut guard = listener.lock();
+			let server_local = guard.local.clone();
+
+			match &mut guard.connection {
+				Connection::Listening { backlog, pending } => {
+					if pending.len() >= *backlog {
+						return None;
+					}
+
+					let server = StreamState::connected(
+						server_local.clone(),
+						to_server.clone(),
+						to_client.clone(),
+						local.clone(),
+					);
+					pending.push_back(Arc::new(Spinlock::new(server)));
+					Some(Ok(server_local))
+				}
+				_ => Some(Err(io::Error::ECONNREFUSED)),
+			}
+		})?;
+
+		self.state.lock().connection = Connection::Connected {
+			rx: to_client,
+			tx: to_server,
+			peer,
+		};
+
+		Ok(())
+	}
+
+	fn sendto(
+		&self,
+		buf: &[u8],
+		flags: MessageFlags,
+		addr: Option<SocketAddress>,
+	) -> io::Result<usize> {
+		let nonblocking = self.nonblocking || flags.contains(MessageFlags::MSG_DONTWAIT);
+		// the destination of a connection is fixed
+		let (_, tx) = match (self.pipes(), addr) {
+			(Ok(_), Some(_)) => return Err(io::Error::EISCONN),
+			(Err(_), Some(_)) => return Err(io::Error::EOPNOTSUPP),
+			(pipes, None) => pipes?,
+		};
+
+		let mut written = 0;
+		while written < buf.len() {
+			match wait_for(nonblocking, || {
+				let mut pipe = tx.lock();
+				if pipe.read_closed || pipe.write_closed {
+					return Some(Err(io::Error::EPIPE));
+				}
+
+				let len = (UNIX_STREAM_BUFFER_SIZE - pipe.data.len()).min(buf.len() - written);
+				if len == 0 {
+					return None;
+				}
+				pipe.data.extend(&buf[written..written + len]);
+				Some(Ok(len))
+			}) {
+				Ok(len) => written += len,
+				Err(e) if written == 0 => return Err(e),
+				Err(_) => break,
+			}
+		}
+
+		Ok(written)
+	}
+
+	fn recvfrom(
+		&self,
+		buf: &mut [u8],
+		flags: MessageFlags,
+	) -> io::Result<(usize, Option<SocketAddress>)> {
+		let nonblocking = self.nonblocking || flags.contains(MessageFlags::MSG_DONTWAIT);
+		let (rx, _) = self.pipes()?;
+
+		let len = wait_for(nonblocking, || {
+			let mut pipe = rx.lock();
+			if pipe.data.is_empty() {
+				// end of file, if the peer doesn't send further data
+				return (pipe.write_closed || pipe.read_closed || buf.is_empty()).then_some(Ok(0));
+			}
+
+			let len = pipe.data.len().min(buf.len());
+			for (dst, src) in buf.iter_mut().zip(pipe.data.iter()) {
+				*dst = *src;
+			}
+			if !flags.contains(MessageFlags::MSG_PEEK) {
+				pipe.data.drain(..len);
+			}
+			Some(Ok(len))
+		})?;
+
+		Ok((len, None))
+	}
+
+	fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+		let (rx, tx) = self.pipes()?;
+
+		if how != Shutdown::Write {
+			rx.lock().read_closed = true;
+		}
+		if how != Shutdown::Read {
+			tx.lock().write_closed = true;
+		}
+
+		Ok(())
+	}
+
+	fn local_address(&self) -> io::Result<SocketAddress> {
+		Ok(self.state.lock().local.clone())
+	}
+
+	fn peer_address(&self) -> io::Result<SocketAddress> {
+		match &self.state.lock().connection {
+			Connection::Connected { peer, .. } => Ok(peer.clone()),
+			_ => Err(io::Error::ENOTCONN),
+		}
+	}
+}
+
+#[derive(Debug)]
+struct DatagramState {
+	/// address, which is reported by `getsockname`
+	local: SocketAddress,
+	/// inode number of the node, if the socket is bound
+	bound: Option<u64>,
+	/// default destination and its address, which is set by `connect`.
+	/// In addition, only datagrams of this peer are accepted.
+	peer: Option<(Weak<Spinlock<DatagramState>>, SocketAddress)>,
+	/// received datagrams and the addresses of their senders
+	queue: VecDeque<(SocketAddress, Vec<u8>)>,
+	/// the receiving direction is shut down
+	read_shutdown: bool,
+	/// the sending direction is shut down
+	write_shutdown: bool,
+}
+
+impl DatagramState {
+	fn new() -> Self {
+		DatagramState {
+			local: unnamed(),
+			bound: None,
+			peer: None,
+			queue: VecDeque::new(),
+			read_shutdown: false,
+			write_shutdown: false,
+		}
+	}
+}
+
+/// Socket of the type `SOCK_DGRAM`
+#[derive(Debug)]
+pub(super) struct UnixDatagram {
+	state: Arc<Spinlock<DatagramState>>,
+	/// inode number, if the socket isn't bound
+	ino: u64,
+	/// operations return `EAGAIN` instead of waiting
+	nonblocking: bool,
+}
+
+impl UnixDatagram {
+	pub fn new(nonblocking: bool) -> Self {
+		UnixDatagram {
+			state: Arc::new(Spinlock::new(DatagramState::new())),
+			ino: fs::next_ino(),
+			nonblocking,
+		}
+	}
+
+	/// Returns two sockets, which are connected to each other
+	pub fn pair(nonblocking: bool) -> (Self, Self) {
+		let first = Self::new(nonblocking);
+		let second = Self::new(nonblocking);
+
+		first.state.lock().peer = Some((Arc::downgrade(&second.state), unnamed()));
+		second.state.lock().peer = Some((Arc::downgrade(&first.state), unnamed()));
+
+		(first, second)
+	}
+}
+
+impl Drop for UnixDatagram {
+	fn drop(&mut self) {
+		if let Some(ino) = self.state.lock().bound.take() {
+			BOUND_SOCKETS.lock().remove(&ino);
+		}
+	}
+}
+
+impl IoInterface for UnixDatagram {
+	fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		self.recvfrom(buf, MessageFlags::empty())
+			.map(|(len, _)| len)
+	}
+
+	fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		self.sendto(buf, MessageFlags::empty(), None)
+	}
+
+	fn fstat(&self) -> io::Result<FileStatus> {
+		Ok(socket_status(self.state.lock().bound.unwrap_or(self.ino)))
+	}
+
+	fn bind(&self, addr: SocketAddress) -> io::Result<()> {
+		let path = unix_path(&addr)?;
+		if self.state.lock().bound.is_some() {
+			return Err(io::Error::EINVAL);
+		}
+
+		let ino = bind_node(path, BoundSocket::Datagram(Arc::downgrade(&self.state)))?;
+		let mut state = self.state.lock();
+		state.bound = Some(ino);
+		state.local = addr;
+
+		Ok(())
+	}
+
+	fn listen(&self, _backlog: usize) -> io::Result<()> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	fn accept(&self, _nonblocking: bool) -> io::Result<(Arc<dyn IoInterface>, SocketAddress)> {
+		Err(io::Error::EOPNOTSUPP)
+	}
+
+	fn connect(&self, addr: SocketAddress) -> io::Result<()> {
+		let BoundSocket::Datagram(peer) = find_bound(unix_path(&addr)?)? else {
+			return Err(io::Error::EPROTOTYPE);
+		};
+		if peer.strong_count() == 0 {
+			return Err(io::Error::ECONNREFUSED);
+		}
+
+		self.state.lock().peer = Some((peer, addr));
+
+		Ok(())
+	}
+
+	fn sendto(
+		&self,
+		buf: &[u8],
+		flags: MessageFlags,
+		addr: Option<SocketAddress>,
+	) -> io::Result<usize> {
+		let nonblocking = self.nonblocking || flags.contains(MessageFlags::MSG_DONTWAIT);
+		if buf.len() > UNIX_DATAGRAM_MAX_SIZE {
+			return Err(io::Error::EMSGSIZE);
+		}
+
+		let target = match addr {
+			Some(addr) => match find_bound(unix_path(&addr)?)? {
+				BoundSocket::Datagram(target) => target,
+				BoundSocket::Stream(_) => return Err(io::Error::EPROTOTYPE),
+			},
+			None => self
+				.state
+				.lock()
+				.peer
+				.as_ref()
+				.map(|(peer, _)| peer.clone())
+				.ok_or(io::Error::ENOTCONN)?,
+		};
+		let target = target.upgrade().ok_or(io::Error::ECONNREFUSED)?;
+
+		let local = {
+			let state = self.state.lock();
+			if state.write_shutdown {
+				return Err(io::Error::EPIPE);
+			}
+			state.local.clone()
+		};
+
+		let me = Arc::downgrade(&self.state);
+		wait_for(nonblocking, || {
+			let mut target = target.lock();
+			// a connected socket accepts only datagrams of its peer
+			if target
+				.peer
+				.as_ref()
+				.is_some_and(|(peer, _)| !Weak::ptr_eq(peer, &me))
+			{
+				return Some(Err(io::Error::EPERM));
+			}
+			if target.read_shutdown {
+				return Some(Err(io::Error::EPIPE));
+			}
+			if target.queue.len() >= UNIX_DATAGRAM_QUEUE_LEN {
+				return None;
+			}
+
+			target.queue.push_back((local.clone(), Vec::from(buf)));
+			Some(Ok(buf.len()))
+		})
+	}
+
+	/// Receive the next datagram. The rest of the datagram,
+	/// which doesn't fit into `buf`, is discarded.
+	fn recvfrom(
+		&self,
+		buf: &mut [u8],
+		flags: MessageFlags,
+	) -> io::Result<(usize, Option<SocketAddress>)> {
+		let nonblocking = self.nonblocking || flags.contains(MessageFlags::MSG_DONTWAIT);
+
+		wait_for(nonblocking, || {
+			let mut state = self.state.lock();
+			let Some((src, data)) = state.queue.front() else {
+				return state.read_shutdown.then_some(Ok((0, None)));
+			};
+
+			let len = data.len().min(buf.len());
+			buf[..len].copy_from_slice(&data[..len]);
+			let src = src.clone();
+			if !flags.contains(MessageFlags::MSG_PEEK) {
+				state.queue.pop_front();
+			}
+			Some(Ok((len, Some(src))))
+		})
+	}
+
+	fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+		let mut state = self.state.lock();
+
+		if how != Shutdown::Write {
+			state.read_shutdown = true;
+			state.queue.clear();
+		}
+		if how != Shutdown::Read {
+			state.write_shutdown = true;
+		}
+
+		Ok(())
+	}
+
+	fn local_address(&self) -> io::Result<SocketAddress> {
+		Ok(self.state.lock().local.clone())
+	}
+
+	fn peer_address(&self) -> io::Result<SocketAddress> {
+		self.state
+			.lock()
+			.peer
+			.as_ref()
+			.map(|(_, addr)| addr.clone())
+			.ok_or(io::Error::ENOTCONN)
+	}
+}
@@ -0,0 +1,89 @@
+//! Handle to wait for the termination of a kernel task
+
+use crate::scheduler::task::{PriorityTaskQueue, TaskId};
+use crate::scheduler::{block_current_task, reschedule, wakeup_task};
+use crate::synch::spinlock::SpinlockIrqSave;
+use alloc::sync::Arc;
+
+struct PacketInner<T> {
+	/// return value of the task, which is available after its termination
+	result: Option<T>,
+	/// Priority queue of tasks, which wait for the termination
+	queue: PriorityTaskQueue,
+}
+
+/// Shared state between a kernel task and its `JoinHandle`
+pub(crate) struct Packet<T> {
+	inner: SpinlockIrqSave<PacketInner<T>>,
+}
+
+// The task queue is only accessed with disabled interrupts
+unsafe impl<T: Send> Sync for Packet<T> {}
+unsafe impl<T: Send> Send for Packet<T> {}
+
+impl<T> Packet<T> {
+	pub fn new() -> Self {
+		Packet {
+			inner: SpinlockIrqSave::new(PacketInner {
+				result: None,
+				queue: PriorityTaskQueue::new(),
+			}),
+		}
+	}
+
+	/// Store the return value of the task and wake up all waiting tasks
+	pub fn set_result(&self, result: T) {
+		let mut guard = self.inner.lock();
+
+		guard.result = Some(result);
+		while let Some(task) = guard.queue.pop() {
+			wakeup_task(task);
+		}
+	}
+}
+
+/// An owned permission to join on a kernel task (block on its termination)
+///
+/// A `JoinHandle` is returned by `scheduler::spawn_with` and
+/// `scheduler::spawn_named_with`. Dropping the handle detaches
+/// the task, which continues to run.
+pub struct JoinHandle<T> {
+	id: TaskId,
+	packet: Arc<Packet<T>>,
+}
+
+impl<T> JoinHandle<T> {
+	pub(crate) fn new(id: TaskId, packet: Arc<Packet<T>>) -> Self {
+		JoinHandle { id, packet }
+	}
+
+	/// Returns the identifier of the associated task
+	pub fn id(&self) -> TaskId {
+		self.id
+	}
+
+	/// Checks if the associated task has finished running its closure
+	pub fn is_finished(&self) -> bool {
+		self.packet.inner.lock().result.is_some()
+	}
+
+	/// Waits for the associated task to finish and returns its result
+	///
+	/// The idle task is not able to block. Consequently, `join`
+	/// must not be called by the idle task.
+	pub fn join(self) -> T {
+		loop {
+			{
+				let mut guard = self.packet.inner.lock();
+
+				if let Some(result) = guard.result.take() {
+					return result;
+				}
+
+				guard.queue.push(block_current_task());
+			}
+
+			reschedule();
+		}
+	}
+}
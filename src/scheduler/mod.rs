@@ -1,17 +1,26 @@
 //! Interface to the scheduler
 
+mod join;
 mod scheduler;
 /// task control block
 pub mod task;
 
+pub use crate::scheduler::join::JoinHandle;
+
 use crate::arch;
 use crate::arch::mm::{PhysAddr, VirtAddr};
 use crate::errno::*;
 use crate::fd::{FileDescriptor, IoInterface};
 use crate::io;
-use crate::scheduler::task::{Task, TaskPriority};
+use crate::scheduler::join::Packet;
+use crate::scheduler::task::{CloneFlags, Credentials, MemoryRegion, Task, TaskInfo, TaskPriority};
+use crate::synch::futex::futex_wake;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::cell::RefCell;
 
 static mut SCHEDULER: Option<scheduler::Scheduler> = None;
@@ -25,30 +34,162 @@ pub(crate) fn init() {
 	arch::register_task();
 }
 
+/// Entry point of a kernel task, which is created by `spawn`
+extern "C" fn call_func(func: usize) {
+	let func: extern "C" fn() = unsafe { core::mem::transmute(func) };
+
+	func();
+}
+
+/// Entry point of a kernel task, which is created by `spawn_with`
+extern "C" fn call_closure(arg: usize) {
+	let closure = unsafe { Box::from_raw(arg as *mut Box<dyn FnOnce() + Send + 'static>) };
+
+	closure();
+}
+
+/// Name of kernel tasks, which are created by `spawn` or `spawn_with`
+const DEFAULT_TASK_NAME: &str = "kernel";
+
 /// Create a new kernel task
 pub fn spawn(func: extern "C" fn(), prio: TaskPriority) -> Result<task::TaskId> {
-	unsafe { SCHEDULER.as_mut().unwrap().spawn(func, prio) }
+	spawn_named(DEFAULT_TASK_NAME, func, prio)
+}
+
+/// Create a new kernel task with the name `name`
+pub fn spawn_named(name: &str, func: extern "C" fn(), prio: TaskPriority) -> Result<task::TaskId> {
+	unsafe {
+		SCHEDULER
+			.as_mut()
+			.unwrap()
+			.spawn(name, call_func, func as usize, prio)
+	}
+}
+
+/// Create a new kernel task, which runs the closure `f`
+///
+/// The returned `JoinHandle` can be used to wait for the
+/// termination of the task and to receive the result of `f`.
+pub fn spawn_with<F, T>(f: F, prio: TaskPriority) -> Result<JoinHandle<T>>
+where
+	F: FnOnce() -> T + Send + 'static,
+	T: Send + 'static,
+{
+	spawn_named_with(DEFAULT_TASK_NAME, f, prio)
+}
+
+/// Create a new kernel task with the name `name`, which runs the closure `f`
+///
+/// The returned `JoinHandle` can be used to wait for the
+/// termination of the task and to receive the result of `f`.
+pub fn spawn_named_with<F, T>(name: &str, f: F, prio: TaskPriority) -> Result<JoinHandle<T>>
+where
+	F: FnOnce() -> T + Send + 'static,
+	T: Send + 'static,
+{
+	let packet = Arc::new(Packet::new());
+	let their_packet = packet.clone();
+	let closure: Box<dyn FnOnce() + Send + 'static> = Box::new(move || {
+		their_packet.set_result(f());
+	});
+	let arg = Box::into_raw(Box::new(closure));
+
+	match unsafe {
+		SCHEDULER
+			.as_mut()
+			.unwrap()
+			.spawn(name, call_closure, arg as usize, prio)
+	} {
+		Ok(id) => Ok(JoinHandle::new(id, packet)),
+		Err(err) => {
+			// the task isn't created => release the closure
+			drop(unsafe { Box::from_raw(arg) });
+			Err(err)
+		}
+	}
 }
 
 /// Trigger the scheduler to switch to the next available task
 pub fn reschedule() {
 	unsafe { SCHEDULER.as_mut().unwrap().reschedule() }
+
+	exit_if_killed();
+}
+
+/// Terminate the current task, if another thread of its group has called `exit_group`
+pub(crate) fn exit_if_killed() {
+	if unsafe { SCHEDULER.as_mut().unwrap().is_current_task_exiting() } {
+		do_exit();
+	}
 }
 
 /// Timer interrupt  call scheduler to switch to the next available task
 pub(crate) fn schedule() {
-	unsafe { SCHEDULER.as_mut().unwrap().schedule() }
+	unsafe {
+		let scheduler = SCHEDULER.as_mut().unwrap();
+
+		scheduler.account_tick();
+		scheduler.wakeup_sleeping_tasks(arch::irq::get_timer_ticks());
+		scheduler.schedule()
+	}
+}
+
+/// Block the current task for `ticks` timer ticks
+pub fn sleep(ticks: u64) {
+	let deadline = arch::irq::get_timer_ticks() + ticks;
+
+	unsafe { SCHEDULER.as_mut().unwrap().sleep_until(deadline) }
+
+	exit_if_killed();
+}
+
+/// Create a new thread, which returns from the current system call
+/// with the same user-level context as the current task
+pub(crate) fn clone(
+	flags: CloneFlags,
+	user_stack: VirtAddr,
+	parent_tid: VirtAddr,
+	child_tid: VirtAddr,
+	tls: Option<VirtAddr>,
+) -> io::Result<task::TaskId> {
+	unsafe {
+		SCHEDULER
+			.as_mut()
+			.unwrap()
+			.clone(flags, user_stack, parent_tid, child_tid, tls)
+	}
 }
 
 /// Terminate the current running task
 pub fn do_exit() -> ! {
+	// wake up tasks, which wait for the termination of the current thread
+	if let Some(addr) = unsafe { SCHEDULER.as_mut().unwrap().take_clear_child_tid() } {
+		unsafe {
+			*addr.as_mut_ptr::<u32>() = 0;
+		}
+		futex_wake(addr, 1);
+	}
+
+	crate::fs::release_task(get_current_taskid());
+
 	unsafe {
 		SCHEDULER.as_mut().unwrap().exit();
 	}
 }
 
+/// Terminate all threads of the current process
+pub fn do_exit_group() -> ! {
+	unsafe {
+		SCHEDULER.as_mut().unwrap().kill_thread_group();
+	}
+
+	do_exit();
+}
+
 /// Terminate the current running task
 pub fn abort() -> ! {
+	crate::fs::release_task(get_current_taskid());
+
 	unsafe { SCHEDULER.as_mut().unwrap().abort() }
 }
 
@@ -56,6 +197,11 @@ pub(crate) fn get_current_interrupt_stack() -> VirtAddr {
 	unsafe { SCHEDULER.as_mut().unwrap().get_current_interrupt_stack() }
 }
 
+/// Returns true, if `addr` is part of a guard page of the current task's stacks
+pub(crate) fn is_guard_page(addr: VirtAddr) -> bool {
+	unsafe { SCHEDULER.as_ref().unwrap().is_guard_page(addr) }
+}
+
 pub(crate) fn get_root_page_table() -> PhysAddr {
 	unsafe { SCHEDULER.as_mut().unwrap().get_root_page_table() }
 }
@@ -66,6 +212,43 @@ pub(crate) fn set_root_page_table(addr: PhysAddr) {
 	}
 }
 
+/// Set the user-level address, which will be cleared at the exit of the current task
+pub(crate) fn set_clear_child_tid(addr: Option<VirtAddr>) {
+	unsafe { SCHEDULER.as_mut().unwrap().set_clear_child_tid(addr) }
+}
+
+/// Reserve `size` bytes of virtual memory in the address space of the current task
+pub(crate) fn reserve_user_memory(size: usize) -> Option<VirtAddr> {
+	unsafe { SCHEDULER.as_mut().unwrap().reserve_user_memory(size) }
+}
+
+/// Reserve the range `[addr, addr + size)` in the address space of the current task
+pub(crate) fn reserve_fixed_user_memory(addr: VirtAddr, size: usize) {
+	unsafe {
+		SCHEDULER
+			.as_mut()
+			.unwrap()
+			.reserve_fixed_user_memory(addr, size)
+	}
+}
+
+/// Set the size of the executable, which is mapped into the address space of the current task
+pub(crate) fn set_image_size(size: usize) {
+	unsafe { SCHEDULER.as_ref().unwrap().set_image_size(size) }
+}
+
+/// Returns the memory regions of the user space of task `id`
+pub(crate) fn get_memory_regions(id: task::TaskId) -> Option<Vec<MemoryRegion>> {
+	unsafe { SCHEDULER.as_ref().unwrap().get_memory_regions(id) }
+}
+
+/// Returns a copy of the file descriptor table of task `id`
+pub(crate) fn get_file_descriptors(
+	id: task::TaskId,
+) -> Option<BTreeMap<FileDescriptor, Arc<dyn IoInterface>>> {
+	unsafe { SCHEDULER.as_ref().unwrap().get_file_descriptors(id) }
+}
+
 pub(crate) fn block_current_task() -> Rc<RefCell<Task>> {
 	unsafe { SCHEDULER.as_mut().unwrap().block_current_task() }
 }
@@ -94,11 +277,82 @@ pub(crate) fn remove_io_interface(fd: FileDescriptor) -> io::Result<Arc<dyn IoIn
 	unsafe { SCHEDULER.as_mut().unwrap().remove_io_interface(fd) }
 }
 
+/// Set the name of the current running task
+pub fn set_current_task_name(name: &str) {
+	unsafe { SCHEDULER.as_mut().unwrap().set_current_task_name(name) }
+}
+
+/// Get the name of the current running task
+pub fn get_current_task_name() -> String {
+	unsafe { SCHEDULER.as_ref().unwrap().get_current_task_name() }
+}
+
+/// Set the absolute path of the working directory of the current task
+pub(crate) fn set_current_working_dir(path: String) {
+	unsafe { SCHEDULER.as_mut().unwrap().set_current_working_dir(path) }
+}
+
+/// Get the absolute path of the working directory of the current task
+pub(crate) fn get_current_working_dir() -> String {
+	unsafe { SCHEDULER.as_ref().unwrap().get_current_working_dir() }
+}
+
+/// Get the file mode creation mask of the current task
+pub fn get_current_umask() -> u32 {
+	unsafe { SCHEDULER.as_ref().unwrap().get_current_umask() }
+}
+
+/// Set the file mode creation mask of the current task and return the previous mask
+pub fn set_current_umask(mask: u32) -> u32 {
+	unsafe { SCHEDULER.as_mut().unwrap().set_current_umask(mask) }
+}
+
+/// Get the user and group identities of the current task
+pub fn get_current_credentials() -> Credentials {
+	unsafe { SCHEDULER.as_ref().unwrap().get_current_credentials() }
+}
+
+/// Set the user and group identities of the current process
+pub(crate) fn set_current_credentials(credentials: Credentials) {
+	unsafe {
+		SCHEDULER
+			.as_mut()
+			.unwrap()
+			.set_current_credentials(credentials)
+	}
+}
+
+/// Call `f` for a snapshot of each task
+///
+/// The snapshot is created in advance. Consequently, `f` is
+/// able to use all kernel services, e.g. to print the information.
+pub fn for_each_task<F: FnMut(&TaskInfo)>(mut f: F) {
+	let infos = unsafe { SCHEDULER.as_ref().unwrap().get_task_infos() };
+
+	for info in infos.iter() {
+		f(info);
+	}
+}
+
+/// Print a list of all tasks (similar to `ps`)
+///
+/// The list is printed without allocating memory. Consequently,
+/// the function is callable by an interrupt handler.
+pub fn dump_tasks() {
+	let mut console = crate::console::Console {};
+	let _ = unsafe { SCHEDULER.as_ref().unwrap().write_task_list(&mut console) };
+}
+
 /// Get the TaskID of the current running task
 pub fn get_current_taskid() -> task::TaskId {
 	unsafe { SCHEDULER.as_ref().unwrap().get_current_taskid() }
 }
 
+/// Get the ID of the thread group (i.e. the process) of the current running task
+pub fn get_current_tgid() -> task::TaskId {
+	unsafe { SCHEDULER.as_ref().unwrap().get_current_tgid() }
+}
+
 pub(crate) struct DisabledPreemption {
 	irq_enabled: bool,
 }
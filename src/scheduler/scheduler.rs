@@ -8,10 +8,14 @@ use crate::fd::{FileDescriptor, IoInterface};
 use crate::io;
 use crate::logging::*;
 use crate::scheduler::task::*;
+use crate::synch::futex::futex_remove;
 use alloc::collections::{BTreeMap, VecDeque};
 use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::fmt;
 use core::sync::atomic::{AtomicU32, Ordering};
 
 static TID_COUNTER: AtomicU32 = AtomicU32::new(0);
@@ -27,6 +31,25 @@ pub(crate) struct Scheduler {
 	finished_tasks: VecDeque<TaskId>,
 	/// map between task id and task control block
 	tasks: BTreeMap<TaskId, Rc<RefCell<Task>>>,
+	/// blocked tasks and the timer tick, at which they are woken up
+	sleeping_tasks: Vec<(u64, Rc<RefCell<Task>>)>,
+}
+
+/// Returns the number of bytes, which are currently used on the kernel stack of
+/// `task`. The current task runs on the stack, which contains `current_stack_pointer`.
+fn stack_usage(task: &Task, current_id: TaskId, current_stack_pointer: VirtAddr) -> usize {
+	let stack_pointer = if task.id == current_id {
+		current_stack_pointer
+	} else {
+		task.last_stack_pointer
+	};
+	let stack = &task.stack;
+
+	if stack_pointer >= stack.bottom() && stack_pointer <= stack.top() {
+		(stack.top() - stack_pointer).as_usize()
+	} else {
+		0
+	}
 }
 
 impl Scheduler {
@@ -43,6 +66,7 @@ impl Scheduler {
 			ready_queue: PriorityTaskQueue::new(),
 			finished_tasks: VecDeque::<TaskId>::new(),
 			tasks,
+			sleeping_tasks: Vec::new(),
 		}
 	}
 
@@ -56,7 +80,13 @@ impl Scheduler {
 		}
 	}
 
-	pub fn spawn(&mut self, func: extern "C" fn(), prio: TaskPriority) -> Result<TaskId> {
+	pub fn spawn(
+		&mut self,
+		name: &str,
+		func: extern "C" fn(usize),
+		arg: usize,
+		prio: TaskPriority,
+	) -> Result<TaskId> {
 		let closure = || {
 			let prio_number: usize = prio.into().into();
 
@@ -66,15 +96,65 @@ impl Scheduler {
 
 			// Create the new task.
 			let tid = self.get_tid();
-			let task = Rc::new(RefCell::new(Task::new(tid, TaskStatus::Ready, prio)));
+			let task = Rc::new(RefCell::new(Task::new(tid, name, TaskStatus::Ready, prio)));
+
+			task.borrow_mut().create_stack_frame(func, arg);
+
+			// Add it to the task lists.
+			self.ready_queue.push(task.clone());
+			self.tasks.insert(tid, task);
+
+			info!("Creating task {} ({})", tid, name);
+
+			Ok(tid)
+		};
 
-			task.borrow_mut().create_stack_frame(func);
+		irqsave(closure)
+	}
+
+	/// Create a new thread, which continues the current system call
+	/// of the current task in user space
+	pub fn clone(
+		&mut self,
+		flags: CloneFlags,
+		user_stack: VirtAddr,
+		parent_tid: VirtAddr,
+		child_tid: VirtAddr,
+		tls: Option<VirtAddr>,
+	) -> io::Result<TaskId> {
+		let closure = || {
+			let tid = self.get_tid();
+			let parent_stack = self.current_task.borrow().stack.top();
+			let task = Rc::new(RefCell::new(Task::new_thread(
+				tid,
+				&self.current_task.borrow(),
+				flags,
+			)));
+
+			task.borrow_mut()
+				.create_thread_frame(parent_stack, user_stack, tls);
+
+			// The address space is shared => the thread identifier is
+			// visible for parent and child before the child runs
+			if flags.contains(CloneFlags::CLONE_PARENT_SETTID) && !parent_tid.is_zero() {
+				unsafe {
+					*parent_tid.as_mut_ptr::<u32>() = tid.into();
+				}
+			}
+			if flags.contains(CloneFlags::CLONE_CHILD_SETTID) && !child_tid.is_zero() {
+				unsafe {
+					*child_tid.as_mut_ptr::<u32>() = tid.into();
+				}
+			}
+			if flags.contains(CloneFlags::CLONE_CHILD_CLEARTID) && !child_tid.is_zero() {
+				task.borrow_mut().clear_child_tid = Some(child_tid);
+			}
 
 			// Add it to the task lists.
 			self.ready_queue.push(task.clone());
 			self.tasks.insert(tid, task);
 
-			info!("Creating task {}", tid);
+			info!("Creating thread {}", tid);
 
 			Ok(tid)
 		};
@@ -82,9 +162,236 @@ impl Scheduler {
 		irqsave(closure)
 	}
 
+	/// Mark all other threads of the current thread group as exiting
+	///
+	/// A thread may be preempted, while it holds a lock in the kernel.
+	/// Therefore, it isn't dropped here. Instead, blocked threads are woken
+	/// up and each thread terminates itself at its next reschedule point.
+	pub fn kill_thread_group(&mut self) {
+		let closure = || {
+			let (current_id, tgid) = {
+				let borrowed = self.current_task.borrow();
+				(borrowed.id, borrowed.tgid)
+			};
+			let siblings: Vec<Rc<RefCell<Task>>> = self
+				.tasks
+				.iter()
+				.filter(|(id, task)| **id != current_id && task.borrow().tgid == tgid)
+				.map(|(_, task)| task.clone())
+				.collect();
+
+			for task in siblings {
+				let id = task.borrow().id;
+
+				info!("kill thread {}", id);
+
+				task.borrow_mut().exiting = true;
+				futex_remove(id);
+				self.sleeping_tasks
+					.retain(|(_, task)| task.borrow().id != id);
+				self.wakeup_task(task);
+			}
+		};
+
+		irqsave(closure);
+	}
+
+	/// Returns true, if the current task has to terminate itself,
+	/// because another thread of its group has called `exit_group`
+	pub fn is_current_task_exiting(&self) -> bool {
+		irqsave(|| self.current_task.borrow().exiting)
+	}
+
+	/// Set the user-level address, which will be cleared at the exit of the current task
+	pub fn set_clear_child_tid(&mut self, addr: Option<VirtAddr>) {
+		irqsave(|| self.current_task.borrow_mut().clear_child_tid = addr);
+	}
+
+	/// Take the user-level address, which has to be cleared at the exit of the current task
+	pub fn take_clear_child_tid(&mut self) -> Option<VirtAddr> {
+		irqsave(|| self.current_task.borrow_mut().clear_child_tid.take())
+	}
+
+	/// Set the name of the current task
+	pub fn set_current_task_name(&mut self, name: &str) {
+		irqsave(|| self.current_task.borrow_mut().name = String::from(name));
+	}
+
+	/// Get the name of the current task
+	pub fn get_current_task_name(&self) -> String {
+		irqsave(|| self.current_task.borrow().name.clone())
+	}
+
+	/// Set the working directory of the current task
+	pub fn set_current_working_dir(&mut self, path: String) {
+		irqsave(|| *self.current_task.borrow().cwd.borrow_mut() = path);
+	}
+
+	/// Get the working directory of the current task
+	pub fn get_current_working_dir(&self) -> String {
+		irqsave(|| self.current_task.borrow().cwd.borrow().clone())
+	}
+
+	/// Get the file mode creation mask of the current task
+	pub fn get_current_umask(&self) -> u32 {
+		irqsave(|| self.current_task.borrow().umask.get())
+	}
+
+	/// Set the file mode creation mask of the current task and return the previous mask
+	pub fn set_current_umask(&mut self, mask: u32) -> u32 {
+		irqsave(|| self.current_task.borrow().umask.replace(mask))
+	}
+
+	/// Get the user and group identities of the current task
+	pub fn get_current_credentials(&self) -> Credentials {
+		irqsave(|| self.current_task.borrow().credentials.borrow().clone())
+	}
+
+	/// Set the user and group identities of the current process
+	pub fn set_current_credentials(&mut self, credentials: Credentials) {
+		irqsave(|| *self.current_task.borrow().credentials.borrow_mut() = credentials);
+	}
+
+	/// Charge the current timer tick to the current task
+	pub fn account_tick(&mut self) {
+		self.current_task.borrow_mut().runtime += 1;
+	}
+
+	/// Create a snapshot of all tasks
+	pub fn get_task_infos(&self) -> Vec<TaskInfo> {
+		// the current task runs on this stack => use
+		// the address of a local variable as stack pointer
+		let marker: u8 = 0;
+		let current_stack_pointer = VirtAddr::from(&marker as *const u8 as usize);
+
+		let closure = || {
+			let current_id = self.current_task.borrow().id;
+
+			self.tasks
+				.values()
+				.map(|task| {
+					let borrowed = task.borrow();
+					let credentials = borrowed.credentials.borrow().clone();
+
+					TaskInfo {
+						id: borrowed.id,
+						tgid: borrowed.tgid,
+						name: borrowed.name.clone(),
+						status: borrowed.status,
+						prio: borrowed.prio,
+						runtime: borrowed.runtime * 1000 / u64::from(TIMER_FREQ),
+						stack_usage: stack_usage(&borrowed, current_id, current_stack_pointer),
+						stack_high_water: borrowed.stack.high_water_mark(),
+						credentials,
+					}
+				})
+				.collect()
+		};
+
+		irqsave(closure)
+	}
+
+	/// Print a list of all tasks to `w`
+	///
+	/// In contrast to `get_task_infos`, the list is printed without
+	/// allocating memory. Consequently, the function is callable by an
+	/// interrupt handler. Tasks, which are currently borrowed by the
+	/// interrupted code, are skipped.
+	pub fn write_task_list(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+		let marker: u8 = 0;
+		let current_stack_pointer = VirtAddr::from(&marker as *const u8 as usize);
+
+		let closure = || {
+			let current_id = self
+				.current_task
+				.try_borrow()
+				.map(|task| task.id)
+				.map_err(|_| fmt::Error)?;
+
+			writeln!(
+				w,
+				"{:>5} {:>5} {:<16} {:<8} {:>4} {:>10} {:>6} {:>6}",
+				"TID", "PID", "NAME", "STATE", "PRIO", "TIME(ms)", "STACK", "MAXSTK"
+			)?;
+			for task in self.tasks.values() {
+				let Ok(borrowed) = task.try_borrow() else {
+					continue;
+				};
+
+				write!(
+					w,
+					"{:>5} {:>5} {:<16} {:<8} {:>4} {:>10} {:>6} ",
+					borrowed.id.into(),
+					borrowed.tgid.into(),
+					borrowed.name,
+					borrowed.status.as_str(),
+					borrowed.prio.into(),
+					borrowed.runtime * 1000 / u64::from(TIMER_FREQ),
+					stack_usage(&borrowed, current_id, current_stack_pointer),
+				)?;
+				match borrowed.stack.high_water_mark() {
+					Some(n) => writeln!(w, "{:>6}", n)?,
+					None => writeln!(w, "{:>6}", "-")?,
+				}
+			}
+
+			Ok(())
+		};
+
+		irqsave(closure)
+	}
+
+	/// Returns the memory regions of the user space of task `id`
+	pub fn get_memory_regions(&self, id: TaskId) -> Option<Vec<MemoryRegion>> {
+		irqsave(|| {
+			self.tasks
+				.get(&id)
+				.map(|task| task.borrow().address_space.regions())
+		})
+	}
+
+	/// Returns a copy of the file descriptor table of task `id`
+	pub fn get_file_descriptors(
+		&self,
+		id: TaskId,
+	) -> Option<BTreeMap<FileDescriptor, Arc<dyn IoInterface>>> {
+		irqsave(|| {
+			self.tasks
+				.get(&id)
+				.map(|task| task.borrow().fd_map.borrow().clone())
+		})
+	}
+
+	/// Set the size of the executable, which is mapped into the address space of the current task
+	pub fn set_image_size(&self, size: usize) {
+		irqsave(|| {
+			self.current_task
+				.borrow()
+				.address_space
+				.set_image_size(size)
+		})
+	}
+
+	/// Reserve virtual memory in the address space of the current task
+	pub fn reserve_user_memory(&mut self, size: usize) -> Option<VirtAddr> {
+		irqsave(|| self.current_task.borrow().address_space.reserve(size))
+	}
+
+	/// Reserve a fixed range of virtual memory in the address space of the current task
+	pub fn reserve_fixed_user_memory(&mut self, addr: VirtAddr, size: usize) {
+		irqsave(|| {
+			self.current_task
+				.borrow()
+				.address_space
+				.reserve_fixed(addr, size)
+		})
+	}
+
 	fn cleanup(&mut self) {
-		// destroy user space
-		drop_user_space();
+		// destroy user space, if no other thread uses it
+		if Rc::strong_count(&self.current_task.borrow().address_space) == 1 {
+			drop_user_space();
+		}
 
 		self.current_task.borrow_mut().status = TaskStatus::Finished;
 	}
@@ -140,6 +447,30 @@ impl Scheduler {
 		irqsave(closure)
 	}
 
+	/// Block the current task until the timer tick `deadline`
+	pub fn sleep_until(&mut self, deadline: u64) {
+		irqsave(|| {
+			let task = self.block_current_task();
+			self.sleeping_tasks.push((deadline, task));
+		});
+
+		self.reschedule();
+	}
+
+	/// Wake up the sleeping tasks, whose deadline is reached at the timer tick `now`
+	pub fn wakeup_sleeping_tasks(&mut self, now: u64) {
+		let mut i = 0;
+
+		while i < self.sleeping_tasks.len() {
+			if self.sleeping_tasks[i].0 <= now {
+				let (_, task) = self.sleeping_tasks.swap_remove(i);
+				self.wakeup_task(task);
+			} else {
+				i += 1;
+			}
+		}
+	}
+
 	pub fn wakeup_task(&mut self, task: Rc<RefCell<Task>>) {
 		let closure = || {
 			if task.borrow().status == TaskStatus::Blocked {
@@ -160,7 +491,7 @@ impl Scheduler {
 		let new_fd = || -> io::Result<FileDescriptor> {
 			let mut fd: FileDescriptor = 0;
 			loop {
-				if !self.current_task.borrow().fd_map.contains_key(&fd) {
+				if !self.current_task.borrow().fd_map.borrow().contains_key(&fd) {
 					break Ok(fd);
 				} else if fd == FileDescriptor::MAX {
 					break Err(io::Error::EOVERFLOW);
@@ -172,8 +503,9 @@ impl Scheduler {
 
 		let fd = new_fd()?;
 		self.current_task
-			.borrow_mut()
+			.borrow()
 			.fd_map
+			.borrow_mut()
 			.insert(fd, io_interface.clone());
 
 		Ok(fd)
@@ -181,8 +513,9 @@ impl Scheduler {
 
 	pub fn remove_io_interface(&self, fd: FileDescriptor) -> io::Result<Arc<dyn IoInterface>> {
 		self.current_task
-			.borrow_mut()
+			.borrow()
 			.fd_map
+			.borrow_mut()
 			.remove(&fd)
 			.ok_or(io::Error::EBADF)
 	}
@@ -192,7 +525,7 @@ impl Scheduler {
 		fd: FileDescriptor,
 	) -> crate::io::Result<Arc<dyn IoInterface>> {
 		let closure = || {
-			if let Some(io_interface) = self.current_task.borrow().fd_map.get(&fd) {
+			if let Some(io_interface) = self.current_task.borrow().fd_map.borrow().get(&fd) {
 				Ok(io_interface.clone())
 			} else {
 				Err(crate::io::Error::ENOENT)
@@ -206,6 +539,15 @@ impl Scheduler {
 		irqsave(|| self.current_task.borrow().id)
 	}
 
+	pub fn get_current_tgid(&self) -> TaskId {
+		irqsave(|| self.current_task.borrow().tgid)
+	}
+
+	/// Returns true, if `addr` is part of a guard page of the current task's stacks
+	pub fn is_guard_page(&self, addr: VirtAddr) -> bool {
+		irqsave(|| self.current_task.borrow().stack.is_guard_page(addr))
+	}
+
 	/// Determines the start address of the stack
 	#[no_mangle]
 	pub fn get_current_interrupt_stack(&self) -> VirtAddr {
@@ -213,11 +555,11 @@ impl Scheduler {
 	}
 
 	pub fn get_root_page_table(&self) -> PhysAddr {
-		self.current_task.borrow().root_page_table
+		self.current_task.borrow().address_space.root_page_table()
 	}
 
 	pub fn set_root_page_table(&self, addr: PhysAddr) {
-		self.current_task.borrow_mut().root_page_table = addr;
+		self.current_task.borrow_mut().address_space = Rc::new(AddressSpace::new(addr));
 	}
 
 	pub fn schedule(&mut self) {
@@ -1,8 +1,10 @@
 #![allow(dead_code)]
 
 use crate::arch;
+use crate::arch::mm::paging::{self, PageTableEntryFlags};
 use crate::arch::mm::PhysAddr;
 use crate::arch::mm::VirtAddr;
+use crate::arch::mm::{physicalmem, virtualmem};
 use crate::arch::{BasePageSize, PageSize};
 use crate::consts::*;
 use crate::fd::stdio::{GenericStderr, GenericStdin, GenericStdout};
@@ -11,13 +13,15 @@ use crate::logging::*;
 use alloc::boxed::Box;
 use alloc::collections::{BTreeMap, VecDeque};
 use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::sync::Arc;
-use core::cell::RefCell;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
 use core::fmt;
 
 /// The status of the task - used for scheduling
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub(crate) enum TaskStatus {
+pub enum TaskStatus {
 	Invalid,
 	Ready,
 	Running,
@@ -26,6 +30,20 @@ pub(crate) enum TaskStatus {
 	Idle,
 }
 
+impl TaskStatus {
+	/// Returns the name of the status
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			TaskStatus::Invalid => "Invalid",
+			TaskStatus::Ready => "Ready",
+			TaskStatus::Running => "Running",
+			TaskStatus::Blocked => "Blocked",
+			TaskStatus::Finished => "Finished",
+			TaskStatus::Idle => "Idle",
+		}
+	}
+}
+
 /// Unique identifier for a task (i.e. `pid`).
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub struct TaskId(u32);
@@ -88,6 +106,11 @@ impl PriorityTaskQueue {
 		}
 	}
 
+	/// Returns true, if the queue doesn't contain any task
+	pub fn is_empty(&self) -> bool {
+		self.prio_bitmap == 0
+	}
+
 	/// Add a task by its priority to the queue
 	pub fn push(&mut self, task: Rc<RefCell<Task>>) {
 		let i: usize = task.borrow().prio.into().into();
@@ -115,6 +138,22 @@ impl PriorityTaskQueue {
 		None
 	}
 
+	/// Remove the task with the identifier `id` from the queue
+	pub fn remove(&mut self, id: TaskId) -> Option<Rc<RefCell<Task>>> {
+		for i in 0..NO_PRIORITIES {
+			if let Some(pos) = self.queues[i].iter().position(|t| t.borrow().id == id) {
+				let task = self.queues[i].remove(pos);
+				if self.queues[i].is_empty() {
+					self.prio_bitmap &= !(1 << i);
+				}
+
+				return task;
+			}
+		}
+
+		None
+	}
+
 	/// Pop the next task, which has a higher or the same priority as `prio`
 	pub fn pop_with_prio(&mut self, prio: TaskPriority) -> Option<Rc<RefCell<Task>>> {
 		if let Some(i) = self.prio_bitmap.highest_one() {
@@ -134,13 +173,34 @@ pub(crate) trait Stack {
 	fn bottom(&self) -> VirtAddr;
 	fn interrupt_top(&self) -> VirtAddr;
 	fn interrupt_bottom(&self) -> VirtAddr;
+
+	/// Returns true, if `addr` is part of a guard page of the stack
+	fn is_guard_page(&self, _addr: VirtAddr) -> bool {
+		false
+	}
+
+	/// Returns the maximum number of bytes, which have been used on
+	/// the stack so far, if the stack is filled with `STACK_PATTERN`
+	fn high_water_mark(&self) -> Option<usize> {
+		None
+	}
 }
 
-#[derive(Copy, Clone)]
-#[repr(C, align(64))]
+/// Size of the unmapped guard page below a stack
+const GUARD_PAGE_SIZE: usize = BasePageSize::SIZE;
+
+/// Kernel stack and interrupt stack of a task
+///
+/// Both stacks are mapped into the virtual address space of the
+/// kernel, where an unmapped guard page below each stack detects an
+/// overflow. Layout: guard page | stack | guard page | interrupt stack
 pub(crate) struct TaskStack {
-	buffer: [u8; STACK_SIZE],
-	ist_buffer: [u8; INTERRUPT_STACK_SIZE],
+	/// start of the guard page below the stack
+	start: VirtAddr,
+	/// physical address of the stack
+	physical_stack: PhysAddr,
+	/// physical address of the interrupt stack
+	physical_ist: PhysAddr,
 }
 
 impl Default for TaskStack {
@@ -150,29 +210,265 @@ impl Default for TaskStack {
 }
 
 impl TaskStack {
-	pub const fn new() -> TaskStack {
+	const VIRTUAL_SIZE: usize = 2 * GUARD_PAGE_SIZE + STACK_SIZE + INTERRUPT_STACK_SIZE;
+
+	pub fn new() -> TaskStack {
+		let start = virtualmem::allocate(Self::VIRTUAL_SIZE);
+		let physical_stack = physicalmem::allocate(STACK_SIZE);
+		let physical_ist = physicalmem::allocate(INTERRUPT_STACK_SIZE);
+		let flags = PageTableEntryFlags::WRITABLE | PageTableEntryFlags::EXECUTE_DISABLE;
+
+		paging::map::<BasePageSize>(
+			start + GUARD_PAGE_SIZE,
+			physical_stack,
+			STACK_SIZE / BasePageSize::SIZE,
+			flags,
+		);
+		paging::map::<BasePageSize>(
+			start + 2 * GUARD_PAGE_SIZE + STACK_SIZE,
+			physical_ist,
+			INTERRUPT_STACK_SIZE / BasePageSize::SIZE,
+			flags,
+		);
+
 		TaskStack {
-			buffer: [0; STACK_SIZE],
-			ist_buffer: [0; INTERRUPT_STACK_SIZE],
+			start,
+			physical_stack,
+			physical_ist,
 		}
 	}
 }
 
+impl Drop for TaskStack {
+	fn drop(&mut self) {
+		debug!("Deallocate stack at 0x{:x}", self.start);
+
+		paging::unmap::<BasePageSize>(self.bottom(), STACK_SIZE / BasePageSize::SIZE);
+		paging::unmap::<BasePageSize>(
+			self.interrupt_bottom(),
+			INTERRUPT_STACK_SIZE / BasePageSize::SIZE,
+		);
+		physicalmem::deallocate(self.physical_stack, STACK_SIZE);
+		physicalmem::deallocate(self.physical_ist, INTERRUPT_STACK_SIZE);
+		virtualmem::deallocate(self.start, Self::VIRTUAL_SIZE);
+	}
+}
+
 impl Stack for TaskStack {
 	fn top(&self) -> VirtAddr {
-		VirtAddr::from(self.buffer.as_ptr() as usize + STACK_SIZE - 16)
+		self.bottom() + (STACK_SIZE - 16)
 	}
 
 	fn bottom(&self) -> VirtAddr {
-		VirtAddr::from(self.buffer.as_ptr() as usize)
+		self.start + GUARD_PAGE_SIZE
 	}
 
 	fn interrupt_top(&self) -> VirtAddr {
-		VirtAddr::from(self.ist_buffer.as_ptr() as usize + INTERRUPT_STACK_SIZE - 16)
+		self.interrupt_bottom() + (INTERRUPT_STACK_SIZE - 16)
 	}
 
 	fn interrupt_bottom(&self) -> VirtAddr {
-		VirtAddr::from(self.ist_buffer.as_ptr() as usize)
+		self.start + 2 * GUARD_PAGE_SIZE + STACK_SIZE
+	}
+
+	fn is_guard_page(&self, addr: VirtAddr) -> bool {
+		let stack_guard = self.start;
+		let ist_guard = self.bottom() + STACK_SIZE;
+
+		(addr >= stack_guard && addr < stack_guard + GUARD_PAGE_SIZE)
+			|| (addr >= ist_guard && addr < ist_guard + GUARD_PAGE_SIZE)
+	}
+
+	fn high_water_mark(&self) -> Option<usize> {
+		let stack =
+			unsafe { core::slice::from_raw_parts(self.bottom().as_ptr::<u8>(), STACK_SIZE) };
+		let unused = stack
+			.iter()
+			.position(|&b| b != STACK_PATTERN)
+			.unwrap_or(STACK_SIZE);
+
+		Some(STACK_SIZE - unused)
+	}
+}
+
+/// User ID of the superuser, which passes all permission checks
+pub const ROOT_UID: u32 = 0;
+
+/// Default mask of permission bits, which are cleared for new files
+pub const DEFAULT_UMASK: u32 = 0o022;
+
+/// User and group identities of a task, which determine its access permissions
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Credentials {
+	/// real user ID
+	pub uid: u32,
+	/// real group ID
+	pub gid: u32,
+	/// effective user ID, which is used for permission checks
+	pub euid: u32,
+	/// effective group ID, which is used for permission checks
+	pub egid: u32,
+	/// saved set-user-ID
+	pub suid: u32,
+	/// saved set-group-ID
+	pub sgid: u32,
+	/// supplementary group IDs
+	pub groups: Vec<u32>,
+}
+
+impl Credentials {
+	/// Returns true, if the effective user is the superuser
+	pub fn is_superuser(&self) -> bool {
+		self.euid == ROOT_UID
+	}
+
+	/// Returns true, if `gid` is the effective group or a supplementary group
+	pub fn in_group(&self, gid: u32) -> bool {
+		self.egid == gid || self.groups.contains(&gid)
+	}
+
+	/// Returns the credentials, where the effective IDs are replaced by
+	/// the real IDs. They are used to check permissions on behalf of
+	/// the real user, e.g. by the system call `access`.
+	pub fn real(&self) -> Credentials {
+		Credentials {
+			euid: self.uid,
+			egid: self.gid,
+			..self.clone()
+		}
+	}
+}
+
+/// Snapshot of the state of a task, see `scheduler::for_each_task`
+#[derive(Clone, Debug)]
+pub struct TaskInfo {
+	/// The ID of the task
+	pub id: TaskId,
+	/// The ID of the thread group (i.e. the `pid` of the process)
+	pub tgid: TaskId,
+	/// Name of the task
+	pub name: String,
+	/// Status of the task
+	pub status: TaskStatus,
+	/// Priority of the task
+	pub prio: TaskPriority,
+	/// Time in milliseconds, which the task has been running
+	pub runtime: u64,
+	/// Number of bytes, which are currently used on the kernel stack
+	pub stack_usage: usize,
+	/// Maximum number of bytes, which have been used on the kernel stack so far
+	pub stack_high_water: Option<usize>,
+	/// User and group identities of the task
+	pub credentials: Credentials,
+}
+
+/// Memory region in the user-level address space of a process
+#[derive(Clone, Debug)]
+pub(crate) struct MemoryRegion {
+	/// Start address of the region
+	pub start: VirtAddr,
+	/// End address of the region (exclusive)
+	pub end: VirtAddr,
+	/// Access permissions in the notation of `/proc/<pid>/maps`
+	pub permissions: &'static str,
+	/// Description of the region
+	pub name: &'static str,
+}
+
+/// Address space of a task, which is shared by all threads of a process
+pub(crate) struct AddressSpace {
+	/// Physical address of the 1st level page table
+	root_page_table: PhysAddr,
+	/// Size of the executable, which is mapped at `USER_ENTRY`
+	image_size: Cell<usize>,
+	/// Start address of the next anonymous memory mapping
+	mmap_end: Cell<VirtAddr>,
+}
+
+impl AddressSpace {
+	pub fn new(root_page_table: PhysAddr) -> Self {
+		AddressSpace {
+			root_page_table,
+			image_size: Cell::new(0),
+			mmap_end: Cell::new(USER_MMAP_START),
+		}
+	}
+
+	pub fn set_image_size(&self, size: usize) {
+		self.image_size.set(size);
+	}
+
+	/// Returns all memory regions of the user space. Kernel
+	/// tasks don't have a user space.
+	pub fn regions(&self) -> Vec<MemoryRegion> {
+		let mut regions = Vec::new();
+
+		if self.root_page_table == arch::get_kernel_root_page_table() {
+			return regions;
+		}
+
+		if self.image_size.get() > 0 {
+			regions.push(MemoryRegion {
+				start: USER_ENTRY,
+				end: USER_ENTRY + self.image_size.get(),
+				permissions: "rwxp",
+				name: "[image]",
+			});
+		}
+		regions.push(MemoryRegion {
+			start: USER_STACK_TOP - USER_STACK_SIZE,
+			end: USER_STACK_TOP,
+			permissions: "rw-p",
+			name: "[stack]",
+		});
+		if self.mmap_end.get() > USER_MMAP_START {
+			regions.push(MemoryRegion {
+				start: USER_MMAP_START,
+				end: self.mmap_end.get(),
+				permissions: "rw-p",
+				name: "[mmap]",
+			});
+		}
+
+		regions
+	}
+
+	pub fn root_page_table(&self) -> PhysAddr {
+		self.root_page_table
+	}
+
+	/// Reserve `size` bytes of virtual memory for an anonymous mapping.
+	/// The page fault handler maps the memory on demand. Returns `None`,
+	/// if the region for anonymous mappings is exhausted.
+	pub fn reserve(&self, size: usize) -> Option<VirtAddr> {
+		let addr = self.mmap_end.get();
+		let end = size
+			.checked_next_multiple_of(BasePageSize::SIZE)
+			.and_then(|size| addr.as_u64().checked_add(size as u64))
+			.map(VirtAddr)
+			.filter(|end| *end <= USER_MMAP_END)?;
+
+		self.mmap_end.set(end);
+
+		Some(addr)
+	}
+
+	/// Reserve the range `[addr, addr + size)` for a mapping at a fixed
+	/// address. Later reservations start behind this range.
+	pub fn reserve_fixed(&self, addr: VirtAddr, size: usize) {
+		let end = addr + align_up!(size, BasePageSize::SIZE) as u64;
+		if end > self.mmap_end.get() {
+			self.mmap_end.set(end);
+		}
+	}
+}
+
+impl Drop for AddressSpace {
+	fn drop(&mut self) {
+		if self.root_page_table != arch::get_kernel_root_page_table() {
+			debug!("Deallocate page table 0x{:x}", self.root_page_table);
+			arch::mm::physicalmem::deallocate(self.root_page_table, BasePageSize::SIZE);
+		}
 	}
 }
 
@@ -181,6 +477,10 @@ impl Stack for TaskStack {
 pub(crate) struct Task {
 	/// The ID of this context
 	pub id: TaskId,
+	/// The ID of the thread group (i.e. the `pid` of the process)
+	pub tgid: TaskId,
+	/// Name of the task, which is used for debugging purposes
+	pub name: String,
 	/// Task Priority
 	pub prio: TaskPriority,
 	/// Status of a task, e.g. if the task is ready or blocked
@@ -189,26 +489,47 @@ pub(crate) struct Task {
 	pub last_stack_pointer: VirtAddr,
 	/// Stack of the task
 	pub stack: Box<dyn Stack>,
-	/// Physical address of the 1st level page table
-	pub root_page_table: PhysAddr,
+	/// Address space, which is shared by all threads of a process
+	pub address_space: Rc<AddressSpace>,
 	/// Mapping between file descriptor and the referenced IO interface
-	pub fd_map: BTreeMap<FileDescriptor, Arc<dyn IoInterface>>,
+	pub fd_map: Rc<RefCell<BTreeMap<FileDescriptor, Arc<dyn IoInterface>>>>,
+	/// Absolute path of the current working directory
+	pub cwd: Rc<RefCell<String>>,
+	/// Mask of permission bits, which are cleared for new files
+	pub umask: Rc<Cell<u32>>,
+	/// User and group identities, which are shared by all threads of a process
+	pub credentials: Rc<RefCell<Credentials>>,
+	/// User-level address, which will be cleared at the exit of the thread
+	pub clear_child_tid: Option<VirtAddr>,
+	/// Number of timer ticks, which the task has been running
+	pub runtime: u64,
+	/// True, if another thread of the group has called `exit_group`.
+	/// The task terminates itself at its next reschedule point.
+	pub exiting: bool,
 }
 
 impl Task {
 	pub fn new_idle(id: TaskId) -> Task {
 		Task {
 			id,
+			tgid: id,
+			name: String::from("idle"),
 			prio: LOW_PRIORITY,
 			status: TaskStatus::Idle,
 			last_stack_pointer: VirtAddr::zero(),
 			stack: Box::new(crate::arch::mm::get_boot_stack()),
-			root_page_table: arch::get_kernel_root_page_table(),
-			fd_map: BTreeMap::new(),
+			address_space: Rc::new(AddressSpace::new(arch::get_kernel_root_page_table())),
+			fd_map: Rc::new(RefCell::new(BTreeMap::new())),
+			cwd: Rc::new(RefCell::new(String::from("/"))),
+			umask: Rc::new(Cell::new(DEFAULT_UMASK)),
+			credentials: Rc::new(RefCell::new(Credentials::default())),
+			clear_child_tid: None,
+			runtime: 0,
+			exiting: false,
 		}
 	}
 
-	pub fn new(id: TaskId, status: TaskStatus, prio: TaskPriority) -> Task {
+	pub fn new(id: TaskId, name: &str, status: TaskStatus, prio: TaskPriority) -> Task {
 		let mut fd_map: BTreeMap<FileDescriptor, Arc<dyn IoInterface>> = BTreeMap::new();
 		fd_map
 			.try_insert(STDIN_FILENO, Arc::new(GenericStdin::new()))
@@ -222,29 +543,103 @@ impl Task {
 
 		Task {
 			id,
+			tgid: id,
+			name: String::from(name),
 			prio,
 			status,
 			last_stack_pointer: VirtAddr::zero(),
 			stack: Box::new(TaskStack::new()),
-			root_page_table: arch::get_kernel_root_page_table(),
+			address_space: Rc::new(AddressSpace::new(arch::get_kernel_root_page_table())),
+			fd_map: Rc::new(RefCell::new(fd_map)),
+			cwd: Rc::new(RefCell::new(String::from("/"))),
+			umask: Rc::new(Cell::new(DEFAULT_UMASK)),
+			credentials: Rc::new(RefCell::new(Credentials::default())),
+			clear_child_tid: None,
+			runtime: 0,
+			exiting: false,
+		}
+	}
+
+	/// Create a new thread, which shares the resources of `parent`
+	/// as specified by `flags`
+	pub fn new_thread(id: TaskId, parent: &Task, flags: CloneFlags) -> Task {
+		let fd_map = if flags.contains(CloneFlags::CLONE_FILES) {
+			parent.fd_map.clone()
+		} else {
+			Rc::new(RefCell::new(parent.fd_map.borrow().clone()))
+		};
+		let cwd = if flags.contains(CloneFlags::CLONE_FS) {
+			parent.cwd.clone()
+		} else {
+			Rc::new(RefCell::new(parent.cwd.borrow().clone()))
+		};
+		let umask = if flags.contains(CloneFlags::CLONE_FS) {
+			parent.umask.clone()
+		} else {
+			Rc::new(Cell::new(parent.umask.get()))
+		};
+		let credentials = if flags.contains(CloneFlags::CLONE_THREAD) {
+			parent.credentials.clone()
+		} else {
+			Rc::new(RefCell::new(parent.credentials.borrow().clone()))
+		};
+
+		Task {
+			id,
+			tgid: if flags.contains(CloneFlags::CLONE_THREAD) {
+				parent.tgid
+			} else {
+				id
+			},
+			name: parent.name.clone(),
+			prio: parent.prio,
+			status: TaskStatus::Ready,
+			last_stack_pointer: VirtAddr::zero(),
+			stack: Box::new(TaskStack::new()),
+			address_space: parent.address_space.clone(),
 			fd_map,
+			cwd,
+			umask,
+			credentials,
+			clear_child_tid: None,
+			runtime: 0,
+			exiting: false,
 		}
 	}
 }
 
-pub(crate) trait TaskFrame {
-	/// Create the initial stack frame for a new task
-	fn create_stack_frame(&mut self, func: extern "C" fn());
+bitflags! {
+	/// Flags of the system call `clone`, which specify the
+	/// resources shared between parent and child
+	#[derive(Debug, Copy, Clone)]
+	pub struct CloneFlags: u64 {
+		const CLONE_VM = 0x00000100;
+		const CLONE_FS = 0x00000200;
+		const CLONE_FILES = 0x00000400;
+		const CLONE_SIGHAND = 0x00000800;
+		const CLONE_THREAD = 0x00010000;
+		const CLONE_SYSVSEM = 0x00040000;
+		const CLONE_SETTLS = 0x00080000;
+		const CLONE_PARENT_SETTID = 0x00100000;
+		const CLONE_CHILD_CLEARTID = 0x00200000;
+		const CLONE_DETACHED = 0x00400000;
+		const CLONE_CHILD_SETTID = 0x01000000;
+	}
 }
 
-impl Drop for Task {
-	fn drop(&mut self) {
-		if self.root_page_table != arch::get_kernel_root_page_table() {
-			debug!(
-				"Deallocate page table 0x{:x} of task {}",
-				self.root_page_table, self.id
-			);
-			arch::mm::physicalmem::deallocate(self.root_page_table, BasePageSize::SIZE);
-		}
-	}
+pub(crate) trait TaskFrame {
+	/// Create the initial stack frame for a new task, which
+	/// calls `func` with the argument `arg`
+	fn create_stack_frame(&mut self, func: extern "C" fn(usize), arg: usize);
+
+	/// Create the initial stack frame for a new thread, which returns
+	/// from the current system call of its parent. `parent_stack` is
+	/// the top of the parent's kernel stack. If `user_stack` is zero,
+	/// the thread uses the user stack of its parent.
+	fn create_thread_frame(
+		&mut self,
+		parent_stack: VirtAddr,
+		user_stack: VirtAddr,
+		tls: Option<VirtAddr>,
+	);
 }
@@ -0,0 +1,79 @@
+//! Fast user-space mutex (futex)
+//!
+//! A futex is a 32bit value in the user space. The kernel provides
+//! only a wait queue for each futex, while the user space checks
+//! and modifies the value by atomic operations.
+
+use crate::arch::mm::{PhysAddr, VirtAddr};
+use crate::collections::irqsave;
+use crate::io;
+use crate::scheduler::task::{PriorityTaskQueue, TaskId};
+use crate::scheduler::{block_current_task, get_root_page_table, reschedule, wakeup_task};
+use alloc::collections::BTreeMap;
+
+/// Wait queues of all futexes. A futex is identified by the
+/// address space and its virtual address.
+static mut FUTEX_QUEUES: BTreeMap<(PhysAddr, VirtAddr), PriorityTaskQueue> = BTreeMap::new();
+
+/// Block the current task, if the futex at `addr` still has the value `expected`.
+pub(crate) fn futex_wait(addr: VirtAddr, expected: u32) -> io::Result<()> {
+	let key = (get_root_page_table(), addr);
+
+	irqsave(|| {
+		if unsafe { core::ptr::read_volatile(addr.as_ptr::<u32>()) } != expected {
+			return Err(io::Error::EAGAIN);
+		}
+
+		unsafe {
+			FUTEX_QUEUES
+				.entry(key)
+				.or_insert_with(PriorityTaskQueue::new)
+				.push(block_current_task());
+		}
+
+		Ok(())
+	})?;
+
+	reschedule();
+
+	Ok(())
+}
+
+/// Wake up at most `count` tasks, which wait on the futex at `addr`.
+/// Returns the number of tasks, which are woken up.
+pub(crate) fn futex_wake(addr: VirtAddr, count: usize) -> usize {
+	let key = (get_root_page_table(), addr);
+
+	irqsave(|| {
+		let mut woken = 0;
+
+		unsafe {
+			if let Some(queue) = FUTEX_QUEUES.get_mut(&key) {
+				while woken < count {
+					if let Some(task) = queue.pop() {
+						wakeup_task(task);
+						woken += 1;
+					} else {
+						break;
+					}
+				}
+
+				if queue.is_empty() {
+					FUTEX_QUEUES.remove(&key);
+				}
+			}
+		}
+
+		woken
+	})
+}
+
+/// Remove the task `id` from all wait queues, e.g. if the task is killed.
+pub(crate) fn futex_remove(id: TaskId) {
+	irqsave(|| unsafe {
+		FUTEX_QUEUES.retain(|_, queue| {
+			let _ = queue.remove(id);
+			!queue.is_empty()
+		});
+	});
+}
@@ -1,4 +1,5 @@
 //! Synchronization primitives
 
+pub mod futex;
 pub mod mutex;
 pub mod spinlock;
@@ -0,0 +1,37 @@
+use crate::fd::FileDescriptor;
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::syscall::{from_c_str, AT_FDCWD};
+
+/// Mask of all valid modes of `access`, i.e. `R_OK`, `W_OK` and `X_OK`
+const ACCESS_MODES: u32 = fs::MAY_READ | fs::MAY_WRITE | fs::MAY_EXEC;
+
+/// Check the permissions of the real user and group for the file `path`
+pub(crate) unsafe extern "C" fn sys_access(path: *const u8, mode: u32) -> isize {
+	if mode & !ACCESS_MODES != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall access {} {}", path, mode);
+
+	fs::access(path, mode).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_faccessat(
+	dirfd: FileDescriptor,
+	path: *const u8,
+	mode: u32,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	sys_access(path, mode)
+}
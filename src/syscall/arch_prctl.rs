@@ -0,0 +1,53 @@
+use crate::io;
+use crate::logging::*;
+use x86::bits64::segmentation::{rdfsbase, wrfsbase};
+use x86::msr::{rdmsr, wrmsr, IA32_KERNEL_GSBASE};
+
+const ARCH_SET_GS: i32 = 0x1001;
+const ARCH_SET_FS: i32 = 0x1002;
+const ARCH_GET_FS: i32 = 0x1003;
+const ARCH_GET_GS: i32 = 0x1004;
+
+/// Upper limit of the canonical lower half of the address space
+const CANONICAL_LIMIT: u64 = 0x8000_0000_0000u64;
+
+/// Set or get the FS and GS registers of the current task. The
+/// registers are stored and restored by the function `switch`.
+///
+/// During a system call, the user-level GS register is the inactive
+/// one, which the syscall handler swaps in before it returns.
+pub(crate) unsafe extern "C" fn sys_arch_prctl(code: i32, addr: u64) -> isize {
+	debug!("enter syscall arch_prctl, code 0x{:x}", code);
+
+	match code {
+		ARCH_SET_FS => {
+			if addr >= CANONICAL_LIMIT {
+				return -num::ToPrimitive::to_isize(&io::Error::EPERM).unwrap();
+			}
+			wrfsbase(addr);
+			0
+		}
+		ARCH_SET_GS => {
+			if addr >= CANONICAL_LIMIT {
+				return -num::ToPrimitive::to_isize(&io::Error::EPERM).unwrap();
+			}
+			wrmsr(IA32_KERNEL_GSBASE, addr);
+			0
+		}
+		ARCH_GET_FS => {
+			if addr == 0 {
+				return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+			}
+			*(addr as *mut u64) = rdfsbase();
+			0
+		}
+		ARCH_GET_GS => {
+			if addr == 0 {
+				return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+			}
+			*(addr as *mut u64) = rdmsr(IA32_KERNEL_GSBASE);
+			0
+		}
+		_ => -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap(),
+	}
+}
@@ -0,0 +1,43 @@
+use crate::fd::FileDescriptor;
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::syscall::from_c_str;
+use core::ptr;
+
+pub(crate) unsafe extern "C" fn sys_chdir(path: *const u8) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall chdir {}", path);
+
+	fs::chdir(path).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) extern "C" fn sys_fchdir(fd: FileDescriptor) -> isize {
+	debug!("Enter syscall fchdir {}", fd);
+
+	fs::fchdir(fd).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+/// Copy the working directory of the current task including the
+/// terminating zero to `buf` and return the length of the copied string
+pub(crate) unsafe extern "C" fn sys_getcwd(buf: *mut u8, size: usize) -> isize {
+	debug!("Enter syscall getcwd");
+
+	if buf.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	let cwd = fs::getcwd();
+	if cwd.len() + 1 > size {
+		return -num::ToPrimitive::to_isize(&io::Error::ERANGE).unwrap();
+	}
+
+	ptr::copy_nonoverlapping(cwd.as_ptr(), buf, cwd.len());
+	*buf.add(cwd.len()) = 0;
+
+	(cwd.len() + 1) as isize
+}
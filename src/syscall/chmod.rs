@@ -0,0 +1,85 @@
+use crate::fd::FileDescriptor;
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::set_current_umask;
+use crate::syscall::{from_c_str, AT_FDCWD, AT_SYMLINK_NOFOLLOW};
+
+pub(crate) unsafe extern "C" fn sys_chmod(path: *const u8, mode: u32) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall chmod {} {:o}", path, mode);
+
+	fs::chmod(path, mode).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_fchmodat(
+	dirfd: FileDescriptor,
+	path: *const u8,
+	mode: u32,
+	flags: i32,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	// the permissions of symbolic links aren't used
+	if flags & AT_SYMLINK_NOFOLLOW != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EOPNOTSUPP).unwrap();
+	} else if flags != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	sys_chmod(path, mode)
+}
+
+unsafe fn do_chown(path: *const u8, uid: u32, gid: u32, follow: bool) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall chown {} {} {}", path, uid, gid);
+
+	fs::chown(path, uid, gid, follow)
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_chown(path: *const u8, uid: u32, gid: u32) -> isize {
+	do_chown(path, uid, gid, true)
+}
+
+/// Change the owner of a symbolic link instead of its target
+pub(crate) unsafe extern "C" fn sys_lchown(path: *const u8, uid: u32, gid: u32) -> isize {
+	do_chown(path, uid, gid, false)
+}
+
+pub(crate) unsafe extern "C" fn sys_fchownat(
+	dirfd: FileDescriptor,
+	path: *const u8,
+	uid: u32,
+	gid: u32,
+	flags: i32,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	do_chown(path, uid, gid, flags & AT_SYMLINK_NOFOLLOW == 0)
+}
+
+/// Set the file mode creation mask and return the previous mask
+pub(crate) extern "C" fn sys_umask(mask: u32) -> isize {
+	debug!("Enter syscall umask {:o}", mask);
+
+	set_current_umask(mask & 0o777) as isize
+}
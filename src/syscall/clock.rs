@@ -0,0 +1,32 @@
+use crate::io;
+use crate::logging::*;
+use crate::time::{self, Timespec};
+
+/// Wall-clock time since the Unix epoch
+const CLOCK_REALTIME: i32 = 0;
+/// Time since the boot
+const CLOCK_MONOTONIC: i32 = 1;
+/// Faster, but less precise variant of `CLOCK_REALTIME`
+const CLOCK_REALTIME_COARSE: i32 = 5;
+/// Faster, but less precise variant of `CLOCK_MONOTONIC`
+const CLOCK_MONOTONIC_COARSE: i32 = 6;
+/// Time since the boot including suspended periods
+const CLOCK_BOOTTIME: i32 = 7;
+
+pub(crate) unsafe extern "C" fn sys_clock_gettime(clock: i32, tp: *mut Timespec) -> isize {
+	debug!("Enter syscall clock_gettime {}", clock);
+
+	let time = match clock {
+		CLOCK_REALTIME | CLOCK_REALTIME_COARSE => time::realtime(),
+		CLOCK_MONOTONIC | CLOCK_MONOTONIC_COARSE | CLOCK_BOOTTIME => time::monotonic(),
+		_ => return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap(),
+	};
+
+	if tp.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	*tp = time;
+
+	0
+}
@@ -0,0 +1,54 @@
+use crate::arch::mm::VirtAddr;
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::task::CloneFlags;
+use crate::scheduler::*;
+
+/// Create a new thread. In contrast to Linux, eduOS-rs supports only
+/// threads, which share the address space with their parent.
+pub(crate) extern "C" fn sys_clone(
+	flags: u64,
+	newsp: u64,
+	parent_tid: u64,
+	child_tid: u64,
+	tls: u64,
+) -> isize {
+	debug!("enter syscall clone, flags 0x{:x}", flags);
+
+	let flags = CloneFlags::from_bits_truncate(flags);
+	if !flags.contains(CloneFlags::CLONE_VM) {
+		// fork isn't supported
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+	if flags.contains(CloneFlags::CLONE_THREAD) && !flags.contains(CloneFlags::CLONE_SIGHAND) {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	let tls = if flags.contains(CloneFlags::CLONE_SETTLS) {
+		Some(VirtAddr(tls))
+	} else {
+		None
+	};
+
+	clone(
+		flags,
+		VirtAddr(newsp),
+		VirtAddr(parent_tid),
+		VirtAddr(child_tid),
+		tls,
+	)
+	.map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|tid| tid.into() as isize,
+	)
+}
+
+/// Set the user-level address, which will be cleared at the exit of the thread.
+pub(crate) extern "C" fn sys_set_tid_address(tidptr: u64) -> isize {
+	debug!("enter syscall set_tid_address");
+
+	let addr = VirtAddr(tidptr);
+	set_clear_child_tid(if addr.is_zero() { None } else { Some(addr) });
+
+	get_current_taskid().into() as isize
+}
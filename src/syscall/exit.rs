@@ -5,3 +5,8 @@ pub(crate) extern "C" fn sys_exit() {
 	debug!("enter syscall exit");
 	do_exit();
 }
+
+pub(crate) extern "C" fn sys_exit_group() {
+	debug!("enter syscall exit_group");
+	do_exit_group();
+}
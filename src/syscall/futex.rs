@@ -0,0 +1,28 @@
+use crate::arch::mm::VirtAddr;
+use crate::io;
+use crate::logging::*;
+use crate::synch::futex::{futex_wait, futex_wake};
+
+const FUTEX_WAIT: i32 = 0;
+const FUTEX_WAKE: i32 = 1;
+const FUTEX_PRIVATE_FLAG: i32 = 128;
+const FUTEX_CLOCK_REALTIME: i32 = 256;
+
+/// Wait on or wake up a futex. Timeouts are currently not supported,
+/// because eduOS-rs doesn't provide a clock.
+pub(crate) extern "C" fn sys_futex(uaddr: u64, op: i32, val: u32, _timeout: u64) -> isize {
+	debug!("enter syscall futex, op {}", op);
+
+	let addr = VirtAddr(uaddr);
+	if addr.is_zero() || !uaddr.is_multiple_of(4) {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	match op & !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME) {
+		FUTEX_WAIT => {
+			futex_wait(addr, val).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+		}
+		FUTEX_WAKE => futex_wake(addr, val as usize) as isize,
+		_ => -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap(),
+	}
+}
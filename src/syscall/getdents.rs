@@ -0,0 +1,96 @@
+use crate::fd::{FileDescriptor, SeekFrom};
+use crate::fs::NodeKind;
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::get_io_interface;
+use core::mem::align_of;
+use core::ptr;
+
+const DT_CHR: u8 = 2;
+const DT_DIR: u8 = 4;
+const DT_BLK: u8 = 6;
+const DT_REG: u8 = 8;
+const DT_LNK: u8 = 10;
+const DT_SOCK: u8 = 12;
+
+/// Header of a directory entry, which is followed by the
+/// zero-terminated name of the entry
+#[repr(C)]
+struct Dirent64 {
+	d_ino: u64,
+	d_off: i64,
+	d_reclen: u16,
+	d_type: u8,
+}
+
+/// Offset of the name within a directory entry
+const NAME_OFFSET: usize = 19;
+
+/// Read directory entries of `fd` into the buffer `dirp`. Returns the
+/// number of bytes, which are written to the buffer, or zero, if the
+/// end of the directory is reached.
+pub(crate) unsafe extern "C" fn sys_getdents64(
+	fd: FileDescriptor,
+	dirp: *mut u8,
+	count: usize,
+) -> isize {
+	debug!("Enter syscall getdents64");
+
+	let obj = match get_io_interface(fd) {
+		Ok(obj) => obj,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+	let mut written: usize = 0;
+
+	loop {
+		let entry = match obj.readdir() {
+			Ok(Some(entry)) => entry,
+			Ok(None) => break,
+			Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+		};
+
+		let reclen = align_up!(NAME_OFFSET + entry.name.len() + 1, align_of::<Dirent64>());
+		if written + reclen > count {
+			// the entry doesn't fit => return it by the next call
+			let _ = obj.seek(SeekFrom::Current(-1));
+
+			if written == 0 {
+				return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+			}
+			break;
+		}
+
+		let offset = obj.seek(SeekFrom::Current(0)).unwrap_or(0);
+		let dirent = dirp.add(written);
+		ptr::write_unaligned(
+			dirent as *mut Dirent64,
+			Dirent64 {
+				d_ino: entry.ino,
+				d_off: offset as i64,
+				d_reclen: reclen as u16,
+				d_type: match entry.kind {
+					NodeKind::CharDevice => DT_CHR,
+					NodeKind::BlockDevice => DT_BLK,
+					NodeKind::Directory => DT_DIR,
+					NodeKind::File => DT_REG,
+					NodeKind::Symlink => DT_LNK,
+					NodeKind::Socket => DT_SOCK,
+				},
+			},
+		);
+		ptr::copy_nonoverlapping(
+			entry.name.as_ptr(),
+			dirent.add(NAME_OFFSET),
+			entry.name.len(),
+		);
+		ptr::write_bytes(
+			dirent.add(NAME_OFFSET + entry.name.len()),
+			0,
+			reclen - NAME_OFFSET - entry.name.len(),
+		);
+
+		written += reclen;
+	}
+
+	written as isize
+}
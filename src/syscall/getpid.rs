@@ -0,0 +1,14 @@
+use crate::logging::*;
+use crate::scheduler::*;
+
+/// Returns the ID of the process, which is the ID of the thread group
+pub(crate) extern "C" fn sys_getpid() -> isize {
+	debug!("enter syscall getpid");
+	get_current_tgid().into() as isize
+}
+
+/// Returns the ID of the current thread
+pub(crate) extern "C" fn sys_gettid() -> isize {
+	debug!("enter syscall gettid");
+	get_current_taskid().into() as isize
+}
@@ -0,0 +1,123 @@
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::*;
+use alloc::vec::Vec;
+use core::ptr;
+
+/// Maximum number of supplementary groups of a process
+const NGROUPS_MAX: usize = 32;
+
+/// Returns the real user ID of the current process
+pub(crate) extern "C" fn sys_getuid() -> isize {
+	debug!("enter syscall getuid");
+	get_current_credentials().uid as isize
+}
+
+/// Returns the effective user ID of the current process
+pub(crate) extern "C" fn sys_geteuid() -> isize {
+	debug!("enter syscall geteuid");
+	get_current_credentials().euid as isize
+}
+
+/// Returns the real group ID of the current process
+pub(crate) extern "C" fn sys_getgid() -> isize {
+	debug!("enter syscall getgid");
+	get_current_credentials().gid as isize
+}
+
+/// Returns the effective group ID of the current process
+pub(crate) extern "C" fn sys_getegid() -> isize {
+	debug!("enter syscall getegid");
+	get_current_credentials().egid as isize
+}
+
+/// Set the user ID of the current process. The superuser changes
+/// the real, the effective and the saved user ID. All other users
+/// are only able to switch the effective user ID to the real or
+/// the saved user ID.
+pub(crate) extern "C" fn sys_setuid(uid: u32) -> isize {
+	debug!("enter syscall setuid {}", uid);
+
+	if uid == u32::MAX {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	let mut cred = get_current_credentials();
+	if cred.is_superuser() {
+		cred.uid = uid;
+		cred.suid = uid;
+	} else if uid != cred.uid && uid != cred.suid {
+		return -num::ToPrimitive::to_isize(&io::Error::EPERM).unwrap();
+	}
+	cred.euid = uid;
+	set_current_credentials(cred);
+
+	0
+}
+
+/// Set the group ID of the current process, see `sys_setuid`
+pub(crate) extern "C" fn sys_setgid(gid: u32) -> isize {
+	debug!("enter syscall setgid {}", gid);
+
+	if gid == u32::MAX {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	let mut cred = get_current_credentials();
+	if cred.is_superuser() {
+		cred.gid = gid;
+		cred.sgid = gid;
+	} else if gid != cred.gid && gid != cred.sgid {
+		return -num::ToPrimitive::to_isize(&io::Error::EPERM).unwrap();
+	}
+	cred.egid = gid;
+	set_current_credentials(cred);
+
+	0
+}
+
+/// Copy the supplementary group IDs to `list`. If `size` is
+/// zero, only the number of supplementary groups is returned.
+pub(crate) unsafe extern "C" fn sys_getgroups(size: i32, list: *mut u32) -> isize {
+	debug!("enter syscall getgroups");
+
+	let groups = get_current_credentials().groups;
+	if size == 0 {
+		return groups.len() as isize;
+	} else if size < 0 || (size as usize) < groups.len() {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	} else if list.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	ptr::copy_nonoverlapping(groups.as_ptr(), list, groups.len());
+
+	groups.len() as isize
+}
+
+/// Replace the supplementary group IDs by the `size` IDs of `list`.
+/// Only the superuser is allowed to change them.
+pub(crate) unsafe extern "C" fn sys_setgroups(size: usize, list: *const u32) -> isize {
+	debug!("enter syscall setgroups");
+
+	let mut cred = get_current_credentials();
+	if !cred.is_superuser() {
+		return -num::ToPrimitive::to_isize(&io::Error::EPERM).unwrap();
+	} else if size > NGROUPS_MAX {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	} else if size > 0 && list.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	cred.groups = if size > 0 {
+		let mut groups = Vec::from(core::slice::from_raw_parts(list, size));
+		groups.sort_unstable();
+		groups.dedup();
+		groups
+	} else {
+		Vec::new()
+	};
+	set_current_credentials(cred);
+
+	0
+}
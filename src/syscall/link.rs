@@ -0,0 +1,113 @@
+use crate::fd::FileDescriptor;
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::syscall::{from_c_str, AT_FDCWD};
+use core::{cmp, ptr};
+
+/// Flag of `linkat` to follow a symbolic link as `old_path`
+const AT_SYMLINK_FOLLOW: i32 = 0x400;
+
+pub(crate) unsafe extern "C" fn sys_link(old_path: *const u8, new_path: *const u8) -> isize {
+	let (old_path, new_path) = match (from_c_str(old_path), from_c_str(new_path)) {
+		(Ok(old_path), Ok(new_path)) => (old_path, new_path),
+		(Err(e), _) | (_, Err(e)) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall link {} => {}", old_path, new_path);
+
+	fs::link(old_path, new_path, false)
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_linkat(
+	old_dirfd: FileDescriptor,
+	old_path: *const u8,
+	new_dirfd: FileDescriptor,
+	new_path: *const u8,
+	flags: i32,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if old_dirfd != AT_FDCWD || new_dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	if flags & !AT_SYMLINK_FOLLOW != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	let (old_path, new_path) = match (from_c_str(old_path), from_c_str(new_path)) {
+		(Ok(old_path), Ok(new_path)) => (old_path, new_path),
+		(Err(e), _) | (_, Err(e)) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall linkat {} => {}", old_path, new_path);
+
+	fs::link(old_path, new_path, flags & AT_SYMLINK_FOLLOW != 0)
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_symlink(target: *const u8, path: *const u8) -> isize {
+	let (target, path) = match (from_c_str(target), from_c_str(path)) {
+		(Ok(target), Ok(path)) => (target, path),
+		(Err(e), _) | (_, Err(e)) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall symlink {} => {}", path, target);
+
+	fs::symlink(target, path).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_symlinkat(
+	target: *const u8,
+	dirfd: FileDescriptor,
+	path: *const u8,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	sys_symlink(target, path)
+}
+
+/// Copy the target of the symbolic link `path` without a terminating
+/// zero to `buf`. The target is truncated, if the buffer is too small.
+pub(crate) unsafe extern "C" fn sys_readlink(path: *const u8, buf: *mut u8, size: usize) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall readlink {}", path);
+
+	if buf.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	} else if size == 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	match fs::readlink(path) {
+		Ok(target) => {
+			let len = cmp::min(target.len(), size);
+			ptr::copy_nonoverlapping(target.as_ptr(), buf, len);
+
+			len as isize
+		}
+		Err(e) => -num::ToPrimitive::to_isize(&e).unwrap(),
+	}
+}
+
+pub(crate) unsafe extern "C" fn sys_readlinkat(
+	dirfd: FileDescriptor,
+	path: *const u8,
+	buf: *mut u8,
+	size: usize,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	sys_readlink(path, buf, size)
+}
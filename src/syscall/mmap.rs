@@ -0,0 +1,86 @@
+use crate::arch::mm::VirtAddr;
+use crate::arch::{BasePageSize, PageSize};
+use crate::consts::{USER_MMAP_END, USER_MMAP_START};
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::*;
+
+const MAP_FIXED: u64 = 0x10;
+const MAP_ANONYMOUS: u64 = 0x20;
+
+/// Returns an error, if `[addr, addr + len)` isn't a page-aligned range
+/// of the region for anonymous mappings
+fn check_range(addr: u64, len: usize) -> io::Result<()> {
+	if len == 0
+		|| !addr.is_multiple_of(BasePageSize::SIZE as u64)
+		|| VirtAddr(addr) < USER_MMAP_START
+		|| addr.checked_add(len as u64).is_none()
+	{
+		Err(io::Error::EINVAL)
+	} else {
+		Ok(())
+	}
+}
+
+/// Map anonymous memory into the user space. The page fault
+/// handler allocates the memory on demand.
+pub(crate) extern "C" fn sys_mmap(
+	addr: u64,
+	len: usize,
+	_prot: u64,
+	flags: u64,
+	_fd: i32,
+	_offset: u64,
+) -> isize {
+	debug!("enter syscall mmap, len 0x{:x}, flags 0x{:x}", len, flags);
+
+	if len == 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	if flags & MAP_ANONYMOUS == 0 {
+		// file mappings aren't supported
+		return -num::ToPrimitive::to_isize(&io::Error::ENODEV).unwrap();
+	}
+
+	if flags & MAP_FIXED != 0 {
+		if let Err(e) = check_range(addr, len) {
+			return -num::ToPrimitive::to_isize(&e).unwrap();
+		}
+
+		let end = (len as u64)
+			.checked_next_multiple_of(BasePageSize::SIZE as u64)
+			.and_then(|len| addr.checked_add(len));
+		if end.is_none_or(|end| VirtAddr(end) > USER_MMAP_END) {
+			return -num::ToPrimitive::to_isize(&io::Error::ENOMEM).unwrap();
+		}
+
+		// later mappings must not overlap with the fixed range
+		reserve_fixed_user_memory(VirtAddr(addr), len);
+
+		addr as isize
+	} else {
+		match reserve_user_memory(len) {
+			Some(addr) => addr.as_usize() as isize,
+			None => -num::ToPrimitive::to_isize(&io::Error::ENOMEM).unwrap(),
+		}
+	}
+}
+
+/// The memory is released at the exit of the process. Consequently,
+/// `munmap` validates its arguments, but returns `ENOSYS`.
+pub(crate) extern "C" fn sys_munmap(addr: u64, len: usize) -> isize {
+	debug!("enter syscall munmap");
+
+	let err = check_range(addr, len).err().unwrap_or(io::Error::ENOSYS);
+	-num::ToPrimitive::to_isize(&err).unwrap()
+}
+
+/// The protection of user-level memory isn't changeable. Consequently,
+/// `mprotect` validates its arguments, but returns `ENOSYS`.
+pub(crate) extern "C" fn sys_mprotect(addr: u64, len: usize, _prot: u64) -> isize {
+	debug!("enter syscall mprotect");
+
+	let err = check_range(addr, len).err().unwrap_or(io::Error::ENOSYS);
+	-num::ToPrimitive::to_isize(&err).unwrap()
+}
@@ -1,34 +1,308 @@
+mod access;
+mod arch_prctl;
+mod chdir;
+mod chmod;
+mod clock;
+mod clone;
 mod exit;
+mod futex;
+mod getdents;
+mod getpid;
+mod getuid;
 mod invalid;
+mod link;
+mod mmap;
+mod mount;
 mod nothing;
+mod open;
+mod prctl;
+mod reboot;
+mod rename;
+mod sched_yield;
+mod socket;
+mod stat;
+mod sync;
+mod unlink;
+mod utimensat;
 mod write;
 
-use crate::syscall::exit::sys_exit;
+use crate::io;
+use crate::syscall::access::{sys_access, sys_faccessat};
+use crate::syscall::arch_prctl::sys_arch_prctl;
+use crate::syscall::chdir::{sys_chdir, sys_fchdir, sys_getcwd};
+use crate::syscall::chmod::{
+	sys_chmod, sys_chown, sys_fchmodat, sys_fchownat, sys_lchown, sys_umask,
+};
+use crate::syscall::clock::sys_clock_gettime;
+use crate::syscall::clone::{sys_clone, sys_set_tid_address};
+use crate::syscall::exit::{sys_exit, sys_exit_group};
+use crate::syscall::futex::sys_futex;
+use crate::syscall::getdents::sys_getdents64;
+use crate::syscall::getpid::{sys_getpid, sys_gettid};
+use crate::syscall::getuid::{
+	sys_getegid, sys_geteuid, sys_getgid, sys_getgroups, sys_getuid, sys_setgid, sys_setgroups,
+	sys_setuid,
+};
 use crate::syscall::invalid::sys_invalid;
+use crate::syscall::link::{
+	sys_link, sys_linkat, sys_readlink, sys_readlinkat, sys_symlink, sys_symlinkat,
+};
+use crate::syscall::mmap::{sys_mmap, sys_mprotect, sys_munmap};
+use crate::syscall::mount::{sys_mount, sys_umount2};
 use crate::syscall::nothing::sys_nothing;
+use crate::syscall::open::{sys_close, sys_lseek, sys_open, sys_openat, sys_read};
+use crate::syscall::prctl::sys_prctl;
+use crate::syscall::reboot::sys_reboot;
+use crate::syscall::rename::{sys_rename, sys_renameat};
+use crate::syscall::sched_yield::sys_sched_yield;
+use crate::syscall::socket::{
+	sys_accept, sys_accept4, sys_bind, sys_connect, sys_getpeername, sys_getsockname, sys_listen,
+	sys_recvfrom, sys_sendto, sys_setsockopt, sys_shutdown, sys_socket, sys_socketpair,
+};
+use crate::syscall::stat::{sys_fstat, sys_lstat, sys_newfstatat, sys_stat};
+use crate::syscall::sync::{sys_fdatasync, sys_fsync, sys_sync, sys_syncfs};
+use crate::syscall::unlink::{sys_rmdir, sys_unlink, sys_unlinkat};
+use crate::syscall::utimensat::sys_utimensat;
 use crate::syscall::write::{sys_write, sys_writev};
 
+/// number of the system call `read`
+pub const SYSNO_READ: usize = 0;
+
 /// number of the system call `write`
 pub const SYSNO_WRITE: usize = 1;
 
+/// number of the system call `open`
+pub const SYSNO_OPEN: usize = 2;
+
 /// number of the system call `close`
 pub const SYSNO_CLOSE: usize = 3;
 
+/// get file status
+pub const SYSNO_STAT: usize = 4;
+
+/// get file status of a file descriptor
+pub const SYSNO_FSTAT: usize = 5;
+
+/// get file status without following a symbolic link
+pub const SYSNO_LSTAT: usize = 6;
+
+/// reposition the offset of a file descriptor
+pub const SYSNO_LSEEK: usize = 8;
+
+/// map memory into the address space
+pub const SYSNO_MMAP: usize = 9;
+
+/// set protection of a memory region
+pub const SYSNO_MPROTECT: usize = 10;
+
+/// unmap memory from the address space
+///
+/// `munmap` doesn't release any memory. For instance, the stacks of
+/// threads, which are created by musl, leak until the process exits.
+pub const SYSNO_MUNMAP: usize = 11;
+
+/// examine and change blocked signals
+pub const SYSNO_RT_SIGPROCMASK: usize = 14;
+
 pub const SYSNO_IOCTL: usize = 16;
 
 pub const SYSNO_WRITEV: usize = 20;
 
+/// check the permissions of the real user for a file
+pub const SYSNO_ACCESS: usize = 21;
+
+/// yield the processor
+pub const SYSNO_SCHED_YIELD: usize = 24;
+
+/// get process identification
+pub const SYSNO_GETPID: usize = 39;
+
+/// create an endpoint for communication
+pub const SYSNO_SOCKET: usize = 41;
+
+/// initiate a connection on a socket
+pub const SYSNO_CONNECT: usize = 42;
+
+/// accept a connection on a socket
+pub const SYSNO_ACCEPT: usize = 43;
+
+/// send a message on a socket
+pub const SYSNO_SENDTO: usize = 44;
+
+/// receive a message from a socket
+pub const SYSNO_RECVFROM: usize = 45;
+
+/// shut down part of a full-duplex connection
+pub const SYSNO_SHUTDOWN: usize = 48;
+
+/// bind an address to a socket
+pub const SYSNO_BIND: usize = 49;
+
+/// listen for connections on a socket
+pub const SYSNO_LISTEN: usize = 50;
+
+/// get the local address of a socket
+pub const SYSNO_GETSOCKNAME: usize = 51;
+
+/// get the address of the peer of a socket
+pub const SYSNO_GETPEERNAME: usize = 52;
+
+/// create a pair of connected sockets
+pub const SYSNO_SOCKETPAIR: usize = 53;
+
+/// set an option of a socket
+pub const SYSNO_SETSOCKOPT: usize = 54;
+
+/// create a new thread
+pub const SYSNO_CLONE: usize = 56;
+
 /// number of the system call `exit`
 pub const SYSNO_EXIT: usize = 60;
 
+/// synchronize the state of a file with the storage device
+pub const SYSNO_FSYNC: usize = 74;
+
+/// synchronize the data of a file with the storage device
+pub const SYSNO_FDATASYNC: usize = 75;
+
+/// get current working directory
+pub const SYSNO_GETCWD: usize = 79;
+
+/// change working directory
+pub const SYSNO_CHDIR: usize = 80;
+
+/// change working directory to a directory file descriptor
+pub const SYSNO_FCHDIR: usize = 81;
+
+/// rename a file
+pub const SYSNO_RENAME: usize = 82;
+
+/// remove a directory
+pub const SYSNO_RMDIR: usize = 84;
+
+/// make a new name for a file
+pub const SYSNO_LINK: usize = 86;
+
+/// remove a file
+pub const SYSNO_UNLINK: usize = 87;
+
+/// make a symbolic link
+pub const SYSNO_SYMLINK: usize = 88;
+
+/// read the target of a symbolic link
+pub const SYSNO_READLINK: usize = 89;
+
+/// change the permissions of a file
+pub const SYSNO_CHMOD: usize = 90;
+
+/// change the owner of a file
+pub const SYSNO_CHOWN: usize = 92;
+
+/// change the owner of a file without following a symbolic link
+pub const SYSNO_LCHOWN: usize = 94;
+
+/// set the file mode creation mask
+pub const SYSNO_UMASK: usize = 95;
+
+/// get the real user ID
+pub const SYSNO_GETUID: usize = 102;
+
+/// get the real group ID
+pub const SYSNO_GETGID: usize = 104;
+
+/// set the user ID
+pub const SYSNO_SETUID: usize = 105;
+
+/// set the group ID
+pub const SYSNO_SETGID: usize = 106;
+
+/// get the effective user ID
+pub const SYSNO_GETEUID: usize = 107;
+
+/// get the effective group ID
+pub const SYSNO_GETEGID: usize = 108;
+
+/// get the supplementary group IDs
+pub const SYSNO_GETGROUPS: usize = 115;
+
+/// set the supplementary group IDs
+pub const SYSNO_SETGROUPS: usize = 116;
+
+/// operations on a process or thread
+pub const SYSNO_PRCTL: usize = 157;
+
+/// set architecture-specific thread state
 pub const SYSNO_ARCH_PRCTL: usize = 158;
 
+/// commit the buffer cache to the storage devices
+pub const SYSNO_SYNC: usize = 162;
+
+/// mount a file system
+pub const SYSNO_MOUNT: usize = 165;
+
+/// unmount a file system
+pub const SYSNO_UMOUNT2: usize = 166;
+
+/// restart or turn off the machine
+pub const SYSNO_REBOOT: usize = 169;
+
+/// get thread identification
+pub const SYSNO_GETTID: usize = 186;
+
+/// fast user-space locking
+pub const SYSNO_FUTEX: usize = 202;
+
+/// get directory entries
+pub const SYSNO_GETDENTS64: usize = 217;
+
 /// set pointer to thread ID
 pub const SYSNO_SET_TID_ADDRESS: usize = 218;
 
+/// retrieve the time of a clock
+pub const SYSNO_CLOCK_GETTIME: usize = 228;
+
 /// exit all threads in a process
 pub const SYSNO_EXIT_GROUP: usize = 231;
 
+/// open a file relative to a directory file descriptor
+pub const SYSNO_OPENAT: usize = 257;
+
+/// change the owner of a file relative to a directory file descriptor
+pub const SYSNO_FCHOWNAT: usize = 260;
+
+/// get file status relative to a directory file descriptor
+pub const SYSNO_NEWFSTATAT: usize = 262;
+
+/// remove a file or directory relative to a directory file descriptor
+pub const SYSNO_UNLINKAT: usize = 263;
+
+/// rename a file relative to directory file descriptors
+pub const SYSNO_RENAMEAT: usize = 264;
+
+/// make a new name for a file relative to directory file descriptors
+pub const SYSNO_LINKAT: usize = 265;
+
+/// make a symbolic link relative to a directory file descriptor
+pub const SYSNO_SYMLINKAT: usize = 266;
+
+/// read the target of a symbolic link relative to a directory file descriptor
+pub const SYSNO_READLINKAT: usize = 267;
+
+/// change the permissions of a file relative to a directory file descriptor
+pub const SYSNO_FCHMODAT: usize = 268;
+
+/// check the permissions of a file relative to a directory file descriptor
+pub const SYSNO_FACCESSAT: usize = 269;
+
+/// change the timestamps of a file relative to a directory file descriptor
+pub const SYSNO_UTIMENSAT: usize = 280;
+
+/// accept a connection on a socket with flags
+pub const SYSNO_ACCEPT4: usize = 288;
+
+/// commit the file system of a file to the storage device
+pub const SYSNO_SYNCFS: usize = 306;
+
 /// total number of system calls
 pub const NO_SYSCALLS: usize = 400;
 
@@ -44,14 +318,85 @@ impl SyscallTable {
 			handle: [sys_invalid as *const _; NO_SYSCALLS],
 		};
 
+		table.handle[SYSNO_READ] = sys_read as *const _;
 		table.handle[SYSNO_WRITE] = sys_write as *const _;
-		table.handle[SYSNO_CLOSE] = sys_nothing as *const _;
+		table.handle[SYSNO_OPEN] = sys_open as *const _;
+		table.handle[SYSNO_CLOSE] = sys_close as *const _;
+		table.handle[SYSNO_STAT] = sys_stat as *const _;
+		table.handle[SYSNO_FSTAT] = sys_fstat as *const _;
+		table.handle[SYSNO_LSTAT] = sys_lstat as *const _;
+		table.handle[SYSNO_LSEEK] = sys_lseek as *const _;
+		table.handle[SYSNO_MMAP] = sys_mmap as *const _;
+		table.handle[SYSNO_MPROTECT] = sys_mprotect as *const _;
+		table.handle[SYSNO_MUNMAP] = sys_munmap as *const _;
+		table.handle[SYSNO_RT_SIGPROCMASK] = sys_nothing as *const _;
 		table.handle[SYSNO_IOCTL] = sys_nothing as *const _;
 		table.handle[SYSNO_WRITEV] = sys_writev as *const _;
+		table.handle[SYSNO_ACCESS] = sys_access as *const _;
+		table.handle[SYSNO_SCHED_YIELD] = sys_sched_yield as *const _;
+		table.handle[SYSNO_GETPID] = sys_getpid as *const _;
+		table.handle[SYSNO_SOCKET] = sys_socket as *const _;
+		table.handle[SYSNO_CONNECT] = sys_connect as *const _;
+		table.handle[SYSNO_ACCEPT] = sys_accept as *const _;
+		table.handle[SYSNO_SENDTO] = sys_sendto as *const _;
+		table.handle[SYSNO_RECVFROM] = sys_recvfrom as *const _;
+		table.handle[SYSNO_SHUTDOWN] = sys_shutdown as *const _;
+		table.handle[SYSNO_BIND] = sys_bind as *const _;
+		table.handle[SYSNO_LISTEN] = sys_listen as *const _;
+		table.handle[SYSNO_GETSOCKNAME] = sys_getsockname as *const _;
+		table.handle[SYSNO_GETPEERNAME] = sys_getpeername as *const _;
+		table.handle[SYSNO_SOCKETPAIR] = sys_socketpair as *const _;
+		table.handle[SYSNO_SETSOCKOPT] = sys_setsockopt as *const _;
+		table.handle[SYSNO_CLONE] = sys_clone as *const _;
 		table.handle[SYSNO_EXIT] = sys_exit as *const _;
-		table.handle[SYSNO_ARCH_PRCTL] = sys_nothing as *const _;
-		table.handle[SYSNO_SET_TID_ADDRESS] = sys_nothing as *const _;
-		table.handle[SYSNO_EXIT_GROUP] = sys_exit as *const _;
+		table.handle[SYSNO_FSYNC] = sys_fsync as *const _;
+		table.handle[SYSNO_FDATASYNC] = sys_fdatasync as *const _;
+		table.handle[SYSNO_GETCWD] = sys_getcwd as *const _;
+		table.handle[SYSNO_CHDIR] = sys_chdir as *const _;
+		table.handle[SYSNO_FCHDIR] = sys_fchdir as *const _;
+		table.handle[SYSNO_RENAME] = sys_rename as *const _;
+		table.handle[SYSNO_RMDIR] = sys_rmdir as *const _;
+		table.handle[SYSNO_LINK] = sys_link as *const _;
+		table.handle[SYSNO_UNLINK] = sys_unlink as *const _;
+		table.handle[SYSNO_SYMLINK] = sys_symlink as *const _;
+		table.handle[SYSNO_READLINK] = sys_readlink as *const _;
+		table.handle[SYSNO_CHMOD] = sys_chmod as *const _;
+		table.handle[SYSNO_CHOWN] = sys_chown as *const _;
+		table.handle[SYSNO_LCHOWN] = sys_lchown as *const _;
+		table.handle[SYSNO_UMASK] = sys_umask as *const _;
+		table.handle[SYSNO_GETUID] = sys_getuid as *const _;
+		table.handle[SYSNO_GETGID] = sys_getgid as *const _;
+		table.handle[SYSNO_SETUID] = sys_setuid as *const _;
+		table.handle[SYSNO_SETGID] = sys_setgid as *const _;
+		table.handle[SYSNO_GETEUID] = sys_geteuid as *const _;
+		table.handle[SYSNO_GETEGID] = sys_getegid as *const _;
+		table.handle[SYSNO_GETGROUPS] = sys_getgroups as *const _;
+		table.handle[SYSNO_SETGROUPS] = sys_setgroups as *const _;
+		table.handle[SYSNO_PRCTL] = sys_prctl as *const _;
+		table.handle[SYSNO_ARCH_PRCTL] = sys_arch_prctl as *const _;
+		table.handle[SYSNO_SYNC] = sys_sync as *const _;
+		table.handle[SYSNO_MOUNT] = sys_mount as *const _;
+		table.handle[SYSNO_UMOUNT2] = sys_umount2 as *const _;
+		table.handle[SYSNO_REBOOT] = sys_reboot as *const _;
+		table.handle[SYSNO_GETTID] = sys_gettid as *const _;
+		table.handle[SYSNO_FUTEX] = sys_futex as *const _;
+		table.handle[SYSNO_GETDENTS64] = sys_getdents64 as *const _;
+		table.handle[SYSNO_SET_TID_ADDRESS] = sys_set_tid_address as *const _;
+		table.handle[SYSNO_CLOCK_GETTIME] = sys_clock_gettime as *const _;
+		table.handle[SYSNO_EXIT_GROUP] = sys_exit_group as *const _;
+		table.handle[SYSNO_OPENAT] = sys_openat as *const _;
+		table.handle[SYSNO_FCHOWNAT] = sys_fchownat as *const _;
+		table.handle[SYSNO_NEWFSTATAT] = sys_newfstatat as *const _;
+		table.handle[SYSNO_UNLINKAT] = sys_unlinkat as *const _;
+		table.handle[SYSNO_RENAMEAT] = sys_renameat as *const _;
+		table.handle[SYSNO_LINKAT] = sys_linkat as *const _;
+		table.handle[SYSNO_SYMLINKAT] = sys_symlinkat as *const _;
+		table.handle[SYSNO_READLINKAT] = sys_readlinkat as *const _;
+		table.handle[SYSNO_FCHMODAT] = sys_fchmodat as *const _;
+		table.handle[SYSNO_FACCESSAT] = sys_faccessat as *const _;
+		table.handle[SYSNO_UTIMENSAT] = sys_utimensat as *const _;
+		table.handle[SYSNO_ACCEPT4] = sys_accept4 as *const _;
+		table.handle[SYSNO_SYNCFS] = sys_syncfs as *const _;
 
 		table
 	}
@@ -67,3 +412,33 @@ impl Default for SyscallTable {
 }
 
 pub(crate) static SYSHANDLER_TABLE: SyscallTable = SyscallTable::new();
+
+/// Special value of `dirfd`, which represents the current working directory
+pub(crate) const AT_FDCWD: crate::fd::FileDescriptor = -100;
+
+/// Flag of the `*at` system calls to operate on a symbolic link itself
+pub(crate) const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
+
+/// Maximum length of a path including the terminating zero
+const PATH_MAX: usize = 4096;
+
+/// Convert a zero-terminated string of the user space to a string slice
+///
+/// # Safety
+///
+/// `ptr` has to be a valid pointer into the address space of the current task.
+pub(crate) unsafe fn from_c_str<'a>(ptr: *const u8) -> io::Result<&'a str> {
+	if ptr.is_null() {
+		return Err(io::Error::EFAULT);
+	}
+
+	let mut len = 0;
+	while *ptr.add(len) != 0 {
+		len += 1;
+		if len >= PATH_MAX {
+			return Err(io::Error::ENAMETOOLONG);
+		}
+	}
+
+	core::str::from_utf8(core::slice::from_raw_parts(ptr, len)).map_err(|_| io::Error::EINVAL)
+}
@@ -0,0 +1,52 @@
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::syscall::from_c_str;
+
+/// Abort pending requests before unmounting (ignored)
+const MNT_FORCE: i32 = 1;
+/// Lazy unmount (ignored, the file system is always detached immediately)
+const MNT_DETACH: i32 = 2;
+
+/// Mount the file system of the type `fstype` on `target`. The
+/// mount flags and the file system specific `data` are ignored.
+pub(crate) unsafe extern "C" fn sys_mount(
+	source: *const u8,
+	target: *const u8,
+	fstype: *const u8,
+	_flags: u64,
+	_data: *const u8,
+) -> isize {
+	let source = if source.is_null() {
+		None
+	} else {
+		match from_c_str(source) {
+			Ok(source) => Some(source),
+			Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+		}
+	};
+	let (target, fstype) = match (from_c_str(target), from_c_str(fstype)) {
+		(Ok(target), Ok(fstype)) => (target, fstype),
+		(Err(e), _) | (_, Err(e)) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall mount {} on {}", fstype, target);
+
+	fs::mount_by_type(source, target, fstype)
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_umount2(target: *const u8, flags: i32) -> isize {
+	let target = match from_c_str(target) {
+		Ok(target) => target,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall umount2 {}", target);
+
+	if flags & !(MNT_FORCE | MNT_DETACH) != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	fs::umount(target).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
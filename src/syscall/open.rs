@@ -0,0 +1,74 @@
+use crate::fd::{self, FileDescriptor, OpenOption, SeekFrom};
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::remove_io_interface;
+use crate::syscall::{from_c_str, AT_FDCWD};
+
+const SEEK_SET: i32 = 0;
+const SEEK_CUR: i32 = 1;
+const SEEK_END: i32 = 2;
+
+pub(crate) unsafe extern "C" fn sys_open(path: *const u8, flags: i32, mode: u32) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall open {}", path);
+
+	fs::open(path, OpenOption::from_bits_truncate(flags), mode).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|fd| fd as isize,
+	)
+}
+
+pub(crate) unsafe extern "C" fn sys_openat(
+	dirfd: FileDescriptor,
+	path: *const u8,
+	flags: i32,
+	mode: u32,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	sys_open(path, flags, mode)
+}
+
+pub(crate) extern "C" fn sys_close(fd: FileDescriptor) -> isize {
+	debug!("Enter syscall close {}", fd);
+
+	remove_io_interface(fd).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_read(fd: FileDescriptor, buf: *mut u8, len: usize) -> isize {
+	debug!("Enter syscall read");
+	let slice = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+	fd::read(fd, slice).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|v| v.try_into().unwrap(),
+	)
+}
+
+pub(crate) extern "C" fn sys_lseek(fd: FileDescriptor, offset: isize, whence: i32) -> isize {
+	debug!("Enter syscall lseek");
+
+	let style = match whence {
+		SEEK_SET => {
+			if offset < 0 {
+				return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+			}
+			SeekFrom::Start(offset as usize)
+		}
+		SEEK_CUR => SeekFrom::Current(offset),
+		SEEK_END => SeekFrom::End(offset),
+		_ => return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap(),
+	};
+
+	fd::lseek(fd, style).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|v| v.try_into().unwrap(),
+	)
+}
@@ -0,0 +1,51 @@
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::{get_current_task_name, set_current_task_name};
+use core::{ptr, slice, str};
+
+const PR_SET_NAME: i32 = 15;
+const PR_GET_NAME: i32 = 16;
+
+/// Maximum length of a task name including the terminating zero
+const TASK_COMM_LEN: usize = 16;
+
+/// Operations on the current task. Only the name of
+/// the task can be set or get.
+pub(crate) unsafe extern "C" fn sys_prctl(option: i32, arg2: u64) -> isize {
+	debug!("enter syscall prctl, option {}", option);
+
+	match option {
+		PR_SET_NAME => {
+			if arg2 == 0 {
+				return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+			}
+
+			// the name is truncated, if it is longer than TASK_COMM_LEN - 1
+			let buf = slice::from_raw_parts(arg2 as *const u8, TASK_COMM_LEN - 1);
+			let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+
+			// stop at a truncated or invalid UTF-8 sequence
+			let name = match str::from_utf8(&buf[..len]) {
+				Ok(name) => name,
+				Err(e) => str::from_utf8_unchecked(&buf[..e.valid_up_to()]),
+			};
+			set_current_task_name(name);
+
+			0
+		}
+		PR_GET_NAME => {
+			if arg2 == 0 {
+				return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+			}
+
+			let name = get_current_task_name();
+			let len = name.len().min(TASK_COMM_LEN - 1);
+			let buf = arg2 as *mut u8;
+
+			ptr::copy_nonoverlapping(name.as_ptr(), buf, len);
+			ptr::write_bytes(buf.add(len), 0, TASK_COMM_LEN - len);
+			0
+		}
+		_ => -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap(),
+	}
+}
@@ -0,0 +1,48 @@
+use crate::arch::processor;
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::scheduler::get_current_credentials;
+
+/// Magic number, which has to be passed as first argument
+const LINUX_REBOOT_MAGIC1: u32 = 0xfee1_dead;
+/// Valid magic numbers for the second argument
+const LINUX_REBOOT_MAGIC2: [u32; 4] = [672274793, 85072278, 369367448, 537993216];
+
+/// Restart the system
+const LINUX_REBOOT_CMD_RESTART: u32 = 0x0123_4567;
+/// Stop the system
+const LINUX_REBOOT_CMD_HALT: u32 = 0xcdef_0123;
+/// Stop the system and turn the machine off
+const LINUX_REBOOT_CMD_POWER_OFF: u32 = 0x4321_fedc;
+/// Enable the Ctrl-Alt-Del keystroke (ignored)
+const LINUX_REBOOT_CMD_CAD_ON: u32 = 0x89ab_cdef;
+/// Disable the Ctrl-Alt-Del keystroke (ignored)
+const LINUX_REBOOT_CMD_CAD_OFF: u32 = 0;
+
+/// Restart or turn off the machine. Before, all file systems are
+/// synchronized. Only the superuser is allowed to reboot the system.
+pub(crate) extern "C" fn sys_reboot(magic1: u32, magic2: u32, cmd: u32, _arg: *const u8) -> isize {
+	debug!("Enter syscall reboot 0x{:x}", cmd);
+
+	if !get_current_credentials().is_superuser() {
+		return -num::ToPrimitive::to_isize(&io::Error::EPERM).unwrap();
+	} else if magic1 != LINUX_REBOOT_MAGIC1 || !LINUX_REBOOT_MAGIC2.contains(&magic2) {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	match cmd {
+		LINUX_REBOOT_CMD_RESTART => {
+			info!("Restart system!");
+			let _ = fs::sync();
+			processor::reboot()
+		}
+		LINUX_REBOOT_CMD_HALT | LINUX_REBOOT_CMD_POWER_OFF => {
+			info!("Shutdown system!");
+			let _ = fs::sync();
+			processor::poweroff()
+		}
+		LINUX_REBOOT_CMD_CAD_ON | LINUX_REBOOT_CMD_CAD_OFF => 0,
+		_ => -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap(),
+	}
+}
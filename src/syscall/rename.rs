@@ -0,0 +1,30 @@
+use crate::fd::FileDescriptor;
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::syscall::{from_c_str, AT_FDCWD};
+
+pub(crate) unsafe extern "C" fn sys_rename(old_path: *const u8, new_path: *const u8) -> isize {
+	let (old_path, new_path) = match (from_c_str(old_path), from_c_str(new_path)) {
+		(Ok(old_path), Ok(new_path)) => (old_path, new_path),
+		(Err(e), _) | (_, Err(e)) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall rename {} => {}", old_path, new_path);
+
+	fs::rename(old_path, new_path).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_renameat(
+	old_dirfd: FileDescriptor,
+	old_path: *const u8,
+	new_dirfd: FileDescriptor,
+	new_path: *const u8,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if old_dirfd != AT_FDCWD || new_dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	sys_rename(old_path, new_path)
+}
@@ -0,0 +1,8 @@
+use crate::logging::*;
+use crate::scheduler::*;
+
+pub(crate) extern "C" fn sys_sched_yield() -> isize {
+	debug!("enter syscall sched_yield");
+	reschedule();
+	0
+}
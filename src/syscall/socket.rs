@@ -0,0 +1,350 @@
+use crate::fd::FileDescriptor;
+use crate::io;
+use crate::logging::*;
+use crate::net::{self, MessageFlags, Shutdown, SocketAddress, AF_INET, AF_UNIX, IPPROTO_TCP};
+use crate::scheduler::remove_io_interface;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::net::{Ipv4Addr, SocketAddrV4};
+use core::{mem, ptr, slice};
+
+/// Flag of `accept4`: the new socket doesn't wait
+const SOCK_NONBLOCK: i32 = net::SOCK_NONBLOCK;
+/// Flag of `accept4`: close the new descriptor on `exec` (ignored)
+const SOCK_CLOEXEC: i32 = net::SOCK_CLOEXEC;
+
+// directions of `shutdown`
+const SHUT_RD: i32 = 0;
+const SHUT_WR: i32 = 1;
+const SHUT_RDWR: i32 = 2;
+
+// options of `setsockopt`, which are accepted without effect
+const SOL_SOCKET: i32 = 1;
+const SO_REUSEADDR: i32 = 2;
+const SO_KEEPALIVE: i32 = 9;
+const TCP_NODELAY: i32 = 1;
+
+/// IPv4 socket address, see `struct sockaddr_in` of Linux
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct SockAddrIn {
+	sin_family: u16,
+	/// port in network byte order
+	sin_port: u16,
+	sin_addr: [u8; 4],
+	sin_zero: [u8; 8],
+}
+
+/// Size of `struct sockaddr_un`, i.e. the address family and the path
+const SOCKADDR_UN_SIZE: usize = 110;
+
+/// Convert the socket address of the user space at `addr` with the length `len`
+unsafe fn read_sockaddr(addr: *const u8, len: u32) -> io::Result<SocketAddress> {
+	if addr.is_null() {
+		return Err(io::Error::EFAULT);
+	}
+	if (len as usize) < mem::size_of::<u16>() {
+		return Err(io::Error::EINVAL);
+	}
+
+	match i32::from(ptr::read_unaligned(addr as *const u16)) {
+		AF_INET => {
+			if (len as usize) < mem::size_of::<SockAddrIn>() {
+				return Err(io::Error::EINVAL);
+			}
+
+			let sin = ptr::read_unaligned(addr as *const SockAddrIn);
+			Ok(SocketAddress::Inet(SocketAddrV4::new(
+				Ipv4Addr::from(sin.sin_addr),
+				u16::from_be(sin.sin_port),
+			)))
+		}
+		AF_UNIX => {
+			if len as usize > SOCKADDR_UN_SIZE {
+				return Err(io::Error::EINVAL);
+			}
+
+			// the path is terminated by a zero or by the end of the address
+			let path = slice::from_raw_parts(addr.add(2), len as usize - 2);
+			let path = path.split(|c| *c == 0).next().unwrap();
+			// abstract addresses, which start with a zero, aren't supported
+			if path.is_empty() {
+				return Err(io::Error::EINVAL);
+			}
+
+			let path = core::str::from_utf8(path).map_err(|_| io::Error::EINVAL)?;
+			Ok(SocketAddress::Unix(String::from(path)))
+		}
+		_ => Err(io::Error::EAFNOSUPPORT),
+	}
+}
+
+/// Copy `addr` to the buffer `buf` of the user space, whose length is
+/// stored at `len`. The address is truncated, if the buffer is too small.
+/// Afterwards, `len` contains the real length of the address.
+unsafe fn write_sockaddr(addr: SocketAddress, buf: *mut u8, len: *mut u32) -> io::Result<()> {
+	if buf.is_null() {
+		return Ok(());
+	}
+	if len.is_null() {
+		return Err(io::Error::EFAULT);
+	}
+
+	let bytes = match addr {
+		SocketAddress::Inet(addr) => {
+			let sin = SockAddrIn {
+				sin_family: AF_INET as u16,
+				sin_port: addr.port().to_be(),
+				sin_addr: addr.ip().octets(),
+				sin_zero: [0; 8],
+			};
+
+			Vec::from(slice::from_raw_parts(
+				&sin as *const SockAddrIn as *const u8,
+				mem::size_of::<SockAddrIn>(),
+			))
+		}
+		SocketAddress::Unix(path) => {
+			let mut bytes = Vec::from((AF_UNIX as u16).to_ne_bytes());
+			// an unnamed socket has only the address family
+			if !path.is_empty() {
+				bytes.extend_from_slice(path.as_bytes());
+				bytes.push(0);
+			}
+
+			bytes
+		}
+	};
+
+	ptr::copy_nonoverlapping(bytes.as_ptr(), buf, bytes.len().min(*len as usize));
+	*len = bytes.len() as u32;
+
+	Ok(())
+}
+
+pub(crate) extern "C" fn sys_socket(domain: i32, kind: i32, protocol: i32) -> isize {
+	debug!("Enter syscall socket {} {} {}", domain, kind, protocol);
+
+	net::socket(domain, kind, protocol).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|fd| fd as isize,
+	)
+}
+
+/// Create two connected sockets and store their descriptors in `sv`
+pub(crate) unsafe extern "C" fn sys_socketpair(
+	domain: i32,
+	kind: i32,
+	protocol: i32,
+	sv: *mut FileDescriptor,
+) -> isize {
+	debug!("Enter syscall socketpair {} {} {}", domain, kind, protocol);
+
+	if sv.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	match net::socketpair(domain, kind, protocol) {
+		Ok((first, second)) => {
+			*sv = first;
+			*sv.add(1) = second;
+			0
+		}
+		Err(e) => -num::ToPrimitive::to_isize(&e).unwrap(),
+	}
+}
+
+pub(crate) unsafe extern "C" fn sys_bind(fd: FileDescriptor, addr: *const u8, len: u32) -> isize {
+	debug!("Enter syscall bind {}", fd);
+
+	read_sockaddr(addr, len)
+		.and_then(|addr| net::bind(fd, addr))
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) extern "C" fn sys_listen(fd: FileDescriptor, backlog: i32) -> isize {
+	debug!("Enter syscall listen {}", fd);
+
+	net::listen(fd, backlog).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_accept4(
+	fd: FileDescriptor,
+	addr: *mut u8,
+	len: *mut u32,
+	flags: i32,
+) -> isize {
+	debug!("Enter syscall accept4 {}", fd);
+
+	if flags & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	net::accept(fd, flags & SOCK_NONBLOCK != 0)
+		.and_then(|(new_fd, peer)| {
+			if let Err(e) = write_sockaddr(peer, addr, len) {
+				// the connection is closed with the file descriptor
+				let _ = remove_io_interface(new_fd);
+				return Err(e);
+			}
+
+			Ok(new_fd)
+		})
+		.map_or_else(
+			|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+			|fd| fd as isize,
+		)
+}
+
+pub(crate) unsafe extern "C" fn sys_accept(
+	fd: FileDescriptor,
+	addr: *mut u8,
+	len: *mut u32,
+) -> isize {
+	sys_accept4(fd, addr, len, 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_connect(
+	fd: FileDescriptor,
+	addr: *const u8,
+	len: u32,
+) -> isize {
+	debug!("Enter syscall connect {}", fd);
+
+	read_sockaddr(addr, len)
+		.and_then(|addr| net::connect(fd, addr))
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_sendto(
+	fd: FileDescriptor,
+	buf: *const u8,
+	len: usize,
+	flags: i32,
+	addr: *const u8,
+	addrlen: u32,
+) -> isize {
+	debug!("Enter syscall sendto {}", fd);
+
+	if buf.is_null() && len > 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	let addr = if addr.is_null() {
+		None
+	} else {
+		match read_sockaddr(addr, addrlen) {
+			Ok(addr) => Some(addr),
+			Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+		}
+	};
+	let slice = if len > 0 {
+		slice::from_raw_parts(buf, len)
+	} else {
+		&[]
+	};
+
+	net::sendto(fd, slice, MessageFlags::from_bits_truncate(flags), addr).map_or_else(
+		|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+		|len| len as isize,
+	)
+}
+
+pub(crate) unsafe extern "C" fn sys_recvfrom(
+	fd: FileDescriptor,
+	buf: *mut u8,
+	len: usize,
+	flags: i32,
+	addr: *mut u8,
+	addrlen: *mut u32,
+) -> isize {
+	debug!("Enter syscall recvfrom {}", fd);
+
+	if buf.is_null() && len > 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	let slice = if len > 0 {
+		slice::from_raw_parts_mut(buf, len)
+	} else {
+		&mut []
+	};
+
+	net::recvfrom(fd, slice, MessageFlags::from_bits_truncate(flags))
+		.and_then(|(len, peer)| {
+			if let Some(peer) = peer {
+				write_sockaddr(peer, addr, addrlen)?;
+			}
+			Ok(len)
+		})
+		.map_or_else(
+			|e| -num::ToPrimitive::to_isize(&e).unwrap(),
+			|len| len as isize,
+		)
+}
+
+pub(crate) extern "C" fn sys_shutdown(fd: FileDescriptor, how: i32) -> isize {
+	debug!("Enter syscall shutdown {}", fd);
+
+	let how = match how {
+		SHUT_RD => Shutdown::Read,
+		SHUT_WR => Shutdown::Write,
+		SHUT_RDWR => Shutdown::Both,
+		_ => return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap(),
+	};
+
+	net::shutdown(fd, how).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_getsockname(
+	fd: FileDescriptor,
+	addr: *mut u8,
+	len: *mut u32,
+) -> isize {
+	debug!("Enter syscall getsockname {}", fd);
+
+	if addr.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	net::getsockname(fd)
+		.and_then(|local| write_sockaddr(local, addr, len))
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_getpeername(
+	fd: FileDescriptor,
+	addr: *mut u8,
+	len: *mut u32,
+) -> isize {
+	debug!("Enter syscall getpeername {}", fd);
+
+	if addr.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	net::getpeername(fd)
+		.and_then(|peer| write_sockaddr(peer, addr, len))
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+/// Accept a few common options, which don't change the behavior of the stack
+pub(crate) extern "C" fn sys_setsockopt(
+	fd: FileDescriptor,
+	level: i32,
+	name: i32,
+	_value: *const u8,
+	_len: u32,
+) -> isize {
+	debug!("Enter syscall setsockopt {} {} {}", fd, level, name);
+
+	// the descriptor has to be a socket
+	if let Err(e) = net::getsockname(fd) {
+		return -num::ToPrimitive::to_isize(&e).unwrap();
+	}
+
+	match (level, name) {
+		(SOL_SOCKET, SO_REUSEADDR | SO_KEEPALIVE) | (IPPROTO_TCP, TCP_NODELAY) => 0,
+		_ => -num::ToPrimitive::to_isize(&io::Error::ENOPROTOOPT).unwrap(),
+	}
+}
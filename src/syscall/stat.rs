@@ -0,0 +1,137 @@
+use crate::fd::{self, FileDescriptor, FileStatus};
+use crate::fs::{self, NodeKind};
+use crate::io;
+use crate::logging::*;
+use crate::syscall::{from_c_str, AT_FDCWD, AT_SYMLINK_NOFOLLOW};
+
+/// Flag of `newfstatat` to examine the file descriptor `dirfd`, if `path` is empty
+const AT_EMPTY_PATH: i32 = 0x1000;
+
+const S_IFCHR: u32 = 0o020000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFSOCK: u32 = 0o140000;
+
+/// Preferred block size for file system I/O
+const BLOCK_SIZE: i64 = 4096;
+
+/// Status of a file, see `struct stat` of Linux (x86_64)
+#[repr(C)]
+#[derive(Debug, Default)]
+struct Stat {
+	st_dev: u64,
+	st_ino: u64,
+	st_nlink: u64,
+	st_mode: u32,
+	st_uid: u32,
+	st_gid: u32,
+	__pad0: i32,
+	st_rdev: u64,
+	st_size: i64,
+	st_blksize: i64,
+	st_blocks: i64,
+	st_atime: i64,
+	st_atime_nsec: i64,
+	st_mtime: i64,
+	st_mtime_nsec: i64,
+	st_ctime: i64,
+	st_ctime_nsec: i64,
+	__unused: [i64; 3],
+}
+
+/// Convert `status` to the layout of Linux and copy it to `buf`
+unsafe fn copy_status(status: io::Result<FileStatus>, buf: *mut Stat) -> isize {
+	if buf.is_null() {
+		return -num::ToPrimitive::to_isize(&io::Error::EFAULT).unwrap();
+	}
+
+	let status = match status {
+		Ok(status) => status,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	let file_type = match status.kind {
+		NodeKind::File => S_IFREG,
+		NodeKind::Directory => S_IFDIR,
+		NodeKind::CharDevice => S_IFCHR,
+		NodeKind::BlockDevice => S_IFBLK,
+		NodeKind::Symlink => S_IFLNK,
+		NodeKind::Socket => S_IFSOCK,
+	};
+
+	*buf = Stat {
+		st_ino: status.ino,
+		st_nlink: status.nlink as u64,
+		st_mode: file_type | status.attr.mode,
+		st_uid: status.attr.uid,
+		st_gid: status.attr.gid,
+		st_size: status.file_size as i64,
+		st_blksize: BLOCK_SIZE,
+		st_blocks: (status.file_size as i64 + 511) / 512,
+		st_atime: status.times.atime.sec,
+		st_atime_nsec: status.times.atime.nsec,
+		st_mtime: status.times.mtime.sec,
+		st_mtime_nsec: status.times.mtime.nsec,
+		st_ctime: status.times.ctime.sec,
+		st_ctime_nsec: status.times.ctime.nsec,
+		..Default::default()
+	};
+
+	0
+}
+
+pub(crate) unsafe extern "C" fn sys_stat(path: *const u8, buf: *mut u8) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall stat {}", path);
+
+	copy_status(fs::stat(path, true), buf as *mut Stat)
+}
+
+pub(crate) unsafe extern "C" fn sys_lstat(path: *const u8, buf: *mut u8) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall lstat {}", path);
+
+	copy_status(fs::stat(path, false), buf as *mut Stat)
+}
+
+pub(crate) unsafe extern "C" fn sys_fstat(fd: FileDescriptor, buf: *mut u8) -> isize {
+	debug!("Enter syscall fstat {}", fd);
+
+	copy_status(fd::fstat(fd), buf as *mut Stat)
+}
+
+pub(crate) unsafe extern "C" fn sys_newfstatat(
+	dirfd: FileDescriptor,
+	path: *const u8,
+	buf: *mut u8,
+	flags: i32,
+) -> isize {
+	if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	if flags & AT_EMPTY_PATH != 0 && !path.is_null() && *path == 0 {
+		return sys_fstat(dirfd, buf);
+	}
+
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	if flags & AT_SYMLINK_NOFOLLOW != 0 {
+		sys_lstat(path, buf)
+	} else {
+		sys_stat(path, buf)
+	}
+}
@@ -0,0 +1,32 @@
+use crate::fd::FileDescriptor;
+use crate::fs;
+use crate::logging::*;
+
+/// Write all modified data of the buffer cache back to the devices
+pub(crate) extern "C" fn sys_sync() -> isize {
+	debug!("Enter syscall sync");
+
+	// sync always succeeds, errors are only logged
+	let _ = fs::sync();
+
+	0
+}
+
+pub(crate) extern "C" fn sys_fsync(fd: FileDescriptor) -> isize {
+	debug!("Enter syscall fsync {}", fd);
+
+	fs::fsync(fd).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+/// All metadata is written immediately, so `fdatasync` is equivalent to `fsync`
+pub(crate) extern "C" fn sys_fdatasync(fd: FileDescriptor) -> isize {
+	debug!("Enter syscall fdatasync {}", fd);
+
+	fs::fsync(fd).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) extern "C" fn sys_syncfs(fd: FileDescriptor) -> isize {
+	debug!("Enter syscall syncfs {}", fd);
+
+	fs::syncfs(fd).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
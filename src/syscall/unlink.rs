@@ -0,0 +1,51 @@
+use crate::fd::FileDescriptor;
+use crate::fs;
+use crate::io;
+use crate::logging::*;
+use crate::syscall::{from_c_str, AT_FDCWD};
+
+/// Flag of `unlinkat` to remove a directory instead of a file
+const AT_REMOVEDIR: i32 = 0x200;
+
+pub(crate) unsafe extern "C" fn sys_unlink(path: *const u8) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall unlink {}", path);
+
+	fs::unlink(path).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_rmdir(path: *const u8) -> isize {
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall rmdir {}", path);
+
+	fs::rmdir(path).map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
+
+pub(crate) unsafe extern "C" fn sys_unlinkat(
+	dirfd: FileDescriptor,
+	path: *const u8,
+	flags: i32,
+) -> isize {
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	if flags & !AT_REMOVEDIR != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	if flags & AT_REMOVEDIR != 0 {
+		sys_rmdir(path)
+	} else {
+		sys_unlink(path)
+	}
+}
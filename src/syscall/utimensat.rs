@@ -0,0 +1,70 @@
+use crate::fd::FileDescriptor;
+use crate::fs::{self, TimeUpdate};
+use crate::io;
+use crate::logging::*;
+use crate::syscall::{from_c_str, AT_FDCWD, AT_SYMLINK_NOFOLLOW};
+use crate::time::Timespec;
+
+/// Special value of `tv_nsec` to set a timestamp to the current time
+const UTIME_NOW: i64 = (1 << 30) - 1;
+/// Special value of `tv_nsec` to keep a timestamp
+const UTIME_OMIT: i64 = (1 << 30) - 2;
+
+/// Convert a timestamp of the user space to an update of the file system
+fn to_update(time: &Timespec) -> io::Result<TimeUpdate> {
+	match time.nsec {
+		UTIME_NOW => Ok(TimeUpdate::Now),
+		UTIME_OMIT => Ok(TimeUpdate::Omit),
+		_ if time.is_valid() => Ok(TimeUpdate::Set(*time)),
+		_ => Err(io::Error::EINVAL),
+	}
+}
+
+/// Change the access time `times[0]` and the modification time `times[1]`
+/// of the file `path`. If `times` is null, both are set to the current time.
+/// `futimens` passes a null pointer as `path` to change the file `dirfd`.
+pub(crate) unsafe extern "C" fn sys_utimensat(
+	dirfd: FileDescriptor,
+	path: *const u8,
+	times: *const Timespec,
+	flags: i32,
+) -> isize {
+	if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+		return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+	}
+
+	let (atime, mtime) = if times.is_null() {
+		(TimeUpdate::Now, TimeUpdate::Now)
+	} else {
+		match (to_update(&*times), to_update(&*times.add(1))) {
+			(Ok(atime), Ok(mtime)) => (atime, mtime),
+			(Err(e), _) | (_, Err(e)) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+		}
+	};
+
+	if path.is_null() {
+		debug!("Enter syscall futimens {}", dirfd);
+
+		if flags != 0 {
+			return -num::ToPrimitive::to_isize(&io::Error::EINVAL).unwrap();
+		}
+
+		return fs::futimens(dirfd, atime, mtime)
+			.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0);
+	}
+
+	// only paths relative to the current working directory are supported
+	if dirfd != AT_FDCWD {
+		return -num::ToPrimitive::to_isize(&io::Error::ENOSYS).unwrap();
+	}
+
+	let path = match from_c_str(path) {
+		Ok(path) => path,
+		Err(e) => return -num::ToPrimitive::to_isize(&e).unwrap(),
+	};
+
+	debug!("Enter syscall utimensat {} {:?} {:?}", path, atime, mtime);
+
+	fs::utimens(path, atime, mtime, flags & AT_SYMLINK_NOFOLLOW == 0)
+		.map_or_else(|e| -num::ToPrimitive::to_isize(&e).unwrap(), |_| 0)
+}
@@ -0,0 +1,60 @@
+//! Wall-clock and monotonic time of the kernel
+//!
+//! The wall-clock time is read once from the RTC at the boot time.
+//! Afterwards, the time is derived from the timer interrupts.
+//! Consequently, the resolution is limited by `TIMER_FREQ`.
+
+use crate::arch::irq::get_timer_ticks;
+use crate::arch::rtc::get_boot_time;
+use crate::consts::TIMER_FREQ;
+
+/// Number of nanoseconds per second
+pub const NSEC_PER_SEC: i64 = 1_000_000_000;
+
+/// Point in time, see `struct timespec` of Linux (x86_64)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timespec {
+	/// Seconds
+	pub sec: i64,
+	/// Nanoseconds, which are always less than `NSEC_PER_SEC`
+	pub nsec: i64,
+}
+
+impl Timespec {
+	pub const fn new(sec: i64, nsec: i64) -> Self {
+		Timespec { sec, nsec }
+	}
+
+	/// Returns true, if the nanoseconds are in the valid range
+	pub const fn is_valid(&self) -> bool {
+		self.nsec >= 0 && self.nsec < NSEC_PER_SEC
+	}
+}
+
+/// Returns the number of days since 1970-01-01 of the given date
+/// in the proleptic Gregorian calendar
+pub const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = if year >= 0 { year } else { year - 399 } / 400;
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+	era * 146097 + day_of_era - 719468
+}
+
+/// Returns the time since the boot
+pub fn monotonic() -> Timespec {
+	let ticks = get_timer_ticks() as i64;
+	let freq = i64::from(TIMER_FREQ);
+
+	Timespec::new(ticks / freq, (ticks % freq) * (NSEC_PER_SEC / freq))
+}
+
+/// Returns the time since the Unix epoch
+pub fn realtime() -> Timespec {
+	let uptime = monotonic();
+
+	Timespec::new(get_boot_time() as i64 + uptime.sec, uptime.nsec)
+}